@@ -25,14 +25,11 @@ fn main() {
     let mut renderer = Renderer::new();
     renderer.clear_color(glm::vec4(0.0, 0.0, 0.0, 1.0));
 
-    let buffer = VertexBuffer::new(
-        &[
-            glm::vec2(0.0, 0.5),
-            glm::vec2(0.5, 0.0),
-            glm::vec2(-0.5, 0.0),
-        ],
-        None,
-    );
+    let buffer = VertexBuffer::from_vertices(&[
+        glm::vec2(0.0, 0.5),
+        glm::vec2(0.5, 0.0),
+        glm::vec2(-0.5, 0.0),
+    ]);
 
     let vertex_shader = Shader::new(
         r#"
@@ -59,13 +56,14 @@ fn main() {
         ShaderType::Fragment,
     );
 
-    let mut program = Program::new();
-    program
+    let program = Program::new()
         .attach_and_link(vec![vertex_shader, fragment_shader])
         .expect("Failed to link program");
 
     while !window.should_close() {
-        renderer.draw(&buffer, &program, DrawMode::Triangles, &uniforms! {});
+        renderer
+            .draw(&buffer, &program, DrawMode::Triangles, &uniforms! {})
+            .expect("Failed to draw triangle");
 
         window.swap_buffers();
         glfw.poll_events();
@@ -1,13 +1,13 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Fields};
+use syn::{parse_macro_input, Fields, LitInt, LitStr};
 
 #[proc_macro_derive(Vertex)]
 pub fn derive_vertex(item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as syn::ItemStruct);
     let ident = item.ident;
     let fields = item.fields;
-    let (_field_types, names) = if let Fields::Named(fields) = fields {
+    let (field_types, names) = if let Fields::Named(fields) = fields {
         let types = fields
             .named
             .iter()
@@ -36,12 +36,240 @@ pub fn derive_vertex(item: TokenStream) -> TokenStream {
             }
         }
 
+        #[automatically_derived]
+        impl glium2::buffer::FromVertexData for #ident {
+            fn from_vertex_data(data: &[u8]) -> Self {
+                let mut offset = 0;
+                // SAFETY: The fields are read back in the order `into` wrote them
+                unsafe {
+                    #ident {
+                        #(#names: glium2::buffer::read_vertex_field(data, &mut offset),)*
+                    }
+                }
+            }
+        }
+
         #[automatically_derived]
         impl glium2::shader::Vertex for #ident {
             fn get_vertex_spec() -> glium2::shader::VertexAttributeSpec {
+                glium2::shader::VertexAttributeSpec::builder()
+                    #(.attribute_of::<#field_types>())*
+                    .build()
+            }
+        }
+    }
+    .into()
+}
+
+#[proc_macro_derive(Uniforms, attributes(uniform))]
+pub fn derive_uniforms(item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as syn::ItemStruct);
+    let ident = item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    let fields = if let Fields::Named(fields) = item.fields {
+        fields.named
+    } else {
+        panic!("Only structs with named fields are supported");
+    };
+
+    let mut names = vec![];
+    let mut uniform_names = vec![];
+    for field in fields {
+        let Some(name) = field.ident else {
+            continue;
+        };
+        let mut uniform_name = name.to_string();
+        let mut skip = false;
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("uniform")) {
+            let result = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    uniform_name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"...\"` or `skip`"))
+                }
+            });
+
+            if let Err(err) = result {
+                return err.to_compile_error().into();
+            }
+        }
+
+        if !skip {
+            names.push(name);
+            uniform_names.push(uniform_name);
+        }
+    }
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics glium2::uniforms::UniformStruct for #ident #ty_generics #where_clause {
+            fn apply(&self, program: &glium2::shader::LinkedProgram) {
+                #(program.set_uniform(#uniform_names, &self.#names);)*
+            }
+        }
+    }
+    .into()
+}
 
+#[proc_macro_derive(Std430, attributes(std430))]
+pub fn derive_std430(item: TokenStream) -> TokenStream {
+    derive_layout(
+        item,
+        "std430",
+        quote!(glium2::layout::Std430),
+        quote!(glium2::layout::Std430Block),
+        quote!(write_std430),
+        1,
+    )
+}
+
+#[proc_macro_derive(UniformBlock, attributes(uniform_block))]
+pub fn derive_uniform_block(item: TokenStream) -> TokenStream {
+    derive_layout(
+        item,
+        "uniform_block",
+        quote!(glium2::layout::Std140),
+        quote!(glium2::layout::UniformBlock),
+        quote!(write_std140),
+        16,
+    )
+}
+
+/// Generates a layout trait implementation computing field offsets at compile time.
+///
+/// Struct alignment is the largest member alignment, rounded up to `min_struct_align`.
+fn derive_layout(
+    item: TokenStream,
+    attr_name: &str,
+    layout_trait: proc_macro2::TokenStream,
+    block_trait: proc_macro2::TokenStream,
+    write_fn: proc_macro2::TokenStream,
+    min_struct_align: usize,
+) -> TokenStream {
+    let item = parse_macro_input!(item as syn::ItemStruct);
+    let ident = item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    let fields = if let Fields::Named(fields) = item.fields {
+        fields.named
+    } else {
+        panic!("Only structs with named fields are supported");
+    };
+
+    let mut names = vec![];
+    let mut glsl_names = vec![];
+    let mut types = vec![];
+    let mut offset_assertions = vec![];
+    for (index, field) in fields.into_iter().enumerate() {
+        let Some(name) = field.ident else {
+            continue;
+        };
+        let mut glsl_name = name.to_string();
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident(attr_name)) {
+            let result = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    glsl_name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("offset") {
+                    let offset = meta.value()?.parse::<LitInt>()?;
+                    let message = format!(
+                        "{attr_name} offset of `{ident}::{name}` does not match the declared offset {offset}",
+                    );
+                    offset_assertions.push(quote! {
+                        assert!(
+                            <#ident as #block_trait>::FIELD_OFFSETS[#index] == #offset,
+                            #message
+                        );
+                    });
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"...\"` or `offset = N`"))
+                }
+            });
+
+            if let Err(err) = result {
+                return err.to_compile_error().into();
             }
         }
+
+        names.push(name);
+        glsl_names.push(glsl_name);
+        types.push(field.ty);
+    }
+
+    let count = names.len();
+    let indices = 0..count;
+    let last_size = match types.last() {
+        Some(ty) => quote!(<#ty as #layout_trait>::SIZE),
+        None => quote!(0),
+    };
+    let last_offset = if count > 0 {
+        let last = count - 1;
+        quote!(<Self as #block_trait>::FIELD_OFFSETS[#last])
+    } else {
+        quote!(0)
+    };
+
+    let assertions = if offset_assertions.is_empty() || !item.generics.params.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            const _: () = {
+                #(#offset_assertions)*
+            };
+        }
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #layout_trait for #ident #ty_generics #where_clause {
+            const ALIGN: usize = {
+                let align = #min_struct_align;
+                #(let align = glium2::layout::max(align, <#types as #layout_trait>::ALIGN);)*
+                align
+            };
+            const SIZE: usize = glium2::layout::align_to(
+                #last_offset + #last_size,
+                <Self as #layout_trait>::ALIGN,
+            );
+
+            fn #write_fn(&self, out: &mut Vec<u8>) {
+                let start = out.len();
+                #(
+                    glium2::layout::pad_to(
+                        out,
+                        start + <Self as #block_trait>::FIELD_OFFSETS[#indices],
+                    );
+                    #layout_trait::#write_fn(&self.#names, out);
+                )*
+                glium2::layout::pad_to(out, start + <Self as #layout_trait>::SIZE);
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics #block_trait for #ident #ty_generics #where_clause {
+            const FIELD_NAMES: &'static [&'static str] = &[#(#glsl_names),*];
+            const FIELD_OFFSETS: &'static [usize] = &{
+                let mut offsets = [0usize; #count];
+                let mut offset = 0;
+                let mut index = 0;
+                #(
+                    offset = glium2::layout::align_to(offset, <#types as #layout_trait>::ALIGN);
+                    offsets[index] = offset;
+                    offset += <#types as #layout_trait>::SIZE;
+                    index += 1;
+                )*
+                let _ = (offset, index);
+                offsets
+            };
+        }
+
+        #assertions
     }
     .into()
 }
@@ -0,0 +1,98 @@
+use crate::buffer::BufferTarget;
+use std::marker::PhantomData;
+
+/// An OpenGL binding point that a [`BindGuard`] can restore
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum BindTarget {
+    VertexArray,
+    Buffer(BufferTarget),
+    Program,
+    DrawFramebuffer,
+    ReadFramebuffer,
+    #[cfg(feature = "passes")]
+    Texture2D,
+}
+
+impl BindTarget {
+    /// Returns the object currently bound to this binding point
+    fn current(self) -> u32 {
+        let binding = match self {
+            BindTarget::VertexArray => gl::VERTEX_ARRAY_BINDING,
+            BindTarget::Buffer(target) => target.binding(),
+            BindTarget::Program => gl::CURRENT_PROGRAM,
+            BindTarget::DrawFramebuffer => gl::DRAW_FRAMEBUFFER_BINDING,
+            BindTarget::ReadFramebuffer => gl::READ_FRAMEBUFFER_BINDING,
+            #[cfg(feature = "passes")]
+            BindTarget::Texture2D => gl::TEXTURE_BINDING_2D,
+        };
+
+        let mut id = 0;
+        unsafe { gl::GetIntegerv(binding, std::ptr::addr_of_mut!(id)) };
+        id as u32
+    }
+
+    /// Binds `id` to this binding point
+    fn bind(self, id: u32) {
+        unsafe {
+            match self {
+                BindTarget::VertexArray => gl::BindVertexArray(id),
+                BindTarget::Buffer(target) => gl::BindBuffer(target.into(), id),
+                BindTarget::Program => gl::UseProgram(id),
+                BindTarget::DrawFramebuffer => gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, id),
+                BindTarget::ReadFramebuffer => gl::BindFramebuffer(gl::READ_FRAMEBUFFER, id),
+                #[cfg(feature = "passes")]
+                BindTarget::Texture2D => gl::BindTexture(gl::TEXTURE_2D, id),
+            }
+        };
+    }
+}
+
+/// Keeps an object bound for as long as it lives, and restores whatever was bound before
+/// when dropped.
+///
+/// Guards should be dropped in the reverse order they were created, which is what happens
+/// naturally when they're held in local variables.
+#[must_use = "the previous binding is restored as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct BindGuard<'a> {
+    previous: [Option<(BindTarget, u32)>; 2],
+    _bound: PhantomData<&'a ()>,
+}
+
+impl<'a> BindGuard<'a> {
+    /// Binds `id` to `target`, remembering the previous binding
+    pub(crate) fn new(target: BindTarget, id: u32) -> Self {
+        Self::with_targets([Some((target, id)), None])
+    }
+
+    /// Binds `id` to both `first` and `second`, remembering the previous bindings
+    pub(crate) fn pair(first: BindTarget, second: BindTarget, id: u32) -> Self {
+        Self::with_targets([Some((first, id)), Some((second, id))])
+    }
+
+    /// Binds a different object to each target, remembering the previous bindings
+    pub(crate) fn with_targets(bindings: [Option<(BindTarget, u32)>; 2]) -> Self {
+        let previous = bindings.map(|binding| {
+            binding.map(|(target, id)| {
+                let previous = target.current();
+                target.bind(id);
+                (target, previous)
+            })
+        });
+
+        Self {
+            previous,
+            _bound: PhantomData,
+        }
+    }
+}
+
+impl Drop for BindGuard<'_> {
+    fn drop(&mut self) {
+        self.previous
+            .iter()
+            .rev()
+            .flatten()
+            .for_each(|(target, id)| target.bind(*id));
+    }
+}
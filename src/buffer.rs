@@ -1,294 +1,1627 @@
-use std::{marker::PhantomData, mem::MaybeUninit};
+use std::{
+    marker::PhantomData,
+    ops::{BitOr, BitOrAssign, Deref, DerefMut, Range},
+};
 
-use gl::types::GLuint;
+use crate::{
+    bind::{BindGuard, BindTarget},
+    context::{self, Unsupported},
+    diagnostics::diagnostic,
+    layout::Std140,
+    shader::{Vertex, VertexAttributeSpec, VertexFormat},
+    sync::Fence,
+    types::{IndexType, Pod},
+};
 
-use crate::shader::{Vertex, VertexAttributeSpec};
+/// The binding point a [`Buffer`] is used through
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BufferTarget {
+    /// Vertex attributes, `GL_ARRAY_BUFFER`
+    Array,
+    /// Vertex indices, `GL_ELEMENT_ARRAY_BUFFER`
+    ElementArray,
+    /// Uniform blocks, `GL_UNIFORM_BUFFER`
+    Uniform,
+    /// Shader storage blocks, `GL_SHADER_STORAGE_BUFFER`
+    ShaderStorage,
+    /// Pixel read-back, `GL_PIXEL_PACK_BUFFER`
+    PixelPack,
+    /// Pixel uploads, `GL_PIXEL_UNPACK_BUFFER`
+    PixelUnpack,
+    /// The source of buffer copies, `GL_COPY_READ_BUFFER`
+    CopyRead,
+    /// The destination of buffer copies, `GL_COPY_WRITE_BUFFER`
+    CopyWrite,
+    /// Indirect draw commands, `GL_DRAW_INDIRECT_BUFFER`
+    DrawIndirect,
+    /// Indirect compute dispatches, `GL_DISPATCH_INDIRECT_BUFFER`
+    DispatchIndirect,
+}
+
+impl BufferTarget {
+    /// The parameter used to query the buffer bound to this target
+    pub(crate) fn binding(self) -> u32 {
+        match self {
+            BufferTarget::Array => gl::ARRAY_BUFFER_BINDING,
+            BufferTarget::ElementArray => gl::ELEMENT_ARRAY_BUFFER_BINDING,
+            BufferTarget::Uniform => gl::UNIFORM_BUFFER_BINDING,
+            BufferTarget::ShaderStorage => gl::SHADER_STORAGE_BUFFER_BINDING,
+            BufferTarget::PixelPack => gl::PIXEL_PACK_BUFFER_BINDING,
+            BufferTarget::PixelUnpack => gl::PIXEL_UNPACK_BUFFER_BINDING,
+            BufferTarget::CopyRead => gl::COPY_READ_BUFFER_BINDING,
+            BufferTarget::CopyWrite => gl::COPY_WRITE_BUFFER_BINDING,
+            BufferTarget::DrawIndirect => gl::DRAW_INDIRECT_BUFFER_BINDING,
+            BufferTarget::DispatchIndirect => gl::DISPATCH_INDIRECT_BUFFER_BINDING,
+        }
+    }
+}
+
+impl From<BufferTarget> for u32 {
+    fn from(target: BufferTarget) -> u32 {
+        match target {
+            BufferTarget::Array => gl::ARRAY_BUFFER,
+            BufferTarget::ElementArray => gl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Uniform => gl::UNIFORM_BUFFER,
+            BufferTarget::ShaderStorage => gl::SHADER_STORAGE_BUFFER,
+            BufferTarget::PixelPack => gl::PIXEL_PACK_BUFFER,
+            BufferTarget::PixelUnpack => gl::PIXEL_UNPACK_BUFFER,
+            BufferTarget::CopyRead => gl::COPY_READ_BUFFER,
+            BufferTarget::CopyWrite => gl::COPY_WRITE_BUFFER,
+            BufferTarget::DrawIndirect => gl::DRAW_INDIRECT_BUFFER,
+            BufferTarget::DispatchIndirect => gl::DISPATCH_INDIRECT_BUFFER,
+        }
+    }
+}
+
+/// A hint to the driver about how the contents of a [`Buffer`] are going to be used
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BufferUsage {
+    StaticDraw,
+    DynamicDraw,
+    StreamDraw,
+    StaticRead,
+    DynamicRead,
+    StreamRead,
+    StaticCopy,
+    DynamicCopy,
+    StreamCopy,
+}
+
+impl From<BufferUsage> for u32 {
+    fn from(usage: BufferUsage) -> u32 {
+        match usage {
+            BufferUsage::StaticDraw => gl::STATIC_DRAW,
+            BufferUsage::DynamicDraw => gl::DYNAMIC_DRAW,
+            BufferUsage::StreamDraw => gl::STREAM_DRAW,
+            BufferUsage::StaticRead => gl::STATIC_READ,
+            BufferUsage::DynamicRead => gl::DYNAMIC_READ,
+            BufferUsage::StreamRead => gl::STREAM_READ,
+            BufferUsage::StaticCopy => gl::STATIC_COPY,
+            BufferUsage::DynamicCopy => gl::DYNAMIC_COPY,
+            BufferUsage::StreamCopy => gl::STREAM_COPY,
+        }
+    }
+}
+
+/// Flags describing how a buffer with immutable storage can be accessed, combined with `|`.
+///
+/// See [`Buffer::immutable`]. The default, [`StorageFlags::NONE`], only allows the buffer to be
+/// written by the GPU, e.g. through copies, which is ideal for static data uploaded once.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct StorageFlags(u32);
+
+impl StorageFlags {
+    /// No access from the CPU after creation
+    pub const NONE: Self = Self(0);
+    /// The contents can be updated with [`Buffer::set_sub_data`], `GL_DYNAMIC_STORAGE_BIT`
+    pub const DYNAMIC_STORAGE: Self = Self(gl::DYNAMIC_STORAGE_BIT);
+    /// The buffer can be mapped for reading, `GL_MAP_READ_BIT`
+    pub const MAP_READ: Self = Self(gl::MAP_READ_BIT);
+    /// The buffer can be mapped for writing, `GL_MAP_WRITE_BIT`
+    pub const MAP_WRITE: Self = Self(gl::MAP_WRITE_BIT);
+    /// The buffer can stay mapped while the GPU uses it, `GL_MAP_PERSISTENT_BIT`
+    pub const MAP_PERSISTENT: Self = Self(gl::MAP_PERSISTENT_BIT);
+    /// Persistent mappings are coherent without explicit barriers, `GL_MAP_COHERENT_BIT`
+    pub const MAP_COHERENT: Self = Self(gl::MAP_COHERENT_BIT);
+    /// Prefer storage in client memory, `GL_CLIENT_STORAGE_BIT`
+    pub const CLIENT_STORAGE: Self = Self(gl::CLIENT_STORAGE_BIT);
+
+    /// Returns the raw `GLbitfield` passed to `glBufferStorage`
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns whether every flag in `other` is set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for StorageFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for StorageFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl From<StorageFlags> for u32 {
+    fn from(flags: StorageFlags) -> u32 {
+        flags.0
+    }
+}
+
+/// An OpenGL buffer object holding elements of type `T`.
+///
+/// Uploads and read-backs go through `GL_COPY_WRITE_BUFFER` and `GL_COPY_READ_BUFFER`, so
+/// they never disturb the buffer bound to `target` (or the index buffer of the bound vertex
+/// array).
+#[derive(Debug)]
+pub struct Buffer<T> {
+    id: u32,
+    len: usize,
+    target: BufferTarget,
+    usage: BufferUsage,
+    storage: Option<StorageFlags>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> Buffer<T>
+where
+    T: Copy,
+{
+    /// Creates a buffer holding a copy of `data`
+    pub fn new(target: BufferTarget, usage: BufferUsage, data: &[T]) -> Self {
+        let mut buffer = Self::generate(target, usage);
+        buffer.set_data(data);
+        buffer
+    }
+
+    /// Creates a buffer with room for `len` elements with undefined contents
+    pub fn empty(target: BufferTarget, usage: BufferUsage, len: usize) -> Self {
+        let mut buffer = Self::generate(target, usage);
+        buffer.allocate(len, std::ptr::null());
+        buffer
+    }
+
+    /// Creates a buffer with immutable storage holding a copy of `data`.
+    ///
+    /// Immutable buffers can't be resized, and can only be accessed from the CPU as allowed
+    /// by `flags`, which lets the driver place them optimally.
+    pub fn immutable(
+        target: BufferTarget,
+        flags: StorageFlags,
+        data: &[T],
+    ) -> Result<Self, Unsupported> {
+        Self::check_storage_support()?;
+        Ok(Self::with_storage(target, data.len(), data.as_ptr(), flags))
+    }
+
+    /// Creates a buffer with immutable storage for `len` elements with undefined contents. See
+    /// [`Buffer::immutable`].
+    pub fn immutable_empty(
+        target: BufferTarget,
+        flags: StorageFlags,
+        len: usize,
+    ) -> Result<Self, Unsupported> {
+        Self::check_storage_support()?;
+        Ok(Self::with_storage(target, len, std::ptr::null(), flags))
+    }
+
+    /// Creates a buffer holding `data` that is never modified, with immutable storage where
+    /// supported and `GL_STATIC_DRAW` otherwise
+    pub(crate) fn new_static(target: BufferTarget, data: &[T]) -> Self {
+        Self::immutable(target, StorageFlags::NONE, data)
+            .unwrap_or_else(|_| Self::new(target, BufferUsage::StaticDraw, data))
+    }
+
+    fn check_storage_support() -> Result<(), Unsupported> {
+        if context::supports_buffer_storage() {
+            Ok(())
+        } else {
+            Err(Unsupported {
+                feature: "GL_ARB_buffer_storage",
+            })
+        }
+    }
+
+    fn generate(target: BufferTarget, usage: BufferUsage) -> Self {
+        let mut id = 0;
+        unsafe { gl::GenBuffers(1, std::ptr::addr_of_mut!(id)) };
+
+        Self {
+            id,
+            len: 0,
+            target,
+            usage,
+            storage: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn with_storage(target: BufferTarget, len: usize, data: *const T, flags: StorageFlags) -> Self {
+        let mut buffer = Self::generate(target, BufferUsage::StaticDraw);
+        let _binding = BindGuard::new(BindTarget::Buffer(BufferTarget::CopyWrite), buffer.id);
+        unsafe {
+            gl::BufferStorage(
+                gl::COPY_WRITE_BUFFER,
+                (len * std::mem::size_of::<T>()) as isize,
+                data.cast(),
+                flags.into(),
+            )
+        };
+        buffer.len = len;
+        buffer.storage = Some(flags);
+        buffer
+    }
+
+    fn allocate(&mut self, len: usize, data: *const T) {
+        assert!(
+            self.storage.is_none(),
+            "Tried to reallocate buffer {} with immutable storage",
+            self.id
+        );
+
+        let _binding = BindGuard::new(BindTarget::Buffer(BufferTarget::CopyWrite), self.id);
+        unsafe {
+            gl::BufferData(
+                gl::COPY_WRITE_BUFFER,
+                (len * std::mem::size_of::<T>()) as isize,
+                data.cast(),
+                self.usage.into(),
+            )
+        };
+        self.len = len;
+    }
+
+    /// Returns the OpenGL name of the buffer
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the number of elements in the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the buffer holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the size of the buffer in bytes
+    pub fn size(&self) -> usize {
+        self.len * std::mem::size_of::<T>()
+    }
+
+    /// Returns the binding point the buffer is used through
+    pub fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    /// Returns the usage hint the buffer was created with. Buffers with immutable storage
+    /// report [`BufferUsage::StaticDraw`].
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    /// Returns the flags the buffer's storage was created with, or `None` if its storage is
+    /// mutable
+    pub fn storage_flags(&self) -> Option<StorageFlags> {
+        self.storage
+    }
+
+    /// Binds the buffer to its target
+    pub fn bind(&self) {
+        unsafe { gl::BindBuffer(self.target.into(), self.id) };
+    }
+
+    /// Binds the buffer to its target until the returned guard is dropped
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        BindGuard::new(BindTarget::Buffer(self.target), self.id)
+    }
+
+    /// Replaces the contents of the buffer with `data`, reallocating it
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has immutable storage.
+    pub fn set_data(&mut self, data: &[T]) {
+        self.allocate(data.len(), data.as_ptr());
+    }
+
+    /// Detaches the buffer's data store, which the GPU may still be reading from, and replaces
+    /// it with a fresh one of `data.len()` elements holding `data`.
+    ///
+    /// Unlike overwriting the data in place, this never waits for pending draws that use the
+    /// old contents, which makes it the fastest way to rewrite a buffer every frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has immutable storage.
+    pub fn orphan_and_write(&mut self, data: &[T]) {
+        self.allocate(data.len(), std::ptr::null());
+        self.set_sub_data(0, data);
+    }
+
+    /// Copies the elements of `source` in `range` to the elements starting at `offset`,
+    /// entirely on the GPU.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` extends past the end of `source` or the copied elements don't fit in
+    /// the buffer at `offset`.
+    pub fn copy_from(&mut self, source: &Buffer<T>, range: Range<usize>, offset: usize) {
+        assert!(
+            range.start <= range.end && range.end <= source.len,
+            "Range {range:?} is out of bounds for a buffer of {} elements",
+            source.len
+        );
+        assert!(
+            offset + range.len() <= self.len,
+            "Tried to copy {} elements to offset {offset} of a buffer of {} elements",
+            range.len(),
+            self.len
+        );
+
+        let size = std::mem::size_of::<T>();
+        let _binding = BindGuard::with_targets([
+            Some((BindTarget::Buffer(BufferTarget::CopyRead), source.id)),
+            Some((BindTarget::Buffer(BufferTarget::CopyWrite), self.id)),
+        ]);
+        unsafe {
+            gl::CopyBufferSubData(
+                gl::COPY_READ_BUFFER,
+                gl::COPY_WRITE_BUFFER,
+                (range.start * size) as isize,
+                (offset * size) as isize,
+                (range.len() * size) as isize,
+            )
+        };
+    }
+
+    /// Overwrites the elements starting at `offset` with `data` without reallocating.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` doesn't fit in the buffer at `offset`, or if the buffer has immutable
+    /// storage without [`StorageFlags::DYNAMIC_STORAGE`].
+    pub fn set_sub_data(&mut self, offset: usize, data: &[T]) {
+        assert!(
+            offset + data.len() <= self.len,
+            "Tried to write {} elements at offset {offset} into a buffer of {} elements",
+            data.len(),
+            self.len
+        );
+        assert!(
+            self.storage
+                .is_none_or(|flags| flags.contains(StorageFlags::DYNAMIC_STORAGE)),
+            "Tried to update buffer {} whose immutable storage lacks DYNAMIC_STORAGE",
+            self.id
+        );
+
+        let _binding = BindGuard::new(BindTarget::Buffer(BufferTarget::CopyWrite), self.id);
+        unsafe {
+            gl::BufferSubData(
+                gl::COPY_WRITE_BUFFER,
+                (offset * std::mem::size_of::<T>()) as isize,
+                std::mem::size_of_val(data) as isize,
+                data.as_ptr().cast(),
+            )
+        };
+    }
+
+    fn map_raw(&self, range: Range<usize>, access: u32) -> Option<*mut T> {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "Range {range:?} is out of bounds for a buffer of {} elements",
+            self.len
+        );
+        if range.is_empty() {
+            return None;
+        }
+
+        let size = std::mem::size_of::<T>();
+        let _binding = BindGuard::new(BindTarget::Buffer(BufferTarget::CopyWrite), self.id);
+        let data = unsafe {
+            gl::MapBufferRange(
+                gl::COPY_WRITE_BUFFER,
+                (range.start * size) as isize,
+                (range.len() * size) as isize,
+                access,
+            )
+        };
+        if data.is_null() {
+            diagnostic!(Error, Resource, "Failed to map buffer {}", self.id);
+            None
+        } else {
+            Some(data.cast())
+        }
+    }
+
+    fn unmap(&self) {
+        let _binding = BindGuard::new(BindTarget::Buffer(BufferTarget::CopyWrite), self.id);
+        unsafe { gl::UnmapBuffer(gl::COPY_WRITE_BUFFER) };
+    }
+}
+
+// Building `T` from the bytes in GPU memory is only sound for types without invalid bit
+// patterns, hence the `Pod` bound on everything that reads the contents back
+impl<T: Pod> Buffer<T> {
+    /// Copies the contents of the buffer back to the CPU, waiting for any pending writes
+    pub fn read(&self) -> Vec<T> {
+        let mut data: Vec<T> = Vec::with_capacity(self.len);
+        let _binding = BindGuard::new(BindTarget::Buffer(BufferTarget::CopyRead), self.id);
+        unsafe {
+            gl::GetBufferSubData(
+                gl::COPY_READ_BUFFER,
+                0,
+                self.size() as isize,
+                data.as_mut_ptr().cast(),
+            );
+            data.set_len(self.len);
+        };
+        data
+    }
+
+    /// Starts copying the contents of the buffer back to the CPU without waiting for pending
+    /// writes. The contents are copied to a staging buffer on the GPU, and the returned read
+    /// can be polled until the copy completes.
+    pub fn read_async(&self) -> PendingBufferRead<T> {
+        let mut staging = Buffer::empty(BufferTarget::CopyWrite, BufferUsage::StreamRead, self.len);
+        staging.copy_from(self, 0..self.len, 0);
+        let fence = Fence::new();
+        // Polling never flushes, so submit the copy now to make sure the fence is reached
+        unsafe { gl::Flush() };
+        PendingBufferRead { staging, fence }
+    }
+
+    /// Maps the buffer for reading until the returned mapping is dropped.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the buffer is empty or couldn't be mapped.
+    pub fn map(&self) -> Option<BufferMap<'_, T>> {
+        self.map_range(0..self.len)
+    }
+
+    /// Maps the elements in `range` for reading until the returned mapping is dropped.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the range is empty or couldn't be mapped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` extends past the end of the buffer.
+    pub fn map_range(&self, range: Range<usize>) -> Option<BufferMap<'_, T>> {
+        let len = range.len();
+        let data = self.map_raw(range, gl::MAP_READ_BIT)?;
+        Some(BufferMap {
+            buffer: self,
+            data,
+            len,
+        })
+    }
+
+    /// Maps the buffer for reading and writing until the returned mapping is dropped.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the buffer is empty or couldn't be mapped.
+    pub fn map_mut(&mut self) -> Option<BufferMapMut<'_, T>> {
+        self.map_range_mut(0..self.len)
+    }
+
+    /// Maps the elements in `range` for reading and writing until the returned mapping is
+    /// dropped.
+    ///
+    /// Writes are flushed to the GPU when the mapping is dropped, or earlier with
+    /// [`BufferMapMut::flush`].
+    ///
+    /// # Returns
+    ///
+    /// `None` if the range is empty or couldn't be mapped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` extends past the end of the buffer.
+    pub fn map_range_mut(&mut self, range: Range<usize>) -> Option<BufferMapMut<'_, T>> {
+        let len = range.len();
+        let data = self.map_raw(
+            range,
+            gl::MAP_READ_BIT | gl::MAP_WRITE_BIT | gl::MAP_FLUSH_EXPLICIT_BIT,
+        )?;
+        Some(BufferMapMut {
+            buffer: self,
+            data,
+            len,
+            flushed: false,
+        })
+    }
+
+    /// Maps the buffer for reading and calls `f` with its contents.
+    ///
+    /// # Returns
+    ///
+    /// The result of `f`, or `None` if the buffer is empty or couldn't be mapped.
+    pub fn map_read<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&[T]) -> R,
+    {
+        self.map().map(|mapped| f(&mapped))
+    }
+
+    /// Maps the buffer for writing and calls `f` with its contents, flushing and unmapping it
+    /// afterwards. Only the elements `f` changes need to be written, unlike with
+    /// [`Buffer::set_data`].
+    ///
+    /// # Returns
+    ///
+    /// The result of `f`, or `None` if the buffer is empty or couldn't be mapped.
+    pub fn map_write<R, F>(&mut self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut [T]) -> R,
+    {
+        self.map_mut().map(|mut mapped| f(&mut mapped))
+    }
+
+    /// Maps the elements in `range` for writing and calls `f` with them, flushing only that
+    /// range afterwards.
+    ///
+    /// # Returns
+    ///
+    /// The result of `f`, or `None` if the range is empty or couldn't be mapped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` extends past the end of the buffer.
+    pub fn map_write_range<R, F>(&mut self, range: Range<usize>, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut [T]) -> R,
+    {
+        self.map_range_mut(range).map(|mut mapped| f(&mut mapped))
+    }
+}
+
+impl<T> Clone for Buffer<T>
+where
+    T: Copy,
+{
+    /// Creates a buffer with the same target, usage and storage, and copies the contents into
+    /// it on the GPU
+    fn clone(&self) -> Self {
+        let mut copy = match self.storage {
+            Some(flags) => Self::with_storage(self.target, self.len, std::ptr::null(), flags),
+            None => Self::empty(self.target, self.usage, self.len),
+        };
+        copy.copy_from(self, 0..self.len, 0);
+        copy
+    }
+}
+
+impl<T> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.id) };
+    }
+}
+
+/// A range of a [`Buffer`] mapped for reading, unmapped when dropped
+#[derive(Debug)]
+pub struct BufferMap<'a, T: Pod> {
+    buffer: &'a Buffer<T>,
+    data: *const T,
+    len: usize,
+}
+
+impl<T: Pod> Deref for BufferMap<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.data, self.len) }
+    }
+}
+
+impl<T: Pod> Drop for BufferMap<'_, T> {
+    fn drop(&mut self) {
+        self.buffer.unmap();
+    }
+}
+
+/// A range of a [`Buffer`] mapped for reading and writing, flushed and unmapped when dropped
+#[derive(Debug)]
+pub struct BufferMapMut<'a, T: Pod> {
+    buffer: &'a mut Buffer<T>,
+    data: *mut T,
+    len: usize,
+    flushed: bool,
+}
+
+impl<T: Pod> BufferMapMut<'_, T> {
+    /// Makes the writes to the elements in `range`, relative to the start of the mapping,
+    /// visible to the GPU.
+    ///
+    /// Once this has been called, only the flushed ranges are guaranteed to be visible after
+    /// the mapping is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` extends past the end of the mapping.
+    pub fn flush(&mut self, range: Range<usize>) {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "Range {range:?} is out of bounds for a mapping of {} elements",
+            self.len
+        );
+
+        let size = std::mem::size_of::<T>();
+        let _binding = BindGuard::new(BindTarget::Buffer(BufferTarget::CopyWrite), self.buffer.id);
+        unsafe {
+            gl::FlushMappedBufferRange(
+                gl::COPY_WRITE_BUFFER,
+                (range.start * size) as isize,
+                (range.len() * size) as isize,
+            )
+        };
+        self.flushed = true;
+    }
+}
+
+impl<T: Pod> Deref for BufferMapMut<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.data, self.len) }
+    }
+}
+
+impl<T: Pod> DerefMut for BufferMapMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.data, self.len) }
+    }
+}
+
+impl<T: Pod> Drop for BufferMapMut<'_, T> {
+    fn drop(&mut self) {
+        if !self.flushed {
+            self.flush(0..self.len);
+        }
+        self.buffer.unmap();
+    }
+}
+
+/// Vertices uploaded to the GPU, optionally with indices of type `I` (`u8`, `u16` or `u32`)
+#[derive(Debug)]
+pub struct VertexBuffer<V, I = u32> {
+    vao: u32,
+    vertices: Buffer<u8>,
+    indices: Option<Buffer<I>>,
+    vertex_count: usize,
+    is_static: bool,
+    _phantom: PhantomData<V>,
+}
+
+impl<V, I> Clone for VertexBuffer<V, I>
+where
+    V: Into<VertexData>,
+    I: IndexType,
+{
+    fn clone(&self) -> Self {
+        let mut vao = 0;
+        unsafe { gl::GenVertexArrays(1, std::ptr::addr_of_mut!(vao)) };
+
+        let buffer = Self {
+            vao,
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+            vertex_count: self.vertex_count,
+            is_static: self.is_static,
+            _phantom: PhantomData,
+        };
+        buffer.attach_indices();
+        buffer
+    }
+}
+
+impl<V> VertexBuffer<V>
+where
+    V: Into<VertexData>,
+{
+    /// Creates a new vertex buffer without indices
+    pub fn from_vertices(vertices: &[V]) -> Self
+    where
+        V: Clone + std::fmt::Debug,
+    {
+        Self::new(vertices, None, BufferUsage::DynamicDraw)
+    }
+}
+
+impl<V, I> VertexBuffer<V, I>
+where
+    V: Into<VertexData>,
+    I: IndexType,
+{
+    /// Creates a new vertex buffer from some vertices and, optionally, indices, allocated
+    /// with the usage hint `usage`.
+    ///
+    /// Passing `None` leaves the index type to be inferred; use
+    /// [`VertexBuffer::from_vertices`] for buffers that never have indices.
+    pub fn new(vertices: &[V], indices: Option<&[I]>, usage: BufferUsage) -> Self
+    where
+        V: Clone + std::fmt::Debug,
+    {
+        Self::from_raw(
+            &Self::vertex_data(vertices),
+            vertices.len(),
+            indices,
+            usage,
+            false,
+        )
+    }
+
+    /// Creates a vertex buffer for a mesh whose vertices and indices never change.
+    ///
+    /// The buffers use immutable storage where supported, which lets the driver keep them in
+    /// the fastest memory. Static vertex buffers can't be updated.
+    pub fn new_static(vertices: &[V], indices: Option<&[I]>) -> Self
+    where
+        V: Clone,
+    {
+        Self::from_raw(
+            &Self::vertex_data(vertices),
+            vertices.len(),
+            indices,
+            BufferUsage::StaticDraw,
+            true,
+        )
+    }
+
+    /// Creates a vertex buffer with room for `vertex_capacity` vertices and, if
+    /// `index_capacity` is given, that many indices, with undefined contents.
+    ///
+    /// Meant for buffers filled on the GPU, e.g. by transform feedback or compute shaders, or
+    /// through streaming writes. The whole capacity counts as vertices (or indices), so use
+    /// [`VertexBuffer::slice`] to draw only the part that was filled.
+    pub fn empty(vertex_capacity: usize, index_capacity: Option<usize>) -> Self
+    where
+        V: Vertex,
+    {
+        let vertex_size = <V as Vertex>::get_vertex_spec().vertex_size();
+
+        let mut vao = 0;
+        unsafe { gl::GenVertexArrays(1, std::ptr::addr_of_mut!(vao)) };
+
+        let buffer = Self {
+            vao,
+            vertices: Buffer::empty(
+                BufferTarget::Array,
+                BufferUsage::DynamicDraw,
+                vertex_capacity * vertex_size,
+            ),
+            indices: index_capacity.map(|capacity| {
+                Buffer::empty(
+                    BufferTarget::ElementArray,
+                    BufferUsage::DynamicDraw,
+                    capacity,
+                )
+            }),
+            vertex_count: vertex_capacity,
+            is_static: false,
+            _phantom: PhantomData,
+        };
+        buffer.attach_indices();
+        buffer
+    }
+
+    fn from_raw(
+        vertex_data: &[u8],
+        vertex_count: usize,
+        indices: Option<&[I]>,
+        usage: BufferUsage,
+        is_static: bool,
+    ) -> Self {
+        let create = |target, data| {
+            if is_static {
+                Buffer::new_static(target, data)
+            } else {
+                Buffer::new(target, usage, data)
+            }
+        };
+        let create_indices = |indices| {
+            if is_static {
+                Buffer::new_static(BufferTarget::ElementArray, indices)
+            } else {
+                Buffer::new(BufferTarget::ElementArray, usage, indices)
+            }
+        };
+
+        let mut vao = 0;
+        unsafe { gl::GenVertexArrays(1, std::ptr::addr_of_mut!(vao)) };
+
+        let buffer = Self {
+            vao,
+            vertices: create(BufferTarget::Array, vertex_data),
+            indices: indices.map(create_indices),
+            vertex_count,
+            is_static,
+            _phantom: PhantomData,
+        };
+        buffer.attach_indices();
+        buffer
+    }
+
+    fn vertex_data(vertices: &[V]) -> Vec<u8>
+    where
+        V: Clone,
+    {
+        vertices
+            .iter()
+            .flat_map(|v| <V as Into<VertexData>>::into(v.clone()).data)
+            .collect::<Vec<_>>()
+    }
+
+    /// Records the index buffer in the vertex array's state
+    fn attach_indices(&self) {
+        let _binding = BindGuard::new(BindTarget::VertexArray, self.vao);
+        let ibo = self.indices.as_ref().map_or(0, Buffer::id);
+        unsafe { gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo) };
+    }
+
+    /// Binds all of the OpenGL buffers associated with the VertexBuffer
+    pub fn bind(&self) {
+        unsafe { gl::BindVertexArray(self.vao) };
+        self.vertices.bind();
+    }
+
+    /// Binds the vertex array and vertex buffer until the returned guard is dropped, after
+    /// which the previous bindings are restored.
+    ///
+    /// The index buffer is part of the vertex array's state, so it's restored as well.
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        BindGuard::with_targets([
+            Some((BindTarget::VertexArray, self.vao)),
+            Some((BindTarget::Buffer(BufferTarget::Array), self.vertices.id())),
+        ])
+    }
+
+    /// Returns a view of the vertices in `range`, or of the indices in `range` if the buffer
+    /// has indices, that can be drawn on its own.
+    ///
+    /// # Panics
+    /// This function panics if `range` extends past the end of the vertices (or indices).
+    pub fn slice(&self, range: Range<usize>) -> VertexBufferSlice<'_, V, I> {
+        let len = self.draw_count();
+        assert!(
+            range.start <= range.end && range.end <= len,
+            "Range {range:?} is out of bounds for a vertex buffer of {len} elements"
+        );
+
+        VertexBufferSlice {
+            buffer: self,
+            start: range.start,
+            len: range.len(),
+        }
+    }
+
+    /// Returns the number of indices if the buffer has any, and the number of vertices
+    /// otherwise
+    fn draw_count(&self) -> usize {
+        if self.has_indices() {
+            self.index_count()
+        } else {
+            self.vertex_count()
+        }
+    }
+
+    /// Returns the buffer holding the raw vertex data
+    pub fn vertex_buffer(&self) -> &Buffer<u8> {
+        &self.vertices
+    }
+
+    /// Returns the buffer holding the indices, if any
+    pub fn index_buffer(&self) -> Option<&Buffer<I>> {
+        self.indices.as_ref()
+    }
+
+    /// Downloads the vertices from the GPU and reconstructs them, e.g. for debugging or tests
+    pub fn read_vertices(&self) -> Vec<V>
+    where
+        V: FromVertexData,
+    {
+        if self.vertex_count == 0 {
+            return Vec::new();
+        }
+
+        let data = self.vertices.read();
+        let vertex_size = data.len() / self.vertex_count;
+        data.chunks_exact(vertex_size)
+            .take(self.vertex_count)
+            .map(V::from_vertex_data)
+            .collect()
+    }
+
+    /// Downloads the indices from the GPU
+    ///
+    /// # Returns
+    ///
+    /// The indices, or `None` if the buffer has none.
+    pub fn read_indices(&self) -> Option<Vec<I>> {
+        self.indices.as_ref().map(Buffer::read)
+    }
+
+    /// Returns the number of vertices in the VertexBuffer
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    /// Returns whether the buffer was created with [`VertexBuffer::new_static`]
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+
+    /// Returns the usage hint the buffers were allocated with
+    pub fn usage(&self) -> BufferUsage {
+        self.vertices.usage()
+    }
+
+    /// Returns whether the buffer contains an index buffer
+    pub fn has_indices(&self) -> bool {
+        self.indices.is_some()
+    }
+
+    /// Returns the OpenGL type of the indices, e.g. `GL_UNSIGNED_SHORT`
+    pub fn index_type(&self) -> u32 {
+        I::opengl_type()
+    }
+
+    /// Returns the number of indices in the VertexBuffer.
+    ///
+    /// # Returns
+    ///
+    /// The number of indices, or `0` if there are none.
+    pub fn index_count(&self) -> usize {
+        self.indices.as_ref().map_or(0, Buffer::len)
+    }
+
+    /// Updates the contents of a vertex buffer, reallocating it with the usage hint `usage`
+    /// if necessary.
+    /// This function will call [`VertexBuffer::replace`] when appropriate.
+    ///
+    /// # Panics
+    /// This function panics if the buffer was created with [`VertexBuffer::new_static`].
+    pub fn update_buffer(&mut self, vertices: &[V], indices: Option<&[I]>, usage: BufferUsage)
+    where
+        V: Clone,
+    {
+        assert!(!self.is_static(), "Tried to update a static vertex buffer");
+
+        if let Some(new_indices) = indices {
+            if self.usage() == usage
+                && self.index_count() == new_indices.len()
+                && self.has_indices()
+                && self.vertex_count() == vertices.len()
+            {
+                unsafe { self.replace(vertices, indices) };
+                return;
+            }
+        }
+
+        match (&mut self.indices, indices) {
+            (Some(buffer), Some(indices)) => {
+                buffer.usage = usage;
+                buffer.set_data(indices);
+            }
+            (None, Some(indices)) => {
+                diagnostic!(
+                    Debug,
+                    Resource,
+                    "Allocating new index buffer for existing vertex buffer"
+                );
+                self.indices = Some(Buffer::new(BufferTarget::ElementArray, usage, indices));
+                self.attach_indices();
+            }
+            (Some(_), None) => {
+                diagnostic!(
+                    Debug,
+                    Resource,
+                    "Deleting unused index buffer after update with no indices"
+                );
+                self.indices = None;
+                self.attach_indices();
+            }
+            (None, None) => {}
+        }
+
+        self.vertices.usage = usage;
+        self.vertices.set_data(&Self::vertex_data(vertices));
+        self.vertex_count = vertices.len();
+    }
+
+    /// Replaces the vertices by orphaning the vertex buffer's data store. See
+    /// [`Buffer::orphan_and_write`].
+    ///
+    /// Meant for vertices rewritten every frame, such as UI or particles. The index buffer, if
+    /// any, is left untouched.
+    ///
+    /// # Panics
+    /// This function panics if the buffer was created with [`VertexBuffer::new_static`].
+    pub fn orphan_and_write(&mut self, vertices: &[V])
+    where
+        V: Clone,
+    {
+        assert!(!self.is_static(), "Tried to update a static vertex buffer");
+
+        self.vertices.orphan_and_write(&Self::vertex_data(vertices));
+        self.vertex_count = vertices.len();
+    }
+
+    /// Replaces the contents of the buffer(s) without reallocating the buffer.
+    ///
+    /// # Safety
+    /// Callers of this function must ensure that the lengths and types of vertices and indices
+    /// are exactly equal to [`VertexBuffer::vertex_count`] and [`VertexBuffer::index_count`]
+    /// respectively.
+    ///
+    /// # Panics
+    /// This function panics if the supplied indices are None, but the buffer previously
+    /// contained indices and vice-versa, or if the buffer was created with
+    /// [`VertexBuffer::new_static`].
+    pub unsafe fn replace(&mut self, vertices: &[V], indices: Option<&[I]>)
+    where
+        V: Clone,
+    {
+        assert!(!self.is_static(), "Tried to update a static vertex buffer");
+        if indices.is_some() != self.has_indices() {
+            panic!("Expected to replace indices, but none were given.")
+        }
+
+        if let (Some(buffer), Some(indices)) = (&mut self.indices, indices) {
+            buffer.set_sub_data(0, indices);
+        }
+        self.vertices.set_sub_data(0, &Self::vertex_data(vertices));
+    }
+}
+
+/// A range of the vertices of a [`VertexBuffer`], or of its indices if it has any, obtained
+/// through [`VertexBuffer::slice`].
+///
+/// Every draw function accepts a slice in place of a whole buffer, so part of a large shared
+/// buffer can be drawn without copying it.
+#[derive(Debug)]
+pub struct VertexBufferSlice<'a, V, I = u32> {
+    buffer: &'a VertexBuffer<V, I>,
+    start: usize,
+    len: usize,
+}
+
+impl<V, I> Clone for VertexBufferSlice<'_, V, I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V, I> Copy for VertexBufferSlice<'_, V, I> {}
+
+impl<'a, V, I> From<&'a VertexBuffer<V, I>> for VertexBufferSlice<'a, V, I>
+where
+    V: Into<VertexData>,
+    I: IndexType,
+{
+    fn from(buffer: &'a VertexBuffer<V, I>) -> Self {
+        Self {
+            buffer,
+            start: 0,
+            len: buffer.draw_count(),
+        }
+    }
+}
+
+impl<'a, V, I> VertexBufferSlice<'a, V, I> {
+    /// Returns the buffer this slice is a part of
+    pub fn buffer(&self) -> &'a VertexBuffer<V, I> {
+        self.buffer
+    }
 
+    /// Returns the range of vertices (or indices) covered by the slice
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+
+    /// Returns the number of vertices (or indices) covered by the slice
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the slice covers no vertices
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<V, I> Drop for VertexBuffer<V, I> {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteVertexArrays(1, &self.vao) };
+    }
+}
+
+/// Indices of type `I` (`u8`, `u16` or `u32`) stored separately from any vertex buffer.
+///
+/// A single index buffer can be drawn with any number of [`VertexBuffer`]s through
+/// [`crate::Renderer::draw_indexed`], e.g. to share quad indices between sprite batches.
 #[derive(Debug)]
-pub struct VertexBuffer<V> {
-    vbo: u32,
-    vao: u32,
-    ibo: Option<u32>,
-    vertex_count: usize,
-    index_count: usize,
-    _phantom: PhantomData<V>,
+pub struct IndexBuffer<I = u32> {
+    buffer: Buffer<I>,
 }
 
-impl<V> Clone for VertexBuffer<V>
+impl<I> IndexBuffer<I>
 where
-    V: Into<VertexData>,
+    I: IndexType,
 {
-    fn clone(&self) -> Self {
-        let mut vertices: Vec<MaybeUninit<u8>> =
-            vec![MaybeUninit::uninit(); self.vertex_count * std::mem::size_of::<V>()];
-        let mut indices: Vec<MaybeUninit<u32>> = vec![MaybeUninit::uninit(); self.index_count];
-        self.bind();
+    /// Uploads `indices` into a new index buffer
+    pub fn new(indices: &[I]) -> Self {
+        Self {
+            buffer: Buffer::new(
+                BufferTarget::ElementArray,
+                BufferUsage::DynamicDraw,
+                indices,
+            ),
+        }
+    }
 
-        unsafe {
-            gl::GetBufferSubData(
-                gl::ARRAY_BUFFER,
-                0,
-                vertices.len() as isize,
-                vertices.as_mut_ptr().cast(),
-            );
-            if self.has_indices() {
-                gl::GetBufferSubData(
-                    gl::ELEMENT_ARRAY_BUFFER,
-                    0,
-                    (indices.len() * std::mem::size_of::<u32>()) as isize,
-                    indices.as_mut_ptr().cast(),
-                );
-            }
-        };
-        let vertices = vertices
-            .into_iter()
-            .map(|v| unsafe { v.assume_init() })
-            .collect::<Vec<_>>();
+    /// Replaces the contents of the buffer with `indices`, reallocating it
+    pub fn update(&mut self, indices: &[I]) {
+        self.buffer.set_data(indices);
+    }
 
-        let mut vao = 0;
-        unsafe {
-            gl::GenVertexArrays(1, std::ptr::addr_of_mut!(vao));
-            gl::BindVertexArray(vao);
-        };
+    /// Binds the buffer to `GL_ELEMENT_ARRAY_BUFFER` of the currently bound vertex array
+    /// until the returned guard is dropped
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        self.buffer.bind_scoped()
+    }
 
-        let mut ibo = None;
-        if self.has_indices() {
-            let indices = indices
-                .into_iter()
-                .map(|i| unsafe { i.assume_init() })
-                .collect::<Vec<_>>();
-            let mut ibo_id = 0;
-            ibo = Some(ibo_id);
-            unsafe {
-                gl::GenBuffers(1, std::ptr::addr_of_mut!(ibo_id));
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo_id);
-                gl::BufferData(
-                    gl::ELEMENT_ARRAY_BUFFER,
-                    (self.index_count * std::mem::size_of::<u32>()) as isize,
-                    indices.as_ptr().cast(),
-                    gl::DYNAMIC_DRAW,
-                );
-            };
-        }
+    /// Returns the underlying buffer
+    pub fn buffer(&self) -> &Buffer<I> {
+        &self.buffer
+    }
 
-        let mut vbo = 0;
-        unsafe {
-            gl::GenBuffers(1, std::ptr::addr_of_mut!(vbo));
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                vertices.len() as isize,
-                vertices.as_ptr().cast(),
-                gl::DYNAMIC_DRAW,
-            );
-        };
+    /// Returns the number of indices in the buffer
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
 
-        Self {
-            vbo,
-            vao,
-            ibo,
-            vertex_count: self.vertex_count,
-            index_count: self.index_count,
-            _phantom: PhantomData,
-        }
+    /// Returns whether the buffer contains no indices
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns the OpenGL type of the indices, e.g. `GL_UNSIGNED_SHORT`
+    pub fn index_type(&self) -> u32 {
+        I::opengl_type()
     }
 }
 
-impl<V> VertexBuffer<V>
+/// A uniform buffer holding a single value of type `T`, laid out according to `std140`.
+///
+/// Bind it to a binding point with [`UniformBuffer::bind_to`] and connect uniform blocks of
+/// any number of programs to the same point with
+/// [`crate::shader::LinkedProgram::bind_uniform_block`], so shared data such as camera
+/// matrices is uploaded once per frame rather than once per program.
+#[derive(Debug)]
+pub struct UniformBuffer<T> {
+    buffer: Buffer<u8>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> UniformBuffer<T>
 where
-    V: Into<VertexData>,
+    T: Std140,
 {
-    /// Creates a new vertex buffer from some vertices and, optionally, indices.
-    pub fn new(vertices: &[V], indices: Option<&[gl::types::GLuint]>) -> Self
-    where
-        V: Clone + std::fmt::Debug,
-    {
-        let mut id = 0;
-        let mut vao = 0;
-        let mut ibo = 0;
-        let vertex_data = vertices
-            .iter()
-            .flat_map(|v| <V as Into<VertexData>>::into(v.clone()).data)
-            .collect::<Vec<_>>();
+    /// Creates a uniform buffer holding `value`
+    pub fn new(value: &T) -> Self {
+        Self {
+            buffer: Buffer::new(
+                BufferTarget::Uniform,
+                BufferUsage::DynamicDraw,
+                &value.to_std140_bytes(),
+            ),
+            _phantom: PhantomData,
+        }
+    }
 
-        unsafe {
-            gl::GenVertexArrays(1, std::ptr::addr_of_mut!(vao));
-            gl::BindVertexArray(vao);
-            gl::GenBuffers(1, std::ptr::addr_of_mut!(id));
-            gl::BindBuffer(gl::ARRAY_BUFFER, id);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                vertex_data.len() as isize,
-                vertex_data.as_ptr_range().start.cast(),
-                gl::DYNAMIC_DRAW,
-            );
+    /// Replaces the value held by the buffer
+    pub fn set(&mut self, value: &T) {
+        self.buffer.set_sub_data(0, &value.to_std140_bytes());
+    }
 
-            if let Some(indices) = indices {
-                gl::GenBuffers(1, std::ptr::addr_of_mut!(ibo));
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo);
+    /// Binds the buffer to uniform buffer binding point `binding`
+    pub fn bind_to(&self, binding: u32) {
+        unsafe { gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, self.buffer.id()) };
+    }
 
-                gl::BufferData(
-                    gl::ELEMENT_ARRAY_BUFFER,
-                    std::mem::size_of_val(indices) as isize,
-                    indices.as_ptr_range().start.cast(),
-                    gl::DYNAMIC_DRAW,
-                )
-            }
-        };
+    /// Returns the underlying buffer
+    pub fn buffer(&self) -> &Buffer<u8> {
+        &self.buffer
+    }
+
+    /// Returns the size of the value in bytes
+    pub fn size(&self) -> usize {
+        self.buffer.size()
+    }
+}
+
+/// A shader storage buffer holding an array of `T`, e.g. the runtime-sized array of a
+/// `buffer` block read and written by compute shaders.
+///
+/// `T` is copied to the GPU as is, so it must match the `std430` layout of the GLSL array
+/// element, e.g. a `#[repr(C)]` struct with explicit padding.
+#[derive(Debug)]
+pub struct ShaderStorageBuffer<T> {
+    buffer: Buffer<T>,
+}
+
+impl<T> ShaderStorageBuffer<T>
+where
+    T: Copy,
+{
+    /// Creates a storage buffer holding a copy of `data`
+    pub fn new(data: &[T]) -> Self {
+        Self {
+            buffer: Buffer::new(BufferTarget::ShaderStorage, BufferUsage::DynamicCopy, data),
+        }
+    }
 
+    /// Creates a storage buffer with room for `len` elements with undefined contents, e.g. to
+    /// be filled by a shader
+    pub fn empty(len: usize) -> Self {
         Self {
-            vbo: id,
-            vertex_count: vertices.len(),
-            vao,
-            ibo: indices.as_ref().map(|_| ibo),
-            index_count: indices.map_or_else(|| 0, |indices| indices.len()),
-            _phantom: PhantomData,
+            buffer: Buffer::empty(BufferTarget::ShaderStorage, BufferUsage::DynamicCopy, len),
         }
     }
 
-    /// Binds all of the OpenGL buffers associated with the VertexBuffer
-    pub fn bind(&self) {
+    /// Binds the whole buffer to shader storage binding point `binding`
+    pub fn bind_to(&self, binding: u32) {
+        unsafe { gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, self.buffer.id()) };
+    }
+
+    /// Binds the elements in `range` to shader storage binding point `binding`.
+    ///
+    /// The start of the range must respect `GL_SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` extends past the end of the buffer.
+    pub fn bind_range_to(&self, binding: u32, range: Range<usize>) {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "Range {range:?} is out of bounds for a buffer of {} elements",
+            self.len()
+        );
+
+        let size = std::mem::size_of::<T>();
         unsafe {
-            gl::BindVertexArray(self.vao);
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-            if let Some(ref ibo) = self.ibo {
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, *ibo);
-            }
+            gl::BindBufferRange(
+                gl::SHADER_STORAGE_BUFFER,
+                binding,
+                self.buffer.id(),
+                (range.start * size) as isize,
+                (range.len() * size) as isize,
+            )
         };
     }
 
-    /// Returns the number of vertices in the VertexBuffer
-    pub fn vertex_count(&self) -> usize {
-        self.vertex_count
+    /// Returns the number of elements in the buffer
+    pub fn len(&self) -> usize {
+        self.buffer.len()
     }
 
-    /// Returns whether the buffer contains an index buffer
-    pub fn has_indices(&self) -> bool {
-        self.ibo.is_some()
+    /// Returns whether the buffer holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
     }
 
-    /// Returns the number of indices in the VertexBuffer.
+    /// Returns the underlying buffer
+    pub fn buffer(&self) -> &Buffer<T> {
+        &self.buffer
+    }
+
+    /// Replaces the contents of the buffer with `data`, reallocating it
     ///
-    /// # Returns
+    /// # Panics
     ///
-    /// The number of indices, or `0` if there are none.
-    pub fn index_count(&self) -> usize {
-        self.index_count
+    /// Panics if the buffer has immutable storage.
+    pub fn set_data(&mut self, data: &[T]) {
+        self.buffer.set_data(data);
     }
 
-    /// Updates the contents of a vertex buffer.
-    /// This function will call [`VertexBuffer::replace`] when appropriate.
-    pub fn update_buffer(&mut self, vertices: &[V], indices: Option<&[GLuint]>)
-    where
-        V: Clone,
-    {
-        self.bind();
-        #[allow(clippy::unwrap_used)]
-        if indices.is_some()
-            && self.has_indices()
-            && self.index_count() == indices.as_ref().unwrap().len()
-            && self.vertex_count() == vertices.len()
-        {
-            unsafe { self.replace(vertices, indices) };
-            return;
-        }
+    /// Overwrites the elements starting at `offset` with `data`. See [`Buffer::set_sub_data`].
+    pub fn set_sub_data(&mut self, offset: usize, data: &[T]) {
+        self.buffer.set_sub_data(offset, data);
+    }
+
+    /// Makes shader writes to storage buffers visible to shader reads issued afterwards, e.g.
+    /// between two compute dispatches
+    pub fn shader_barrier() {
+        unsafe { gl::MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT) };
+    }
+
+    /// Makes shader writes to storage buffers visible to reads and mappings from the CPU
+    pub fn update_barrier() {
+        unsafe { gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT) };
+    }
+}
+
+impl<T: Pod> ShaderStorageBuffer<T> {
+    /// Copies the contents of the buffer back to the CPU, including the results of earlier
+    /// shader writes
+    pub fn read(&self) -> Vec<T> {
+        Self::update_barrier();
+        self.buffer.read()
+    }
+
+    /// Starts copying the contents of the buffer back to the CPU, including the results of
+    /// earlier shader writes, without stalling. See [`Buffer::read_async`].
+    pub fn read_async(&self) -> PendingBufferRead<T> {
+        Self::update_barrier();
+        self.buffer.read_async()
+    }
+
+    /// Maps the buffer for reading, including the results of earlier shader writes. See
+    /// [`Buffer::map`].
+    pub fn map(&self) -> Option<BufferMap<'_, T>> {
+        Self::update_barrier();
+        self.buffer.map()
+    }
 
-        if indices.is_some() && !self.has_indices() {
-            log::debug!("Allocating new index buffer for existing vertex buffer");
-            let mut ibo = 0;
-            unsafe {
-                gl::GenBuffers(1, &mut ibo);
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo);
-            };
-            self.ibo = Some(ibo);
-            self.index_count = 0;
+    /// Maps the buffer for reading and writing, including the results of earlier shader
+    /// writes. See [`Buffer::map_mut`].
+    pub fn map_mut(&mut self) -> Option<BufferMapMut<'_, T>> {
+        Self::update_barrier();
+        self.buffer.map_mut()
+    }
+}
+
+/// A buffer that stays mapped for its whole lifetime and is written in a ring of regions, for
+/// streaming data such as dynamic vertices to the GPU every frame without stalls.
+///
+/// The buffer is split into `regions` regions of `region_len` elements. Each frame, the
+/// current region is filled through [`StreamingBuffer::region_mut`] and drawn from, after which
+/// [`StreamingBuffer::advance`] fences it and moves on to the next region. A region is only
+/// written again once the GPU has passed its fence, so data still being read is never
+/// overwritten. Three regions are usually enough to never wait.
+///
+/// Requires OpenGL 4.4 or `GL_ARB_buffer_storage`.
+#[derive(Debug)]
+pub struct StreamingBuffer<T: Pod> {
+    buffer: Buffer<T>,
+    data: *mut T,
+    region_len: usize,
+    current: usize,
+    fences: Vec<Option<Fence>>,
+}
+
+impl<T> StreamingBuffer<T>
+where
+    T: Pod,
+{
+    /// Allocates a persistently mapped buffer of `regions` regions with `region_len`
+    /// elements each, used through `target`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `region_len` or `regions` is `0`.
+    pub fn new(
+        target: BufferTarget,
+        region_len: usize,
+        regions: usize,
+    ) -> Result<Self, Unsupported> {
+        assert!(
+            region_len > 0 && regions > 0,
+            "A streaming buffer needs at least one region of at least one element"
+        );
+
+        let flags =
+            StorageFlags::MAP_WRITE | StorageFlags::MAP_PERSISTENT | StorageFlags::MAP_COHERENT;
+        let buffer = Buffer::immutable_empty(target, flags, region_len * regions)?;
+        let Some(data) = buffer.map_raw(0..buffer.len(), flags.into()) else {
+            return Err(Unsupported {
+                feature: "persistent buffer mapping",
+            });
+        };
+        // Storage allocated without data is undefined, and regions are handed out as slices
+        unsafe { std::ptr::write_bytes(data, 0, buffer.len()) };
+
+        Ok(Self {
+            buffer,
+            data,
+            region_len,
+            current: 0,
+            fences: (0..regions).map(|_| None).collect(),
+        })
+    }
+
+    /// Returns the current region for writing, first waiting until the GPU has finished the
+    /// commands submitted before the region was last fenced.
+    ///
+    /// Writes become visible to commands issued afterwards without any flushing. Elements are
+    /// zeroed until first written, and keep whatever was written to the region before.
+    pub fn region_mut(&mut self) -> &mut [T] {
+        if let Some(fence) = self.fences[self.current].take() {
+            fence.wait();
         }
 
-        if let Some(ref ibo) = self.ibo {
-            if let Some(indices) = indices {
-                unsafe {
-                    gl::BufferData(
-                        gl::ELEMENT_ARRAY_BUFFER,
-                        std::mem::size_of_val(indices) as isize,
-                        indices.as_ptr_range().start.cast(),
-                        gl::DYNAMIC_DRAW,
-                    );
-                }
-
-                self.index_count = indices.len();
-            } else {
-                log::debug!("Deleting unused index buffer after update with no indices");
-                unsafe { gl::DeleteBuffers(1, ibo) };
-                self.ibo = None;
-                self.index_count = 0;
-            }
+        unsafe {
+            std::slice::from_raw_parts_mut(self.data.add(self.region_offset()), self.region_len)
         }
+    }
 
-        let vertex_data = vertices
-            .iter()
-            .flat_map(|v| <V as Into<VertexData>>::into(v.clone()).data)
-            .collect::<Vec<_>>();
+    /// Fences the current region, so that it isn't written again before the commands issued
+    /// so far have completed, and moves on to the next region.
+    ///
+    /// Call this after the last draw reading from the current region, usually once per frame.
+    pub fn advance(&mut self) {
+        self.fences[self.current] = Some(Fence::new());
+        self.current = (self.current + 1) % self.fences.len();
+    }
+
+    /// Returns the offset of the current region from the start of the buffer, in elements,
+    /// e.g. to use as the base vertex of draws reading from it
+    pub fn region_offset(&self) -> usize {
+        self.current * self.region_len
+    }
+
+    /// Returns the index of the current region
+    pub fn region(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the number of elements in each region
+    pub fn region_len(&self) -> usize {
+        self.region_len
+    }
 
+    /// Returns the number of regions
+    pub fn regions(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// Binds the current region to indexed binding point `binding` of a uniform or shader
+    /// storage block.
+    ///
+    /// The size of a region must respect `GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT` or
+    /// `GL_SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT` for every region to be bindable.
+    pub fn bind_region_to(&self, binding: u32) {
+        let size = std::mem::size_of::<T>();
         unsafe {
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                vertex_data.len() as isize,
-                vertex_data.as_ptr_range().start.cast(),
-                gl::DYNAMIC_DRAW,
-            );
+            gl::BindBufferRange(
+                self.buffer.target().into(),
+                binding,
+                self.buffer.id(),
+                (self.region_offset() * size) as isize,
+                (self.region_len * size) as isize,
+            )
         };
-        self.vertex_count = vertices.len();
     }
 
-    /// Replaces the contents of the buffer(s) without reallocating the buffer.
-    ///
-    /// # Safety
-    /// Callers of this function must ensure that the lengths and types of vertices and indices
-    /// are exactly equal to [`VertexBuffer::vertex_count`] and [`VertexBuffer::index_count`]
-    /// respectively.
-    ///
-    /// # Panics
-    /// This function panics if the supplied indices are None, but the buffer previously
-    /// contained indices and vice-versa.
-    pub unsafe fn replace(&mut self, vertices: &[V], indices: Option<&[GLuint]>)
-    where
-        V: Clone,
-    {
-        if indices.is_some() != self.has_indices() {
-            panic!("Expected to replace indices, but none were given.")
+    /// Returns the underlying buffer, e.g. to bind it as a vertex attribute source
+    pub fn buffer(&self) -> &Buffer<T> {
+        &self.buffer
+    }
+}
+
+impl<T: Pod> Drop for StreamingBuffer<T> {
+    fn drop(&mut self) {
+        self.buffer.unmap();
+    }
+}
+
+/// A buffer that receives pixel data from the GPU, used for asynchronous read-back.
+///
+/// See [`crate::Renderer::start_read`].
+#[derive(Debug)]
+pub struct PixelPackBuffer {
+    buffer: Buffer<u8>,
+}
+
+impl PixelPackBuffer {
+    /// Allocates a pixel pack buffer of `size` bytes
+    pub fn new(size: usize) -> Self {
+        Self {
+            buffer: Buffer::empty(BufferTarget::PixelPack, BufferUsage::StreamRead, size),
         }
+    }
 
-        self.bind();
-        if let Some(indices) = indices {
-            // We know from the previous if statement that we have an IBO
-            #[allow(clippy::unwrap_used)]
-            gl::BufferSubData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                0,
-                std::mem::size_of_val(indices) as isize,
-                indices.as_ptr_range().start.cast(),
-            )
+    /// Binds the buffer to `GL_PIXEL_PACK_BUFFER`
+    pub fn bind(&self) {
+        self.buffer.bind();
+    }
+
+    /// Binds the buffer to `GL_PIXEL_PACK_BUFFER` until the returned guard is dropped
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        self.buffer.bind_scoped()
+    }
+
+    /// Returns the size of the buffer in bytes
+    pub fn size(&self) -> usize {
+        self.buffer.size()
+    }
+}
+
+/// A pixel read-back into a [`PixelPackBuffer`] that may not have completed yet
+#[derive(Debug)]
+pub struct PendingRead<'a> {
+    buffer: &'a PixelPackBuffer,
+    fence: Fence,
+    len: usize,
+}
+
+impl<'a> PendingRead<'a> {
+    pub(crate) fn new(buffer: &'a PixelPackBuffer, len: usize) -> Self {
+        Self {
+            buffer,
+            fence: Fence::new(),
+            len,
         }
-        let vertex_data = vertices
-            .iter()
-            .flat_map(|v| <V as Into<VertexData>>::into(v.clone()).data)
-            .collect::<Vec<_>>();
-        gl::BufferSubData(
-            gl::ARRAY_BUFFER,
-            0,
-            vertex_data.len() as isize,
-            vertex_data.as_ptr_range().start.cast(),
-        );
+    }
+
+    /// Returns whether the transfer has completed, so that [`PendingRead::finish_read`]
+    /// won't block
+    pub fn is_ready(&self) -> bool {
+        self.fence.is_signaled()
+    }
+
+    /// Waits for the transfer to complete and copies the pixels out of the buffer.
+    ///
+    /// # Returns
+    ///
+    /// The pixels, or `None` if the buffer couldn't be mapped.
+    pub fn finish_read(self) -> Option<Vec<u8>> {
+        self.fence.wait();
+        self.buffer
+            .buffer
+            .map()
+            .map(|mapped| mapped[..self.len].to_vec())
     }
 }
 
-impl<V> Drop for VertexBuffer<V> {
-    fn drop(&mut self) {
-        unsafe { gl::DeleteBuffers(1, &self.vbo) };
-        if let Some(ref ibo) = self.ibo {
-            unsafe { gl::DeleteBuffers(1, ibo) };
+/// A copy of a buffer's contents in flight to the CPU, started by [`Buffer::read_async`].
+///
+/// The read owns its copy, so the source buffer can be written to or dropped in the meantime.
+pub struct PendingBufferRead<T> {
+    staging: Buffer<T>,
+    fence: Fence,
+}
+
+impl<T> PendingBufferRead<T>
+where
+    T: Pod,
+{
+    /// Returns whether the copy has completed, so that [`PendingBufferRead::finish`] won't
+    /// block
+    pub fn is_ready(&self) -> bool {
+        self.fence.is_signaled()
+    }
+
+    /// Returns the copied elements if the copy has completed, or the read itself to poll
+    /// again later
+    pub fn try_finish(self) -> Result<Vec<T>, Self> {
+        if self.is_ready() {
+            Ok(self.finish())
+        } else {
+            Err(self)
         }
     }
+
+    /// Waits for the copy to complete and returns the copied elements
+    pub fn finish(self) -> Vec<T> {
+        self.fence.wait();
+        self.staging.map_read(<[T]>::to_vec).unwrap_or_default()
+    }
 }
 
 /// A container for raw vertex data
@@ -296,6 +1629,46 @@ pub struct VertexData {
     pub data: Vec<u8>,
 }
 
+/// The counterpart of `Into<VertexData>`, reconstructing a vertex from the bytes it was
+/// converted to, e.g. when reading a [`VertexBuffer`] back
+pub trait FromVertexData: Sized {
+    /// Reconstructs a vertex from `data`, which holds the bytes of exactly one vertex
+    fn from_vertex_data(data: &[u8]) -> Self;
+}
+
+/// Reads a field of type `T` at `offset` in `data` and advances `offset` past it. Used by
+/// `#[derive(Vertex)]`.
+///
+/// # Safety
+/// The bytes at `offset` must be a valid value of `T`, which holds as long as they were
+/// written from a `T` by its `Into<VertexData>` implementation.
+///
+/// # Panics
+/// This function panics if `data` ends before the field does.
+#[doc(hidden)]
+pub unsafe fn read_vertex_field<T: Copy>(data: &[u8], offset: &mut usize) -> T {
+    let end = *offset + std::mem::size_of::<T>();
+    assert!(
+        end <= data.len(),
+        "Vertex data ended in the middle of a field"
+    );
+
+    let value = std::ptr::read_unaligned(data[*offset..end].as_ptr().cast::<T>());
+    *offset = end;
+    value
+}
+
+/// Splits tightly packed native-endian floats into an array
+pub(crate) fn read_floats<const N: usize>(data: &[u8]) -> [f32; N] {
+    let mut floats = [0.0; N];
+    for (float, bytes) in floats.iter_mut().zip(data.chunks_exact(4)) {
+        #[allow(clippy::unwrap_used)]
+        let bytes = bytes.try_into().unwrap();
+        *float = f32::from_ne_bytes(bytes);
+    }
+    floats
+}
+
 impl From<glm::Vec2> for VertexData {
     fn from(value: glm::Vec2) -> Self {
         Self {
@@ -308,16 +1681,44 @@ impl From<glm::Vec2> for VertexData {
     }
 }
 
+impl FromVertexData for glm::Vec2 {
+    fn from_vertex_data(data: &[u8]) -> Self {
+        let [x, y] = read_floats(data);
+        glm::vec2(x, y)
+    }
+}
+
 impl Vertex for glm::Vec2 {
     fn get_vertex_spec() -> crate::shader::VertexAttributeSpec {
-        VertexAttributeSpec {
-            layouts: vec![(
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                2 * std::mem::size_of::<f32>() as i32,
-                0,
-            )],
+        VertexAttributeSpec::builder()
+            .attribute(VertexFormat::Float32x2)
+            .build()
+    }
+}
+
+impl From<glm::Vec3> for VertexData {
+    fn from(value: glm::Vec3) -> Self {
+        Self {
+            data: value
+                .as_array()
+                .iter()
+                .flat_map(|f| f.to_ne_bytes())
+                .collect::<Vec<_>>(),
         }
     }
 }
+
+impl FromVertexData for glm::Vec3 {
+    fn from_vertex_data(data: &[u8]) -> Self {
+        let [x, y, z] = read_floats(data);
+        glm::vec3(x, y, z)
+    }
+}
+
+impl Vertex for glm::Vec3 {
+    fn get_vertex_spec() -> crate::shader::VertexAttributeSpec {
+        VertexAttributeSpec::builder()
+            .attribute(VertexFormat::Float32x3)
+            .build()
+    }
+}
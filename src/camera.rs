@@ -0,0 +1,69 @@
+use crate::{
+    matrix::{self, Ray},
+    renderer::{ClipDepth, Rect},
+};
+use glm::{GenSquareMat, Mat4, Vec2, Vec3};
+
+/// A viewpoint described by a view and a projection matrix, with conversions between screen
+/// and world space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    /// Transforms world space into view space
+    pub view: Mat4,
+    /// Transforms view space into clip space
+    pub projection: Mat4,
+    /// The clip-space depth convention of `projection`. See [`crate::Renderer::clip_control`].
+    pub clip_depth: ClipDepth,
+}
+
+impl Camera {
+    /// Creates a camera using the default OpenGL depth convention
+    pub fn new(view: Mat4, projection: Mat4) -> Self {
+        Self {
+            view,
+            projection,
+            clip_depth: ClipDepth::default(),
+        }
+    }
+
+    /// Returns the combined view and projection matrix
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection * self.view
+    }
+
+    /// Returns the position of the camera in world space, or `None` if the view matrix isn't
+    /// invertible
+    pub fn position(&self) -> Option<Vec3> {
+        let origin = self.view.inverse()?[3];
+        Some(glm::vec3(origin.x, origin.y, origin.z))
+    }
+
+    /// Returns the ray from the camera through `cursor`, in pixels from the top left of a
+    /// surface of `size` pixels covered by the camera's viewport, e.g. the cursor position
+    /// reported by the windowing system
+    pub fn screen_to_ray(&self, cursor: Vec2, size: (u32, u32)) -> Option<Ray> {
+        let (window, viewport) = Self::to_window(cursor, size);
+        matrix::ray_through(window, self.view_projection(), viewport, self.clip_depth)
+    }
+
+    /// Returns the world-space position of the point at `cursor` whose depth buffer value is
+    /// `depth`. See [`Camera::screen_to_ray`] and [`matrix::unproject`].
+    pub fn screen_to_world(&self, cursor: Vec2, depth: f32, size: (u32, u32)) -> Option<Vec3> {
+        let (window, viewport) = Self::to_window(cursor, size);
+        matrix::unproject(
+            glm::vec3(window.x, window.y, depth),
+            self.view_projection(),
+            viewport,
+            self.clip_depth,
+        )
+    }
+
+    /// Converts a top-left based cursor position into window coordinates and the viewport
+    /// covering the surface
+    fn to_window(cursor: Vec2, (width, height): (u32, u32)) -> (Vec2, Rect) {
+        (
+            glm::vec2(cursor.x, height as f32 - cursor.y),
+            Rect::new(0, 0, width, height),
+        )
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{
+    buffer::{ShaderStorageBuffer, UniformBuffer},
+    compute::{ComputeProgram, ComputeProgramError},
+    context::Unsupported,
+    diagnostics::diagnostic,
+    layout::{pad_to, Std140},
+    light::{self, GpuLight, Light},
+    uniforms,
+};
+use glm::{Mat4, Vec2};
+
+/// The uniform buffer binding point of the cluster parameters. See [`ClusteredLights::GLSL`].
+pub const CLUSTER_BINDING: u32 = 14;
+
+/// The shader storage binding point of the light range of every cluster
+pub const CLUSTER_RANGE_BINDING: u32 = 13;
+
+/// The shader storage binding point of the light indices referenced by the cluster ranges
+pub const CLUSTER_INDEX_BINDING: u32 = 14;
+
+/// The cluster parameters as laid out in the `Clusters` uniform block
+#[derive(Debug, Copy, Clone)]
+struct ClusterParameters {
+    view: Mat4,
+    inverse_projection: Mat4,
+    grid: glm::UVec3,
+    near: f32,
+    far: f32,
+    max_lights_per_cluster: u32,
+    light_count: u32,
+    screen_size: Vec2,
+}
+
+impl Std140 for ClusterParameters {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 176;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        self.view.write_std140(out);
+        self.inverse_projection.write_std140(out);
+        self.grid.write_std140(out);
+        self.near.write_std140(out);
+        self.far.write_std140(out);
+        self.max_lights_per_cluster.write_std140(out);
+        self.light_count.write_std140(out);
+        pad_to(out, start + 160);
+        self.screen_size.write_std140(out);
+        pad_to(out, start + Self::SIZE);
+    }
+}
+
+/// Assigns lights to the cells of a view-space grid on the GPU, so that forward-shaded
+/// fragments only evaluate the lights that can reach them.
+///
+/// The view frustum is divided into `x * y` screen tiles and `z` depth slices, which get
+/// thinner towards the camera. Every frame, [`ClusteredLights::update`] runs a compute pass
+/// that tests every light against every cluster, after which fragment shaders including
+/// [`ClusteredLights::GLSL`] shade with `clustered_lighting`.
+///
+/// Clusters are built for perspective projections only.
+pub struct ClusteredLights {
+    grid: (u32, u32, u32),
+    max_lights_per_cluster: u32,
+    program: ComputeProgram,
+    lights: ShaderStorageBuffer<GpuLight>,
+    ranges: ShaderStorageBuffer<[u32; 2]>,
+    indices: ShaderStorageBuffer<u32>,
+    parameters: Option<UniformBuffer<ClusterParameters>>,
+}
+
+impl ClusteredLights {
+    /// The cluster buffers and a `clustered_lighting` function, to be inserted after the
+    /// `#version` directive and [`Light::GLSL`] of a fragment shader.
+    ///
+    /// `clustered_lighting(albedo, specular, position, normal, camera_position, frag_coord)`
+    /// returns the summed contribution of the lights in the fragment's cluster, where
+    /// `position` and `normal` are in world space and `frag_coord` is `gl_FragCoord.xy`.
+    pub const GLSL: &'static str = r#"
+        layout(std140, binding = 14) uniform Clusters {
+            mat4 cluster_view;
+            mat4 cluster_inverse_projection;
+            uvec3 cluster_grid;
+            float cluster_near;
+            float cluster_far;
+            uint cluster_max_lights;
+            uint cluster_light_count;
+            vec2 cluster_screen_size;
+        };
+
+        layout(std430, binding = 13) buffer ClusterRanges {
+            uvec2 cluster_ranges[];
+        };
+
+        layout(std430, binding = 14) buffer ClusterIndices {
+            uint cluster_light_indices[];
+        };
+
+        uint cluster_index(vec2 frag_coord, float view_depth) {
+            vec2 tiles = vec2(cluster_grid.xy);
+            uvec2 tile = uvec2(clamp(frag_coord / cluster_screen_size * tiles, vec2(0.0), tiles - 1.0));
+            float slice = log(max(view_depth, cluster_near) / cluster_near)
+                / log(cluster_far / cluster_near) * float(cluster_grid.z);
+            uint z = uint(clamp(slice, 0.0, float(cluster_grid.z) - 1.0));
+            return tile.x + cluster_grid.x * (tile.y + cluster_grid.y * z);
+        }
+
+        vec3 clustered_lighting(
+            vec3 albedo,
+            float specular,
+            vec3 position,
+            vec3 normal,
+            vec3 camera_position,
+            vec2 frag_coord
+        ) {
+            float view_depth = -(cluster_view * vec4(position, 1.0)).z;
+            uvec2 range = cluster_ranges[cluster_index(frag_coord, view_depth)];
+            vec3 view = normalize(camera_position - position);
+
+            vec3 result = vec3(0.0);
+            for (uint i = 0u; i < range.y; i++) {
+                Light light = lights[cluster_light_indices[range.x + i]];
+                result += evaluate_light(light, albedo, specular, position, normal, view);
+            }
+            return result;
+        }
+    "#;
+
+    /// The body of the light assignment shader, which follows the `#version` directive,
+    /// [`Light::GLSL`] and [`ClusteredLights::GLSL`]
+    const ASSIGNMENT_SHADER: &'static str = r#"
+        layout(local_size_x = 64) in;
+
+        vec3 near_plane_point(vec2 ndc) {
+            vec4 point = cluster_inverse_projection * vec4(ndc, -1.0, 1.0);
+            return point.xyz / point.w;
+        }
+
+        float slice_depth(uint slice) {
+            return cluster_near * pow(cluster_far / cluster_near, float(slice) / float(cluster_grid.z));
+        }
+
+        void main() {
+            uint index = gl_GlobalInvocationID.x;
+            if (index >= cluster_grid.x * cluster_grid.y * cluster_grid.z) {
+                return;
+            }
+
+            uvec3 cell = uvec3(
+                index % cluster_grid.x,
+                index / cluster_grid.x % cluster_grid.y,
+                index / (cluster_grid.x * cluster_grid.y)
+            );
+            vec2 tile_min = vec2(cell.xy) / vec2(cluster_grid.xy) * 2.0 - 1.0;
+            vec2 tile_max = vec2(cell.xy + 1u) / vec2(cluster_grid.xy) * 2.0 - 1.0;
+            float depths[2] = float[2](slice_depth(cell.z), slice_depth(cell.z + 1u));
+            vec3 corners[4] = vec3[4](
+                near_plane_point(tile_min),
+                near_plane_point(vec2(tile_max.x, tile_min.y)),
+                near_plane_point(vec2(tile_min.x, tile_max.y)),
+                near_plane_point(tile_max)
+            );
+
+            // Bounds of the rays through the tile corners between the slice's depths
+            vec3 bounds_min = vec3(1e30);
+            vec3 bounds_max = vec3(-1e30);
+            for (int corner = 0; corner < 4; corner++) {
+                for (int depth = 0; depth < 2; depth++) {
+                    vec3 point = corners[corner] * (depths[depth] / -corners[corner].z);
+                    bounds_min = min(bounds_min, point);
+                    bounds_max = max(bounds_max, point);
+                }
+            }
+
+            uint offset = index * cluster_max_lights;
+            uint count = 0u;
+            for (uint i = 0u; i < cluster_light_count && count < cluster_max_lights; i++) {
+                Light light = lights[i];
+                bool reaches = true;
+                if (light.direction_kind.w != 0.0) {
+                    vec3 center = (cluster_view * vec4(light.position_radius.xyz, 1.0)).xyz;
+                    vec3 closest = clamp(center, bounds_min, bounds_max);
+                    vec3 distance = closest - center;
+                    reaches = dot(distance, distance) <= light.position_radius.w * light.position_radius.w;
+                }
+
+                if (reaches) {
+                    cluster_light_indices[offset + count] = i;
+                    count++;
+                }
+            }
+            cluster_ranges[index] = uvec2(offset, count);
+        }
+    "#;
+
+    /// Creates clusters dividing the view frustum into `grid` (tiles across, tiles down,
+    /// depth slices), each holding at most `max_lights_per_cluster` lights.
+    ///
+    /// A grid of `(16, 9, 24)` with 64 lights per cluster is a good starting point for
+    /// hundreds of lights.
+    ///
+    /// # Panics
+    /// This function panics if any dimension of `grid` or `max_lights_per_cluster` is `0`.
+    pub fn new(grid: (u32, u32, u32), max_lights_per_cluster: u32) -> Result<Self, Unsupported> {
+        assert!(
+            grid.0 > 0 && grid.1 > 0 && grid.2 > 0 && max_lights_per_cluster > 0,
+            "Clusters need at least one cell and room for one light"
+        );
+        let program = match ComputeProgram::new(format!(
+            "#version 460 core\n{}{}{}",
+            Light::GLSL,
+            Self::GLSL,
+            Self::ASSIGNMENT_SHADER
+        )) {
+            Ok(program) => program,
+            Err(ComputeProgramError::Unsupported(error)) => return Err(error),
+            Err(ComputeProgramError::Compilation(error)) => {
+                panic!("Built-in light assignment shader failed to compile: {error}")
+            }
+        };
+
+        let cluster_count = (grid.0 * grid.1 * grid.2) as usize;
+        Ok(Self {
+            grid,
+            max_lights_per_cluster,
+            program,
+            lights: ShaderStorageBuffer::new(&[GpuLight::default()]),
+            ranges: ShaderStorageBuffer::empty(cluster_count),
+            indices: ShaderStorageBuffer::empty(cluster_count * max_lights_per_cluster as usize),
+            parameters: None,
+        })
+    }
+
+    /// Assigns `lights` to the clusters of the frustum described by `view` and the
+    /// perspective `projection` with the clip planes `near` and `far`, for a target of
+    /// `screen_size` pixels, and binds the results for the following draws.
+    ///
+    /// Lights beyond a cluster's capacity are dropped from that cluster.
+    ///
+    /// # Panics
+    /// This function panics if `projection` isn't invertible.
+    pub fn update(
+        &mut self,
+        lights: &[Light],
+        view: Mat4,
+        projection: Mat4,
+        (near, far): (f32, f32),
+        screen_size: (u32, u32),
+    ) {
+        light::upload(&mut self.lights, lights);
+
+        let parameters = ClusterParameters {
+            view,
+            inverse_projection: glm::inverse(&projection),
+            grid: glm::uvec3(self.grid.0, self.grid.1, self.grid.2),
+            near,
+            far,
+            max_lights_per_cluster: self.max_lights_per_cluster,
+            light_count: lights.len() as u32,
+            screen_size: glm::vec2(screen_size.0 as f32, screen_size.1 as f32),
+        };
+        match self.parameters {
+            Some(ref mut buffer) => buffer.set(&parameters),
+            None => self.parameters = Some(UniformBuffer::new(&parameters)),
+        }
+        self.bind();
+
+        let invocations = (self.cluster_count() as u32, 1, 1);
+        if let Err(error) = self.program.dispatch_invocations(invocations, &uniforms!()) {
+            diagnostic!(Error, Draw, "Failed to assign lights to clusters: {error}");
+        }
+        ShaderStorageBuffer::<u32>::shader_barrier();
+    }
+
+    /// Binds the lights and clusters of the last [`ClusteredLights::update`], e.g. after other
+    /// buffers were bound to the same binding points
+    pub fn bind(&self) {
+        self.lights.bind_to(light::LIGHT_BINDING);
+        self.ranges.bind_to(CLUSTER_RANGE_BINDING);
+        self.indices.bind_to(CLUSTER_INDEX_BINDING);
+        if let Some(ref parameters) = self.parameters {
+            parameters.bind_to(CLUSTER_BINDING);
+        }
+    }
+
+    /// Returns the number of tiles across, tiles down and depth slices
+    pub fn grid(&self) -> (u32, u32, u32) {
+        self.grid
+    }
+
+    /// Returns the total number of clusters
+    pub fn cluster_count(&self) -> usize {
+        (self.grid.0 * self.grid.1 * self.grid.2) as usize
+    }
+
+    /// Returns the maximum number of lights assigned to a single cluster
+    pub fn max_lights_per_cluster(&self) -> u32 {
+        self.max_lights_per_cluster
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    buffer::VertexBuffer,
+    draw_parameters::DrawParameters,
+    renderer::{DrawError, DrawMode, Renderer},
+    shader::{LinkedProgram, Vertex},
+    types::IndexType,
+    uniforms::{Uniform, UniformValue, Uniforms},
+};
+use glm::Vec4;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// The id of the next object added to any [`Resources`], so that a handle can't refer to an
+/// object of another registry that happens to have the same id
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The shared part of a handle. Queues its resource for deletion once the last clone of the
+/// handle is dropped.
+#[derive(Debug)]
+struct HandleInner {
+    id: u64,
+    released: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Drop for HandleInner {
+    fn drop(&mut self) {
+        if let Ok(mut released) = self.released.lock() {
+            released.push(self.id);
+        }
+    }
+}
+
+/// A cheap, thread-safe reference to a [`LinkedProgram`] owned by [`Resources`]
+#[derive(Debug, Clone)]
+pub struct ProgramHandle(Arc<HandleInner>);
+
+/// A cheap, thread-safe reference to a [`VertexBuffer`] owned by [`Resources`]
+#[derive(Debug, Clone)]
+pub struct BufferHandle(Arc<HandleInner>);
+
+/// A vertex buffer with its vertex and index types erased, so buffers of different types can
+/// be stored together
+trait ErasedVertexBuffer {
+    fn draw(
+        &self,
+        renderer: &Renderer,
+        program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError>;
+}
+
+impl<V, I> ErasedVertexBuffer for VertexBuffer<V, I>
+where
+    V: Vertex,
+    I: IndexType,
+{
+    fn draw(
+        &self,
+        renderer: &Renderer,
+        program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError> {
+        renderer.draw_with_parameters(self, program, mode, uniforms, parameters)
+    }
+}
+
+/// Owns the OpenGL objects referenced by handles and executes [`CommandList`]s.
+///
+/// `Resources` lives on the thread owning the OpenGL context. Handles to its objects can be
+/// cloned and sent to other threads, which record command lists that are submitted back here.
+/// An object is deleted once every handle to it has been dropped and the registry next
+/// submits a list or collects garbage.
+pub struct Resources {
+    programs: HashMap<u64, LinkedProgram>,
+    buffers: HashMap<u64, Box<dyn ErasedVertexBuffer>>,
+    released: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Default for Resources {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resources {
+    /// Constructs an empty registry
+    pub fn new() -> Self {
+        Self {
+            programs: HashMap::new(),
+            buffers: HashMap::new(),
+            released: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    fn next_handle(&mut self) -> Arc<HandleInner> {
+        Arc::new(HandleInner {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            released: Arc::clone(&self.released),
+        })
+    }
+
+    /// Returns whether `handle` refers to an object of this registry
+    fn owns(&self, handle: &HandleInner) -> bool {
+        Arc::ptr_eq(&handle.released, &self.released)
+    }
+
+    /// Takes ownership of `program`, returning a handle to it
+    pub fn add_program(&mut self, program: LinkedProgram) -> ProgramHandle {
+        let handle = self.next_handle();
+        self.programs.insert(handle.id, program);
+        ProgramHandle(handle)
+    }
+
+    /// Takes ownership of `buffer`, returning a handle to it
+    pub fn add_vertex_buffer<V, I>(&mut self, buffer: VertexBuffer<V, I>) -> BufferHandle
+    where
+        V: Vertex + 'static,
+        I: IndexType + 'static,
+    {
+        let handle = self.next_handle();
+        self.buffers.insert(handle.id, Box::new(buffer));
+        BufferHandle(handle)
+    }
+
+    /// Returns the program behind `handle`, e.g. to resolve uniform locations
+    ///
+    /// # Returns
+    ///
+    /// The program, or `None` if `handle` belongs to another registry.
+    pub fn program(&self, handle: &ProgramHandle) -> Option<&LinkedProgram> {
+        self.owns(&handle.0)
+            .then(|| self.programs.get(&handle.0.id))
+            .flatten()
+    }
+
+    /// Deletes every object whose handles have all been dropped
+    pub fn collect_garbage(&mut self) {
+        let released = match self.released.lock() {
+            Ok(mut released) => std::mem::take(&mut *released),
+            Err(_) => return,
+        };
+
+        for id in released {
+            self.programs.remove(&id);
+            self.buffers.remove(&id);
+        }
+    }
+
+    /// Executes the commands of `list` in order, rendering to the currently bound framebuffer.
+    ///
+    /// # Returns
+    ///
+    /// The error of the first draw command that failed, after which the remaining commands
+    /// are skipped. Commands referring to objects of another registry fail with
+    /// [`DrawError::ForeignResource`].
+    pub fn submit(&mut self, renderer: &Renderer, list: CommandList) -> Result<(), DrawError> {
+        let result = list
+            .commands
+            .into_iter()
+            .try_for_each(|command| match command {
+                Command::Clear { color, depth } => {
+                    let mut previous_color = [0.0; 4];
+                    let mut previous_depth = 0.0;
+                    unsafe {
+                        gl::GetFloatv(gl::COLOR_CLEAR_VALUE, previous_color.as_mut_ptr());
+                        gl::GetDoublev(gl::DEPTH_CLEAR_VALUE, &mut previous_depth);
+                        gl::ClearColor(color.x, color.y, color.z, color.w);
+                        gl::ClearDepth(depth);
+                        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+                        let [red, green, blue, alpha] = previous_color;
+                        gl::ClearColor(red, green, blue, alpha);
+                        gl::ClearDepth(previous_depth);
+                    };
+                    Ok(())
+                }
+                Command::Draw(draw) => self.execute_draw(renderer, draw),
+            });
+
+        self.collect_garbage();
+        result
+    }
+
+    fn execute_draw(&self, renderer: &Renderer, draw: DrawCommand) -> Result<(), DrawError> {
+        if !self.owns(&draw.program.0) || !self.owns(&draw.buffer.0) {
+            return Err(DrawError::ForeignResource);
+        }
+        let (Some(program), Some(buffer)) = (
+            self.programs.get(&draw.program.0.id),
+            self.buffers.get(&draw.buffer.0.id),
+        ) else {
+            return Err(DrawError::ForeignResource);
+        };
+
+        let uniforms = Uniforms {
+            data: draw
+                .uniforms
+                .into_iter()
+                .map(|(name, value)| {
+                    let value: Box<dyn Uniform> = value;
+                    (
+                        program.get_uniform_location(&name),
+                        UniformValue::Boxed(value),
+                    )
+                })
+                .collect(),
+        };
+
+        buffer.draw(renderer, program, draw.mode, &uniforms, &draw.parameters)
+    }
+}
+
+impl std::fmt::Debug for Resources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resources")
+            .field("programs", &self.programs.len())
+            .field("buffers", &self.buffers.len())
+            .finish()
+    }
+}
+
+/// A draw recorded into a [`CommandList`]. Uniforms and parameters can be added through the
+/// reference returned by [`CommandList::draw`].
+#[derive(Debug)]
+pub struct DrawCommand {
+    buffer: BufferHandle,
+    program: ProgramHandle,
+    mode: DrawMode,
+    uniforms: Vec<(String, Box<dyn Uniform + Send>)>,
+    parameters: DrawParameters,
+}
+
+impl DrawCommand {
+    /// Sets the uniform called `name` for this draw
+    pub fn uniform<U>(&mut self, name: &str, value: U) -> &mut Self
+    where
+        U: Uniform + Send + 'static,
+    {
+        self.uniforms.push((name.to_owned(), Box::new(value)));
+        self
+    }
+
+    /// Sets the fixed-function state applied for this draw
+    pub fn parameters(&mut self, parameters: DrawParameters) -> &mut Self {
+        self.parameters = parameters;
+        self
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    Clear { color: Vec4, depth: f64 },
+    Draw(DrawCommand),
+}
+
+/// A list of rendering commands that can be recorded on any thread without access to the
+/// OpenGL context, and executed later with [`Resources::submit`]
+#[derive(Debug, Default)]
+pub struct CommandList {
+    commands: Vec<Command>,
+}
+
+impl CommandList {
+    /// Constructs an empty command list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a clear of the target framebuffer to `color` and `depth`
+    pub fn clear(&mut self, color: Vec4, depth: f64) {
+        self.commands.push(Command::Clear { color, depth });
+    }
+
+    /// Records a draw of `buffer` with `program`, returning the command so uniforms can be
+    /// attached to it
+    pub fn draw(
+        &mut self,
+        buffer: &BufferHandle,
+        program: &ProgramHandle,
+        mode: DrawMode,
+    ) -> &mut DrawCommand {
+        self.commands.push(Command::Draw(DrawCommand {
+            buffer: buffer.clone(),
+            program: program.clone(),
+            mode,
+            uniforms: vec![],
+            parameters: DrawParameters::default(),
+        }));
+
+        match self.commands.last_mut() {
+            Some(Command::Draw(draw)) => draw,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Appends the commands of `other`, e.g. to merge lists recorded on several threads
+    pub fn append(&mut self, other: CommandList) {
+        self.commands.extend(other.commands);
+    }
+
+    /// Returns the number of recorded commands
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns whether no commands have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    bind::{BindGuard, BindTarget},
+    buffer::{Buffer, BufferTarget},
+    context::{self, Unsupported},
+    renderer::DrawError,
+    shader::{LinkedProgram, Program, Shader, ShaderCompilationError, ShaderType},
+    types::Pod,
+    uniforms::Uniforms,
+};
+
+/// The arguments of an indirect dispatch as read from a buffer by
+/// [`ComputeProgram::dispatch_indirect`], usually written by an earlier compute pass
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DispatchIndirectCommand {
+    pub groups_x: u32,
+    pub groups_y: u32,
+    pub groups_z: u32,
+}
+
+// `#[repr(C)]` with only `u32` fields
+unsafe impl Pod for DispatchIndirectCommand {}
+
+/// An error that occurred while creating a [`ComputeProgram`]
+#[derive(Debug, Clone)]
+pub enum ComputeProgramError {
+    /// The context doesn't support compute shaders
+    Unsupported(Unsupported),
+    /// The compute shader failed to compile or link
+    Compilation(ShaderCompilationError),
+}
+
+impl std::fmt::Display for ComputeProgramError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComputeProgramError::Unsupported(error) => write!(f, "{error}"),
+            ComputeProgramError::Compilation(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for ComputeProgramError {}
+
+impl From<Unsupported> for ComputeProgramError {
+    fn from(error: Unsupported) -> Self {
+        ComputeProgramError::Unsupported(error)
+    }
+}
+
+impl From<ShaderCompilationError> for ComputeProgramError {
+    fn from(error: ShaderCompilationError) -> Self {
+        ComputeProgramError::Compilation(error)
+    }
+}
+
+/// A dispatch that was rejected before reaching OpenGL
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DispatchError {
+    /// More work groups were requested along some axis than
+    /// [`max_work_group_count`] allows
+    TooManyGroups {
+        groups: (u32, u32, u32),
+        limit: (u32, u32, u32),
+    },
+    /// The uniforms couldn't be uploaded
+    Uniforms(DrawError),
+}
+
+impl std::fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispatchError::TooManyGroups { groups, limit } => write!(
+                f,
+                "Dispatched {groups:?} work groups, but at most {limit:?} are supported"
+            ),
+            DispatchError::Uniforms(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for DispatchError {}
+
+/// Queries an indexed `(x, y, z)` limit
+fn indexed_limit(name: u32) -> (u32, u32, u32) {
+    let [x, y, z] = [0, 1, 2].map(|index| {
+        let mut value = 0;
+        unsafe { gl::GetIntegeri_v(name, index, &mut value) };
+        value.max(0) as u32
+    });
+    (x, y, z)
+}
+
+/// Returns the largest number of work groups a single dispatch may have along each axis
+pub fn max_work_group_count() -> (u32, u32, u32) {
+    indexed_limit(gl::MAX_COMPUTE_WORK_GROUP_COUNT)
+}
+
+/// Returns the largest local size a compute shader may declare along each axis
+pub fn max_work_group_size() -> (u32, u32, u32) {
+    indexed_limit(gl::MAX_COMPUTE_WORK_GROUP_SIZE)
+}
+
+/// Returns the largest number of invocations in a single work group, i.e. the largest
+/// product of the local size
+pub fn max_work_group_invocations() -> u32 {
+    let mut value = 0;
+    unsafe { gl::GetIntegerv(gl::MAX_COMPUTE_WORK_GROUP_INVOCATIONS, &mut value) };
+    value.max(0) as u32
+}
+
+/// A program made of a single compute shader, which is dispatched over a grid of work groups
+/// rather than drawn.
+///
+/// Results written to buffers or images must be made visible to later commands with a
+/// memory barrier, such as [`crate::buffer::ShaderStorageBuffer::shader_barrier`] or
+/// [`crate::texture::image_barrier`].
+pub struct ComputeProgram {
+    program: LinkedProgram,
+    local_size: (u32, u32, u32),
+}
+
+impl ComputeProgram {
+    /// Compiles and links the compute shader `source`
+    pub fn new(source: impl AsRef<str>) -> Result<Self, ComputeProgramError> {
+        if !context::supports_compute_shaders() {
+            return Err(Unsupported {
+                feature: "GL_ARB_compute_shader",
+            }
+            .into());
+        }
+
+        let program =
+            Program::new().attach_and_link(vec![Shader::new(source, ShaderType::Compute)])?;
+        let mut local_size = [0; 3];
+        unsafe {
+            gl::GetProgramiv(
+                program.id(),
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                local_size.as_mut_ptr(),
+            )
+        };
+        let [x, y, z] = local_size.map(|size| size.max(1) as u32);
+
+        Ok(Self {
+            program,
+            local_size: (x, y, z),
+        })
+    }
+
+    /// Returns the linked program, e.g. to build uniforms for it
+    pub fn program(&self) -> &LinkedProgram {
+        &self.program
+    }
+
+    /// Returns the work group size declared by the shader's `local_size_*` layout qualifiers
+    pub fn local_size(&self) -> (u32, u32, u32) {
+        self.local_size
+    }
+
+    /// Returns the number of work groups needed to run at least `invocations` invocations
+    /// along each axis
+    pub fn groups_for(&self, invocations: (u32, u32, u32)) -> (u32, u32, u32) {
+        (
+            invocations.0.div_ceil(self.local_size.0),
+            invocations.1.div_ceil(self.local_size.1),
+            invocations.2.div_ceil(self.local_size.2),
+        )
+    }
+
+    /// Runs `x * y * z` work groups with `uniforms`. Dispatching no groups along any axis
+    /// does nothing.
+    pub fn dispatch(
+        &self,
+        x: u32,
+        y: u32,
+        z: u32,
+        uniforms: &Uniforms,
+    ) -> Result<(), DispatchError> {
+        let limit = max_work_group_count();
+        if x > limit.0 || y > limit.1 || z > limit.2 {
+            return Err(DispatchError::TooManyGroups {
+                groups: (x, y, z),
+                limit,
+            });
+        }
+        if x == 0 || y == 0 || z == 0 {
+            return Ok(());
+        }
+
+        let _program_binding = self.bind(uniforms)?;
+        unsafe { gl::DispatchCompute(x, y, z) };
+        Ok(())
+    }
+
+    /// Runs enough work groups for at least `invocations` invocations along each axis, which
+    /// shaders should bounds-check against the actual size. See [`ComputeProgram::groups_for`].
+    pub fn dispatch_invocations(
+        &self,
+        invocations: (u32, u32, u32),
+        uniforms: &Uniforms,
+    ) -> Result<(), DispatchError> {
+        let (x, y, z) = self.groups_for(invocations);
+        self.dispatch(x, y, z, uniforms)
+    }
+
+    /// Runs the number of work groups stored in element `index` of `commands`, without
+    /// reading it back to the CPU. Counts above the limits make the dispatch undefined.
+    ///
+    /// Commands written by shaders must be made visible with
+    /// [`crate::sync::MemoryBarrier::COMMAND`] first.
+    ///
+    /// # Panics
+    /// This function panics if `index` is out of range.
+    pub fn dispatch_indirect(
+        &self,
+        commands: &Buffer<DispatchIndirectCommand>,
+        index: usize,
+        uniforms: &Uniforms,
+    ) -> Result<(), DispatchError> {
+        assert!(
+            index < commands.len(),
+            "Dispatch command {index} is out of range for a buffer of {} commands",
+            commands.len()
+        );
+
+        let _buffer_binding = BindGuard::new(
+            BindTarget::Buffer(BufferTarget::DispatchIndirect),
+            commands.id(),
+        );
+        let _program_binding = self.bind(uniforms)?;
+        let offset = index * std::mem::size_of::<DispatchIndirectCommand>();
+        unsafe { gl::DispatchComputeIndirect(offset as isize) };
+        Ok(())
+    }
+
+    fn bind(&self, uniforms: &Uniforms) -> Result<BindGuard<'_>, DispatchError> {
+        let binding = self.program.bind_scoped();
+        self.program.apply_subroutines();
+        self.program.report_uniform_mismatches(uniforms);
+        uniforms.upload_all().map_err(DispatchError::Uniforms)?;
+        Ok(binding)
+    }
+}
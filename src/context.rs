@@ -0,0 +1,265 @@
+use crate::texture::TextureFormat;
+use std::{
+    cell::Cell,
+    ffi::CStr,
+    fmt::{Display, Write},
+};
+
+/// An error returned when a feature isn't supported by the current context
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// The name of the missing feature, e.g. `"glClipControl"`
+    pub feature: &'static str,
+}
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} isn't supported by the current context", self.feature)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Capabilities of the current context that are checked on hot paths, resolved once rather
+/// than by walking the extension list every time
+#[derive(Debug, Copy, Clone)]
+struct Capabilities {
+    int64_uniforms: bool,
+    /// The capability enabling conservative rasterization, if any
+    conservative_rasterization: Option<u32>,
+    limits: Limits,
+}
+
+impl Capabilities {
+    fn query() -> Self {
+        /// `GL_CONSERVATIVE_RASTERIZATION_NV`
+        const CONSERVATIVE_RASTERIZATION_NV: u32 = 0x9346;
+        /// `GL_CONSERVATIVE_RASTERIZATION_INTEL`
+        const CONSERVATIVE_RASTERIZATION_INTEL: u32 = 0x83FE;
+
+        let conservative_rasterization = if has_extension("GL_NV_conservative_raster") {
+            Some(CONSERVATIVE_RASTERIZATION_NV)
+        } else if has_extension("GL_INTEL_conservative_rasterization") {
+            Some(CONSERVATIVE_RASTERIZATION_INTEL)
+        } else {
+            None
+        };
+        Self {
+            int64_uniforms: crate::ext::int64_uniforms_loaded()
+                && (has_extension("GL_ARB_gpu_shader_int64") || has_extension("GL_NV_gpu_shader5")),
+            conservative_rasterization,
+            limits: Limits::query(),
+        }
+    }
+}
+
+thread_local! {
+    // A context is current on a single thread at a time, so the capabilities follow the thread
+    static CAPABILITIES: Cell<Option<Capabilities>> = const { Cell::new(None) };
+}
+
+/// Resolves the capabilities of the current context, replacing those of any previous one.
+/// Called by [`crate::Renderer::load_opengl_functions`].
+pub(crate) fn load_capabilities() {
+    CAPABILITIES.set(Some(Capabilities::query()));
+}
+
+/// Returns the capabilities of the current context, resolving them on first use if the
+/// functions were loaded on another thread
+fn capabilities() -> Capabilities {
+    CAPABILITIES.get().unwrap_or_else(|| {
+        let capabilities = Capabilities::query();
+        CAPABILITIES.set(Some(capabilities));
+        capabilities
+    })
+}
+
+/// Returns whether the current context advertises the extension `name`, e.g.
+/// `"GL_NV_conservative_raster"`
+pub fn has_extension(name: &str) -> bool {
+    let mut count = 0;
+    unsafe { gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count) };
+
+    (0..count.max(0) as u32).any(|index| {
+        let extension = unsafe { gl::GetStringi(gl::EXTENSIONS, index) };
+        !extension.is_null()
+            && unsafe { CStr::from_ptr(extension.cast()) }.to_bytes() == name.as_bytes()
+    })
+}
+
+/// The capability enabling conservative rasterization, if the current context supports it.
+///
+/// Checks `GL_NV_conservative_raster` first and falls back to
+/// `GL_INTEL_conservative_rasterization`.
+pub(crate) fn conservative_rasterization_capability() -> Option<u32> {
+    capabilities().conservative_rasterization
+}
+
+/// Returns whether the current context supports `glClipControl` (OpenGL 4.5 or
+/// `GL_ARB_clip_control`)
+pub fn supports_clip_control() -> bool {
+    gl::ClipControl::is_loaded()
+}
+
+/// Returns whether the current context supports immutable buffer storage with persistent
+/// mappings (OpenGL 4.4 or `GL_ARB_buffer_storage`)
+pub fn supports_buffer_storage() -> bool {
+    gl::BufferStorage::is_loaded()
+}
+
+/// Returns whether the current context supports compute shaders (OpenGL 4.3 or
+/// `GL_ARB_compute_shader`)
+pub fn supports_compute_shaders() -> bool {
+    gl::DispatchCompute::is_loaded()
+}
+
+/// Returns whether the current context supports image load/store (OpenGL 4.2 or
+/// `GL_ARB_shader_image_load_store`)
+pub fn supports_image_load_store() -> bool {
+    gl::BindImageTexture::is_loaded()
+}
+
+/// Returns whether the current context can load SPIR-V shader modules (OpenGL 4.6 or
+/// `GL_ARB_gl_spirv`)
+pub fn supports_spirv() -> bool {
+    crate::ext::specialize_shader_loaded()
+}
+
+/// Returns whether the current context supports 64-bit integer uniforms
+/// (`GL_ARB_gpu_shader_int64` or `GL_NV_gpu_shader5`)
+pub fn supports_int64_uniforms() -> bool {
+    capabilities().int64_uniforms
+}
+
+/// Returns whether the current context supports conservative rasterization
+pub fn supports_conservative_rasterization() -> bool {
+    conservative_rasterization_capability().is_some()
+}
+
+/// Returns the sample counts the current context supports for multisampled render targets of
+/// `format`, from most to fewest. Empty if the format can't be multisampled at all.
+pub fn supported_sample_counts(format: TextureFormat) -> Vec<u32> {
+    let internal_format = format.internal_format();
+    let mut count = 0;
+    unsafe {
+        gl::GetInternalformativ(
+            gl::RENDERBUFFER,
+            internal_format,
+            gl::NUM_SAMPLE_COUNTS,
+            1,
+            &mut count,
+        )
+    };
+    if count <= 0 {
+        return vec![];
+    }
+
+    let mut samples = vec![0; count as usize];
+    unsafe {
+        gl::GetInternalformativ(
+            gl::RENDERBUFFER,
+            internal_format,
+            gl::SAMPLES,
+            count,
+            samples.as_mut_ptr(),
+        )
+    };
+    let mut samples = samples
+        .into_iter()
+        .filter(|&samples| samples > 0)
+        .map(|samples| samples as u32)
+        .collect::<Vec<_>>();
+    samples.sort_unstable_by(|a, b| b.cmp(a));
+    samples
+}
+
+/// Picks the most samples the current context supports for `format` that don't exceed
+/// `requested`, so that asking for 8x falls back to 4x on hardware without 8x rather than
+/// failing.
+///
+/// # Returns
+///
+/// The sample count, or `1` if no multisampled count fits, meaning the target shouldn't be
+/// multisampled.
+pub fn negotiate_sample_count(format: TextureFormat, requested: u32) -> u32 {
+    supported_sample_counts(format)
+        .into_iter()
+        .find(|&samples| samples <= requested)
+        .unwrap_or(1)
+}
+
+/// Implementation limits of the current context that shaders may want to scale to.
+///
+/// Every [`crate::shader::Shader`] is compiled with these as `#define`s right after its
+/// `#version` directive, e.g. `GLIUM2_MAX_SAMPLES`. See [`Limits::defines`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// The largest uniform block in bytes, `GLIUM2_MAX_UNIFORM_BLOCK_SIZE`
+    pub max_uniform_block_size: u32,
+    /// The most [`crate::light::Light`]s that fit an array in a single uniform block,
+    /// `GLIUM2_MAX_UNIFORM_LIGHTS`
+    pub max_uniform_lights: u32,
+    /// The most samples of a multisampled texture or renderbuffer, `GLIUM2_MAX_SAMPLES`
+    pub max_samples: u32,
+    /// The most color attachments a fragment shader can write, `GLIUM2_MAX_DRAW_BUFFERS`
+    pub max_draw_buffers: u32,
+    /// The most textures all stages can sample at once,
+    /// `GLIUM2_MAX_COMBINED_TEXTURE_IMAGE_UNITS`
+    pub max_combined_texture_image_units: u32,
+    /// The most images that can be bound for load/store, `GLIUM2_MAX_IMAGE_UNITS`
+    pub max_image_units: u32,
+    /// The most vertices in a patch drawn with [`crate::DrawMode::Patches`],
+    /// `GLIUM2_MAX_PATCH_VERTICES`
+    pub max_patch_vertices: u32,
+}
+
+impl Limits {
+    /// Returns the limits of the current context, queried once when its functions were
+    /// loaded rather than on every call
+    pub fn current() -> Self {
+        capabilities().limits
+    }
+
+    /// Queries the limits of the current context
+    pub fn query() -> Self {
+        let max_uniform_block_size = integer_limit(gl::MAX_UNIFORM_BLOCK_SIZE);
+        Self {
+            max_uniform_block_size,
+            max_uniform_lights: max_uniform_block_size
+                / std::mem::size_of::<crate::light::GpuLight>() as u32,
+            max_samples: integer_limit(gl::MAX_SAMPLES),
+            max_draw_buffers: integer_limit(gl::MAX_DRAW_BUFFERS),
+            max_combined_texture_image_units: integer_limit(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+            max_image_units: integer_limit(gl::MAX_IMAGE_UNITS),
+            max_patch_vertices: integer_limit(gl::MAX_PATCH_VERTICES),
+        }
+    }
+
+    /// Returns the limits as preprocessor definitions, one per line
+    pub fn defines(&self) -> String {
+        [
+            ("GLIUM2_MAX_UNIFORM_BLOCK_SIZE", self.max_uniform_block_size),
+            ("GLIUM2_MAX_UNIFORM_LIGHTS", self.max_uniform_lights),
+            ("GLIUM2_MAX_SAMPLES", self.max_samples),
+            ("GLIUM2_MAX_DRAW_BUFFERS", self.max_draw_buffers),
+            (
+                "GLIUM2_MAX_COMBINED_TEXTURE_IMAGE_UNITS",
+                self.max_combined_texture_image_units,
+            ),
+            ("GLIUM2_MAX_IMAGE_UNITS", self.max_image_units),
+            ("GLIUM2_MAX_PATCH_VERTICES", self.max_patch_vertices),
+        ]
+        .iter()
+        .fold(String::new(), |mut defines, (name, value)| {
+            let _ = writeln!(defines, "#define {name} {value}");
+            defines
+        })
+    }
+}
+
+/// Queries a single integer limit, which is `0` if the context doesn't know it
+fn integer_limit(name: u32) -> u32 {
+    let mut value = 0;
+    unsafe { gl::GetIntegerv(name, &mut value) };
+    value.max(0) as u32
+}
@@ -0,0 +1,233 @@
+use crate::{
+    buffer::VertexBuffer,
+    camera::Camera,
+    draw_parameters,
+    renderer::{ClipDepth, DrawError, DrawMode, Renderer},
+    shader::{LinkedProgram, Program, Shader, ShaderType},
+    surface::{Surface, ViewportGuard},
+    texture::Texture2D,
+    uniforms,
+};
+use glm::{Mat4, Vec3, Vec4};
+
+/// A texture projected onto the scene inside a box, such as a bullet hole or a blob shadow
+#[derive(Debug, Copy, Clone)]
+pub struct Decal<'a> {
+    /// Transforms the unit cube centered on the origin into the projection box in world
+    /// space. The texture is projected along the box's local `-Y` axis, with `u` following
+    /// `X` and `v` following `Z`.
+    pub transform: Mat4,
+    pub texture: &'a Texture2D,
+    /// Multiplied with the texture; the alpha fades the decal out
+    pub color: Vec4,
+}
+
+impl<'a> Decal<'a> {
+    /// Creates an untinted decal projecting `texture` inside the box described by `transform`
+    pub fn new(transform: Mat4, texture: &'a Texture2D) -> Self {
+        Self {
+            transform,
+            texture,
+            color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// Draws [`Decal`]s onto already rendered geometry.
+///
+/// Every decal draws the back faces of its box. Each covered pixel's position is
+/// reconstructed from a depth buffer and the camera, and the decal's texture is alpha blended
+/// into the first color attachment of the target wherever that position lies inside the box.
+/// Other attachments and the alpha channel of the target are left untouched, so decals can be
+/// drawn straight into the albedo of a G-buffer with
+/// [`crate::deferred::DeferredRenderer::draw_decals`].
+pub struct DecalRenderer {
+    program: LinkedProgram,
+    cube: VertexBuffer<Vec3>,
+}
+
+impl Default for DecalRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecalRenderer {
+    const VERTEX_SHADER: &'static str = r#"
+        #version 460 core
+        layout(location = 0) in vec3 position;
+
+        uniform mat4 u_model;
+        uniform mat4 u_view_projection;
+
+        void main() {
+            gl_Position = u_view_projection * u_model * vec4(position, 1.0);
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &'static str = r#"
+        #version 460 core
+
+        uniform sampler2D u_depth;
+        uniform sampler2D u_texture;
+        uniform mat4 u_inverse_model;
+        uniform mat4 u_inverse_view_projection;
+        uniform vec4 u_color;
+        uniform vec2 u_target_size;
+        uniform int u_zero_to_one_depth;
+
+        layout(location = 0) out vec4 color;
+
+        void main() {
+            vec2 uv = gl_FragCoord.xy / u_target_size;
+            float depth = texture(u_depth, uv).r;
+            float ndc_depth = u_zero_to_one_depth != 0 ? depth : depth * 2.0 - 1.0;
+            vec4 world = u_inverse_view_projection * vec4(uv * 2.0 - 1.0, ndc_depth, 1.0);
+            vec3 local = (u_inverse_model * vec4(world.xyz / world.w, 1.0)).xyz;
+            if (any(greaterThan(abs(local), vec3(0.5)))) {
+                discard;
+            }
+
+            color = texture(u_texture, local.xz + 0.5) * u_color;
+        }
+    "#;
+
+    /// Compiles the decal shaders and creates the projection box
+    pub fn new() -> Self {
+        #[allow(clippy::expect_used)]
+        let program = Program::new()
+            .attach_and_link(vec![
+                Shader::new(Self::VERTEX_SHADER.to_owned(), ShaderType::Vertex),
+                Shader::new(Self::FRAGMENT_SHADER.to_owned(), ShaderType::Fragment),
+            ])
+            .expect("Built-in decal shader failed to compile");
+
+        let corners = (0..8)
+            .map(|corner| {
+                glm::vec3(
+                    (corner & 1) as f32 - 0.5,
+                    (corner >> 1 & 1) as f32 - 0.5,
+                    (corner >> 2 & 1) as f32 - 0.5,
+                )
+            })
+            .collect::<Vec<_>>();
+        let indices: [u32; 36] = [
+            0, 4, 6, 0, 6, 2, 1, 3, 7, 1, 7, 5, 0, 1, 5, 0, 5, 4, 2, 6, 7, 2, 7, 3, 0, 2, 3, 0, 3,
+            1, 4, 5, 7, 4, 7, 6,
+        ];
+
+        Self {
+            program,
+            cube: VertexBuffer::new_static(&corners, Some(&indices)),
+        }
+    }
+
+    /// Blends `decals` into `target`, whose geometry was drawn as seen by `camera` with its
+    /// depth written to `depth`.
+    ///
+    /// `depth` may be the depth attachment of `target`, as depth testing and writes are
+    /// disabled while decals are drawn.
+    ///
+    /// # Panics
+    /// This function panics if the camera's view-projection matrix or a decal's transform
+    /// isn't invertible.
+    pub fn draw<S: Surface>(
+        &self,
+        renderer: &Renderer,
+        target: &S,
+        depth: &Texture2D,
+        camera: &Camera,
+        decals: &[Decal],
+    ) -> Result<(), DrawError> {
+        let view_projection = camera.view_projection();
+        let inverse_view_projection = glm::inverse(&view_projection);
+        let (width, height) = target.dimensions();
+        let target_size = glm::vec2(width as f32, height as f32);
+        let zero_to_one_depth = (camera.clip_depth == ClipDepth::ZeroToOne) as i32;
+
+        let _binding = target.bind_target();
+        let _viewport = ViewportGuard::new((width, height));
+        let _state = DecalState::apply();
+        let program = &self.program;
+        for decal in decals {
+            let uniforms = uniforms!(program => {
+                "u_model": decal.transform,
+                "u_view_projection": view_projection,
+                "u_inverse_model": glm::inverse(&decal.transform),
+                "u_inverse_view_projection": inverse_view_projection,
+                "u_depth": depth.binding(),
+                "u_texture": decal.texture.binding(),
+                "u_color": decal.color,
+                "u_target_size": target_size,
+                "u_zero_to_one_depth": zero_to_one_depth
+            });
+            renderer.draw(&self.cube, program, DrawMode::Triangles, &uniforms)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sets up blending, culling and depth state for decals until dropped, restoring the
+/// previous state afterwards
+struct DecalState {
+    blend: bool,
+    cull_face: bool,
+    cull_face_mode: i32,
+    depth_test: bool,
+}
+
+impl DecalState {
+    /// Writes to draw buffers `1` up to this one are masked
+    const MASKED_DRAW_BUFFERS: u32 = 8;
+
+    fn apply() -> Self {
+        let mut cull_face_mode = 0;
+        let state = unsafe {
+            gl::GetIntegerv(gl::CULL_FACE_MODE, &mut cull_face_mode);
+            Self {
+                blend: gl::IsEnabled(gl::BLEND) == gl::TRUE,
+                cull_face: gl::IsEnabled(gl::CULL_FACE) == gl::TRUE,
+                cull_face_mode,
+                depth_test: gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE,
+            }
+        };
+
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO, gl::ONE);
+            // Back faces keep the decal visible when the camera is inside its box
+            gl::Enable(gl::CULL_FACE);
+            gl::CullFace(gl::FRONT);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::FALSE);
+            for buffer in 1..Self::MASKED_DRAW_BUFFERS {
+                gl::ColorMaski(buffer, gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            }
+        };
+        draw_parameters::invalidate_state_cache();
+        state
+    }
+}
+
+impl Drop for DecalState {
+    fn drop(&mut self) {
+        unsafe {
+            for buffer in 1..Self::MASKED_DRAW_BUFFERS {
+                gl::ColorMaski(buffer, gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            }
+            gl::DepthMask(gl::TRUE);
+            if self.depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+            gl::CullFace(self.cull_face_mode as u32);
+            if !self.cull_face {
+                gl::Disable(gl::CULL_FACE);
+            }
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            if !self.blend {
+                gl::Disable(gl::BLEND);
+            }
+        };
+        draw_parameters::invalidate_state_cache();
+    }
+}
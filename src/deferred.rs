@@ -0,0 +1,354 @@
+use crate::{
+    bind::{BindGuard, BindTarget},
+    buffer::ShaderStorageBuffer,
+    camera::Camera,
+    decal::{Decal, DecalRenderer},
+    draw_parameters,
+    frame_graph::FrameDescription,
+    framebuffer::{Framebuffer, FramebufferError, TargetDescriptor},
+    light::{self, GpuLight, Light},
+    mesh::FullscreenQuad,
+    renderer::{DrawError, Renderer},
+    shader::LinkedProgram,
+    surface::{Surface, ViewportGuard},
+    texture::{Texture2D, TextureFormat},
+    uniforms,
+};
+use glm::{Vec3, Vec4};
+
+/// A reference deferred shading pipeline, meant to be adopted as is or copied and adapted.
+///
+/// A frame is rendered in three steps:
+///
+/// 1. Opaque geometry is drawn through [`DeferredRenderer::geometry_pass`] into the G-buffer,
+///    using fragment shaders that write the outputs declared by
+///    [`DeferredRenderer::GEOMETRY_OUTPUTS`].
+/// 2. [`DeferredRenderer::light`] shades every covered pixel of the G-buffer with a list of
+///    lights in a single full-screen pass. Point lights are skipped for pixels outside their
+///    radius, so large numbers of small lights stay cheap.
+/// 3. Transparent geometry is drawn through [`DeferredRenderer::forward_pass`] on top of the
+///    lit image, depth-tested against the opaque geometry and alpha blended.
+///
+/// The lit image in [`DeferredRenderer::output`] is in linear HDR and is usually tone mapped
+/// by an [`crate::postprocess::EffectChain`] afterwards.
+pub struct DeferredRenderer {
+    gbuffer: Framebuffer,
+    lit: Framebuffer,
+    lights: ShaderStorageBuffer<GpuLight>,
+    lighting: LinkedProgram,
+    quad: FullscreenQuad,
+    ambient: Vec3,
+    background: Vec4,
+}
+
+impl DeferredRenderer {
+    /// The G-buffer outputs of geometry pass fragment shaders, to be inserted after the
+    /// `#version` directive.
+    ///
+    /// - `g_albedo` - the surface color in `rgb` and the specular strength in `a`
+    /// - `g_normal` - the world-space normal in `xyz`; `w` must be `1`
+    /// - `g_position` - the world-space position in `xyz`
+    pub const GEOMETRY_OUTPUTS: &'static str = r#"
+        layout(location = 0) out vec4 g_albedo;
+        layout(location = 1) out vec4 g_normal;
+        layout(location = 2) out vec4 g_position;
+    "#;
+
+    /// The body of the lighting shader, which follows the `#version` directive and
+    /// [`Light::GLSL`]
+    const LIGHTING_SHADER: &'static str = r#"
+        in vec2 uv;
+
+        uniform sampler2D u_albedo;
+        uniform sampler2D u_normal;
+        uniform sampler2D u_position;
+        uniform int u_light_count;
+        uniform vec3 u_camera_position;
+        uniform vec3 u_ambient;
+
+        out vec4 color;
+
+        void main() {
+            vec4 normal_data = texture(u_normal, uv);
+            if (normal_data.w == 0.0) {
+                discard;
+            }
+
+            vec4 albedo = texture(u_albedo, uv);
+            vec3 position = texture(u_position, uv).xyz;
+            vec3 normal = normalize(normal_data.xyz);
+            vec3 view = normalize(u_camera_position - position);
+
+            vec3 result = u_ambient * albedo.rgb;
+            for (int i = 0; i < u_light_count; i++) {
+                result += evaluate_light(lights[i], albedo.rgb, albedo.a, position, normal, view);
+            }
+
+            color = vec4(result, 1.0);
+        }
+    "#;
+
+    /// Creates a pipeline whose targets have the given size
+    pub fn new(width: u32, height: u32) -> Result<Self, FramebufferError> {
+        let (gbuffer, lit) = Self::create_targets(width, height)?;
+        #[allow(clippy::expect_used)]
+        let lighting = FullscreenQuad::program(&format!(
+            "#version 460 core\n{}{}",
+            Light::GLSL,
+            Self::LIGHTING_SHADER
+        ))
+        .expect("Built-in deferred lighting shader failed to compile");
+
+        Ok(Self {
+            gbuffer,
+            lit,
+            lights: ShaderStorageBuffer::new(&[GpuLight::default()]),
+            lighting,
+            quad: FullscreenQuad::new(),
+            ambient: glm::vec3(0.03, 0.03, 0.03),
+            background: glm::vec4(0.0, 0.0, 0.0, 1.0),
+        })
+    }
+
+    fn create_targets(
+        width: u32,
+        height: u32,
+    ) -> Result<(Framebuffer, Framebuffer), FramebufferError> {
+        let gbuffer = Framebuffer::new(
+            vec![
+                Texture2D::new(width, height, TextureFormat::Rgba8),
+                Texture2D::new(width, height, TextureFormat::Rgba16F),
+                Texture2D::new(width, height, TextureFormat::Rgba32F),
+            ],
+            Some(Texture2D::new(width, height, TextureFormat::Depth24)),
+        )?;
+        let lit = Framebuffer::new(
+            vec![Texture2D::new(width, height, TextureFormat::Rgba16F)],
+            Some(Texture2D::new(width, height, TextureFormat::Depth24)),
+        )?;
+        Ok((gbuffer, lit))
+    }
+
+    /// Recreates the targets with a new size, e.g. after the window was resized
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferError> {
+        if self.dimensions() != (width, height) {
+            (self.gbuffer, self.lit) = Self::create_targets(width, height)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the targets in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.gbuffer.dimensions()
+    }
+
+    /// Sets the light reaching every surface regardless of the lights
+    pub fn set_ambient(&mut self, ambient: Vec3) {
+        self.ambient = ambient;
+    }
+
+    /// Sets the color of pixels not covered by any opaque geometry
+    pub fn set_background(&mut self, background: Vec4) {
+        self.background = background;
+    }
+
+    /// Clears the G-buffer and returns a surface drawing into it with depth testing enabled
+    pub fn geometry_pass(&mut self) -> GeometryPass<'_> {
+        let mut pass = GeometryPass {
+            gbuffer: &mut self.gbuffer,
+            _depth: DepthTest::enable(true),
+        };
+        pass.clear(glm::vec4(0.0, 0.0, 0.0, 0.0), 1.0);
+        pass
+    }
+
+    /// Blends `decals` into the albedo of the G-buffer, between the geometry pass and
+    /// [`DeferredRenderer::light`], so they're lit like the surfaces they cover. `camera` must
+    /// be the camera the geometry pass was drawn with.
+    ///
+    /// # Panics
+    /// See [`DecalRenderer::draw`].
+    pub fn draw_decals(
+        &self,
+        renderer: &Renderer,
+        decal_renderer: &DecalRenderer,
+        camera: &Camera,
+        decals: &[Decal],
+    ) -> Result<(), DrawError> {
+        #[allow(clippy::unwrap_used)]
+        let depth = self.gbuffer.depth_attachment().unwrap();
+        decal_renderer.draw(renderer, &self.gbuffer, depth, camera, decals)
+    }
+
+    /// Shades the G-buffer with `lights` as seen from `camera_position`, and prepares the lit
+    /// image for the forward pass
+    pub fn light(
+        &mut self,
+        renderer: &Renderer,
+        lights: &[Light],
+        camera_position: Vec3,
+    ) -> Result<(), DrawError> {
+        light::upload(&mut self.lights, lights);
+
+        let (width, height) = self.dimensions();
+        self.lit.clear(self.background, 1.0);
+        {
+            let _binding = self.lit.bind_scoped();
+            let _viewport = ViewportGuard::new((width, height));
+
+            #[allow(clippy::unwrap_used)]
+            let [albedo, normal, position] =
+                [0, 1, 2].map(|index| self.gbuffer.color_attachment(index).unwrap().binding());
+            let program = &self.lighting;
+            let uniforms = uniforms!(program => {
+                "u_albedo": albedo,
+                "u_normal": normal,
+                "u_position": position,
+                "u_light_count": lights.len() as i32,
+                "u_camera_position": camera_position,
+                "u_ambient": self.ambient
+            });
+            self.quad.draw(renderer, program, &uniforms)?;
+        }
+
+        // Transparent geometry is depth-tested against the opaque geometry
+        let _binding = BindGuard::with_targets([
+            Some((BindTarget::ReadFramebuffer, self.gbuffer.id())),
+            Some((BindTarget::DrawFramebuffer, self.lit.id())),
+        ]);
+        unsafe {
+            gl::BlitFramebuffer(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            )
+        };
+        Ok(())
+    }
+
+    /// Returns a surface drawing into the lit image with depth testing against the opaque
+    /// geometry and alpha blending enabled, for transparent geometry.
+    ///
+    /// Depth writes are disabled, so transparent objects should be drawn back to front.
+    pub fn forward_pass(&mut self) -> ForwardPass<'_> {
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        };
+        draw_parameters::invalidate_state_cache();
+        ForwardPass {
+            lit: &mut self.lit,
+            _depth: DepthTest::enable(false),
+        }
+    }
+
+    /// Describes the G-buffer, lighting and forward passes and their attachments into
+    /// `frame`.
+    ///
+    /// # Returns
+    ///
+    /// The index of the attachment holding [`DeferredRenderer::output`], e.g. to pass to
+    /// [`crate::postprocess::EffectChain::describe`].
+    pub fn describe(&self, frame: &mut FrameDescription) -> usize {
+        let (width, height) = self.dimensions();
+        let target = |format| TargetDescriptor::new(width, height, format);
+
+        let albedo = frame.add_attachment("albedo", target(TextureFormat::Rgba8));
+        let normal = frame.add_attachment("normal", target(TextureFormat::Rgba16F));
+        let position = frame.add_attachment("position", target(TextureFormat::Rgba32F));
+        let depth = frame.add_attachment("depth", target(TextureFormat::Depth24));
+        let lit = frame.add_attachment("lit", target(TextureFormat::Rgba16F));
+        let lit_depth = frame.add_attachment("lit depth", target(TextureFormat::Depth24));
+
+        frame.add_pass("geometry", &[], &[albedo, normal, position, depth]);
+        frame.add_pass("lighting", &[albedo, normal, position], &[lit]);
+        frame.add_pass("depth copy", &[depth], &[lit_depth]);
+        frame.add_pass("forward", &[lit_depth], &[lit]);
+        lit
+    }
+
+    /// Returns the lit image in linear HDR (`RGBA16F`)
+    pub fn output(&self) -> &Texture2D {
+        #[allow(clippy::unwrap_used)]
+        self.lit.color_attachment(0).unwrap()
+    }
+
+    /// Returns the G-buffer, with albedo, normals and positions in color attachments `0`, `1`
+    /// and `2`
+    pub fn gbuffer(&self) -> &Framebuffer {
+        &self.gbuffer
+    }
+}
+
+/// Enables depth testing until dropped
+struct DepthTest;
+
+impl DepthTest {
+    fn enable(write: bool) -> Self {
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+            gl::DepthMask(if write { gl::TRUE } else { gl::FALSE });
+        };
+        draw_parameters::invalidate_state_cache();
+        Self
+    }
+}
+
+impl Drop for DepthTest {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::DEPTH_TEST);
+        };
+        draw_parameters::invalidate_state_cache();
+    }
+}
+
+/// The G-buffer of a [`DeferredRenderer`] being drawn to, obtained through
+/// [`DeferredRenderer::geometry_pass`]
+pub struct GeometryPass<'a> {
+    gbuffer: &'a mut Framebuffer,
+    _depth: DepthTest,
+}
+
+impl Surface for GeometryPass<'_> {
+    fn bind_target(&self) -> BindGuard<'_> {
+        self.gbuffer.bind_scoped()
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.gbuffer.dimensions()
+    }
+}
+
+/// The lit image of a [`DeferredRenderer`] being drawn to, obtained through
+/// [`DeferredRenderer::forward_pass`]
+pub struct ForwardPass<'a> {
+    lit: &'a mut Framebuffer,
+    _depth: DepthTest,
+}
+
+impl Surface for ForwardPass<'_> {
+    fn bind_target(&self) -> BindGuard<'_> {
+        self.lit.bind_scoped()
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.lit.dimensions()
+    }
+}
+
+impl Drop for ForwardPass<'_> {
+    fn drop(&mut self) {
+        unsafe { gl::Disable(gl::BLEND) };
+        draw_parameters::invalidate_state_cache();
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    framebuffer::{FramebufferError, MipChain},
+    mesh::FullscreenQuad,
+    postprocess::MipPass,
+    renderer::{DrawError, Renderer},
+    shader::LinkedProgram,
+    surface::{Surface, ViewportGuard},
+    texture::{Texture2D, TextureFormat},
+    uniforms,
+};
+
+/// A hierarchical depth buffer, where every texel of a mip level holds the farthest depth of
+/// the texels it covers in the level above.
+///
+/// The pyramid is stored in an `R32F` texture with a complete mip chain, so a screen-space
+/// rectangle can be conservatively tested against the depth buffer with a single
+/// [`texelFetch`] from the level where it covers at most a couple of texels. This is the basis
+/// of GPU occlusion culling and of accelerated screen-space ray marching.
+///
+/// [`texelFetch`]: https://registry.khronos.org/OpenGL-Refpages/gl4/html/texelFetch.xhtml
+pub struct DepthPyramid {
+    chain: MipChain,
+    copy: LinkedProgram,
+    reduce: MipPass,
+    quad: FullscreenQuad,
+    reversed_depth: bool,
+}
+
+impl DepthPyramid {
+    const COPY_SHADER: &'static str = r#"
+        #version 460 core
+
+        in vec2 uv;
+
+        uniform sampler2D u_depth;
+
+        out float depth;
+
+        void main() {
+            depth = texelFetch(u_depth, ivec2(gl_FragCoord.xy), 0).r;
+        }
+    "#;
+
+    const REDUCE_SHADER: &'static str = r#"
+        #version 460 core
+
+        in vec2 uv;
+
+        uniform sampler2D u_input;
+        uniform bool u_reversed_depth;
+
+        out float depth;
+
+        float farthest(float a, float b) {
+            return u_reversed_depth ? min(a, b) : max(a, b);
+        }
+
+        void main() {
+            ivec2 size = textureSize(u_input, 0);
+            ivec2 target_size = max(size / 2, ivec2(1));
+            ivec2 texel = ivec2(gl_FragCoord.xy);
+
+            // Texels at the end of an odd-sized level also cover the level's last row or column
+            ivec2 extent = ivec2(2);
+            if ((size.x & 1) == 1 && texel.x == target_size.x - 1) {
+                extent.x = 3;
+            }
+            if ((size.y & 1) == 1 && texel.y == target_size.y - 1) {
+                extent.y = 3;
+            }
+
+            float result = texelFetch(u_input, min(texel * 2, size - 1), 0).r;
+            for (int y = 0; y < extent.y; y++) {
+                for (int x = 0; x < extent.x; x++) {
+                    ivec2 source = min(texel * 2 + ivec2(x, y), size - 1);
+                    result = farthest(result, texelFetch(u_input, source, 0).r);
+                }
+            }
+            depth = result;
+        }
+    "#;
+
+    /// Creates a depth pyramid for depth buffers of the given size, with a mip level for every
+    /// halving of the size down to 1x1
+    pub fn new(width: u32, height: u32) -> Result<Self, FramebufferError> {
+        #[allow(clippy::expect_used)]
+        let copy = FullscreenQuad::program(Self::COPY_SHADER)
+            .expect("Built-in depth copy shader failed to compile");
+        #[allow(clippy::expect_used)]
+        let reduce = MipPass::new(Self::REDUCE_SHADER)
+            .expect("Built-in depth reduction shader failed to compile");
+
+        Ok(Self {
+            chain: Self::create_chain(width, height)?,
+            copy,
+            reduce,
+            quad: FullscreenQuad::new(),
+            reversed_depth: false,
+        })
+    }
+
+    fn create_chain(width: u32, height: u32) -> Result<MipChain, FramebufferError> {
+        MipChain::new(
+            width,
+            height,
+            Texture2D::max_levels(width, height),
+            TextureFormat::R32F,
+        )
+    }
+
+    /// Recreates the pyramid with a new size, e.g. after the window was resized
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferError> {
+        if self.dimensions() != (width, height) {
+            self.chain = Self::create_chain(width, height)?;
+        }
+        Ok(())
+    }
+
+    /// Sets whether larger depth values are closer to the camera, as with a reversed depth
+    /// buffer. The pyramid then keeps the smallest depth of each region instead of the largest.
+    pub fn set_reversed_depth(&mut self, reversed: bool) {
+        self.reversed_depth = reversed;
+        self.reduce
+            .program()
+            .set_uniform("u_reversed_depth", reversed as i32);
+    }
+
+    /// Returns whether larger depth values are closer to the camera
+    pub fn reversed_depth(&self) -> bool {
+        self.reversed_depth
+    }
+
+    /// Copies `depth` into the base level of the pyramid and builds every other level from it.
+    ///
+    /// # Panics
+    /// This function panics if `depth` doesn't have a depth format or doesn't have the size of
+    /// the pyramid.
+    pub fn build(&mut self, renderer: &Renderer, depth: &Texture2D) -> Result<(), DrawError> {
+        assert!(
+            depth.format().is_depth(),
+            "Expected a depth texture, got a texture of format {:?}",
+            depth.format()
+        );
+        assert_eq!(
+            depth.dimensions(),
+            self.dimensions(),
+            "The depth texture must have the size of the depth pyramid"
+        );
+
+        {
+            #[allow(clippy::unwrap_used)]
+            let base = self.chain.level(0).unwrap();
+            let _binding = base.bind_target();
+            let _viewport = ViewportGuard::new(base.dimensions());
+
+            let copy = &self.copy;
+            let uniforms = uniforms!(copy => { "u_depth": depth.binding() });
+            self.quad.draw(renderer, copy, &uniforms)?;
+        }
+
+        self.reduce.downsample(renderer, &self.chain)
+    }
+
+    /// Returns the `R32F` texture holding the pyramid, with the full-resolution depth in
+    /// level `0`
+    pub fn texture(&self) -> &Texture2D {
+        self.chain.texture()
+    }
+
+    /// Returns the number of mip levels of the pyramid
+    pub fn levels(&self) -> u32 {
+        self.chain.levels()
+    }
+
+    /// Returns the width and height of the base level in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.chain.dimensions()
+    }
+}
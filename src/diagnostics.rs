@@ -0,0 +1,112 @@
+use std::{fmt, sync::RwLock};
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl From<Severity> for log::Level {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Debug => log::Level::Debug,
+            Severity::Info => log::Level::Info,
+            Severity::Warning => log::Level::Warn,
+            Severity::Error => log::Level::Error,
+        }
+    }
+}
+
+/// The part of glium2 a [`Diagnostic`] originates from
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Messages reported by the OpenGL driver through its debug output
+    Gl,
+    /// Shader compilation, program binaries and uniform uploads
+    Shader,
+    /// Creation, mapping and binding of buffers, textures and render targets
+    Resource,
+    /// Draw calls and compute dispatches
+    Draw,
+    /// Calls made out of order, such as frames or profiler scopes left open
+    Usage,
+}
+
+/// A message reported by glium2 to the current [`DiagnosticsSink`]
+#[derive(Debug, Copy, Clone)]
+pub struct Diagnostic<'a> {
+    pub severity: Severity,
+    pub category: Category,
+    pub message: fmt::Arguments<'a>,
+}
+
+/// Receives the diagnostics glium2 reports, e.g. to forward them to an engine's own logging
+/// or telemetry. Installed with [`set_sink`].
+pub trait DiagnosticsSink: Send + Sync {
+    /// Handles a single diagnostic. May be called from any thread that uses glium2.
+    fn report(&self, diagnostic: &Diagnostic);
+}
+
+/// The default [`DiagnosticsSink`], which forwards diagnostics to the [`log`] crate
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LogSink;
+
+impl DiagnosticsSink for LogSink {
+    fn report(&self, diagnostic: &Diagnostic) {
+        log::log!(
+            target: "glium2",
+            diagnostic.severity.into(),
+            "{}",
+            diagnostic.message
+        );
+    }
+}
+
+static SINK: RwLock<Option<Box<dyn DiagnosticsSink>>> = RwLock::new(None);
+
+/// Makes glium2 report its diagnostics to `sink` instead of the [`log`] crate
+pub fn set_sink(sink: impl DiagnosticsSink + 'static) {
+    *SINK
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Box::new(sink));
+}
+
+/// Restores the default [`LogSink`]
+pub fn reset_sink() {
+    *SINK
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// Reports a diagnostic to the current sink
+pub(crate) fn report(severity: Severity, category: Category, message: fmt::Arguments) {
+    let diagnostic = Diagnostic {
+        severity,
+        category,
+        message,
+    };
+    match SINK
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .as_deref()
+    {
+        Some(sink) => sink.report(&diagnostic),
+        None => LogSink.report(&diagnostic),
+    }
+}
+
+/// Reports a diagnostic of the given severity and category with a `format!`-style message
+macro_rules! diagnostic {
+    ($severity:ident, $category:ident, $($arg:tt)+) => {
+        $crate::diagnostics::report(
+            $crate::diagnostics::Severity::$severity,
+            $crate::diagnostics::Category::$category,
+            format_args!($($arg)+),
+        )
+    };
+}
+
+pub(crate) use diagnostic;
@@ -0,0 +1,840 @@
+use crate::{
+    context,
+    diagnostics::diagnostic,
+    renderer::{CullingMode, DrawMode, Rect},
+};
+use std::cell::RefCell;
+
+/// A comparison between an incoming value and a stored one, used by depth and stencil tests
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Comparison {
+    Never,
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+impl From<Comparison> for u32 {
+    fn from(comparison: Comparison) -> u32 {
+        match comparison {
+            Comparison::Never => gl::NEVER,
+            Comparison::Less => gl::LESS,
+            Comparison::Equal => gl::EQUAL,
+            Comparison::LessOrEqual => gl::LEQUAL,
+            Comparison::Greater => gl::GREATER,
+            Comparison::NotEqual => gl::NOTEQUAL,
+            Comparison::GreaterOrEqual => gl::GEQUAL,
+            Comparison::Always => gl::ALWAYS,
+        }
+    }
+}
+
+/// A factor the source or destination color is multiplied by before blending
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SourceColor,
+    OneMinusSourceColor,
+    DestinationColor,
+    OneMinusDestinationColor,
+    SourceAlpha,
+    OneMinusSourceAlpha,
+    DestinationAlpha,
+    OneMinusDestinationAlpha,
+}
+
+impl From<BlendFactor> for u32 {
+    fn from(factor: BlendFactor) -> u32 {
+        match factor {
+            BlendFactor::Zero => gl::ZERO,
+            BlendFactor::One => gl::ONE,
+            BlendFactor::SourceColor => gl::SRC_COLOR,
+            BlendFactor::OneMinusSourceColor => gl::ONE_MINUS_SRC_COLOR,
+            BlendFactor::DestinationColor => gl::DST_COLOR,
+            BlendFactor::OneMinusDestinationColor => gl::ONE_MINUS_DST_COLOR,
+            BlendFactor::SourceAlpha => gl::SRC_ALPHA,
+            BlendFactor::OneMinusSourceAlpha => gl::ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DestinationAlpha => gl::DST_ALPHA,
+            BlendFactor::OneMinusDestinationAlpha => gl::ONE_MINUS_DST_ALPHA,
+        }
+    }
+}
+
+/// How the weighted source and destination colors are combined
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlendOperation {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl From<BlendOperation> for u32 {
+    fn from(operation: BlendOperation) -> u32 {
+        match operation {
+            BlendOperation::Add => gl::FUNC_ADD,
+            BlendOperation::Subtract => gl::FUNC_SUBTRACT,
+            BlendOperation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            BlendOperation::Min => gl::MIN,
+            BlendOperation::Max => gl::MAX,
+        }
+    }
+}
+
+/// Blends `source * source_factor` with `destination * destination_factor` through
+/// `operation`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BlendFunction {
+    pub source: BlendFactor,
+    pub destination: BlendFactor,
+    pub operation: BlendOperation,
+}
+
+impl BlendFunction {
+    /// Blends with `source` and `destination` factors, adding the results
+    pub const fn new(source: BlendFactor, destination: BlendFactor) -> Self {
+        Self {
+            source,
+            destination,
+            operation: BlendOperation::Add,
+        }
+    }
+}
+
+/// How fragments are combined with the colors already in the target
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Blend {
+    /// Fragments replace the colors in the target
+    Disabled,
+    /// Fragments are blended with separate functions for the color and alpha channels
+    Enabled {
+        color: BlendFunction,
+        alpha: BlendFunction,
+    },
+}
+
+impl Blend {
+    /// Standard transparency, for colors that aren't premultiplied by their alpha
+    pub const ALPHA: Self = Self::Enabled {
+        color: BlendFunction::new(BlendFactor::SourceAlpha, BlendFactor::OneMinusSourceAlpha),
+        alpha: BlendFunction::new(BlendFactor::One, BlendFactor::OneMinusSourceAlpha),
+    };
+
+    /// Transparency for colors premultiplied by their alpha
+    pub const PREMULTIPLIED_ALPHA: Self = Self::Enabled {
+        color: BlendFunction::new(BlendFactor::One, BlendFactor::OneMinusSourceAlpha),
+        alpha: BlendFunction::new(BlendFactor::One, BlendFactor::OneMinusSourceAlpha),
+    };
+
+    /// Adds fragments to the target, e.g. for particles and accumulated lighting
+    pub const ADDITIVE: Self = Self::Enabled {
+        color: BlendFunction::new(BlendFactor::One, BlendFactor::One),
+        alpha: BlendFunction::new(BlendFactor::One, BlendFactor::One),
+    };
+}
+
+/// The depth test and depth writes
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Depth {
+    /// Fragments pass when their depth compares this way against the stored depth. `None`
+    /// disables the depth test, which also disables depth writes.
+    pub test: Option<Comparison>,
+    /// Whether fragments that pass write their depth
+    pub write: bool,
+}
+
+impl Depth {
+    /// Neither tests nor writes depth
+    pub const DISABLED: Self = Self {
+        test: None,
+        write: false,
+    };
+
+    /// Keeps the nearest fragments, writing their depth
+    pub const LESS: Self = Self::test(Comparison::Less);
+
+    /// Tests fragments with `comparison` and writes the depth of those that pass
+    pub const fn test(comparison: Comparison) -> Self {
+        Self {
+            test: Some(comparison),
+            write: true,
+        }
+    }
+
+    /// Tests fragments with `comparison` without writing depth, e.g. for transparent geometry
+    pub const fn read_only(comparison: Comparison) -> Self {
+        Self {
+            test: Some(comparison),
+            write: false,
+        }
+    }
+}
+
+/// What happens to the stored stencil value after a stencil test
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum StencilOperation {
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl From<StencilOperation> for u32 {
+    fn from(operation: StencilOperation) -> u32 {
+        match operation {
+            StencilOperation::Keep => gl::KEEP,
+            StencilOperation::Zero => gl::ZERO,
+            StencilOperation::Replace => gl::REPLACE,
+            StencilOperation::Increment => gl::INCR,
+            StencilOperation::IncrementWrap => gl::INCR_WRAP,
+            StencilOperation::Decrement => gl::DECR,
+            StencilOperation::DecrementWrap => gl::DECR_WRAP,
+            StencilOperation::Invert => gl::INVERT,
+        }
+    }
+}
+
+/// The stencil test and stencil writes, applied to front and back faces alike
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Stencil {
+    /// Fragments pass when `reference & read_mask` compares this way against
+    /// `stored & read_mask`. `None` disables the stencil test.
+    pub test: Option<Comparison>,
+    pub reference: i32,
+    pub read_mask: u32,
+    /// The bits of the stored value the operations may change
+    pub write_mask: u32,
+    /// Applied when the stencil test fails
+    pub fail: StencilOperation,
+    /// Applied when the stencil test passes but the depth test fails
+    pub depth_fail: StencilOperation,
+    /// Applied when both tests pass
+    pub pass: StencilOperation,
+}
+
+impl Default for Stencil {
+    fn default() -> Self {
+        Self {
+            test: None,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            fail: StencilOperation::Keep,
+            depth_fail: StencilOperation::Keep,
+            pass: StencilOperation::Keep,
+        }
+    }
+}
+
+/// How polygons are rasterized
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PolygonMode {
+    Fill,
+    /// Only the edges, e.g. for wireframes
+    Line,
+    /// Only the vertices
+    Point,
+}
+
+impl From<PolygonMode> for u32 {
+    fn from(mode: PolygonMode) -> u32 {
+        match mode {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        }
+    }
+}
+
+/// Fixed-function state applied for the duration of a single draw call, so objects needing
+/// different state don't have to change it globally between draws.
+///
+/// State left as `None` keeps whatever is set on the context, e.g. by
+/// [`crate::Renderer::cull_faces`] or by the surface being drawn to. Other state is compared
+/// against the context's current state, changed only where it differs and restored after the
+/// draw. The default value changes no state.
+///
+/// The current state is tracked by glium2 rather than queried on every draw, so state changed
+/// with raw OpenGL calls must be followed by [`invalidate_state_cache`].
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct DrawParameters {
+    /// How fragments are combined with the colors already in the target
+    pub blend: Option<Blend>,
+
+    /// The depth test and whether depth is written
+    pub depth: Option<Depth>,
+
+    /// The stencil test and how the stencil buffer is updated
+    pub stencil: Option<Stencil>,
+
+    /// Which faces are discarded before rasterization
+    pub culling: Option<CullingMode>,
+
+    /// Restricts drawing to a rectangle of the target
+    pub scissor: Option<Rect>,
+
+    /// Whether polygons are filled or drawn as edges or points
+    pub polygon_mode: Option<PolygonMode>,
+
+    /// The rectangle of the target clip space is mapped to
+    pub viewport: Option<Rect>,
+
+    /// The width of lines in pixels. Core contexts only support widths other than `1.0`
+    /// when the driver allows wide lines.
+    pub line_width: Option<f32>,
+
+    /// Rasterizes every pixel a primitive touches, rather than only those whose center it
+    /// covers. Useful for voxelization.
+    ///
+    /// Requires `GL_NV_conservative_raster` or `GL_INTEL_conservative_rasterization`; the
+    /// setting is ignored with a warning when neither is available.
+    pub conservative_rasterization: bool,
+
+    /// Restricts which samples of a multisampled target fragments may write to. Bit `i`
+    /// enables sample `i`; `None` leaves every sample enabled.
+    pub sample_mask: Option<u32>,
+
+    /// Added to every index before the vertex is fetched, so a mesh packed into a larger
+    /// vertex buffer can be drawn with indices relative to its first vertex. Only used by
+    /// indexed draws.
+    pub base_vertex: i32,
+
+    /// Draws this many instances of the geometry, exposed to shaders as `gl_InstanceID`.
+    /// `None` draws a single, non-instanced copy.
+    pub instances: Option<u32>,
+
+    /// The instance that per-instance attributes start at, e.g. to draw a range of a shared
+    /// instance buffer
+    pub base_instance: u32,
+}
+
+impl DrawParameters {
+    /// Applies the parameters and the patch size of `mode`, returning a guard that restores the
+    /// defaults when dropped
+    pub(crate) fn apply(&self, mode: DrawMode) -> DrawParametersGuard {
+        let blend = self.blend.and_then(|blend| {
+            change(|current: &BlendState| match blend {
+                Blend::Disabled => BlendState {
+                    enabled: false,
+                    ..*current
+                },
+                Blend::Enabled { color, alpha } => BlendState {
+                    enabled: true,
+                    equations: [color.operation.into(), alpha.operation.into()],
+                    factors: [
+                        color.source.into(),
+                        color.destination.into(),
+                        alpha.source.into(),
+                        alpha.destination.into(),
+                    ],
+                },
+            })
+        });
+        let depth = self.depth.and_then(|depth| {
+            change(|current: &DepthState| DepthState {
+                test: depth.test.is_some(),
+                function: depth.test.map_or(current.function, u32::from),
+                write: depth.write,
+            })
+        });
+        let stencil = self.stencil.and_then(|stencil| {
+            change(|current: &StencilState| match stencil.test {
+                None => StencilState {
+                    test: false,
+                    ..*current
+                },
+                Some(test) => StencilState {
+                    test: true,
+                    function: test.into(),
+                    reference: stencil.reference,
+                    read_mask: stencil.read_mask,
+                    write_mask: stencil.write_mask,
+                    operations: [
+                        stencil.fail.into(),
+                        stencil.depth_fail.into(),
+                        stencil.pass.into(),
+                    ],
+                },
+            })
+        });
+        let culling = self
+            .culling
+            .and_then(|culling| change(|current: &CullState| current.with_mode(culling)));
+        let scissor = self.scissor.and_then(|rect| {
+            change(|_: &ScissorState| ScissorState {
+                enabled: true,
+                rect: rect_to_gl(rect),
+            })
+        });
+        let polygon_mode = self
+            .polygon_mode
+            .and_then(|mode| change(|_: &PolygonModeState| PolygonModeState(mode.into())));
+        let viewport = self
+            .viewport
+            .and_then(|rect| change(|_: &ViewportState| ViewportState(rect_to_gl(rect))));
+        let line_width = self
+            .line_width
+            .and_then(|width| change(|_: &LineWidthState| LineWidthState(width)));
+
+        let conservative_rasterization = if self.conservative_rasterization {
+            let capability = context::conservative_rasterization_capability();
+            match capability {
+                Some(capability) => unsafe { gl::Enable(capability) },
+                None => diagnostic!(
+                    Warning,
+                    Draw,
+                    "Conservative rasterization isn't supported by this context"
+                ),
+            }
+            capability
+        } else {
+            None
+        };
+
+        if let Some(mask) = self.sample_mask {
+            unsafe {
+                gl::Enable(gl::SAMPLE_MASK);
+                gl::SampleMaski(0, mask);
+            };
+        }
+
+        let patch_vertices = if let DrawMode::Patches { vertices } = mode {
+            unsafe { gl::PatchParameteri(gl::PATCH_VERTICES, vertices as i32) };
+            true
+        } else {
+            false
+        };
+
+        DrawParametersGuard {
+            blend,
+            depth,
+            stencil,
+            culling,
+            scissor,
+            polygon_mode,
+            viewport,
+            line_width,
+            conservative_rasterization,
+            sample_mask: self.sample_mask.is_some(),
+            patch_vertices,
+        }
+    }
+}
+
+/// Resets the state changed by [`DrawParameters::apply`] when dropped
+pub(crate) struct DrawParametersGuard {
+    blend: Option<BlendState>,
+    depth: Option<DepthState>,
+    stencil: Option<StencilState>,
+    culling: Option<CullState>,
+    scissor: Option<ScissorState>,
+    polygon_mode: Option<PolygonModeState>,
+    viewport: Option<ViewportState>,
+    line_width: Option<LineWidthState>,
+    conservative_rasterization: Option<u32>,
+    sample_mask: bool,
+    patch_vertices: bool,
+}
+
+impl Drop for DrawParametersGuard {
+    fn drop(&mut self) {
+        self.blend.iter().for_each(GlState::set);
+        self.depth.iter().for_each(GlState::set);
+        self.stencil.iter().for_each(GlState::set);
+        self.culling.iter().for_each(GlState::set);
+        self.scissor.iter().for_each(GlState::set);
+        self.polygon_mode.iter().for_each(GlState::set);
+        self.viewport.iter().for_each(GlState::set);
+        self.line_width.iter().for_each(GlState::set);
+        unsafe {
+            if let Some(capability) = self.conservative_rasterization {
+                gl::Disable(capability);
+            }
+            if self.sample_mask {
+                gl::SampleMaski(0, !0);
+                gl::Disable(gl::SAMPLE_MASK);
+            }
+            if self.patch_vertices {
+                gl::PatchParameteri(gl::PATCH_VERTICES, 3);
+            }
+        };
+    }
+}
+
+/// Forgets the fixed-function state glium2 has recorded for the current context, so that it's
+/// queried again by the next draw.
+///
+/// glium2 tracks the blend, depth, stencil, culling, scissor, polygon mode, viewport and line
+/// width state it sets, so that [`DrawParameters`] don't have to query the context on every
+/// draw. Call this after changing any of that state with raw OpenGL calls, or after making a
+/// different context current on this thread.
+pub fn invalidate_state_cache() {
+    SHADOW.with_borrow_mut(|shadow| *shadow = StateShadow::default());
+}
+
+/// Sets the viewport (x, y, width and height), returning the previous one
+pub(crate) fn replace_viewport(viewport: [i32; 4]) -> [i32; 4] {
+    let previous = ViewportState::current();
+    if previous.0 != viewport {
+        ViewportState(viewport).set();
+    }
+    previous.0
+}
+
+/// Enables or disables the scissor test with the box `rect`, returning the previous state
+pub(crate) fn replace_scissor(enabled: bool, rect: [i32; 4]) -> (bool, [i32; 4]) {
+    let previous = ScissorState::current();
+    let wanted = ScissorState { enabled, rect };
+    if previous != wanted {
+        wanted.set();
+    }
+    (previous.enabled, previous.rect)
+}
+
+/// Sets which faces are culled for draws that don't override it
+pub(crate) fn set_culling(culling: CullingMode) {
+    CullState::current().with_mode(culling).set();
+}
+
+/// The state last set on or queried from the current context, so that each piece is only
+/// queried once. A piece is `None` until a draw first needs it.
+#[derive(Debug, Default)]
+struct StateShadow {
+    blend: Option<BlendState>,
+    depth: Option<DepthState>,
+    stencil: Option<StencilState>,
+    culling: Option<CullState>,
+    scissor: Option<ScissorState>,
+    polygon_mode: Option<PolygonModeState>,
+    viewport: Option<ViewportState>,
+    line_width: Option<LineWidthState>,
+}
+
+thread_local! {
+    // A context is current on a single thread at a time, so the shadow follows the thread
+    static SHADOW: RefCell<StateShadow> = RefCell::new(StateShadow::default());
+}
+
+/// A piece of context state that [`DrawParameters`] changes and restores
+trait GlState: Copy + PartialEq {
+    /// Queries the state set on the context
+    fn query() -> Self;
+
+    /// Sets the state on the context
+    fn write(&self);
+
+    /// Returns where the state is recorded in `shadow`
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self>;
+
+    /// Returns the state set on the context, only querying it if it isn't recorded yet
+    fn current() -> Self {
+        SHADOW.with_borrow_mut(|shadow| *Self::shadowed(shadow).get_or_insert_with(Self::query))
+    }
+
+    /// Sets the state on the context and records it
+    fn set(&self) {
+        self.write();
+        SHADOW.with_borrow_mut(|shadow| *Self::shadowed(shadow) = Some(*self));
+    }
+}
+
+/// Sets the state returned by `wanted` for the current state if it differs from it.
+///
+/// # Returns
+///
+/// The previous state to restore after the draw, or `None` if nothing changed.
+fn change<S: GlState>(wanted: impl FnOnce(&S) -> S) -> Option<S> {
+    let current = S::current();
+    let wanted = wanted(&current);
+    if wanted == current {
+        return None;
+    }
+    wanted.set();
+    Some(current)
+}
+
+fn rect_to_gl(rect: Rect) -> [i32; 4] {
+    [rect.x, rect.y, rect.width as i32, rect.height as i32]
+}
+
+fn is_enabled(capability: u32) -> bool {
+    unsafe { gl::IsEnabled(capability) == gl::TRUE }
+}
+
+fn set_enabled(capability: u32, enabled: bool) {
+    unsafe {
+        if enabled {
+            gl::Enable(capability);
+        } else {
+            gl::Disable(capability);
+        }
+    };
+}
+
+fn integer(name: u32) -> i32 {
+    let mut value = 0;
+    unsafe { gl::GetIntegerv(name, &mut value) };
+    value
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct BlendState {
+    enabled: bool,
+    /// The color and alpha equations
+    equations: [u32; 2],
+    /// The color source and destination, then the alpha source and destination factors
+    factors: [u32; 4],
+}
+
+impl GlState for BlendState {
+    fn query() -> Self {
+        Self {
+            enabled: is_enabled(gl::BLEND),
+            equations: [gl::BLEND_EQUATION_RGB, gl::BLEND_EQUATION_ALPHA]
+                .map(|name| integer(name) as u32),
+            factors: [
+                gl::BLEND_SRC_RGB,
+                gl::BLEND_DST_RGB,
+                gl::BLEND_SRC_ALPHA,
+                gl::BLEND_DST_ALPHA,
+            ]
+            .map(|name| integer(name) as u32),
+        }
+    }
+
+    fn write(&self) {
+        set_enabled(gl::BLEND, self.enabled);
+        let [color, alpha] = self.equations;
+        let [source, destination, alpha_source, alpha_destination] = self.factors;
+        unsafe {
+            gl::BlendEquationSeparate(color, alpha);
+            gl::BlendFuncSeparate(source, destination, alpha_source, alpha_destination);
+        };
+    }
+
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self> {
+        &mut shadow.blend
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct DepthState {
+    test: bool,
+    function: u32,
+    write: bool,
+}
+
+impl GlState for DepthState {
+    fn query() -> Self {
+        let mut write = 0;
+        unsafe { gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut write) };
+        Self {
+            test: is_enabled(gl::DEPTH_TEST),
+            function: integer(gl::DEPTH_FUNC) as u32,
+            write: write == gl::TRUE,
+        }
+    }
+
+    fn write(&self) {
+        set_enabled(gl::DEPTH_TEST, self.test);
+        unsafe {
+            gl::DepthFunc(self.function);
+            gl::DepthMask(if self.write { gl::TRUE } else { gl::FALSE });
+        };
+    }
+
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self> {
+        &mut shadow.depth
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct StencilState {
+    test: bool,
+    function: u32,
+    reference: i32,
+    read_mask: u32,
+    write_mask: u32,
+    /// The operations on stencil failure, depth failure and success
+    operations: [u32; 3],
+}
+
+impl GlState for StencilState {
+    fn query() -> Self {
+        Self {
+            test: is_enabled(gl::STENCIL_TEST),
+            function: integer(gl::STENCIL_FUNC) as u32,
+            reference: integer(gl::STENCIL_REF),
+            read_mask: integer(gl::STENCIL_VALUE_MASK) as u32,
+            write_mask: integer(gl::STENCIL_WRITEMASK) as u32,
+            operations: [
+                gl::STENCIL_FAIL,
+                gl::STENCIL_PASS_DEPTH_FAIL,
+                gl::STENCIL_PASS_DEPTH_PASS,
+            ]
+            .map(|name| integer(name) as u32),
+        }
+    }
+
+    fn write(&self) {
+        set_enabled(gl::STENCIL_TEST, self.test);
+        let [fail, depth_fail, pass] = self.operations;
+        unsafe {
+            gl::StencilFunc(self.function, self.reference, self.read_mask);
+            gl::StencilMask(self.write_mask);
+            gl::StencilOp(fail, depth_fail, pass);
+        };
+    }
+
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self> {
+        &mut shadow.stencil
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct CullState {
+    enabled: bool,
+    face: u32,
+    front: u32,
+}
+
+impl CullState {
+    /// Returns the state culling faces as `culling` describes, keeping the face and winding of
+    /// this state when culling is disabled
+    fn with_mode(&self, culling: CullingMode) -> Self {
+        match culling {
+            CullingMode::None => Self {
+                enabled: false,
+                ..*self
+            },
+            CullingMode::Clockwise => Self {
+                enabled: true,
+                face: gl::BACK,
+                front: gl::CCW,
+            },
+            CullingMode::CounterClockwise => Self {
+                enabled: true,
+                face: gl::BACK,
+                front: gl::CW,
+            },
+        }
+    }
+}
+
+impl GlState for CullState {
+    fn query() -> Self {
+        Self {
+            enabled: is_enabled(gl::CULL_FACE),
+            face: integer(gl::CULL_FACE_MODE) as u32,
+            front: integer(gl::FRONT_FACE) as u32,
+        }
+    }
+
+    fn write(&self) {
+        set_enabled(gl::CULL_FACE, self.enabled);
+        unsafe {
+            gl::CullFace(self.face);
+            gl::FrontFace(self.front);
+        };
+    }
+
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self> {
+        &mut shadow.culling
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct ScissorState {
+    enabled: bool,
+    rect: [i32; 4],
+}
+
+impl GlState for ScissorState {
+    fn query() -> Self {
+        let mut rect = [0; 4];
+        unsafe { gl::GetIntegerv(gl::SCISSOR_BOX, rect.as_mut_ptr()) };
+        Self {
+            enabled: is_enabled(gl::SCISSOR_TEST),
+            rect,
+        }
+    }
+
+    fn write(&self) {
+        set_enabled(gl::SCISSOR_TEST, self.enabled);
+        let [x, y, width, height] = self.rect;
+        unsafe { gl::Scissor(x, y, width, height) };
+    }
+
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self> {
+        &mut shadow.scissor
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct PolygonModeState(u32);
+
+impl GlState for PolygonModeState {
+    fn query() -> Self {
+        // Some drivers still report the front and back modes separately
+        let mut modes = [0; 2];
+        unsafe { gl::GetIntegerv(gl::POLYGON_MODE, modes.as_mut_ptr()) };
+        Self(modes[0] as u32)
+    }
+
+    fn write(&self) {
+        unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, self.0) };
+    }
+
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self> {
+        &mut shadow.polygon_mode
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct ViewportState([i32; 4]);
+
+impl GlState for ViewportState {
+    fn query() -> Self {
+        let mut viewport = [0; 4];
+        unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+        Self(viewport)
+    }
+
+    fn write(&self) {
+        let [x, y, width, height] = self.0;
+        unsafe { gl::Viewport(x, y, width, height) };
+    }
+
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self> {
+        &mut shadow.viewport
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct LineWidthState(f32);
+
+impl GlState for LineWidthState {
+    fn query() -> Self {
+        let mut width = 1.0;
+        unsafe { gl::GetFloatv(gl::LINE_WIDTH, &mut width) };
+        Self(width)
+    }
+
+    fn write(&self) {
+        unsafe { gl::LineWidth(self.0) };
+    }
+
+    fn shadowed(shadow: &mut StateShadow) -> &mut Option<Self> {
+        &mut shadow.line_width
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    buffer::VertexBufferSlice,
+    draw_parameters::DrawParameters,
+    mesh::{Mesh, Primitive},
+    renderer::{self, DrawError, DrawMode},
+    shader::{LinkedProgram, Vertex},
+    types::IndexType,
+    uniforms::Uniforms,
+};
+
+/// Vertices together with the [`DrawMode`] they are drawn with, with the vertex and index
+/// types erased so that different kinds of geometry can be stored together
+pub trait Geometry {
+    /// Returns the mode the vertices are drawn with
+    fn draw_mode(&self) -> DrawMode;
+
+    /// Returns the number of vertices, or indices for indexed geometry, drawn
+    fn vertex_count(&self) -> usize;
+
+    /// Draws the vertices with `program`, `uniforms` and `parameters`
+    fn draw_geometry(
+        &self,
+        program: &LinkedProgram,
+        uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError>;
+}
+
+impl<P: Primitive> Geometry for P {
+    fn draw_mode(&self) -> DrawMode {
+        Primitive::draw_mode(self)
+    }
+
+    fn vertex_count(&self) -> usize {
+        VertexBufferSlice::from(self.buffer()).len()
+    }
+
+    fn draw_geometry(
+        &self,
+        program: &LinkedProgram,
+        uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError> {
+        renderer::draw_vertex_buffer(
+            self.buffer().into(),
+            program,
+            Primitive::draw_mode(self),
+            uniforms,
+            parameters,
+        )
+    }
+}
+
+impl<V: Vertex> Geometry for Mesh<V> {
+    fn draw_mode(&self) -> DrawMode {
+        Mesh::draw_mode(self)
+    }
+
+    fn vertex_count(&self) -> usize {
+        VertexBufferSlice::from(self.buffer()).len()
+    }
+
+    fn draw_geometry(
+        &self,
+        program: &LinkedProgram,
+        uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError> {
+        renderer::draw_vertex_buffer(
+            self.buffer().into(),
+            program,
+            Mesh::draw_mode(self),
+            uniforms,
+            parameters,
+        )
+    }
+}
+
+impl<V: Vertex, I: IndexType> Geometry for (VertexBufferSlice<'_, V, I>, DrawMode) {
+    fn draw_mode(&self) -> DrawMode {
+        self.1
+    }
+
+    fn vertex_count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn draw_geometry(
+        &self,
+        program: &LinkedProgram,
+        uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError> {
+        renderer::draw_vertex_buffer(self.0, program, self.1, uniforms, parameters)
+    }
+}
+
+/// Everything needed for a draw call: geometry, the program shading it, its uniforms and
+/// the fixed-function state to draw with.
+///
+/// Scenes made of different kinds of renderables can be stored as `dyn Drawable` and drawn
+/// with [`crate::Renderer::submit`].
+pub trait Drawable {
+    /// Returns the vertices to draw
+    fn geometry(&self) -> &dyn Geometry;
+
+    /// Returns the program to draw the vertices with
+    fn program(&self) -> &LinkedProgram;
+
+    /// Returns the uniforms uploaded to [`Drawable::program`] before drawing
+    fn uniforms(&self) -> &Uniforms;
+
+    /// Returns the state applied for the duration of the draw
+    fn parameters(&self) -> DrawParameters {
+        DrawParameters::default()
+    }
+}
+
+impl<D: Drawable + ?Sized> Drawable for &D {
+    fn geometry(&self) -> &dyn Geometry {
+        (**self).geometry()
+    }
+
+    fn program(&self) -> &LinkedProgram {
+        (**self).program()
+    }
+
+    fn uniforms(&self) -> &Uniforms {
+        (**self).uniforms()
+    }
+
+    fn parameters(&self) -> DrawParameters {
+        (**self).parameters()
+    }
+}
+
+impl<D: Drawable + ?Sized> Drawable for Box<D> {
+    fn geometry(&self) -> &dyn Geometry {
+        (**self).geometry()
+    }
+
+    fn program(&self) -> &LinkedProgram {
+        (**self).program()
+    }
+
+    fn uniforms(&self) -> &Uniforms {
+        (**self).uniforms()
+    }
+
+    fn parameters(&self) -> DrawParameters {
+        (**self).parameters()
+    }
+}
+
+/// A [`Drawable`] assembled from borrowed geometry and program
+pub struct DrawItem<'a> {
+    geometry: &'a dyn Geometry,
+    program: &'a LinkedProgram,
+    uniforms: Uniforms,
+    parameters: DrawParameters,
+}
+
+impl<'a> DrawItem<'a> {
+    /// Draws `geometry` with `program` and `uniforms`, using the default [`DrawParameters`]
+    pub fn new(geometry: &'a dyn Geometry, program: &'a LinkedProgram, uniforms: Uniforms) -> Self {
+        Self {
+            geometry,
+            program,
+            uniforms,
+            parameters: DrawParameters::default(),
+        }
+    }
+
+    /// Replaces the state applied for the duration of the draw
+    pub fn with_parameters(mut self, parameters: DrawParameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Returns the uniforms, e.g. to update them between frames
+    pub fn uniforms_mut(&mut self) -> &mut Uniforms {
+        &mut self.uniforms
+    }
+}
+
+impl Drawable for DrawItem<'_> {
+    fn geometry(&self) -> &dyn Geometry {
+        self.geometry
+    }
+
+    fn program(&self) -> &LinkedProgram {
+        self.program
+    }
+
+    fn uniforms(&self) -> &Uniforms {
+        &self.uniforms
+    }
+
+    fn parameters(&self) -> DrawParameters {
+        self.parameters
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    layout::{pad_to, Std140},
+    mesh::FullscreenQuad,
+    renderer::{DrawError, Renderer},
+    shader::LinkedProgram,
+    texture::{Texture2D, TextureCube},
+    uniforms,
+};
+use glm::{Mat4, Vec3, Vec4};
+
+/// The uniform buffer binding point the renderer binds the environment block to. See
+/// [`crate::Renderer::set_fog`].
+pub const ENVIRONMENT_BINDING: u32 = 15;
+
+/// How fog density grows with the distance from the camera
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum FogMode {
+    /// No distance fog
+    #[default]
+    None,
+    /// Fog that increases linearly from nothing at `start` to full at `end`
+    Linear { start: f32, end: f32 },
+    /// Fog with a factor of `1 - e^(-density * distance)`
+    Exponential { density: f32 },
+    /// Fog with a factor of `1 - e^(-(density * distance)²)`, which stays clearer near the
+    /// camera than [`FogMode::Exponential`]
+    ExponentialSquared { density: f32 },
+}
+
+impl FogMode {
+    fn index(self) -> i32 {
+        match self {
+            FogMode::None => 0,
+            FogMode::Linear { .. } => 1,
+            FogMode::Exponential { .. } => 2,
+            FogMode::ExponentialSquared { .. } => 3,
+        }
+    }
+}
+
+/// Fog that is densest at `base_height` and thins out exponentially above it, e.g. for mist in
+/// valleys
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HeightFog {
+    /// The height at which the fog has its full density
+    pub base_height: f32,
+    /// How quickly the fog thins out with height
+    pub falloff: f32,
+    /// The density of the fog at `base_height`
+    pub density: f32,
+}
+
+/// Fog settings shared by every shader that includes [`Fog::GLSL`].
+///
+/// Set through [`crate::Renderer::set_fog`], which uploads them to a uniform block bound to
+/// [`ENVIRONMENT_BINDING`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fog {
+    /// Distance fog
+    pub mode: FogMode,
+    /// Height fog, applied on top of distance fog
+    pub height: Option<HeightFog>,
+    /// The color objects fade into
+    pub color: Vec3,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            mode: FogMode::None,
+            height: None,
+            color: glm::vec3(0.5, 0.5, 0.5),
+        }
+    }
+}
+
+impl Fog {
+    /// The environment uniform block and an `apply_fog` function, to be inserted after the
+    /// `#version` directive of a fragment shader.
+    ///
+    /// `apply_fog(color, world_position, camera_position)` returns `color` faded towards the
+    /// fog color according to the current settings.
+    pub const GLSL: &'static str = r#"
+        layout(std140, binding = 15) uniform Environment {
+            int fog_mode;
+            float fog_start;
+            float fog_end;
+            float fog_density;
+            vec3 fog_color;
+            bool height_fog_enabled;
+            float height_fog_base;
+            float height_fog_falloff;
+            float height_fog_density;
+        };
+
+        vec3 apply_fog(vec3 color, vec3 world_position, vec3 camera_position) {
+            float distance = length(world_position - camera_position);
+            float fog = 0.0;
+            if (fog_mode == 1) {
+                fog = clamp((distance - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+            } else if (fog_mode == 2) {
+                fog = 1.0 - exp(-fog_density * distance);
+            } else if (fog_mode == 3) {
+                float scaled = fog_density * distance;
+                fog = 1.0 - exp(-scaled * scaled);
+            }
+
+            if (height_fog_enabled) {
+                // Integrates the density along the view ray through the exponential layer
+                float start = camera_position.y - height_fog_base;
+                float delta = world_position.y - camera_position.y;
+                float amount = height_fog_density * exp(-height_fog_falloff * start) * distance;
+                if (abs(delta) > 0.001) {
+                    float t = height_fog_falloff * delta;
+                    amount *= (1.0 - exp(-t)) / t;
+                }
+                fog = 1.0 - (1.0 - fog) * exp(-max(amount, 0.0));
+            }
+
+            return mix(color, fog_color, fog);
+        }
+    "#;
+}
+
+impl Std140 for Fog {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 48;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        let (fog_start, fog_end, fog_density) = match self.mode {
+            FogMode::None => (0.0, 0.0, 0.0),
+            FogMode::Linear { start, end } => (start, end, 0.0),
+            FogMode::Exponential { density } | FogMode::ExponentialSquared { density } => {
+                (0.0, 0.0, density)
+            }
+        };
+        let height = self.height.unwrap_or(HeightFog {
+            base_height: 0.0,
+            falloff: 0.0,
+            density: 0.0,
+        });
+
+        self.mode.index().write_std140(out);
+        fog_start.write_std140(out);
+        fog_end.write_std140(out);
+        fog_density.write_std140(out);
+        self.color.write_std140(out);
+        self.height.is_some().write_std140(out);
+        height.base_height.write_std140(out);
+        height.falloff.write_std140(out);
+        height.density.write_std140(out);
+        pad_to(out, start + Self::SIZE);
+    }
+}
+
+/// What the renderer fills the screen with when it's cleared. See
+/// [`crate::Renderer::set_background`].
+pub enum Background {
+    /// A single color
+    Color(Vec4),
+    /// A vertical gradient from `top` at the top of the screen to `bottom` at the bottom
+    Gradient { top: Vec4, bottom: Vec4 },
+    /// A cube map seen from the inside
+    Skybox(TextureCube),
+    /// An equirectangular panorama, with the horizon along the middle of the texture
+    Panorama(Texture2D),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Color(glm::vec4(0.0, 0.0, 0.0, 1.0))
+    }
+}
+
+impl Background {
+    /// Returns the color the screen is cleared to before the background is drawn
+    pub(crate) fn clear_color(&self) -> Vec4 {
+        match *self {
+            Background::Color(color) => color,
+            _ => glm::vec4(0.0, 0.0, 0.0, 1.0),
+        }
+    }
+}
+
+/// Draws backgrounds that aren't a single color with a full-screen pass
+pub(crate) struct BackgroundPass {
+    program: LinkedProgram,
+    quad: FullscreenQuad,
+}
+
+impl BackgroundPass {
+    const GRADIENT_SHADER: &'static str = r#"
+        #version 460 core
+
+        in vec2 uv;
+
+        uniform vec4 u_top;
+        uniform vec4 u_bottom;
+
+        out vec4 color;
+
+        void main() {
+            color = mix(u_bottom, u_top, uv.y);
+        }
+    "#;
+
+    /// The direction through each pixel, shared by the skybox and panorama shaders
+    const VIEW_DIRECTION: &'static str = r#"
+        #version 460 core
+
+        in vec2 uv;
+
+        uniform mat4 u_inverse_view_projection;
+
+        out vec4 color;
+
+        vec3 view_direction() {
+            vec4 point = u_inverse_view_projection * vec4(uv * 2.0 - 1.0, 0.5, 1.0);
+            return normalize(point.xyz / point.w);
+        }
+    "#;
+
+    const SKYBOX_SHADER: &'static str = r#"
+        uniform samplerCube u_skybox;
+
+        void main() {
+            color = texture(u_skybox, view_direction());
+        }
+    "#;
+
+    const PANORAMA_SHADER: &'static str = r#"
+        uniform sampler2D u_panorama;
+
+        void main() {
+            vec3 direction = view_direction();
+            vec2 panorama_uv = vec2(
+                atan(direction.z, direction.x) / (2.0 * 3.14159265) + 0.5,
+                asin(clamp(direction.y, -1.0, 1.0)) / 3.14159265 + 0.5
+            );
+            color = texture(u_panorama, panorama_uv);
+        }
+    "#;
+
+    /// Creates the pass for `background`, or `None` if it's a single color and only needs a
+    /// clear
+    pub(crate) fn new(background: &Background) -> Option<Self> {
+        let source = match background {
+            Background::Color(_) => return None,
+            Background::Gradient { .. } => Self::GRADIENT_SHADER.to_owned(),
+            Background::Skybox(_) => format!("{}{}", Self::VIEW_DIRECTION, Self::SKYBOX_SHADER),
+            Background::Panorama(_) => {
+                format!("{}{}", Self::VIEW_DIRECTION, Self::PANORAMA_SHADER)
+            }
+        };
+
+        #[allow(clippy::expect_used)]
+        let program =
+            FullscreenQuad::program(&source).expect("Built-in background shader failed to compile");
+        Some(Self {
+            program,
+            quad: FullscreenQuad::new(),
+        })
+    }
+
+    /// Draws `background`, seen through `inverse_view_projection` for skyboxes and panoramas
+    pub(crate) fn draw(
+        &self,
+        renderer: &Renderer,
+        background: &Background,
+        inverse_view_projection: Mat4,
+    ) -> Result<(), DrawError> {
+        let program = &self.program;
+        let uniforms = match background {
+            Background::Color(_) => return Ok(()),
+            Background::Gradient { top, bottom } => uniforms!(program => {
+                "u_top": *top,
+                "u_bottom": *bottom
+            }),
+            Background::Skybox(texture) => uniforms!(program => {
+                "u_skybox": texture.binding(),
+                "u_inverse_view_projection": inverse_view_projection
+            }),
+            Background::Panorama(texture) => uniforms!(program => {
+                "u_panorama": texture.binding(),
+                "u_inverse_view_projection": inverse_view_projection
+            }),
+        };
+        self.quad.draw(renderer, program, &uniforms)
+    }
+}
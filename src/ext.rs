@@ -0,0 +1,79 @@
+//! Entry points of extensions that aren't part of the generated `gl` bindings
+
+use std::{
+    ffi::{c_char, c_void},
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// `GL_INT64_ARB`
+pub(crate) const INT64: u32 = 0x140E;
+/// `GL_UNSIGNED_INT64_ARB`
+pub(crate) const UNSIGNED_INT64: u32 = 0x140F;
+/// `GL_SHADER_BINARY_FORMAT_SPIR_V`
+pub(crate) const SHADER_BINARY_FORMAT_SPIR_V: u32 = 0x9551;
+
+macro_rules! extension_functions {
+    ( $( $storage: ident, $name: ident ( $($arg: ident: $ty: ty),* ) = [$($symbol: literal),+]; )* ) => {
+        $( static $storage: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut()); )*
+
+        /// Loads the extension entry points with `load_with`, trying each alias in turn
+        pub(crate) fn load_with<F>(mut load_with: F)
+        where
+            F: FnMut(&'static str) -> *const c_void,
+        {
+            $(
+                let pointer = [$($symbol),+]
+                    .into_iter()
+                    .map(&mut load_with)
+                    .find(|pointer| !pointer.is_null())
+                    .unwrap_or(std::ptr::null());
+                $storage.store(pointer.cast_mut(), Ordering::Relaxed);
+            )*
+        }
+
+        $(
+            /// Calls the extension function, which must have been loaded
+            pub(crate) unsafe fn $name($($arg: $ty),*) {
+                let pointer = $storage.load(Ordering::Relaxed);
+                debug_assert!(!pointer.is_null(), concat!(stringify!($name), " isn't loaded"));
+                let function: extern "system" fn($($ty),*) = std::mem::transmute(pointer);
+                function($($arg),*)
+            }
+        )*
+    };
+}
+
+extension_functions! {
+    UNIFORM_1I64, uniform_1i64(location: i32, value: i64) =
+        ["glUniform1i64ARB", "glUniform1i64NV"];
+    UNIFORM_1UI64, uniform_1ui64(location: i32, value: u64) =
+        ["glUniform1ui64ARB", "glUniform1ui64NV"];
+    PROGRAM_UNIFORM_1I64, program_uniform_1i64(program: u32, location: i32, value: i64) =
+        ["glProgramUniform1i64ARB", "glProgramUniform1i64NV"];
+    PROGRAM_UNIFORM_1UI64, program_uniform_1ui64(program: u32, location: i32, value: u64) =
+        ["glProgramUniform1ui64ARB", "glProgramUniform1ui64NV"];
+    SPECIALIZE_SHADER, specialize_shader(
+        shader: u32,
+        entry_point: *const c_char,
+        count: u32,
+        indices: *const u32,
+        values: *const u32
+    ) = ["glSpecializeShader", "glSpecializeShaderARB"];
+}
+
+/// Returns whether `glSpecializeShader` was loaded
+pub(crate) fn specialize_shader_loaded() -> bool {
+    !SPECIALIZE_SHADER.load(Ordering::Relaxed).is_null()
+}
+
+/// Returns whether the 64-bit integer uniform functions were loaded
+pub(crate) fn int64_uniforms_loaded() -> bool {
+    [
+        &UNIFORM_1I64,
+        &UNIFORM_1UI64,
+        &PROGRAM_UNIFORM_1I64,
+        &PROGRAM_UNIFORM_1UI64,
+    ]
+    .iter()
+    .all(|pointer| !pointer.load(Ordering::Relaxed).is_null())
+}
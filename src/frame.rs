@@ -0,0 +1,169 @@
+use crate::{
+    buffer::{IndexBuffer, VertexBuffer, VertexBufferSlice},
+    diagnostics::diagnostic,
+    draw_parameters::DrawParameters,
+    drawable::Drawable,
+    renderer::{DrawError, DrawMode, Renderer},
+    shader::{LinkedProgram, Vertex},
+    types::IndexType,
+    uniforms::Uniforms,
+};
+use std::time::{Duration, Instant};
+
+/// Statistics collected over the lifetime of a [`Frame`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The index of the frame, starting at `0` for the first frame of a renderer
+    pub frame_index: u64,
+    /// The number of draw calls issued
+    pub draw_calls: u32,
+    /// The number of clears issued
+    pub clears: u32,
+    /// The number of vertices (or indices, for indexed draws) submitted
+    pub vertices: usize,
+    /// The CPU time between [`Renderer::begin_frame`] and [`Frame::finish`]
+    pub cpu_time: Duration,
+}
+
+/// A single frame being rendered, obtained from [`Renderer::begin_frame`].
+///
+/// The frame borrows the renderer until it is finished with [`Frame::finish`] or
+/// [`Frame::finish_and_swap`].
+pub struct Frame<'a> {
+    renderer: &'a mut Renderer,
+    started: Instant,
+    stats: FrameStats,
+    finished: bool,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(renderer: &'a mut Renderer, frame_index: u64) -> Self {
+        Self {
+            renderer,
+            started: Instant::now(),
+            stats: FrameStats {
+                frame_index,
+                ..Default::default()
+            },
+            finished: false,
+        }
+    }
+
+    /// Returns the renderer this frame belongs to
+    pub fn renderer(&self) -> &Renderer {
+        self.renderer
+    }
+
+    /// Returns the renderer this frame belongs to mutably, e.g. to change the clear color
+    pub fn renderer_mut(&mut self) -> &mut Renderer {
+        self.renderer
+    }
+
+    /// Returns the statistics collected so far
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Clears the pixel buffer currently being drawn to. See [`Renderer::clear`].
+    pub fn clear(&mut self) {
+        self.renderer.clear();
+        self.stats.clears += 1;
+    }
+
+    /// Draws a buffer to the screen. See [`Renderer::draw`].
+    pub fn draw<'b, V: Vertex + 'b, I: IndexType + 'b>(
+        &mut self,
+        buffer: impl Into<VertexBufferSlice<'b, V, I>>,
+        shader_program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+    ) -> Result<(), DrawError> {
+        self.draw_with_parameters(
+            buffer,
+            shader_program,
+            mode,
+            uniforms,
+            &DrawParameters::default(),
+        )
+    }
+
+    /// Draws a buffer to the screen with `parameters` applied. See
+    /// [`Renderer::draw_with_parameters`].
+    pub fn draw_with_parameters<'b, V: Vertex + 'b, I: IndexType + 'b>(
+        &mut self,
+        buffer: impl Into<VertexBufferSlice<'b, V, I>>,
+        shader_program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError> {
+        let slice = buffer.into();
+        self.renderer
+            .draw_with_parameters(slice, shader_program, mode, uniforms, parameters)?;
+        self.stats.draw_calls += 1;
+        self.stats.vertices += slice.len();
+        Ok(())
+    }
+
+    /// Draws a buffer to the screen using a separate index buffer. See
+    /// [`Renderer::draw_indexed`].
+    pub fn draw_indexed<V: Vertex, I: IndexType, J: IndexType>(
+        &mut self,
+        buffer: &VertexBuffer<V, I>,
+        indices: &IndexBuffer<J>,
+        shader_program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+    ) -> Result<(), DrawError> {
+        self.renderer
+            .draw_indexed(buffer, indices, shader_program, mode, uniforms)?;
+        self.stats.draw_calls += 1;
+        self.stats.vertices += indices.len();
+        Ok(())
+    }
+
+    /// Draws `drawable` to the screen. See [`Renderer::submit`].
+    pub fn submit<D: Drawable + ?Sized>(&mut self, drawable: &D) -> Result<(), DrawError> {
+        self.renderer.submit(drawable)?;
+        self.stats.draw_calls += 1;
+        self.stats.vertices += drawable.geometry().vertex_count();
+        Ok(())
+    }
+
+    /// Ends the frame without presenting it
+    pub fn finish(mut self) -> FrameStats {
+        self.end()
+    }
+
+    /// Ends the frame and swaps the buffers of `window`, presenting the frame
+    #[cfg(feature = "backends")]
+    pub fn finish_and_swap<C>(mut self, window: &mut C) -> FrameStats
+    where
+        C: glfw::Context + ?Sized,
+    {
+        let stats = self.end();
+        window.swap_buffers();
+        stats
+    }
+
+    fn end(&mut self) -> FrameStats {
+        self.finished = true;
+        self.stats.cpu_time = self.started.elapsed();
+        self.renderer.end_frame(self.stats);
+        self.stats
+    }
+}
+
+impl Drop for Frame<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            diagnostic!(
+                Warning,
+                Usage,
+                "Frame {} was dropped without calling Frame::finish",
+                self.stats.frame_index
+            );
+            self.end();
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use crate::framebuffer::TargetDescriptor;
+use std::fmt::Write;
+
+/// A texture read or written by the passes of a [`FrameDescription`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentDescription {
+    pub name: String,
+    /// The size and format of the texture, or `None` if it's provided from outside the frame
+    pub descriptor: Option<TargetDescriptor>,
+}
+
+/// A pass of a [`FrameDescription`], referring to its attachments by index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassDescription {
+    pub name: String,
+    pub reads: Vec<usize>,
+    pub writes: Vec<usize>,
+}
+
+/// A point between two passes where an attachment written by the first is read by the second,
+/// so its writes must be visible before the second pass starts
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Barrier {
+    pub attachment: usize,
+    pub writer: usize,
+    pub reader: usize,
+}
+
+/// A description of the passes of a frame and the attachments flowing between them, for
+/// visualizing and debugging the structure of a frame.
+///
+/// Pipelines such as [`crate::deferred::DeferredRenderer`] and
+/// [`crate::postprocess::EffectChain`] describe themselves into a shared description, which
+/// can then be exported with [`FrameDescription::to_dot`] for Graphviz or
+/// [`FrameDescription::to_json`] for other tools.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameDescription {
+    attachments: Vec<AttachmentDescription>,
+    passes: Vec<PassDescription>,
+}
+
+impl FrameDescription {
+    /// Creates an empty description
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an attachment created for the frame.
+    ///
+    /// # Returns
+    ///
+    /// The index of the attachment, to refer to it from passes.
+    pub fn add_attachment(&mut self, name: &str, descriptor: TargetDescriptor) -> usize {
+        self.attachments.push(AttachmentDescription {
+            name: name.to_owned(),
+            descriptor: Some(descriptor),
+        });
+        self.attachments.len() - 1
+    }
+
+    /// Adds a texture provided from outside the frame, e.g. the input of an effect chain.
+    ///
+    /// # Returns
+    ///
+    /// The index of the attachment, to refer to it from passes.
+    pub fn add_external(&mut self, name: &str) -> usize {
+        self.attachments.push(AttachmentDescription {
+            name: name.to_owned(),
+            descriptor: None,
+        });
+        self.attachments.len() - 1
+    }
+
+    /// Appends a pass reading and writing the attachments at the given indices.
+    ///
+    /// # Returns
+    ///
+    /// The index of the pass.
+    ///
+    /// # Panics
+    /// This function panics if an index doesn't refer to an attachment of this description.
+    pub fn add_pass(&mut self, name: &str, reads: &[usize], writes: &[usize]) -> usize {
+        assert!(
+            reads
+                .iter()
+                .chain(writes)
+                .all(|&attachment| attachment < self.attachments.len()),
+            "Pass {name} refers to an attachment that doesn't exist"
+        );
+        self.passes.push(PassDescription {
+            name: name.to_owned(),
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+        });
+        self.passes.len() - 1
+    }
+
+    /// Returns the attachments in the order they were added
+    pub fn attachments(&self) -> &[AttachmentDescription] {
+        &self.attachments
+    }
+
+    /// Returns the passes in the order they run
+    pub fn passes(&self) -> &[PassDescription] {
+        &self.passes
+    }
+
+    /// Returns a barrier for every attachment a pass reads after an earlier pass wrote it,
+    /// ordered by reading pass
+    pub fn barriers(&self) -> Vec<Barrier> {
+        let mut last_writer: Vec<Option<usize>> = vec![None; self.attachments.len()];
+        let mut barriers = vec![];
+        for (index, pass) in self.passes.iter().enumerate() {
+            for &attachment in &pass.reads {
+                if let Some(writer) = last_writer[attachment] {
+                    barriers.push(Barrier {
+                        attachment,
+                        writer,
+                        reader: index,
+                    });
+                }
+            }
+            for &attachment in &pass.writes {
+                last_writer[attachment] = Some(index);
+            }
+        }
+        barriers
+    }
+
+    /// Returns the indices of the first and last passes using the attachment at `attachment`,
+    /// or `None` if no pass uses it
+    pub fn lifetime(&self, attachment: usize) -> Option<(usize, usize)> {
+        let mut users = self.passes.iter().enumerate().filter(|(_, pass)| {
+            pass.reads.contains(&attachment) || pass.writes.contains(&attachment)
+        });
+        let (first, _) = users.next()?;
+        let last = users.next_back().map_or(first, |(index, _)| index);
+        Some((first, last))
+    }
+
+    /// Formats the description as a Graphviz graph, with passes as boxes, attachments as
+    /// ellipses and barriers as dashed edges between passes
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph frame {\n    rankdir=LR;\n");
+        for (index, attachment) in self.attachments.iter().enumerate() {
+            let mut label = escape(&attachment.name);
+            if let Some(descriptor) = attachment.descriptor {
+                let _ = write!(
+                    label,
+                    "\\n{}x{} {:?}",
+                    descriptor.width, descriptor.height, descriptor.format
+                );
+            }
+            if let Some((first, last)) = self.lifetime(index) {
+                let _ = write!(label, "\\npasses {first}-{last}");
+            }
+            let _ = writeln!(dot, "    a{index} [shape=ellipse, label=\"{label}\"];");
+        }
+        for (index, pass) in self.passes.iter().enumerate() {
+            let _ = writeln!(
+                dot,
+                "    p{index} [shape=box, label=\"{index}: {}\"];",
+                escape(&pass.name)
+            );
+            for attachment in &pass.reads {
+                let _ = writeln!(dot, "    a{attachment} -> p{index};");
+            }
+            for attachment in &pass.writes {
+                let _ = writeln!(dot, "    p{index} -> a{attachment};");
+            }
+        }
+        for barrier in self.barriers() {
+            let _ = writeln!(
+                dot,
+                "    p{} -> p{} [style=dashed, constraint=false, label=\"{}\"];",
+                barrier.writer,
+                barrier.reader,
+                escape(&self.attachments[barrier.attachment].name)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Formats the description as a JSON object with `attachments`, `passes` and `barriers`
+    /// arrays, where passes and barriers refer to attachments and passes by index
+    pub fn to_json(&self) -> String {
+        let attachments = self
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(index, attachment)| {
+                let mut json = format!("{{\"name\":\"{}\"", escape(&attachment.name));
+                if let Some(descriptor) = attachment.descriptor {
+                    let _ = write!(
+                        json,
+                        ",\"width\":{},\"height\":{},\"format\":\"{:?}\"",
+                        descriptor.width, descriptor.height, descriptor.format
+                    );
+                }
+                if let Some((first, last)) = self.lifetime(index) {
+                    let _ = write!(json, ",\"first_pass\":{first},\"last_pass\":{last}");
+                }
+                json.push('}');
+                json
+            })
+            .collect::<Vec<_>>();
+        let passes = self
+            .passes
+            .iter()
+            .map(|pass| {
+                format!(
+                    "{{\"name\":\"{}\",\"reads\":{:?},\"writes\":{:?}}}",
+                    escape(&pass.name),
+                    pass.reads,
+                    pass.writes
+                )
+            })
+            .collect::<Vec<_>>();
+        let barriers = self
+            .barriers()
+            .iter()
+            .map(|barrier| {
+                format!(
+                    "{{\"attachment\":{},\"writer\":{},\"reader\":{}}}",
+                    barrier.attachment, barrier.writer, barrier.reader
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"attachments\":[{}],\"passes\":[{}],\"barriers\":[{}]}}",
+            attachments.join(","),
+            passes.join(","),
+            barriers.join(",")
+        )
+    }
+}
+
+/// Escapes `text` for use inside a double-quoted DOT or JSON string
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::TextureFormat;
+
+    /// A bloom-like frame with names that need escaping and an attachment no pass uses
+    fn bloom_frame() -> FrameDescription {
+        let mut frame = FrameDescription::new();
+        let input = frame.add_external("input \"hdr\"");
+        let bloom = frame.add_attachment(
+            "bloom",
+            TargetDescriptor::new(640, 360, TextureFormat::Rgba16F),
+        );
+        let output = frame.add_attachment(
+            "output",
+            TargetDescriptor::new(1280, 720, TextureFormat::Rgba8),
+        );
+        frame.add_external("scratch\\tmp\t");
+        frame.add_pass("threshold", &[input], &[bloom]);
+        frame.add_pass("blur\n2x", &[bloom], &[bloom]);
+        frame.add_pass("composite", &[input, bloom], &[output]);
+        frame
+    }
+
+    #[test]
+    fn barriers_follow_the_last_writer() {
+        assert_eq!(
+            bloom_frame().barriers(),
+            [
+                Barrier {
+                    attachment: 1,
+                    writer: 0,
+                    reader: 1,
+                },
+                Barrier {
+                    attachment: 1,
+                    writer: 1,
+                    reader: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lifetimes_span_first_to_last_use() {
+        let frame = bloom_frame();
+        assert_eq!(frame.lifetime(0), Some((0, 2)));
+        assert_eq!(frame.lifetime(1), Some((0, 2)));
+        assert_eq!(frame.lifetime(2), Some((2, 2)));
+        assert_eq!(frame.lifetime(3), None);
+    }
+
+    #[test]
+    fn dot_output() {
+        let expected = r##"digraph frame {
+    rankdir=LR;
+    a0 [shape=ellipse, label="input \"hdr\"\npasses 0-2"];
+    a1 [shape=ellipse, label="bloom\n640x360 Rgba16F\npasses 0-2"];
+    a2 [shape=ellipse, label="output\n1280x720 Rgba8\npasses 2-2"];
+    a3 [shape=ellipse, label="scratch\\tmp\u0009"];
+    p0 [shape=box, label="0: threshold"];
+    a0 -> p0;
+    p0 -> a1;
+    p1 [shape=box, label="1: blur\n2x"];
+    a1 -> p1;
+    p1 -> a1;
+    p2 [shape=box, label="2: composite"];
+    a0 -> p2;
+    a1 -> p2;
+    p2 -> a2;
+    p0 -> p1 [style=dashed, constraint=false, label="bloom"];
+    p1 -> p2 [style=dashed, constraint=false, label="bloom"];
+}
+"##;
+        assert_eq!(bloom_frame().to_dot(), expected);
+    }
+
+    #[test]
+    fn json_output() {
+        let expected = concat!(
+            r#"{"attachments":["#,
+            r#"{"name":"input \"hdr\"","first_pass":0,"last_pass":2},"#,
+            r#"{"name":"bloom","width":640,"height":360,"format":"Rgba16F","first_pass":0,"last_pass":2},"#,
+            r#"{"name":"output","width":1280,"height":720,"format":"Rgba8","first_pass":2,"last_pass":2},"#,
+            r#"{"name":"scratch\\tmp\u0009"}],"#,
+            r#""passes":["#,
+            r#"{"name":"threshold","reads":[0],"writes":[1]},"#,
+            r#"{"name":"blur\n2x","reads":[1],"writes":[1]},"#,
+            r#"{"name":"composite","reads":[0, 1],"writes":[2]}],"#,
+            r#""barriers":["#,
+            r#"{"attachment":1,"writer":0,"reader":1},"#,
+            r#"{"attachment":1,"writer":1,"reader":2}]}"#,
+        );
+        assert_eq!(bloom_frame().to_json(), expected);
+    }
+
+    #[test]
+    fn empty_description() {
+        let frame = FrameDescription::new();
+        assert_eq!(frame.to_dot(), "digraph frame {\n    rankdir=LR;\n}\n");
+        assert_eq!(
+            frame.to_json(),
+            r#"{"attachments":[],"passes":[],"barriers":[]}"#
+        );
+    }
+}
@@ -0,0 +1,659 @@
+use crate::{
+    bind::{BindGuard, BindTarget},
+    context,
+    diagnostics::diagnostic,
+    surface::Surface,
+    texture::{Texture2D, TextureFormat},
+};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// An error that occurred while assembling a [`Framebuffer`]
+#[derive(Debug, Clone)]
+pub enum FramebufferError {
+    /// No color or depth attachments were supplied
+    NoAttachments,
+    /// The attachments don't all have the same dimensions
+    MismatchedDimensions,
+    /// A color attachment has a depth format or vice-versa
+    InvalidAttachmentFormat,
+    /// An attachment doesn't have the requested mip level
+    InvalidMipLevel(u32),
+    /// OpenGL reported the framebuffer as incomplete with the given status
+    Incomplete(u32),
+}
+
+/// An off-screen render target made up of texture attachments
+#[derive(Debug)]
+pub struct Framebuffer {
+    id: u32,
+    width: u32,
+    height: u32,
+    level: u32,
+    color_attachments: Vec<Texture2D>,
+    depth_attachment: Option<Texture2D>,
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer that renders into `color_attachments` and, optionally,
+    /// `depth_attachment`.
+    ///
+    /// Color attachments are bound to `GL_COLOR_ATTACHMENT0 + index` in the order given.
+    pub fn new(
+        color_attachments: Vec<Texture2D>,
+        depth_attachment: Option<Texture2D>,
+    ) -> Result<Self, FramebufferError> {
+        Self::with_level(color_attachments, depth_attachment, 0)
+    }
+
+    /// Creates a framebuffer that renders into mip level `level` of `color_attachments` and,
+    /// optionally, `depth_attachment`.
+    ///
+    /// The framebuffer has the size of that mip level.
+    pub fn with_level(
+        color_attachments: Vec<Texture2D>,
+        depth_attachment: Option<Texture2D>,
+        level: u32,
+    ) -> Result<Self, FramebufferError> {
+        let first = color_attachments
+            .first()
+            .or(depth_attachment.as_ref())
+            .ok_or(FramebufferError::NoAttachments)?;
+
+        if color_attachments
+            .iter()
+            .chain(depth_attachment.iter())
+            .any(|texture| level >= texture.levels())
+        {
+            return Err(FramebufferError::InvalidMipLevel(level));
+        }
+
+        let (width, height) = first.level_dimensions(level);
+        if color_attachments
+            .iter()
+            .chain(depth_attachment.iter())
+            .any(|texture| texture.level_dimensions(level) != (width, height))
+        {
+            return Err(FramebufferError::MismatchedDimensions);
+        }
+
+        if color_attachments.iter().any(|t| t.format().is_depth())
+            || depth_attachment
+                .as_ref()
+                .is_some_and(|t| !t.format().is_depth())
+        {
+            return Err(FramebufferError::InvalidAttachmentFormat);
+        }
+
+        let mut id = 0;
+        unsafe {
+            gl::GenFramebuffers(1, std::ptr::addr_of_mut!(id));
+            gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+        };
+
+        for (index, texture) in color_attachments.iter().enumerate() {
+            unsafe {
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0 + index as u32,
+                    gl::TEXTURE_2D,
+                    texture.id(),
+                    level as i32,
+                )
+            };
+        }
+
+        if let Some(ref depth) = depth_attachment {
+            let attachment = if depth.format().has_stencil() {
+                gl::DEPTH_STENCIL_ATTACHMENT
+            } else {
+                gl::DEPTH_ATTACHMENT
+            };
+            unsafe {
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::TEXTURE_2D,
+                    depth.id(),
+                    level as i32,
+                )
+            };
+        }
+
+        let draw_buffers = (0..color_attachments.len() as u32)
+            .map(|i| gl::COLOR_ATTACHMENT0 + i)
+            .collect::<Vec<_>>();
+        unsafe {
+            if draw_buffers.is_empty() {
+                gl::DrawBuffer(gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+            } else {
+                gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+            }
+        };
+
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0) };
+
+        let framebuffer = Self {
+            id,
+            width,
+            height,
+            level,
+            color_attachments,
+            depth_attachment,
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(FramebufferError::Incomplete(status));
+        }
+
+        Ok(framebuffer)
+    }
+
+    /// Binds the framebuffer for both drawing and reading
+    pub fn bind(&self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.id) };
+    }
+
+    /// Binds the framebuffer for both drawing and reading until the returned guard is dropped,
+    /// after which the previous bindings are restored
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        BindGuard::pair(
+            BindTarget::DrawFramebuffer,
+            BindTarget::ReadFramebuffer,
+            self.id,
+        )
+    }
+
+    /// Binds the default framebuffer (usually the window) for both drawing and reading
+    pub fn bind_default() {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0) };
+    }
+
+    /// Returns the OpenGL name of the framebuffer
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the width and height of the framebuffer in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the mip level of the attachments that is rendered into
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// Returns the color attachment at `index`, if there is one
+    pub fn color_attachment(&self, index: usize) -> Option<&Texture2D> {
+        self.color_attachments.get(index)
+    }
+
+    /// Returns all color attachments in attachment order
+    pub fn color_attachments(&self) -> &[Texture2D] {
+        &self.color_attachments
+    }
+
+    /// Returns the depth attachment, if there is one
+    pub fn depth_attachment(&self) -> Option<&Texture2D> {
+        self.depth_attachment.as_ref()
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        // SAFETY: We are being dropped, so we can destroy the framebuffer we correspond with.
+        // The attachments are dropped afterwards.
+        unsafe { gl::DeleteFramebuffers(1, &self.id) };
+    }
+}
+
+/// A multisampled off-screen render target made up of renderbuffers.
+///
+/// Multisampled targets can't be sampled directly, so rendering is resolved into a regular
+/// [`Framebuffer`] with [`MultisampleFramebuffer::resolve`] before its textures are used.
+#[derive(Debug)]
+pub struct MultisampleFramebuffer {
+    id: u32,
+    width: u32,
+    height: u32,
+    samples: u32,
+    color_formats: Vec<TextureFormat>,
+    depth_format: Option<TextureFormat>,
+    renderbuffers: Vec<u32>,
+}
+
+impl MultisampleFramebuffer {
+    /// Creates a framebuffer with a color attachment for each of `color_formats` and,
+    /// optionally, a depth attachment of `depth_format`.
+    ///
+    /// The sample count is negotiated with [`context::negotiate_sample_count`]: every
+    /// attachment gets the most samples that all formats support without exceeding
+    /// `requested_samples`. Check [`MultisampleFramebuffer::samples`] for the count used.
+    pub fn new(
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        requested_samples: u32,
+    ) -> Result<Self, FramebufferError> {
+        if color_formats.is_empty() && depth_format.is_none() {
+            return Err(FramebufferError::NoAttachments);
+        }
+        if color_formats.iter().any(|format| format.is_depth())
+            || depth_format.is_some_and(|format| !format.is_depth())
+        {
+            return Err(FramebufferError::InvalidAttachmentFormat);
+        }
+
+        let samples = color_formats
+            .iter()
+            .chain(depth_format.iter())
+            .map(|&format| context::negotiate_sample_count(format, requested_samples))
+            .min()
+            .unwrap_or(1);
+        if samples < requested_samples {
+            diagnostic!(
+                Info,
+                Resource,
+                "Using {samples}x multisampling instead of the requested {requested_samples}x"
+            );
+        }
+
+        let mut id = 0;
+        unsafe {
+            gl::GenFramebuffers(1, std::ptr::addr_of_mut!(id));
+            gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+        };
+
+        let attachments = color_formats
+            .iter()
+            .enumerate()
+            .map(|(index, &format)| (gl::COLOR_ATTACHMENT0 + index as u32, format))
+            .chain(depth_format.map(|format| {
+                let attachment = if format.has_stencil() {
+                    gl::DEPTH_STENCIL_ATTACHMENT
+                } else {
+                    gl::DEPTH_ATTACHMENT
+                };
+                (attachment, format)
+            }));
+        let renderbuffers = attachments
+            .map(|(attachment, format)| {
+                let mut renderbuffer = 0;
+                unsafe {
+                    gl::GenRenderbuffers(1, &mut renderbuffer);
+                    gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+                    gl::RenderbufferStorageMultisample(
+                        gl::RENDERBUFFER,
+                        samples as i32,
+                        format.internal_format(),
+                        width as i32,
+                        height as i32,
+                    );
+                    gl::FramebufferRenderbuffer(
+                        gl::FRAMEBUFFER,
+                        attachment,
+                        gl::RENDERBUFFER,
+                        renderbuffer,
+                    );
+                };
+                renderbuffer
+            })
+            .collect::<Vec<_>>();
+        unsafe { gl::BindRenderbuffer(gl::RENDERBUFFER, 0) };
+
+        let draw_buffers = (0..color_formats.len() as u32)
+            .map(|i| gl::COLOR_ATTACHMENT0 + i)
+            .collect::<Vec<_>>();
+        unsafe {
+            if draw_buffers.is_empty() {
+                gl::DrawBuffer(gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+            } else {
+                gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+            }
+        };
+
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0) };
+
+        let framebuffer = Self {
+            id,
+            width,
+            height,
+            samples,
+            color_formats: color_formats.to_vec(),
+            depth_format,
+            renderbuffers,
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(FramebufferError::Incomplete(status));
+        }
+
+        Ok(framebuffer)
+    }
+
+    /// Binds the framebuffer for both drawing and reading until the returned guard is dropped,
+    /// after which the previous bindings are restored
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        BindGuard::pair(
+            BindTarget::DrawFramebuffer,
+            BindTarget::ReadFramebuffer,
+            self.id,
+        )
+    }
+
+    /// Returns the OpenGL name of the framebuffer
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the width and height of the framebuffer in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the number of samples per pixel that was negotiated, which is `1` if the
+    /// formats can't be multisampled
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Returns the formats of the color attachments in attachment order
+    pub fn color_formats(&self) -> &[TextureFormat] {
+        &self.color_formats
+    }
+
+    /// Returns the format of the depth attachment, if there is one
+    pub fn depth_format(&self) -> Option<TextureFormat> {
+        self.depth_format
+    }
+
+    /// Averages the samples of each color attachment into the color attachment of `target`
+    /// with the same index, and copies depth if both framebuffers have a depth attachment.
+    ///
+    /// # Panics
+    /// This function panics if `target` doesn't have the same dimensions.
+    pub fn resolve(&self, target: &Framebuffer) {
+        assert_eq!(
+            self.dimensions(),
+            target.dimensions(),
+            "Multisampled framebuffers can only be resolved into targets of the same size"
+        );
+
+        let _read = BindGuard::new(BindTarget::ReadFramebuffer, self.id);
+        let _draw = BindGuard::new(BindTarget::DrawFramebuffer, target.id());
+        let colors = self
+            .color_formats
+            .len()
+            .min(target.color_attachments().len());
+        for index in 0..colors as u32 {
+            unsafe {
+                gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + index);
+                gl::DrawBuffer(gl::COLOR_ATTACHMENT0 + index);
+            };
+            self.blit(gl::COLOR_BUFFER_BIT);
+        }
+        if self.depth_format.is_some() && target.depth_attachment().is_some() {
+            self.blit(gl::DEPTH_BUFFER_BIT);
+        }
+
+        // Restores the read and draw buffers set up when the framebuffers were created
+        let draw_buffers = (0..target.color_attachments().len() as u32)
+            .map(|i| gl::COLOR_ATTACHMENT0 + i)
+            .collect::<Vec<_>>();
+        unsafe {
+            gl::ReadBuffer(if self.color_formats.is_empty() {
+                gl::NONE
+            } else {
+                gl::COLOR_ATTACHMENT0
+            });
+            if !draw_buffers.is_empty() {
+                gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+            }
+        };
+    }
+
+    /// Averages the samples of the first color attachment into the back buffer of the default
+    /// framebuffer, which must be the same size
+    pub fn resolve_to_default(&self) {
+        let _read = BindGuard::new(BindTarget::ReadFramebuffer, self.id);
+        let _draw = BindGuard::new(BindTarget::DrawFramebuffer, 0);
+        self.blit(gl::COLOR_BUFFER_BIT);
+    }
+
+    /// Blits `mask` from the bound read framebuffer to the bound draw framebuffer
+    fn blit(&self, mask: u32) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        unsafe { gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height, mask, gl::NEAREST) };
+    }
+}
+
+impl Drop for MultisampleFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+            gl::DeleteRenderbuffers(self.renderbuffers.len() as i32, self.renderbuffers.as_ptr());
+        };
+    }
+}
+
+/// A mipmapped color texture with a framebuffer for each of its mip levels, e.g. for bloom
+/// downsample chains or hierarchical depth buffers.
+///
+/// Each level is rendered through [`MipChain::level`]. The levels can be processed one after
+/// another with [`crate::postprocess::MipPass`].
+#[derive(Debug)]
+pub struct MipChain {
+    texture: Texture2D,
+    framebuffers: Vec<u32>,
+}
+
+impl MipChain {
+    /// Creates a mip chain with `levels` levels whose base level has the given size and format.
+    ///
+    /// # Panics
+    /// This function panics if `levels` is `0` or greater than [`Texture2D::max_levels`] for
+    /// the given size.
+    pub fn new(
+        width: u32,
+        height: u32,
+        levels: u32,
+        format: TextureFormat,
+    ) -> Result<Self, FramebufferError> {
+        if format.is_depth() {
+            return Err(FramebufferError::InvalidAttachmentFormat);
+        }
+
+        let texture = Texture2D::with_mipmaps(width, height, levels, format);
+        let mut framebuffers = vec![0; levels as usize];
+        unsafe { gl::GenFramebuffers(levels as i32, framebuffers.as_mut_ptr()) };
+        let chain = Self {
+            texture,
+            framebuffers,
+        };
+
+        for level in 0..levels {
+            let _binding = chain.bind_level(level);
+            let status = unsafe {
+                gl::FramebufferTexture2D(
+                    gl::DRAW_FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    chain.texture.id(),
+                    level as i32,
+                );
+                gl::CheckFramebufferStatus(gl::DRAW_FRAMEBUFFER)
+            };
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(FramebufferError::Incomplete(status));
+            }
+        }
+
+        Ok(chain)
+    }
+
+    fn bind_level(&self, level: u32) -> BindGuard<'_> {
+        BindGuard::pair(
+            BindTarget::DrawFramebuffer,
+            BindTarget::ReadFramebuffer,
+            self.framebuffers[level as usize],
+        )
+    }
+
+    /// Returns a surface rendering into mip level `level`, if the chain has that level
+    pub fn level(&self, level: u32) -> Option<MipSurface<'_>> {
+        (level < self.levels()).then_some(MipSurface { chain: self, level })
+    }
+
+    /// Returns surfaces for every mip level, starting with the base level
+    pub fn surfaces(&self) -> impl DoubleEndedIterator<Item = MipSurface<'_>> + '_ {
+        (0..self.levels()).map(|level| MipSurface { chain: self, level })
+    }
+
+    /// Returns the number of mip levels
+    pub fn levels(&self) -> u32 {
+        self.texture.levels()
+    }
+
+    /// Returns the mipmapped texture the chain renders into
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    /// Returns the width and height of the base level in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.texture.dimensions()
+    }
+}
+
+impl Drop for MipChain {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(self.framebuffers.len() as i32, self.framebuffers.as_ptr())
+        };
+    }
+}
+
+/// A single mip level of a [`MipChain`], obtained through [`MipChain::level`]
+#[derive(Debug, Copy, Clone)]
+pub struct MipSurface<'a> {
+    chain: &'a MipChain,
+    level: u32,
+}
+
+impl MipSurface<'_> {
+    /// Returns the mip level this surface renders into
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+}
+
+impl Surface for MipSurface<'_> {
+    fn bind_target(&self) -> BindGuard<'_> {
+        self.chain.bind_level(self.level)
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.chain.texture.level_dimensions(self.level)
+    }
+}
+
+/// The size and format of a render target handed out by a [`RenderTargetPool`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TargetDescriptor {
+    /// The width of the target in pixels
+    pub width: u32,
+    /// The height of the target in pixels
+    pub height: u32,
+    /// The format of the target's color attachment
+    pub format: TextureFormat,
+}
+
+impl TargetDescriptor {
+    /// Describes a target of the given size and format
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Self {
+        Self {
+            width,
+            height,
+            format,
+        }
+    }
+}
+
+/// A shared allocator of transient render targets, each a [`Framebuffer`] with a single color
+/// attachment.
+///
+/// Targets released back into the pool are handed out again to the next request with the same
+/// [`TargetDescriptor`], so passes that only need a target for a moment don't each keep a
+/// full-screen texture alive. Clones of a pool share the same targets.
+#[derive(Clone, Default)]
+pub struct RenderTargetPool {
+    free: Rc<RefCell<HashMap<TargetDescriptor, Vec<Framebuffer>>>>,
+}
+
+impl RenderTargetPool {
+    /// Creates an empty pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a free target matching `descriptor`, creating one if there is none. Its
+    /// contents are undefined.
+    pub fn acquire(&self, descriptor: TargetDescriptor) -> Result<Framebuffer, FramebufferError> {
+        let reused = self
+            .free
+            .borrow_mut()
+            .get_mut(&descriptor)
+            .and_then(Vec::pop);
+
+        match reused {
+            Some(framebuffer) => Ok(framebuffer),
+            None => Framebuffer::new(
+                vec![Texture2D::new(
+                    descriptor.width,
+                    descriptor.height,
+                    descriptor.format,
+                )],
+                None,
+            ),
+        }
+    }
+
+    /// Returns `framebuffer` to the pool for reuse.
+    ///
+    /// Framebuffers without exactly one color attachment and no depth attachment weren't
+    /// acquired from a pool and are dropped instead.
+    pub fn release(&self, framebuffer: Framebuffer) {
+        let descriptor = match (
+            framebuffer.color_attachments(),
+            framebuffer.depth_attachment(),
+        ) {
+            ([color], None) => {
+                let (width, height) = color.dimensions();
+                TargetDescriptor::new(width, height, color.format())
+            }
+            _ => return,
+        };
+
+        self.free
+            .borrow_mut()
+            .entry(descriptor)
+            .or_default()
+            .push(framebuffer);
+    }
+
+    /// Returns the number of targets waiting to be reused
+    pub fn free_count(&self) -> usize {
+        self.free.borrow().values().map(Vec::len).sum()
+    }
+
+    /// Drops every target waiting to be reused, e.g. after the window was resized
+    pub fn trim(&self) {
+        self.free.borrow_mut().clear();
+    }
+}
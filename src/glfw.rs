@@ -0,0 +1,280 @@
+pub use ::glfw::*;
+
+use crate::{diagnostics::diagnostic, draw_parameters, surface::DefaultFramebuffer};
+
+/// A video mode supported by a monitor
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+    pub red_bits: u32,
+    pub green_bits: u32,
+    pub blue_bits: u32,
+}
+
+impl From<VidMode> for VideoMode {
+    fn from(mode: VidMode) -> Self {
+        Self {
+            width: mode.width,
+            height: mode.height,
+            refresh_rate: mode.refresh_rate,
+            red_bits: mode.red_bits,
+            green_bits: mode.green_bits,
+            blue_bits: mode.blue_bits,
+        }
+    }
+}
+
+/// Returns the video modes supported by the primary monitor, or nothing if there is no monitor
+pub fn video_modes(glfw: &mut Glfw) -> Vec<VideoMode> {
+    glfw.with_primary_monitor(|_, monitor| {
+        monitor.map_or_else(Vec::new, |monitor| {
+            monitor
+                .get_video_modes()
+                .into_iter()
+                .map(VideoMode::from)
+                .collect()
+        })
+    })
+}
+
+/// Returns the current video mode of the primary monitor, i.e. the desktop's resolution
+pub fn current_video_mode(glfw: &mut Glfw) -> Option<VideoMode> {
+    glfw.with_primary_monitor(|_, monitor| {
+        monitor
+            .and_then(|monitor| monitor.get_video_mode())
+            .map(VideoMode::from)
+    })
+}
+
+/// How a window occupies the screen
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DisplayMode {
+    /// A regular decorated window
+    #[default]
+    Windowed,
+    /// An undecorated window covering the primary monitor at the desktop's resolution, which
+    /// switches quickly and plays well with other windows
+    Borderless,
+    /// Exclusive fullscreen on the primary monitor, with the given video mode or the current
+    /// one
+    Exclusive(Option<VideoMode>),
+}
+
+/// Switches a window between [`DisplayMode`]s, restoring its previous position and size when
+/// it returns to windowed mode.
+///
+/// After every switch the default framebuffer and viewport are updated to the window's new
+/// framebuffer size, which is also returned so that off-screen targets can be resized along.
+#[derive(Debug, Clone)]
+pub struct DisplayController {
+    mode: DisplayMode,
+    windowed_position: (i32, i32),
+    windowed_size: (u32, u32),
+}
+
+impl DisplayController {
+    /// Creates a controller for `window`, which is expected to be windowed
+    pub fn new(window: &Window) -> Self {
+        let (width, height) = window.get_size();
+        Self {
+            mode: DisplayMode::Windowed,
+            windowed_position: window.get_pos(),
+            windowed_size: (width.max(1) as u32, height.max(1) as u32),
+        }
+    }
+
+    /// Returns the mode the window was last switched to
+    pub fn mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    /// Returns whether the window is currently borderless or exclusive fullscreen
+    pub fn is_fullscreen(&self) -> bool {
+        self.mode != DisplayMode::Windowed
+    }
+
+    /// Switches `window` to `mode`.
+    ///
+    /// # Returns
+    ///
+    /// The new size of the framebuffer in pixels.
+    pub fn set_mode(
+        &mut self,
+        glfw: &mut Glfw,
+        window: &mut Window,
+        framebuffer: &mut DefaultFramebuffer,
+        mode: DisplayMode,
+    ) -> (u32, u32) {
+        if self.mode == DisplayMode::Windowed && mode != DisplayMode::Windowed {
+            let (width, height) = window.get_size();
+            self.windowed_position = window.get_pos();
+            self.windowed_size = (width.max(1) as u32, height.max(1) as u32);
+        }
+
+        match mode {
+            DisplayMode::Windowed => {
+                let (x, y) = self.windowed_position;
+                let (width, height) = self.windowed_size;
+                window.set_monitor(WindowMode::Windowed, x, y, width, height, None);
+                window.set_decorated(true);
+            }
+            DisplayMode::Borderless => glfw.with_primary_monitor(|_, monitor| {
+                if let Some(monitor) = monitor {
+                    let (x, y) = monitor.get_pos();
+                    if let Some(video_mode) = monitor.get_video_mode() {
+                        window.set_decorated(false);
+                        window.set_monitor(
+                            WindowMode::Windowed,
+                            x,
+                            y,
+                            video_mode.width,
+                            video_mode.height,
+                            None,
+                        );
+                    }
+                }
+            }),
+            DisplayMode::Exclusive(video_mode) => glfw.with_primary_monitor(|_, monitor| {
+                if let Some(monitor) = monitor {
+                    let video_mode =
+                        video_mode.or_else(|| monitor.get_video_mode().map(VideoMode::from));
+                    if let Some(video_mode) = video_mode {
+                        window.set_monitor(
+                            WindowMode::FullScreen(monitor),
+                            0,
+                            0,
+                            video_mode.width,
+                            video_mode.height,
+                            Some(video_mode.refresh_rate),
+                        );
+                    }
+                }
+            }),
+        }
+
+        self.mode = mode;
+        sync_framebuffer(window, framebuffer)
+    }
+
+    /// Switches between windowed and borderless fullscreen. See [`DisplayController::set_mode`].
+    pub fn toggle_fullscreen(
+        &mut self,
+        glfw: &mut Glfw,
+        window: &mut Window,
+        framebuffer: &mut DefaultFramebuffer,
+    ) -> (u32, u32) {
+        let mode = if self.is_fullscreen() {
+            DisplayMode::Windowed
+        } else {
+            DisplayMode::Borderless
+        };
+        self.set_mode(glfw, window, framebuffer, mode)
+    }
+}
+
+/// A window created by [`create_window_with_samples`]
+pub struct MultisampledWindow {
+    pub window: PWindow,
+    pub events: GlfwReceiver<(f64, WindowEvent)>,
+    /// The number of samples per pixel of the default framebuffer, `1` if it isn't
+    /// multisampled
+    pub samples: u32,
+}
+
+/// Creates a window whose default framebuffer has the most samples per pixel, up to
+/// `requested_samples`, that the system accepts. Each sample count that fails is halved
+/// until a window can be created, ending with a window without multisampling.
+///
+/// Other window hints are taken from `glfw` as usual.
+///
+/// # Returns
+///
+/// The window and the sample count that was used, or `None` if no window could be created at
+/// all.
+pub fn create_window_with_samples(
+    glfw: &mut Glfw,
+    width: u32,
+    height: u32,
+    title: &str,
+    mode: WindowMode<'_>,
+    requested_samples: u32,
+) -> Option<MultisampledWindow> {
+    let mut samples = requested_samples.max(1).next_power_of_two();
+    if samples > requested_samples.max(1) {
+        samples /= 2;
+    }
+
+    loop {
+        let hint = (samples > 1).then_some(samples);
+        glfw.window_hint(WindowHint::Samples(hint));
+        if let Some((window, events)) = glfw.create_window(width, height, title, mode) {
+            if samples < requested_samples {
+                diagnostic!(
+                    Info,
+                    Resource,
+                    "Using {samples}x multisampling instead of the requested \
+                     {requested_samples}x"
+                );
+            }
+            return Some(MultisampledWindow {
+                window,
+                events,
+                samples,
+            });
+        }
+        if samples <= 1 {
+            return None;
+        }
+        samples /= 2;
+    }
+}
+
+/// Updates `framebuffer` and the viewport to the current framebuffer size of `window`, e.g.
+/// after a `WindowEvent::FramebufferSize` event.
+///
+/// # Returns
+///
+/// The new size of the framebuffer in pixels.
+pub fn sync_framebuffer(window: &Window, framebuffer: &mut DefaultFramebuffer) -> (u32, u32) {
+    let (width, height) = window.get_framebuffer_size();
+    let (width, height) = (width.max(0) as u32, height.max(0) as u32);
+    framebuffer.set_dimensions(width, height);
+    draw_parameters::replace_viewport([0, 0, width as i32, height as i32]);
+    (width, height)
+}
+
+/// Converts tightly packed RGBA pixels, with rows from top to bottom, into an image GLFW
+/// accepts for icons and cursors.
+///
+/// # Panics
+/// This function panics if `rgba` is too small for an image of the given size.
+pub fn pixel_image(width: u32, height: u32, rgba: &[u8]) -> PixelImage {
+    let expected = width as usize * height as usize * 4;
+    assert!(
+        rgba.len() >= expected,
+        "Expected at least {expected} bytes of pixel data, got {}",
+        rgba.len()
+    );
+
+    PixelImage {
+        width,
+        height,
+        pixels: rgba[..expected]
+            .chunks_exact(4)
+            .map(|pixel| u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+            .collect(),
+    }
+}
+
+/// Sets the icon of `window` from RGBA pixels. See [`pixel_image`].
+pub fn set_window_icon(window: &mut Window, width: u32, height: u32, rgba: &[u8]) {
+    window.set_icon_from_pixels(vec![pixel_image(width, height, rgba)]);
+}
+
+/// Creates a cursor from RGBA pixels whose click position is `hotspot` pixels from the top
+/// left. See [`pixel_image`].
+pub fn cursor_from_pixels(width: u32, height: u32, rgba: &[u8], hotspot: (u32, u32)) -> Cursor {
+    Cursor::create_from_pixels(pixel_image(width, height, rgba), hotspot.0, hotspot.1)
+}
@@ -0,0 +1,105 @@
+use crate::{
+    camera::Camera,
+    layout::{pad_to, Std140},
+};
+use glm::{GenSquareMat, Mat4, Vec2, Vec3};
+
+/// The uniform buffer binding point the renderer binds the globals block to. See
+/// [`crate::Renderer::set_camera`].
+pub const GLOBALS_BINDING: u32 = 13;
+
+/// Per-frame values shared by every shader that includes [`Globals::GLSL`], so that camera
+/// matrices and the like don't have to be passed to each program as uniforms.
+///
+/// The renderer keeps these up to date and uploads them to a uniform block bound to
+/// [`GLOBALS_BINDING`] at the start of every frame and whenever the camera changes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Globals {
+    /// Transforms world space into view space
+    pub view: Mat4,
+    /// Transforms view space into clip space
+    pub projection: Mat4,
+    /// The position of the camera in world space
+    pub camera_position: Vec3,
+    /// The time in seconds. See [`crate::Renderer::time`].
+    pub time: f32,
+    /// The size of the viewport in pixels
+    pub viewport_size: Vec2,
+    /// The time in seconds since the previous frame
+    pub delta_time: f32,
+    /// The index of the current frame
+    pub frame_index: u32,
+}
+
+impl Default for Globals {
+    fn default() -> Self {
+        let identity = glm::Matrix4::new(
+            glm::vec4(1.0, 0.0, 0.0, 0.0),
+            glm::vec4(0.0, 1.0, 0.0, 0.0),
+            glm::vec4(0.0, 0.0, 1.0, 0.0),
+            glm::vec4(0.0, 0.0, 0.0, 1.0),
+        );
+        Self {
+            view: identity,
+            projection: identity,
+            camera_position: glm::vec3(0.0, 0.0, 0.0),
+            time: 0.0,
+            viewport_size: glm::vec2(0.0, 0.0),
+            delta_time: 0.0,
+            frame_index: 0,
+        }
+    }
+}
+
+impl Globals {
+    /// The globals uniform block, to be inserted after the `#version` directive of any
+    /// shader stage.
+    ///
+    /// Besides the fields of [`Globals`], it provides the combined `view_projection` matrix and
+    /// its inverse.
+    pub const GLSL: &'static str = r#"
+        layout(std140, binding = 13) uniform Globals {
+            mat4 view;
+            mat4 projection;
+            mat4 view_projection;
+            mat4 inverse_view_projection;
+            vec3 camera_position;
+            float time;
+            vec2 viewport_size;
+            float delta_time;
+            uint frame_index;
+        };
+    "#;
+
+    /// Takes the view, projection and position from `camera`
+    pub fn set_camera(&mut self, camera: &Camera) {
+        self.view = camera.view;
+        self.projection = camera.projection;
+        if let Some(position) = camera.position() {
+            self.camera_position = position;
+        }
+    }
+}
+
+impl Std140 for Globals {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 288;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        let view_projection = self.projection * self.view;
+        self.view.write_std140(out);
+        self.projection.write_std140(out);
+        view_projection.write_std140(out);
+        view_projection
+            .inverse()
+            .unwrap_or(view_projection)
+            .write_std140(out);
+        self.camera_position.write_std140(out);
+        self.time.write_std140(out);
+        self.viewport_size.write_std140(out);
+        self.delta_time.write_std140(out);
+        self.frame_index.write_std140(out);
+        pad_to(out, start + Self::SIZE);
+    }
+}
@@ -0,0 +1,455 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Looks up the files shaders include with `#include "name"`
+pub trait IncludeResolver {
+    /// Returns the source of the file `name`, included from the file `includer`, or `None`
+    /// if there is no such file
+    fn resolve(&self, name: &str, includer: &str) -> Option<String>;
+
+    /// Returns the name identifying the file `name` included from the file `includer`, which
+    /// is reported in [`ExpandedSource::files`], passed as the includer of the files it
+    /// includes and compared to detect recursion and `#pragma once`. Defaults to `name`, for
+    /// resolvers where a name refers to the same file wherever it's included from.
+    fn canonical_name(&self, name: &str, _includer: &str) -> String {
+        name.to_owned()
+    }
+}
+
+impl<F> IncludeResolver for F
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn resolve(&self, name: &str, _includer: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+/// An in-memory set of named shader sources, e.g. embedded with [`include_str!`]
+#[derive(Debug, Default, Clone)]
+pub struct VirtualFileSystem {
+    files: HashMap<String, String>,
+}
+
+impl VirtualFileSystem {
+    /// Creates an empty file system
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the file `name` with `source`, replacing any file of the same name
+    pub fn with_file(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.insert(name, source);
+        self
+    }
+
+    /// Adds the file `name` with `source`, replacing any file of the same name
+    pub fn insert(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.files.insert(name.into(), source.into());
+    }
+}
+
+impl IncludeResolver for VirtualFileSystem {
+    fn resolve(&self, name: &str, _includer: &str) -> Option<String> {
+        self.files.get(name).cloned()
+    }
+}
+
+/// Resolves included names as paths relative to the directory of the including file, within a
+/// root directory on disk.
+///
+/// Absolute paths and paths leading out of the root with `..` aren't found. The including
+/// file is taken to be relative to the root, or to lie under it if its path starts with the
+/// root's; other includers resolve their includes relative to the root itself.
+#[derive(Debug, Clone)]
+pub struct IncludeDirectory {
+    root: PathBuf,
+}
+
+impl IncludeDirectory {
+    /// Creates a resolver reading files from `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the directory files are read from
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the components of the path of `name` relative to the root, or `None` if it
+    /// isn't within the root
+    fn relative_path(&self, name: &str, includer: &str) -> Option<Vec<String>> {
+        let includer = Path::new(includer);
+        let includer = includer.strip_prefix(&self.root).unwrap_or(includer);
+        let directory = includer
+            .parent()
+            .and_then(|directory| normalize(Vec::new(), directory))
+            .unwrap_or_default();
+        normalize(directory, Path::new(name))
+    }
+}
+
+impl IncludeResolver for IncludeDirectory {
+    fn resolve(&self, name: &str, includer: &str) -> Option<String> {
+        let path = self.relative_path(name, includer)?;
+        fs::read_to_string(self.root.join(path.join("/"))).ok()
+    }
+
+    fn canonical_name(&self, name: &str, includer: &str) -> String {
+        self.relative_path(name, includer)
+            .map_or_else(|| name.to_owned(), |path| path.join("/"))
+    }
+}
+
+/// Appends the components of the relative `path` to `base`, resolving `.` and `..`.
+///
+/// # Returns
+///
+/// The components, or `None` if `path` is absolute or leads out of `base`'s root.
+fn normalize(mut base: Vec<String>, path: &Path) -> Option<Vec<String>> {
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => base.push(name.to_str()?.to_owned()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                base.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(base)
+}
+
+/// GLSL source with every `#include` expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedSource {
+    /// The expanded source, with `#line` directives numbering each file's lines separately
+    pub source: String,
+    /// The names of the files the source was assembled from, indexed by the source string
+    /// numbers in its `#line` directives and hence in compiler errors. The root file is `0`.
+    pub files: Vec<String>,
+}
+
+impl ExpandedSource {
+    /// Returns the name of the file with source string number `number`
+    pub fn file_name(&self, number: usize) -> Option<&str> {
+        self.files.get(number).map(String::as_str)
+    }
+}
+
+/// An error encountered while expanding `#include` directives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncludeError {
+    /// The resolver found no file `name`, included on `line` of `includer`
+    NotFound {
+        name: String,
+        includer: String,
+        line: usize,
+    },
+    /// The file `name` includes itself, directly or through other files
+    Recursive { name: String },
+    /// The `#include` directive on `line` of `file` doesn't name a file in quotes
+    Malformed { file: String, line: usize },
+}
+
+impl std::fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound {
+                name,
+                includer,
+                line,
+            } => write!(f, "{includer}:{line}: Included file {name:?} not found"),
+            Self::Recursive { name } => write!(f, "File {name:?} includes itself"),
+            Self::Malformed { file, line } => write!(f, "{file}:{line}: Malformed #include"),
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// Expands the `#include "name"` directives in `source`, the contents of the file `name`,
+/// using `resolver` to load the included files.
+///
+/// Each included file gets its own source string number in the `#line` directives
+/// surrounding it, so compiler errors can be traced back to it through
+/// [`ExpandedSource::files`]. Files containing `#pragma once` are only included the first
+/// time.
+pub fn expand_includes<R>(
+    source: &str,
+    name: &str,
+    resolver: &R,
+) -> Result<ExpandedSource, IncludeError>
+where
+    R: IncludeResolver + ?Sized,
+{
+    let mut expander = Expander {
+        resolver,
+        output: String::with_capacity(source.len()),
+        files: vec![name.to_owned()],
+        stack: vec![name.to_owned()],
+        once: Vec::new(),
+    };
+    expander.expand(source, 0)?;
+
+    Ok(ExpandedSource {
+        source: expander.output,
+        files: expander.files,
+    })
+}
+
+struct Expander<'a, R: ?Sized> {
+    resolver: &'a R,
+    output: String,
+    files: Vec<String>,
+    /// The files currently being expanded, innermost last
+    stack: Vec<String>,
+    /// The files containing `#pragma once` that were already included
+    once: Vec<String>,
+}
+
+impl<R> Expander<'_, R>
+where
+    R: IncludeResolver + ?Sized,
+{
+    fn expand(&mut self, source: &str, file: usize) -> Result<(), IncludeError> {
+        let name = self.files[file].clone();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let directive = line.trim_start();
+            if directive.starts_with("#pragma")
+                && directive.split_whitespace().nth(1) == Some("once")
+            {
+                self.once.push(name.clone());
+                self.output.push('\n');
+                continue;
+            }
+            let Some(argument) = directive.strip_prefix("#include") else {
+                self.output.push_str(line);
+                self.output.push('\n');
+                continue;
+            };
+
+            let requested = argument
+                .trim()
+                .strip_prefix('"')
+                .and_then(|argument| argument.strip_suffix('"'))
+                .ok_or(IncludeError::Malformed {
+                    file: name.clone(),
+                    line: line_number,
+                })?;
+            let included = self.resolver.canonical_name(requested, &name);
+            // A `#pragma once` file including itself is skipped like any other repeat
+            if self.once.contains(&included) {
+                self.output.push('\n');
+                continue;
+            }
+            if self.stack.contains(&included) {
+                return Err(IncludeError::Recursive { name: included });
+            }
+            let included_source =
+                self.resolver
+                    .resolve(requested, &name)
+                    .ok_or_else(|| IncludeError::NotFound {
+                        name: requested.to_owned(),
+                        includer: name.clone(),
+                        line: line_number,
+                    })?;
+
+            let included_file = self.files.len();
+            self.files.push(included.clone());
+            self.stack.push(included);
+            self.output.push_str(&format!("#line 1 {included_file}\n"));
+            self.expand(&included_source, included_file)?;
+            self.output
+                .push_str(&format!("#line {} {file}\n", line_number + 1));
+            self.stack.pop();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_included_lines_by_file() {
+        let files = VirtualFileSystem::new().with_file("common.glsl", "float x;\n");
+        let expanded = expand_includes(
+            "#version 450\n#include \"common.glsl\"\nvoid main() {}\n",
+            "main.frag",
+            &files,
+        )
+        .unwrap();
+
+        assert_eq!(
+            expanded.source,
+            "#version 450\n#line 1 1\nfloat x;\n#line 3 0\nvoid main() {}\n"
+        );
+        assert_eq!(expanded.files, ["main.frag", "common.glsl"]);
+        assert_eq!(expanded.file_name(1), Some("common.glsl"));
+    }
+
+    #[test]
+    fn nested_includes_restore_the_includer_line() {
+        let files = VirtualFileSystem::new()
+            .with_file("a.glsl", "#include \"b.glsl\"\nfloat a;\n")
+            .with_file("b.glsl", "float b;\n");
+        let expanded = expand_includes("#include \"a.glsl\"\n", "main", &files).unwrap();
+
+        assert_eq!(
+            expanded.source,
+            "#line 1 1\n#line 1 2\nfloat b;\n#line 2 1\nfloat a;\n#line 2 0\n"
+        );
+        assert_eq!(expanded.files, ["main", "a.glsl", "b.glsl"]);
+    }
+
+    #[test]
+    fn pragma_once_files_are_included_once() {
+        let files = VirtualFileSystem::new().with_file("once.glsl", "#pragma once\nfloat x;\n");
+        let expanded = expand_includes(
+            "#include \"once.glsl\"\n#include \"once.glsl\"\n",
+            "main",
+            &files,
+        )
+        .unwrap();
+
+        assert_eq!(expanded.source.matches("float x;").count(), 1);
+        assert_eq!(expanded.files, ["main", "once.glsl"]);
+    }
+
+    #[test]
+    fn pragma_once_file_including_itself_is_skipped() {
+        let files = VirtualFileSystem::new().with_file(
+            "self.glsl",
+            "#pragma once\n#include \"self.glsl\"\nfloat x;\n",
+        );
+        let expanded = expand_includes("#include \"self.glsl\"\n", "main", &files).unwrap();
+
+        assert_eq!(expanded.source.matches("float x;").count(), 1);
+    }
+
+    #[test]
+    fn recursion_is_an_error() {
+        let files = VirtualFileSystem::new()
+            .with_file("a.glsl", "#include \"b.glsl\"\n")
+            .with_file("b.glsl", "#include \"a.glsl\"\n");
+
+        assert_eq!(
+            expand_includes("#include \"a.glsl\"\n", "main", &files),
+            Err(IncludeError::Recursive {
+                name: "a.glsl".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_and_missing_includes_are_errors() {
+        let files = VirtualFileSystem::new();
+
+        assert_eq!(
+            expand_includes("\n#include <common.glsl>\n", "main", &files),
+            Err(IncludeError::Malformed {
+                file: "main".to_owned(),
+                line: 2
+            })
+        );
+        assert_eq!(
+            expand_includes("#include \"missing.glsl\"\n", "main", &files),
+            Err(IncludeError::NotFound {
+                name: "missing.glsl".to_owned(),
+                includer: "main".to_owned(),
+                line: 1
+            })
+        );
+    }
+
+    /// A directory under the system's temporary directory, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(path.join("lib/detail")).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, source: &str) {
+            fs::write(self.0.join(name), source).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn directory_resolves_relative_to_the_includer() {
+        let dir = TempDir::new("glium2-include-relative");
+        dir.write(
+            "main.frag",
+            "#include \"lib/light.glsl\"\n#include \"lib/detail/brdf.glsl\"\n",
+        );
+        dir.write(
+            "lib/light.glsl",
+            "#include \"detail/brdf.glsl\"\nfloat light;\n",
+        );
+        dir.write(
+            "lib/detail/brdf.glsl",
+            "#pragma once\n#include \"../../common.glsl\"\n",
+        );
+        dir.write("common.glsl", "float common;\n");
+        let resolver = IncludeDirectory::new(&dir.0);
+        let main = dir.0.join("main.frag");
+
+        let expanded = expand_includes(
+            &fs::read_to_string(&main).unwrap(),
+            &main.display().to_string(),
+            &resolver,
+        )
+        .unwrap();
+
+        assert_eq!(
+            &expanded.files[1..],
+            ["lib/light.glsl", "lib/detail/brdf.glsl", "common.glsl"]
+        );
+        assert_eq!(expanded.source.matches("float common;").count(), 1);
+        assert_eq!(
+            resolver
+                .resolve("../common.glsl", "lib/light.glsl")
+                .as_deref(),
+            Some("float common;\n")
+        );
+    }
+
+    #[test]
+    fn directory_rejects_paths_outside_the_root() {
+        let dir = TempDir::new("glium2-include-escape");
+        dir.write("lib/light.glsl", "float light;\n");
+        let resolver = IncludeDirectory::new(dir.0.join("lib"));
+        let absolute = dir.0.join("lib/light.glsl").display().to_string();
+
+        assert_eq!(
+            resolver.resolve("light.glsl", "main.frag").as_deref(),
+            Some("float light;\n")
+        );
+        assert_eq!(resolver.resolve("../lib/light.glsl", "main.frag"), None);
+        assert_eq!(
+            resolver.resolve("../light.glsl", "detail/x.glsl"),
+            Some("float light;\n".to_owned())
+        );
+        assert_eq!(
+            resolver.resolve("../../lib/light.glsl", "detail/x.glsl"),
+            None
+        );
+        assert_eq!(resolver.resolve(&absolute, "main.frag"), None);
+    }
+}
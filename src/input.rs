@@ -0,0 +1,480 @@
+use glm::Vec2;
+use std::collections::HashSet;
+
+/// A keyboard key, independent of the windowing backend
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    LeftShift,
+    RightShift,
+    LeftControl,
+    RightControl,
+    LeftAlt,
+    RightAlt,
+    /// Any other key, identified by the backend's key code
+    Other(i32),
+}
+
+/// A mouse button, independent of the windowing backend
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// Any other button, numbered from `3`
+    Other(u8),
+}
+
+/// A button of a gamepad with a standard (Xbox-like) layout
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    LeftBumper,
+    RightBumper,
+    Back,
+    Start,
+    Guide,
+    LeftThumb,
+    RightThumb,
+    DpadUp,
+    DpadRight,
+    DpadDown,
+    DpadLeft,
+}
+
+/// An axis of a gamepad with a standard layout. Sticks range from `-1` to `1`, with `-1` up and
+/// left; triggers range from `-1` released to `1` fully pressed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// The state of a connected gamepad
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Gamepad {
+    buttons: [bool; 15],
+    previous_buttons: [bool; 15],
+    axes: [f32; 6],
+}
+
+impl Gamepad {
+    /// Returns whether `button` is held down
+    pub fn is_down(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize]
+    }
+
+    /// Returns whether `button` went down since the previous frame
+    pub fn was_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize] && !self.previous_buttons[button as usize]
+    }
+
+    /// Returns whether `button` went up since the previous frame
+    pub fn was_released(&self, button: GamepadButton) -> bool {
+        !self.buttons[button as usize] && self.previous_buttons[button as usize]
+    }
+
+    /// Returns the position of `axis`
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    /// Returns the position of the left stick, with positions within `dead_zone` of the center
+    /// reported as zero
+    pub fn left_stick(&self, dead_zone: f32) -> Vec2 {
+        Self::stick(self.axes[0], self.axes[1], dead_zone)
+    }
+
+    /// Returns the position of the right stick. See [`Gamepad::left_stick`].
+    pub fn right_stick(&self, dead_zone: f32) -> Vec2 {
+        Self::stick(self.axes[2], self.axes[3], dead_zone)
+    }
+
+    fn stick(x: f32, y: f32, dead_zone: f32) -> Vec2 {
+        if (x * x + y * y).sqrt() <= dead_zone {
+            glm::vec2(0.0, 0.0)
+        } else {
+            glm::vec2(x, y)
+        }
+    }
+}
+
+/// Keyboard, mouse and gamepad state accumulated from a windowing backend, with changes
+/// tracked per frame.
+///
+/// Call [`InputState::begin_frame`] once per frame before polling the backend's events, then
+/// feed every event to it, either through the backend-specific helpers such as
+/// [`InputState::handle_glfw_event`] or the generic `*_changed` methods.
+#[derive(Debug, Clone)]
+pub struct InputState {
+    keys_down: HashSet<Key>,
+    keys_pressed: HashSet<Key>,
+    keys_released: HashSet<Key>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    cursor: Option<Vec2>,
+    cursor_delta: Vec2,
+    scroll_delta: Vec2,
+    gamepads: Vec<Option<Gamepad>>,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+            buttons_down: HashSet::new(),
+            buttons_pressed: HashSet::new(),
+            buttons_released: HashSet::new(),
+            cursor: None,
+            cursor_delta: glm::vec2(0.0, 0.0),
+            scroll_delta: glm::vec2(0.0, 0.0),
+            gamepads: Vec::new(),
+        }
+    }
+}
+
+impl InputState {
+    /// Creates a state with nothing held down
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a new frame, resetting the presses, releases and deltas of the previous one
+    pub fn begin_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.cursor_delta = glm::vec2(0.0, 0.0);
+        self.scroll_delta = glm::vec2(0.0, 0.0);
+        for gamepad in self.gamepads.iter_mut().flatten() {
+            gamepad.previous_buttons = gamepad.buttons;
+        }
+    }
+
+    /// Records that `key` went down or up
+    pub fn key_changed(&mut self, key: Key, down: bool) {
+        if down {
+            if self.keys_down.insert(key) {
+                self.keys_pressed.insert(key);
+            }
+        } else if self.keys_down.remove(&key) {
+            self.keys_released.insert(key);
+        }
+    }
+
+    /// Records that `button` went down or up
+    pub fn button_changed(&mut self, button: MouseButton, down: bool) {
+        if down {
+            if self.buttons_down.insert(button) {
+                self.buttons_pressed.insert(button);
+            }
+        } else if self.buttons_down.remove(&button) {
+            self.buttons_released.insert(button);
+        }
+    }
+
+    /// Records that the cursor moved to `position`, in pixels from the top left of the window
+    pub fn cursor_changed(&mut self, position: Vec2) {
+        if let Some(previous) = self.cursor {
+            self.cursor_delta = self.cursor_delta + (position - previous);
+        }
+        self.cursor = Some(position);
+    }
+
+    /// Records a scroll by `delta`, with positive `y` scrolling up
+    pub fn scroll_changed(&mut self, delta: Vec2) {
+        self.scroll_delta = self.scroll_delta + delta;
+    }
+
+    /// Records the current buttons and axes of gamepad `index`, or that it's disconnected if
+    /// `state` is `None`. Buttons and axes are in the order of [`GamepadButton`] and
+    /// [`GamepadAxis`].
+    pub fn gamepad_changed(&mut self, index: usize, state: Option<([bool; 15], [f32; 6])>) {
+        if self.gamepads.len() <= index {
+            self.gamepads.resize(index + 1, None);
+        }
+
+        self.gamepads[index] = state.map(|(buttons, axes)| Gamepad {
+            buttons,
+            axes,
+            previous_buttons: self.gamepads[index]
+                .map_or(buttons, |gamepad| gamepad.previous_buttons),
+        });
+    }
+
+    /// Returns whether `key` is held down
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Returns whether `key` went down this frame
+    pub fn was_key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Returns whether `key` went up this frame
+    pub fn was_key_released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    /// Returns whether `button` is held down
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    /// Returns whether `button` went down this frame
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    /// Returns whether `button` went up this frame
+    pub fn was_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Returns the cursor position in pixels from the top left of the window, if it has moved
+    /// yet
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor
+    }
+
+    /// Returns how far the cursor moved this frame, in pixels
+    pub fn cursor_delta(&self) -> Vec2 {
+        self.cursor_delta
+    }
+
+    /// Returns how far the scroll wheel moved this frame
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.scroll_delta
+    }
+
+    /// Returns gamepad `index`, if it's connected
+    pub fn gamepad(&self, index: usize) -> Option<&Gamepad> {
+        self.gamepads.get(index).and_then(Option::as_ref)
+    }
+
+    /// Returns the connected gamepads and their indices
+    pub fn gamepads(&self) -> impl Iterator<Item = (usize, &Gamepad)> {
+        self.gamepads
+            .iter()
+            .enumerate()
+            .filter_map(|(index, gamepad)| gamepad.as_ref().map(|gamepad| (index, gamepad)))
+    }
+
+    /// Feeds a GLFW window event into the state. Events other than key, mouse button, cursor
+    /// and scroll events are ignored.
+    #[cfg(feature = "backends")]
+    pub fn handle_glfw_event(&mut self, event: &glfw::WindowEvent) {
+        match *event {
+            glfw::WindowEvent::Key(key, _, action, _) if action != glfw::Action::Repeat => {
+                self.key_changed(key.into(), action == glfw::Action::Press);
+            }
+            glfw::WindowEvent::MouseButton(button, action, _) => {
+                self.button_changed(button.into(), action == glfw::Action::Press);
+            }
+            glfw::WindowEvent::CursorPos(x, y) => {
+                self.cursor_changed(glm::vec2(x as f32, y as f32));
+            }
+            glfw::WindowEvent::Scroll(x, y) => {
+                self.scroll_changed(glm::vec2(x as f32, y as f32));
+            }
+            _ => {}
+        }
+    }
+
+    /// Polls every joystick GLFW knows as a gamepad
+    #[cfg(feature = "backends")]
+    pub fn poll_glfw_gamepads(&mut self, glfw: &glfw::Glfw) {
+        for index in 0..16 {
+            let state = glfw::JoystickId::from_i32(index)
+                .map(|id| glfw.get_joystick(id))
+                .filter(glfw::Joystick::is_gamepad)
+                .and_then(|joystick| joystick.get_gamepad_state())
+                .map(|state| {
+                    let mut buttons = [false; 15];
+                    for (button, down) in buttons.iter_mut().enumerate() {
+                        *down =
+                            glfw::GamepadButton::from_i32(button as i32).is_some_and(|button| {
+                                state.get_button_state(button) == glfw::Action::Press
+                            });
+                    }
+                    let mut axes = [0.0; 6];
+                    for (axis, value) in axes.iter_mut().enumerate() {
+                        if let Some(axis) = glfw::GamepadAxis::from_i32(axis as i32) {
+                            *value = state.get_axis(axis);
+                        }
+                    }
+                    (buttons, axes)
+                });
+
+            if state.is_some() || self.gamepad(index as usize).is_some() {
+                self.gamepad_changed(index as usize, state);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "backends")]
+impl From<glfw::MouseButton> for MouseButton {
+    fn from(button: glfw::MouseButton) -> Self {
+        match button {
+            glfw::MouseButton::Button1 => MouseButton::Left,
+            glfw::MouseButton::Button2 => MouseButton::Right,
+            glfw::MouseButton::Button3 => MouseButton::Middle,
+            other => MouseButton::Other(other as u8),
+        }
+    }
+}
+
+#[cfg(feature = "backends")]
+impl From<glfw::Key> for Key {
+    fn from(key: glfw::Key) -> Self {
+        use glfw::Key as G;
+
+        match key {
+            G::A => Key::A,
+            G::B => Key::B,
+            G::C => Key::C,
+            G::D => Key::D,
+            G::E => Key::E,
+            G::F => Key::F,
+            G::G => Key::G,
+            G::H => Key::H,
+            G::I => Key::I,
+            G::J => Key::J,
+            G::K => Key::K,
+            G::L => Key::L,
+            G::M => Key::M,
+            G::N => Key::N,
+            G::O => Key::O,
+            G::P => Key::P,
+            G::Q => Key::Q,
+            G::R => Key::R,
+            G::S => Key::S,
+            G::T => Key::T,
+            G::U => Key::U,
+            G::V => Key::V,
+            G::W => Key::W,
+            G::X => Key::X,
+            G::Y => Key::Y,
+            G::Z => Key::Z,
+            G::Num0 => Key::Num0,
+            G::Num1 => Key::Num1,
+            G::Num2 => Key::Num2,
+            G::Num3 => Key::Num3,
+            G::Num4 => Key::Num4,
+            G::Num5 => Key::Num5,
+            G::Num6 => Key::Num6,
+            G::Num7 => Key::Num7,
+            G::Num8 => Key::Num8,
+            G::Num9 => Key::Num9,
+            G::F1 => Key::F1,
+            G::F2 => Key::F2,
+            G::F3 => Key::F3,
+            G::F4 => Key::F4,
+            G::F5 => Key::F5,
+            G::F6 => Key::F6,
+            G::F7 => Key::F7,
+            G::F8 => Key::F8,
+            G::F9 => Key::F9,
+            G::F10 => Key::F10,
+            G::F11 => Key::F11,
+            G::F12 => Key::F12,
+            G::Up => Key::Up,
+            G::Down => Key::Down,
+            G::Left => Key::Left,
+            G::Right => Key::Right,
+            G::Space => Key::Space,
+            G::Enter => Key::Enter,
+            G::Escape => Key::Escape,
+            G::Tab => Key::Tab,
+            G::Backspace => Key::Backspace,
+            G::Insert => Key::Insert,
+            G::Delete => Key::Delete,
+            G::Home => Key::Home,
+            G::End => Key::End,
+            G::PageUp => Key::PageUp,
+            G::PageDown => Key::PageDown,
+            G::LeftShift => Key::LeftShift,
+            G::RightShift => Key::RightShift,
+            G::LeftControl => Key::LeftControl,
+            G::RightControl => Key::RightControl,
+            G::LeftAlt => Key::LeftAlt,
+            G::RightAlt => Key::RightAlt,
+            other => Key::Other(other as i32),
+        }
+    }
+}
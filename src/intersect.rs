@@ -0,0 +1,458 @@
+use crate::matrix::Ray;
+use glm::{Vec2, Vec3};
+
+/// The largest number of triangles stored in a single leaf of a [`TriangleMesh`] hierarchy
+const LEAF_TRIANGLES: usize = 4;
+
+/// An axis-aligned bounding box
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Returns the smallest box containing every point, or `None` if there are no points
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |bounds, point| {
+            bounds.union(&Self::new(point, point))
+        }))
+    }
+
+    /// Returns the smallest box containing both boxes
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self::new(glm::min(self.min, other.min), glm::max(self.max, other.max))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Returns the distance along `ray` at which it enters the box, `0` if it starts inside,
+    /// or `None` if it misses
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let inverse = glm::vec3(
+            1.0 / ray.direction.x,
+            1.0 / ray.direction.y,
+            1.0 / ray.direction.z,
+        );
+        let near = (self.min - ray.origin) * inverse;
+        let far = (self.max - ray.origin) * inverse;
+        let entry = glm::min(near, far);
+        let exit = glm::max(near, far);
+
+        let entry = entry.x.max(entry.y).max(entry.z).max(0.0);
+        let exit = exit.x.min(exit.y).min(exit.z);
+        (entry <= exit).then_some(entry)
+    }
+}
+
+/// A sphere, e.g. the bounds of an object
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Returns the distance along `ray` at which it enters the sphere, `0` if it starts
+    /// inside, or `None` if it misses
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let offset = ray.origin - self.center;
+        let b = glm::dot(offset, ray.direction);
+        let c = glm::dot(offset, offset) - self.radius * self.radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        if -b + root < 0.0 {
+            return None;
+        }
+        Some((-b - root).max(0.0))
+    }
+}
+
+/// Where a ray hit a triangle
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TriangleHit {
+    /// The distance along the ray
+    pub distance: f32,
+    /// The weights of the second and third vertex at the hit point; the first vertex has a
+    /// weight of `1 - x - y`. Used to interpolate vertex attributes such as texture coordinates.
+    pub barycentric: Vec2,
+}
+
+/// Intersects `ray` with the triangle `[a, b, c]`, hitting both faces.
+///
+/// # Returns
+///
+/// The hit, or `None` if the ray misses or runs parallel to the triangle.
+pub fn intersect_triangle(ray: &Ray, [a, b, c]: [Vec3; 3]) -> Option<TriangleHit> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = glm::cross(ray.direction, edge2);
+    let determinant = glm::dot(edge1, p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let offset = ray.origin - a;
+    let u = glm::dot(offset, p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = glm::cross(offset, edge1);
+    let v = glm::dot(ray.direction, q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let distance = glm::dot(edge2, q) * inverse;
+    (distance >= 0.0).then(|| TriangleHit {
+        distance,
+        barycentric: glm::vec2(u, v),
+    })
+}
+
+/// Where a ray hit a [`TriangleMesh`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshHit {
+    /// The index of the triangle, i.e. its first index divided by three
+    pub triangle: usize,
+    pub hit: TriangleHit,
+}
+
+/// A node of a bounding volume hierarchy. Leaves hold `count` triangles starting at `first`
+/// in the hierarchy order; inner nodes have their children at `first` and `first + 1`.
+#[derive(Debug, Copy, Clone)]
+struct BvhNode {
+    bounds: Aabb,
+    first: usize,
+    count: usize,
+}
+
+/// A copy of the triangles of a mesh for precise picking on the CPU, complementing the
+/// pixel-accurate [`crate::picking::Picker`].
+///
+/// Rays are tested against every triangle unless a bounding volume hierarchy is built with
+/// [`TriangleMesh::build_bvh`], which is worthwhile for meshes that are tested repeatedly.
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    positions: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+    bvh: Vec<BvhNode>,
+    /// Triangle indices in hierarchy order, referenced by the leaves
+    order: Vec<usize>,
+}
+
+impl TriangleMesh {
+    /// Creates a mesh from vertex positions and a triangle list referencing them, in the
+    /// space rays will be tested in.
+    ///
+    /// # Panics
+    /// This function panics if the number of indices isn't a multiple of three or an index
+    /// is out of range.
+    pub fn new(positions: Vec<Vec3>, indices: &[u32]) -> Self {
+        assert!(
+            indices.len().is_multiple_of(3),
+            "Triangle lists need three indices per triangle"
+        );
+        assert!(
+            indices
+                .iter()
+                .all(|&index| (index as usize) < positions.len()),
+            "Triangle index out of range"
+        );
+
+        Self {
+            positions,
+            triangles: indices
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect(),
+            bvh: Vec::new(),
+            order: Vec::new(),
+        }
+    }
+
+    /// Creates a mesh from non-indexed positions, every three forming a triangle.
+    ///
+    /// # Panics
+    /// This function panics if the number of positions isn't a multiple of three.
+    pub fn from_triangles(positions: Vec<Vec3>) -> Self {
+        let indices = (0..positions.len() as u32).collect::<Vec<_>>();
+        Self::new(positions, &indices)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Returns the corners of the triangle at `index`
+    ///
+    /// # Panics
+    /// This function panics if `index` is out of range.
+    pub fn triangle(&self, index: usize) -> [Vec3; 3] {
+        self.triangles[index].map(|vertex| self.positions[vertex as usize])
+    }
+
+    /// Returns the bounds of every vertex, or `None` if the mesh is empty
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.positions.iter().copied())
+    }
+
+    /// Builds a bounding volume hierarchy over the triangles, making
+    /// [`TriangleMesh::intersect_ray`] logarithmic rather than linear in the triangle count
+    pub fn build_bvh(&mut self) {
+        self.bvh.clear();
+        self.order = (0..self.triangles.len()).collect();
+        if self.triangles.is_empty() {
+            return;
+        }
+
+        let centroids = (0..self.triangles.len())
+            .map(|index| {
+                let [a, b, c] = self.triangle(index);
+                (a + b + c) / 3.0
+            })
+            .collect::<Vec<_>>();
+        self.bvh.push(self.leaf(0, self.triangles.len()));
+
+        let mut pending = vec![0];
+        while let Some(node) = pending.pop() {
+            let BvhNode { first, count, .. } = self.bvh[node];
+            if count <= LEAF_TRIANGLES {
+                continue;
+            }
+
+            // Split at the median centroid along the longest axis
+            let triangles = &mut self.order[first..first + count];
+            #[allow(clippy::unwrap_used)]
+            let extent = Aabb::from_points(triangles.iter().map(|&index| centroids[index]))
+                .unwrap()
+                .size();
+            let axis = if extent.x >= extent.y && extent.x >= extent.z {
+                0
+            } else if extent.y >= extent.z {
+                1
+            } else {
+                2
+            };
+            triangles.sort_by(|&a, &b| centroids[a][axis].total_cmp(&centroids[b][axis]));
+
+            let half = count / 2;
+            let children = self.bvh.len();
+            self.bvh.push(self.leaf(first, half));
+            self.bvh.push(self.leaf(first + half, count - half));
+            self.bvh[node].first = children;
+            self.bvh[node].count = 0;
+            pending.extend([children, children + 1]);
+        }
+    }
+
+    /// Returns a leaf node holding `count` triangles of the hierarchy order from `first`
+    fn leaf(&self, first: usize, count: usize) -> BvhNode {
+        #[allow(clippy::unwrap_used)]
+        let bounds = Aabb::from_points(
+            self.order[first..first + count]
+                .iter()
+                .flat_map(|&index| self.triangle(index)),
+        )
+        .unwrap();
+        BvhNode {
+            bounds,
+            first,
+            count,
+        }
+    }
+
+    /// Returns the closest triangle hit by `ray`, or `None` if it misses the mesh
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<MeshHit> {
+        if self.bvh.is_empty() {
+            return self.closest_hit(ray, 0..self.triangles.len(), None);
+        }
+
+        let mut closest = None;
+        let mut pending = vec![0];
+        while let Some(index) = pending.pop() {
+            let node = &self.bvh[index];
+            let Some(entry) = node.bounds.intersect_ray(ray) else {
+                continue;
+            };
+            if closest.is_some_and(|hit: MeshHit| hit.hit.distance < entry) {
+                continue;
+            }
+
+            if node.count > 0 {
+                let triangles = self.order[node.first..node.first + node.count].iter();
+                closest = self.closest_hit(ray, triangles.copied(), closest);
+            } else {
+                pending.extend([node.first, node.first + 1]);
+            }
+        }
+        closest
+    }
+
+    /// Tests the given triangles, returning the closest of their hits and `closest`
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        triangles: impl Iterator<Item = usize>,
+        mut closest: Option<MeshHit>,
+    ) -> Option<MeshHit> {
+        for triangle in triangles {
+            if let Some(hit) = intersect_triangle(ray, self.triangle(triangle)) {
+                if closest.is_none_or(|closest| hit.distance < closest.hit.distance) {
+                    closest = Some(MeshHit { triangle, hit });
+                }
+            }
+        }
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ray(origin: Vec3, direction: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction: glm::normalize(direction),
+        }
+    }
+
+    fn unit_box() -> Aabb {
+        Aabb::new(glm::vec3(-1.0, -1.0, -1.0), glm::vec3(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn aabb_hit_miss_and_inside() {
+        let bounds = unit_box();
+        let hit = ray(glm::vec3(0.0, 0.0, -5.0), glm::vec3(0.0, 0.0, 1.0));
+        assert_eq!(bounds.intersect_ray(&hit), Some(4.0));
+
+        let miss = ray(glm::vec3(0.0, 3.0, -5.0), glm::vec3(0.0, 0.0, 1.0));
+        assert_eq!(bounds.intersect_ray(&miss), None);
+
+        let away = ray(glm::vec3(0.0, 0.0, -5.0), glm::vec3(0.0, 0.0, -1.0));
+        assert_eq!(bounds.intersect_ray(&away), None);
+
+        let inside = ray(glm::vec3(0.5, 0.0, 0.0), glm::vec3(1.0, 1.0, 0.0));
+        assert_eq!(bounds.intersect_ray(&inside), Some(0.0));
+    }
+
+    #[test]
+    fn sphere_hit_miss_and_inside() {
+        let sphere = Sphere::new(glm::vec3(0.0, 0.0, 0.0), 1.0);
+        let hit = ray(glm::vec3(-5.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(sphere.intersect_ray(&hit), Some(4.0));
+
+        let miss = ray(glm::vec3(-5.0, 2.0, 0.0), glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(sphere.intersect_ray(&miss), None);
+
+        let behind = ray(glm::vec3(5.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(sphere.intersect_ray(&behind), None);
+
+        let inside = ray(glm::vec3(0.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0));
+        assert_eq!(sphere.intersect_ray(&inside), Some(0.0));
+    }
+
+    #[test]
+    fn triangle_hit_miss_and_back_face() {
+        let triangle = [
+            glm::vec3(0.0, 0.0, 0.0),
+            glm::vec3(1.0, 0.0, 0.0),
+            glm::vec3(0.0, 1.0, 0.0),
+        ];
+        let hit = intersect_triangle(
+            &ray(glm::vec3(0.25, 0.5, -2.0), glm::vec3(0.0, 0.0, 1.0)),
+            triangle,
+        )
+        .expect("ray through the triangle should hit it");
+        assert!((hit.distance - 2.0).abs() < 1e-6);
+        assert!((hit.barycentric.x - 0.25).abs() < 1e-6);
+        assert!((hit.barycentric.y - 0.5).abs() < 1e-6);
+
+        let back = ray(glm::vec3(0.25, 0.25, 2.0), glm::vec3(0.0, 0.0, -1.0));
+        assert!(intersect_triangle(&back, triangle).is_some());
+
+        let miss = ray(glm::vec3(1.0, 1.0, -2.0), glm::vec3(0.0, 0.0, 1.0));
+        assert_eq!(intersect_triangle(&miss, triangle), None);
+
+        let parallel = ray(glm::vec3(0.25, 0.25, 0.0), glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(intersect_triangle(&parallel, triangle), None);
+    }
+
+    /// A small deterministic generator, so the test doesn't need a random number crate
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f32 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        fn point(&mut self, scale: f32) -> Vec3 {
+            glm::vec3(self.next(), self.next(), self.next()) * scale - scale * 0.5
+        }
+    }
+
+    #[test]
+    fn bvh_matches_brute_force() {
+        let mut random = Lcg(7);
+        let positions = (0..300)
+            .flat_map(|_| {
+                let center = random.point(20.0);
+                [
+                    center + random.point(1.0),
+                    center + random.point(1.0),
+                    center + random.point(1.0),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let brute_force = TriangleMesh::from_triangles(positions);
+        let mut hierarchy = brute_force.clone();
+        hierarchy.build_bvh();
+
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin = random.point(40.0);
+            let target = random.point(10.0);
+            let ray = ray(origin, target - origin);
+
+            let expected = brute_force.intersect_ray(&ray);
+            let actual = hierarchy.intersect_ray(&ray);
+            match (expected, actual) {
+                (None, None) => {}
+                (Some(expected), Some(actual)) => {
+                    hits += 1;
+                    assert!((expected.hit.distance - actual.hit.distance).abs() < 1e-4);
+                }
+                _ => panic!("BVH returned {actual:?} where brute force returned {expected:?}"),
+            }
+        }
+        assert!(hits > 0, "the test rays should hit some triangles");
+    }
+}
@@ -0,0 +1,544 @@
+use crate::shader::LinkedProgram;
+use std::ffi::CString;
+
+/// Rounds `offset` up to the next multiple of `align`
+pub const fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+/// Returns the larger of `a` and `b`, usable in constant expressions
+pub const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// Pads `out` with zeroes until it is `len` bytes long
+#[doc(hidden)]
+pub fn pad_to(out: &mut Vec<u8>, len: usize) {
+    if out.len() < len {
+        out.resize(len, 0);
+    }
+}
+
+/// A type with a defined memory layout under the GLSL `std430` rules, used by shader storage
+/// blocks.
+///
+/// Implemented for scalars, `glm` vectors and matrices, arrays of those, and structs with
+/// `#[derive(Std430)]`.
+pub trait Std430 {
+    /// The base alignment of the type in bytes
+    const ALIGN: usize;
+
+    /// The size of the type in bytes, including any trailing padding required by the rules
+    const SIZE: usize;
+
+    /// Appends the `std430` representation of `self` to `out`.
+    ///
+    /// `out` must already be padded to a multiple of [`Std430::ALIGN`] relative to the start
+    /// of the block.
+    fn write_std430(&self, out: &mut Vec<u8>);
+}
+
+/// A struct laid out according to `std430`, usually implemented with `#[derive(Std430)]`.
+///
+/// The derive computes each field's offset at compile time. A field annotated with
+/// `#[std430(offset = N)]` additionally fails to compile if its computed offset isn't `N`,
+/// mirroring an explicit `layout(offset = N)` qualifier on the GLSL side.
+pub trait Std430Block: Std430 {
+    /// The GLSL names of the fields, in declaration order
+    const FIELD_NAMES: &'static [&'static str];
+
+    /// The byte offsets of the fields, in declaration order
+    const FIELD_OFFSETS: &'static [usize];
+
+    /// Serializes `self` into a byte buffer ready to be uploaded to a storage buffer
+    fn to_std430_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        self.write_std430(&mut out);
+        out
+    }
+
+    /// Checks the computed field offsets against the offsets `program` reports for the buffer
+    /// variables of the storage block `block`.
+    ///
+    /// Only performs the check in debug builds; release builds always return `Ok`.
+    fn validate_std430(program: &LinkedProgram, block: &str) -> Result<(), LayoutMismatch> {
+        if cfg!(debug_assertions) {
+            validate_offsets(
+                program,
+                gl::BUFFER_VARIABLE,
+                block,
+                Self::FIELD_NAMES,
+                Self::FIELD_OFFSETS,
+            )
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A field whose offset on the Rust side differs from the offset reported by the shader
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutMismatch {
+    /// The GLSL name of the field
+    pub field: String,
+    /// The offset computed for the Rust struct
+    pub expected: usize,
+    /// The offset reported by the program, or `None` if the field isn't active in the block
+    pub actual: Option<usize>,
+}
+
+pub(crate) fn validate_offsets(
+    program: &LinkedProgram,
+    interface: u32,
+    block: &str,
+    names: &[&str],
+    offsets: &[usize],
+) -> Result<(), LayoutMismatch> {
+    for (name, expected) in names.iter().zip(offsets) {
+        let actual = [
+            format!("{block}.{name}"),
+            format!("{block}.{name}[0]"),
+            name.to_string(),
+            format!("{name}[0]"),
+        ]
+        .into_iter()
+        .find_map(|candidate| resource_offset(program, interface, &candidate));
+
+        if actual != Some(*expected) {
+            return Err(LayoutMismatch {
+                field: name.to_string(),
+                expected: *expected,
+                actual,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn resource_offset(program: &LinkedProgram, interface: u32, name: &str) -> Option<usize> {
+    let name = CString::new(name).ok()?;
+    let index = unsafe { gl::GetProgramResourceIndex(program.id(), interface, name.as_ptr()) };
+    if index == gl::INVALID_INDEX {
+        return None;
+    }
+
+    let property = gl::OFFSET;
+    let mut offset = 0;
+    unsafe {
+        gl::GetProgramResourceiv(
+            program.id(),
+            interface,
+            index,
+            1,
+            &property,
+            1,
+            std::ptr::null_mut(),
+            &mut offset,
+        )
+    };
+
+    usize::try_from(offset).ok()
+}
+
+/// A type with a defined memory layout under the GLSL `std140` rules, used by uniform blocks.
+///
+/// Implemented for scalars, `glm` vectors and matrices, arrays of those, and structs with
+/// `#[derive(UniformBlock)]`. Unlike `std430`, array elements and matrix columns are padded
+/// to 16 bytes.
+pub trait Std140 {
+    /// The base alignment of the type in bytes
+    const ALIGN: usize;
+
+    /// The size of the type in bytes, including any trailing padding required by the rules
+    const SIZE: usize;
+
+    /// Appends the `std140` representation of `self` to `out`.
+    ///
+    /// `out` must already be padded to a multiple of [`Std140::ALIGN`] relative to the start
+    /// of the block.
+    fn write_std140(&self, out: &mut Vec<u8>);
+
+    /// Serializes `self` into a byte buffer ready to be uploaded to a uniform buffer
+    fn to_std140_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        self.write_std140(&mut out);
+        pad_to(&mut out, Self::SIZE);
+        out
+    }
+}
+
+/// A struct laid out according to `std140`, usually implemented with
+/// `#[derive(UniformBlock)]`.
+///
+/// The derive computes each field's offset at compile time, rounding the struct's alignment
+/// up to 16 bytes as `std140` requires for nested structs. A field annotated with
+/// `#[uniform_block(offset = N)]` fails to compile if its computed offset isn't `N`.
+pub trait UniformBlock: Std140 {
+    /// The GLSL names of the fields, in declaration order
+    const FIELD_NAMES: &'static [&'static str];
+
+    /// The byte offsets of the fields, in declaration order
+    const FIELD_OFFSETS: &'static [usize];
+
+    /// Checks the computed field offsets against the offsets `program` reports for the
+    /// members of the uniform block `block`.
+    ///
+    /// Only performs the check in debug builds; release builds always return `Ok`.
+    fn validate_std140(program: &LinkedProgram, block: &str) -> Result<(), LayoutMismatch> {
+        if cfg!(debug_assertions) {
+            validate_offsets(
+                program,
+                gl::UNIFORM,
+                block,
+                Self::FIELD_NAMES,
+                Self::FIELD_OFFSETS,
+            )
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The alignment of array elements and matrix columns under `std140`
+const STD140_ARRAY_ALIGN: usize = 16;
+
+macro_rules! impl_std430_scalar {
+    ( $($t: ty),* ) => {
+        $(
+            impl Std430 for $t {
+                const ALIGN: usize = std::mem::size_of::<$t>();
+                const SIZE: usize = std::mem::size_of::<$t>();
+
+                fn write_std430(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_ne_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_std430_scalar!(f32, f64, i32, u32);
+
+macro_rules! impl_std140_scalar {
+    ( $($t: ty),* ) => {
+        $(
+            impl Std140 for $t {
+                const ALIGN: usize = <$t as Std430>::ALIGN;
+                const SIZE: usize = <$t as Std430>::SIZE;
+
+                fn write_std140(&self, out: &mut Vec<u8>) {
+                    self.write_std430(out);
+                }
+            }
+        )*
+    };
+}
+
+impl_std140_scalar!(f32, f64, i32, u32, bool);
+
+impl Std430 for bool {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+
+    fn write_std430(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(*self as u32).to_ne_bytes());
+    }
+}
+
+macro_rules! impl_std430_vector {
+    ( $($t: ident, $n: expr, $align: expr);* ) => {
+        $(
+            impl<T> Std430 for glm::$t<T>
+            where
+                T: Std430 + glm::Primitive,
+            {
+                const ALIGN: usize = $align * T::ALIGN;
+                const SIZE: usize = $n * T::SIZE;
+
+                fn write_std430(&self, out: &mut Vec<u8>) {
+                    self.as_array().iter().for_each(|c| c.write_std430(out));
+                }
+            }
+        )*
+    };
+}
+
+impl_std430_vector!(Vector2, 2, 2; Vector3, 3, 4; Vector4, 4, 4);
+
+macro_rules! impl_std140_vector {
+    ( $($t: ident, $n: expr, $align: expr);* ) => {
+        $(
+            impl<T> Std140 for glm::$t<T>
+            where
+                T: Std140 + glm::Primitive,
+            {
+                const ALIGN: usize = $align * T::ALIGN;
+                const SIZE: usize = $n * T::SIZE;
+
+                fn write_std140(&self, out: &mut Vec<u8>) {
+                    self.as_array().iter().for_each(|c| c.write_std140(out));
+                }
+            }
+        )*
+    };
+}
+
+impl_std140_vector!(Vector2, 2, 2; Vector3, 3, 4; Vector4, 4, 4);
+
+macro_rules! impl_std430_matrix {
+    ( $($t: ident, $column: ident, $n: expr);* ) => {
+        $(
+            impl<T> Std430 for glm::$t<T>
+            where
+                T: Std430 + glm::BaseFloat,
+            {
+                const ALIGN: usize = <glm::$column<T> as Std430>::ALIGN;
+                const SIZE: usize = $n * align_to(
+                    <glm::$column<T> as Std430>::SIZE,
+                    <glm::$column<T> as Std430>::ALIGN,
+                );
+
+                fn write_std430(&self, out: &mut Vec<u8>) {
+                    let start = out.len();
+                    let stride = align_to(
+                        <glm::$column<T> as Std430>::SIZE,
+                        <glm::$column<T> as Std430>::ALIGN,
+                    );
+                    for (i, column) in self.as_array().iter().enumerate() {
+                        pad_to(out, start + i * stride);
+                        column.write_std430(out);
+                    }
+                    pad_to(out, start + Self::SIZE);
+                }
+            }
+        )*
+    };
+}
+
+impl_std430_matrix!(Matrix2, Vector2, 2; Matrix3, Vector3, 3; Matrix4, Vector4, 4);
+
+macro_rules! impl_std140_matrix {
+    ( $($t: ident, $column: ident, $n: expr);* ) => {
+        $(
+            impl<T> Std140 for glm::$t<T>
+            where
+                T: Std140 + glm::BaseFloat,
+            {
+                const ALIGN: usize = max(<glm::$column<T> as Std140>::ALIGN, STD140_ARRAY_ALIGN);
+                const SIZE: usize = $n * align_to(<glm::$column<T> as Std140>::SIZE, Self::ALIGN);
+
+                fn write_std140(&self, out: &mut Vec<u8>) {
+                    let start = out.len();
+                    let stride = align_to(<glm::$column<T> as Std140>::SIZE, Self::ALIGN);
+                    for (i, column) in self.as_array().iter().enumerate() {
+                        pad_to(out, start + i * stride);
+                        column.write_std140(out);
+                    }
+                    pad_to(out, start + Self::SIZE);
+                }
+            }
+        )*
+    };
+}
+
+impl_std140_matrix!(Matrix2, Vector2, 2; Matrix3, Vector3, 3; Matrix4, Vector4, 4);
+
+impl<T, const N: usize> Std430 for [T; N]
+where
+    T: Std430,
+{
+    const ALIGN: usize = T::ALIGN;
+    const SIZE: usize = N * align_to(T::SIZE, T::ALIGN);
+
+    fn write_std430(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        let stride = align_to(T::SIZE, T::ALIGN);
+        for (i, element) in self.iter().enumerate() {
+            pad_to(out, start + i * stride);
+            element.write_std430(out);
+        }
+        pad_to(out, start + Self::SIZE);
+    }
+}
+
+impl<T, const N: usize> Std140 for [T; N]
+where
+    T: Std140,
+{
+    const ALIGN: usize = max(T::ALIGN, STD140_ARRAY_ALIGN);
+    const SIZE: usize = N * align_to(T::SIZE, Self::ALIGN);
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        let stride = align_to(T::SIZE, Self::ALIGN);
+        for (i, element) in self.iter().enumerate() {
+            pad_to(out, start + i * stride);
+            element.write_std140(out);
+        }
+        pad_to(out, start + Self::SIZE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{Mat3, Mat4, Vec2, Vec3, Vec4};
+
+    #[test]
+    fn align_to_rounds_up_to_multiples() {
+        for (offset, align, expected) in
+            [(0, 4, 0), (1, 4, 4), (4, 4, 4), (5, 16, 16), (17, 16, 32)]
+        {
+            assert_eq!(
+                align_to(offset, align),
+                expected,
+                "align_to({offset}, {align})"
+            );
+        }
+    }
+
+    #[test]
+    fn std430_alignments_and_sizes() {
+        let table = [
+            ("f32", <f32 as Std430>::ALIGN, <f32 as Std430>::SIZE, 4, 4),
+            ("f64", <f64 as Std430>::ALIGN, <f64 as Std430>::SIZE, 8, 8),
+            (
+                "bool",
+                <bool as Std430>::ALIGN,
+                <bool as Std430>::SIZE,
+                4,
+                4,
+            ),
+            (
+                "vec2",
+                <Vec2 as Std430>::ALIGN,
+                <Vec2 as Std430>::SIZE,
+                8,
+                8,
+            ),
+            (
+                "vec3",
+                <Vec3 as Std430>::ALIGN,
+                <Vec3 as Std430>::SIZE,
+                16,
+                12,
+            ),
+            (
+                "vec4",
+                <Vec4 as Std430>::ALIGN,
+                <Vec4 as Std430>::SIZE,
+                16,
+                16,
+            ),
+            (
+                "mat3",
+                <Mat3 as Std430>::ALIGN,
+                <Mat3 as Std430>::SIZE,
+                16,
+                48,
+            ),
+            (
+                "mat4",
+                <Mat4 as Std430>::ALIGN,
+                <Mat4 as Std430>::SIZE,
+                16,
+                64,
+            ),
+            (
+                "float[3]",
+                <[f32; 3] as Std430>::ALIGN,
+                <[f32; 3] as Std430>::SIZE,
+                4,
+                12,
+            ),
+            (
+                "vec3[2]",
+                <[Vec3; 2] as Std430>::ALIGN,
+                <[Vec3; 2] as Std430>::SIZE,
+                16,
+                32,
+            ),
+        ];
+        for (name, align, size, expected_align, expected_size) in table {
+            assert_eq!(
+                (align, size),
+                (expected_align, expected_size),
+                "std430 {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn std140_alignments_and_sizes() {
+        let table = [
+            ("f32", <f32 as Std140>::ALIGN, <f32 as Std140>::SIZE, 4, 4),
+            (
+                "vec2",
+                <Vec2 as Std140>::ALIGN,
+                <Vec2 as Std140>::SIZE,
+                8,
+                8,
+            ),
+            (
+                "vec3",
+                <Vec3 as Std140>::ALIGN,
+                <Vec3 as Std140>::SIZE,
+                16,
+                12,
+            ),
+            (
+                "mat3",
+                <Mat3 as Std140>::ALIGN,
+                <Mat3 as Std140>::SIZE,
+                16,
+                48,
+            ),
+            (
+                "mat4",
+                <Mat4 as Std140>::ALIGN,
+                <Mat4 as Std140>::SIZE,
+                16,
+                64,
+            ),
+            (
+                "float[3]",
+                <[f32; 3] as Std140>::ALIGN,
+                <[f32; 3] as Std140>::SIZE,
+                16,
+                48,
+            ),
+            (
+                "vec2[2]",
+                <[Vec2; 2] as Std140>::ALIGN,
+                <[Vec2; 2] as Std140>::SIZE,
+                16,
+                32,
+            ),
+        ];
+        for (name, align, size, expected_align, expected_size) in table {
+            assert_eq!(
+                (align, size),
+                (expected_align, expected_size),
+                "std140 {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn arrays_are_padded_to_their_stride() {
+        let mut out = vec![];
+        [1.0f32, 2.0, 3.0].write_std140(&mut out);
+        assert_eq!(out.len(), 48);
+        assert_eq!(&out[16..20], &2.0f32.to_ne_bytes());
+
+        let mut out = vec![];
+        [1.0f32, 2.0, 3.0].write_std430(&mut out);
+        assert_eq!(out.len(), 12);
+        assert_eq!(&out[4..8], &2.0f32.to_ne_bytes());
+    }
+}
@@ -5,7 +5,9 @@
 //! # Example
 //!
 //! Below is the standard `HelloTriangle` program.
-//! ```
+// The example creates its window through the GLFW backend
+#![cfg_attr(feature = "backends", doc = "```")]
+#![cfg_attr(not(feature = "backends"), doc = "```ignore")]
 //! use glfw::{Action, Context, Key, WindowMode};
 //! use glium2::{
 //!     buffer::VertexBuffer,
@@ -31,14 +33,11 @@
 //! let mut renderer = Renderer::new();
 //! renderer.clear_color(glm::vec4(0.0, 0.0, 0.0, 1.0));
 //!
-//! let buffer = VertexBuffer::new(
-//!     &vec![
-//!         glm::vec2(0.0, 0.5),
-//!         glm::vec2(0.5, 0.0),
-//!         glm::vec2(-0.5, 0.0),
-//!     ],
-//!     None,
-//! );
+//! let buffer = VertexBuffer::from_vertices(&[
+//!     glm::vec2(0.0, 0.5),
+//!     glm::vec2(0.5, 0.0),
+//!     glm::vec2(-0.5, 0.0),
+//! ]);
 //!
 //! let vertex_shader = Shader::new(
 //!     r#"
@@ -65,14 +64,15 @@
 //!     ShaderType::Fragment,
 //! );
 //!
-//! let mut program = Program::new();
-//! program
+//! let program = Program::new()
 //!     .attach_and_link(vec![vertex_shader, fragment_shader])
 //!     .expect("Failed to link program");
 //!
 //! # window.set_should_close(true);
 //! while !window.should_close() {
-//!     renderer.draw(&buffer, &program, DrawMode::Triangles, &uniforms! {});
+//!     renderer
+//!         .draw(&buffer, &program, DrawMode::Triangles, &uniforms! {})
+//!         .expect("Failed to draw triangle");
 //!
 //!     window.swap_buffers();
 //!     glfw.poll_events();
@@ -86,21 +86,160 @@
 //!
 //! ```
 
+/// Scoped binding guards
+pub mod bind;
+
 /// OpenGL buffer utilities
 pub mod buffer;
 
+/// Cameras and conversions between screen and world space
+pub mod camera;
+
+/// Clustered light culling for forward shading with many lights
+#[cfg(feature = "passes")]
+pub mod clustered;
+
+/// Thread-safe resource handles and command lists recorded off the rendering thread
+pub mod command;
+
+/// Compute shader programs and dispatches
+pub mod compute;
+
+/// Capabilities of the current OpenGL context
+pub mod context;
+
+/// Projected decals blended into rendered geometry
+#[cfg(feature = "passes")]
+pub mod decal;
+
+/// A reference deferred shading pipeline
+#[cfg(feature = "passes")]
+pub mod deferred;
+
+/// Hierarchical depth buffers for occlusion culling and screen-space effects
+#[cfg(feature = "passes")]
+pub mod depth_pyramid;
+
+/// Pluggable reporting of GL debug messages, shader warnings and resource events
+pub mod diagnostics;
+
+/// Fixed-function state applied to draw calls
+pub mod draw_parameters;
+
+/// Renderables bundling geometry, program, uniforms and draw state
+pub mod drawable;
+
+/// Scene-wide shading settings such as fog, shared through a uniform block
+pub mod environment;
+
+/// Per-frame rendering lifecycle
+pub mod frame;
+
+/// Debug descriptions of the passes of a frame, exported as DOT or JSON
+#[cfg(feature = "passes")]
+pub mod frame_graph;
+
+mod ext;
+
+/// Off-screen render targets
+pub mod framebuffer;
+
+/// Per-frame camera, time and viewport values shared with shaders through a uniform block
+pub mod globals;
+
+/// Expansion of `#include` directives in GLSL sources
+pub mod include;
+
+/// Keyboard, mouse and gamepad state normalized across windowing backends
+pub mod input;
+
+/// CPU ray intersection tests for precise picking
+pub mod intersect;
+
+/// GLSL memory layouts for buffer-backed blocks
+pub mod layout;
+
+/// Light sources shared by the lighting pipelines
+pub mod light;
+
+/// Baking of static lighting into lightmap textures
+#[cfg(feature = "passes")]
+pub mod lightmap;
+
 /// Functions to generate matrices not supported by [`glm`]
 pub mod matrix;
 
+/// Meshes, and the geometry shared by the renderer and its passes
+pub mod mesh;
+
+/// Vertex cache, overdraw and vertex fetch optimization of indexed meshes
+#[cfg(feature = "mesh-optimizer")]
+pub mod mesh_optimizer;
+
+/// Compact encodings of vertex attributes such as normals, tangents and colors
+pub mod packed;
+
+/// Particle simulation and rendering entirely on the GPU
+#[cfg(feature = "passes")]
+pub mod particles;
+
+/// Object picking through an off-screen ID pass
+#[cfg(feature = "passes")]
+pub mod picking;
+
+/// Post-processing effects applied to rendered images
+#[cfg(feature = "passes")]
+pub mod postprocess;
+
 /// Graphical primitives
+#[cfg(feature = "primitives")]
 pub mod primitive;
 
+/// GPU and CPU frame profiling with timer queries
+pub mod profiler;
+
+/// On-disk caching of linked program binaries for faster startup
+pub mod program_cache;
+
 /// The central structure of glium2
 pub mod renderer;
 
 /// OpenGL shader utilities
 pub mod shader;
 
+/// Ready-made programs for common materials
+#[cfg(feature = "primitives")]
+pub mod shaders;
+
+/// Packing of many lights' shadow maps into a single depth texture
+#[cfg(feature = "passes")]
+pub mod shadow_atlas;
+
+/// Stereo rendering into the layers of an array texture
+#[cfg(feature = "passes")]
+pub mod stereo;
+
+/// GPU synchronization primitives
+pub mod sync;
+
+/// Render targets that can be drawn to
+pub mod surface;
+
+/// Chunked 2D tilemaps drawn from a tile atlas
+#[cfg(feature = "primitives")]
+pub mod tilemap;
+
+/// Import of maps made with the Tiled editor
+#[cfg(feature = "tiled")]
+pub mod tmx;
+
+/// Temporal anti-aliasing through projection jitter and history accumulation
+#[cfg(feature = "passes")]
+pub mod taa;
+
+/// OpenGL textures
+pub mod texture;
+
 /// OpenGL types
 pub mod types;
 
@@ -108,17 +247,21 @@ pub mod types;
 #[macro_use]
 pub mod uniforms;
 
+/// Compilation of shader directories outside of an application, e.g. from a build script
+pub mod validate;
+
 pub mod glm {
     pub use glm::*;
 }
 
-pub mod glfw {
-    pub use glfw::*;
-}
+/// GLFW, re-exported with helpers for display modes, icons and cursors
+#[cfg(feature = "backends")]
+pub mod glfw;
 
 #[macro_use]
 pub mod macros {
     pub use macros::*;
 }
 
-pub use renderer::{DrawMode, Renderer};
+pub use draw_parameters::DrawParameters;
+pub use renderer::{DrawError, DrawMode, Renderer};
@@ -0,0 +1,122 @@
+use glm::Vec3;
+
+/// The shader storage binding point lights are bound to by the lighting passes. See
+/// [`Light::GLSL`].
+pub const LIGHT_BINDING: u32 = 15;
+
+/// A light source, shaded by the [`crate::deferred`] and [`crate::clustered`] pipelines
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Light {
+    /// Light arriving from the same direction everywhere, such as sunlight
+    Directional {
+        /// The direction the light travels in
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+    },
+    /// Light emitted from a point, fading out completely at `radius`
+    Point {
+        position: Vec3,
+        color: Vec3,
+        intensity: f32,
+        radius: f32,
+    },
+}
+
+impl Light {
+    /// The light storage block and an `evaluate_light` function, to be inserted after the
+    /// `#version` directive.
+    ///
+    /// `evaluate_light(light, albedo, specular, position, normal, view)` returns the
+    /// Blinn-Phong contribution of `light` to a surface, where `position` and `normal` are in
+    /// world space and `view` points from the surface towards the camera.
+    pub const GLSL: &'static str = r#"
+        struct Light {
+            vec4 position_radius;
+            vec4 color_intensity;
+            vec4 direction_kind;
+        };
+
+        layout(std430, binding = 15) readonly buffer Lights {
+            Light lights[];
+        };
+
+        vec3 evaluate_light(
+            Light light,
+            vec3 albedo,
+            float specular,
+            vec3 position,
+            vec3 normal,
+            vec3 view
+        ) {
+            vec3 to_light;
+            float attenuation = 1.0;
+            if (light.direction_kind.w == 0.0) {
+                to_light = normalize(-light.direction_kind.xyz);
+            } else {
+                vec3 offset = light.position_radius.xyz - position;
+                float distance = length(offset);
+                float radius = light.position_radius.w;
+                if (distance >= radius) {
+                    return vec3(0.0);
+                }
+                to_light = offset / distance;
+                float falloff = clamp(1.0 - pow(distance / radius, 4.0), 0.0, 1.0);
+                attenuation = falloff * falloff / (distance * distance + 1.0);
+            }
+
+            vec3 halfway = normalize(to_light + view);
+            float diffuse = max(dot(normal, to_light), 0.0);
+            float highlight = pow(max(dot(normal, halfway), 0.0), 32.0) * specular;
+            vec3 radiance = light.color_intensity.rgb * light.color_intensity.w * attenuation;
+            return (albedo * diffuse + highlight) * radiance;
+        }
+    "#;
+}
+
+/// A [`Light`] as laid out in the light storage block (`std430`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct GpuLight {
+    position_radius: [f32; 4],
+    color_intensity: [f32; 4],
+    direction_kind: [f32; 4],
+}
+
+impl From<&Light> for GpuLight {
+    fn from(light: &Light) -> Self {
+        match *light {
+            Light::Directional {
+                direction,
+                color,
+                intensity,
+            } => Self {
+                position_radius: [0.0; 4],
+                color_intensity: [color.x, color.y, color.z, intensity],
+                direction_kind: [direction.x, direction.y, direction.z, 0.0],
+            },
+            Light::Point {
+                position,
+                color,
+                intensity,
+                radius,
+            } => Self {
+                position_radius: [position.x, position.y, position.z, radius],
+                color_intensity: [color.x, color.y, color.z, intensity],
+                direction_kind: [0.0, 0.0, 0.0, 1.0],
+            },
+        }
+    }
+}
+
+/// Uploads `lights` to `buffer` and binds it to [`LIGHT_BINDING`]
+#[cfg(feature = "passes")]
+pub(crate) fn upload(buffer: &mut crate::buffer::ShaderStorageBuffer<GpuLight>, lights: &[Light]) {
+    let mut gpu_lights = lights.iter().map(GpuLight::from).collect::<Vec<_>>();
+    if gpu_lights.is_empty() {
+        // Empty storage buffers can't be bound
+        gpu_lights.push(GpuLight::default());
+    }
+    buffer.set_data(&gpu_lights);
+    buffer.bind_to(LIGHT_BINDING);
+}
@@ -0,0 +1,430 @@
+//! Baking of static lighting into lightmaps. [`bake_lightmap`] rasterizes a mesh into its
+//! second UV set to find the surface point of every texel, then traces rays on the CPU to
+//! gather direct light and sky visibility. The result is meant to be baked once, e.g. with a
+//! [`crate::validate::HeadlessContext`] from a build step, and shipped as a texture.
+
+use crate::{
+    buffer::{FromVertexData, VertexBuffer, VertexData},
+    draw_parameters,
+    framebuffer::{Framebuffer, FramebufferError},
+    intersect::TriangleMesh,
+    light::Light,
+    matrix::Ray,
+    renderer::{DrawError, DrawMode, Rect, Renderer},
+    shader::{
+        LinkedProgram, Program, Shader, ShaderCompilationError, ShaderType, Vertex,
+        VertexAttributeSpec, VertexFormat,
+    },
+    surface::Surface,
+    texture::{PixelFormat, Texture2D, TextureFormat},
+    uniforms::Uniforms,
+};
+use glm::{Vec2, Vec3};
+
+/// A vertex of geometry to bake, with the unique, non-overlapping second UV set the lightmap
+/// is laid out in
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightmapVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    /// The coordinates of the vertex in the lightmap, in `[0, 1]`
+    pub lightmap_uv: Vec2,
+}
+
+impl From<LightmapVertex> for VertexData {
+    fn from(vertex: LightmapVertex) -> VertexData {
+        let mut data = Vec::new();
+        data.extend_from_slice(vertex.position.as_array());
+        data.extend_from_slice(vertex.normal.as_array());
+        data.extend_from_slice(vertex.lightmap_uv.as_array());
+        VertexData {
+            data: data
+                .into_iter()
+                .flat_map(|f| f.to_ne_bytes())
+                .collect::<Vec<_>>(),
+        }
+    }
+}
+
+impl FromVertexData for LightmapVertex {
+    fn from_vertex_data(data: &[u8]) -> Self {
+        let [x, y, z, nx, ny, nz, u, v] = crate::buffer::read_floats(data);
+        Self {
+            position: glm::vec3(x, y, z),
+            normal: glm::vec3(nx, ny, nz),
+            lightmap_uv: glm::vec2(u, v),
+        }
+    }
+}
+
+impl Vertex for LightmapVertex {
+    fn get_vertex_spec() -> VertexAttributeSpec {
+        VertexAttributeSpec::builder()
+            .attribute(VertexFormat::Float32x3)
+            .attribute(VertexFormat::Float32x3)
+            .attribute(VertexFormat::Float32x2)
+            .build()
+    }
+}
+
+/// How [`bake_lightmap`] bakes
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BakeSettings {
+    /// The width of the lightmap in texels
+    pub width: u32,
+    /// The height of the lightmap in texels
+    pub height: u32,
+    /// The radiance of the sky, which lights every surface it is visible from
+    pub sky_color: Vec3,
+    /// The number of rays traced towards the sky from every texel. `0` skips sky lighting.
+    pub sky_samples: u32,
+    /// How far rays start off the surface, to keep them from hitting it
+    pub bias: f32,
+    /// The number of texels the baked texels are grown by into the empty space around them,
+    /// so that filtering near chart edges doesn't blend in black
+    pub dilation: u32,
+}
+
+impl Default for BakeSettings {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            sky_color: glm::vec3(0.2, 0.2, 0.2),
+            sky_samples: 64,
+            bias: 1e-3,
+            dilation: 4,
+        }
+    }
+}
+
+/// An error that occurred while baking a lightmap
+#[derive(Debug, Clone)]
+pub enum BakeError {
+    /// The rasterization program failed to compile
+    Compilation(ShaderCompilationError),
+    /// The rasterization target couldn't be created
+    Framebuffer(FramebufferError),
+    /// The mesh couldn't be rasterized
+    Draw(DrawError),
+}
+
+impl std::fmt::Display for BakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Compilation(error) => write!(f, "Failed to compile the bake program: {error}"),
+            Self::Framebuffer(error) => write!(f, "Failed to create the bake target: {error:?}"),
+            Self::Draw(error) => write!(f, "Failed to rasterize the mesh: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for BakeError {}
+
+impl From<ShaderCompilationError> for BakeError {
+    fn from(error: ShaderCompilationError) -> Self {
+        Self::Compilation(error)
+    }
+}
+
+impl From<FramebufferError> for BakeError {
+    fn from(error: FramebufferError) -> Self {
+        Self::Framebuffer(error)
+    }
+}
+
+impl From<DrawError> for BakeError {
+    fn from(error: DrawError) -> Self {
+        Self::Draw(error)
+    }
+}
+
+/// Baked irradiance, one texel per lightmap texel with rows from bottom to top
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lightmap {
+    pub width: u32,
+    pub height: u32,
+    pub texels: Vec<Vec3>,
+}
+
+impl Lightmap {
+    /// Returns the irradiance at texel (`x`, `y`)
+    pub fn texel(&self, x: u32, y: u32) -> Vec3 {
+        self.texels[(y * self.width + x) as usize]
+    }
+
+    /// Uploads the lightmap into an `Rgba16F` texture, to be sampled with the second UV set
+    /// and multiplied with the albedo
+    pub fn to_texture(&self) -> Texture2D {
+        let data = self
+            .texels
+            .iter()
+            .flat_map(|texel| [texel.x, texel.y, texel.z, 1.0])
+            .flat_map(f32::to_ne_bytes)
+            .collect::<Vec<_>>();
+        Texture2D::with_data(self.width, self.height, TextureFormat::Rgba16F, &data)
+    }
+}
+
+const RASTERIZE_VERTEX_SHADER: &str = r#"
+    #version 460 core
+    layout(location = 0) in vec3 position;
+    layout(location = 1) in vec3 normal;
+    layout(location = 2) in vec2 lightmap_uv;
+
+    out vec3 world_position;
+    out vec3 world_normal;
+
+    void main() {
+        world_position = position;
+        world_normal = normal;
+        gl_Position = vec4(lightmap_uv * 2.0 - 1.0, 0.0, 1.0);
+    }
+"#;
+
+const RASTERIZE_FRAGMENT_SHADER: &str = r#"
+    #version 460 core
+
+    in vec3 world_position;
+    in vec3 world_normal;
+
+    layout(location = 0) out vec4 position;
+    layout(location = 1) out vec4 normal;
+
+    void main() {
+        position = vec4(world_position, 1.0);
+        normal = vec4(normalize(world_normal), 1.0);
+    }
+"#;
+
+/// Bakes the direct light of `lights` and the light of the sky onto the triangles of
+/// `vertices` and `indices`, which also cast the shadows.
+///
+/// Requires a current context, e.g. a [`crate::validate::HeadlessContext`], which is only used
+/// to find the surface point of every texel; the lighting is traced on the CPU.
+pub fn bake_lightmap(
+    vertices: &[LightmapVertex],
+    indices: &[u32],
+    lights: &[Light],
+    settings: &BakeSettings,
+) -> Result<Lightmap, BakeError> {
+    let (positions, normals) = rasterize(vertices, indices, settings)?;
+
+    let mut mesh = TriangleMesh::new(
+        vertices.iter().map(|vertex| vertex.position).collect(),
+        indices,
+    );
+    mesh.build_bvh();
+
+    let texel_count = (settings.width * settings.height) as usize;
+    let mut texels = vec![glm::vec3(0.0, 0.0, 0.0); texel_count];
+    let mut covered = vec![false; texel_count];
+    for index in 0..texel_count {
+        let [x, y, z, coverage] = positions[index];
+        if coverage <= 0.0 {
+            continue;
+        }
+        let [nx, ny, nz, _] = normals[index];
+        let position = glm::vec3(x, y, z);
+        let normal = glm::normalize(glm::vec3(nx, ny, nz));
+        let origin = position + normal * settings.bias;
+
+        let direct = lights.iter().fold(glm::vec3(0.0, 0.0, 0.0), |sum, light| {
+            sum + direct_light(&mesh, light, origin, normal)
+        });
+        let sky = settings.sky_color * sky_visibility(&mesh, origin, normal, index, settings);
+        texels[index] = direct + sky;
+        covered[index] = true;
+    }
+
+    dilate(&mut texels, &mut covered, settings);
+
+    Ok(Lightmap {
+        width: settings.width,
+        height: settings.height,
+        texels,
+    })
+}
+
+/// `Rgba32F` texels read back from a render target
+type Texels = Vec<[f32; 4]>;
+
+/// Rasterizes the mesh into its lightmap UVs, returning the world space position and normal
+/// of every texel. Texels the mesh doesn't cover have a `w` of `0`.
+fn rasterize(
+    vertices: &[LightmapVertex],
+    indices: &[u32],
+    settings: &BakeSettings,
+) -> Result<(Texels, Texels), BakeError> {
+    let program = rasterize_program()?;
+    let (width, height) = (settings.width, settings.height);
+    let mut target = Framebuffer::new(
+        vec![
+            Texture2D::new(width, height, TextureFormat::Rgba32F),
+            Texture2D::new(width, height, TextureFormat::Rgba32F),
+        ],
+        None,
+    )?;
+    target.clear(glm::vec4(0.0, 0.0, 0.0, 0.0), 1.0);
+
+    let buffer = VertexBuffer::new_static(vertices, Some(indices));
+    // Charts may be mirrored in UV space, so both windings have to be rasterized
+    let culling = unsafe { gl::IsEnabled(gl::CULL_FACE) } == gl::TRUE;
+    unsafe { gl::Disable(gl::CULL_FACE) };
+    draw_parameters::invalidate_state_cache();
+    let result = target.draw(&buffer, &program, DrawMode::Triangles, &Uniforms::default());
+    if culling {
+        unsafe { gl::Enable(gl::CULL_FACE) };
+    }
+    draw_parameters::invalidate_state_cache();
+    result?;
+
+    let rect = Rect::new(0, 0, width, height);
+    let read = |attachment: u32| {
+        let mut texels = vec![[0.0f32; 4]; (width * height) as usize];
+        Renderer::read_pixels_into(
+            target.id(),
+            gl::COLOR_ATTACHMENT0 + attachment,
+            rect,
+            PixelFormat::RgbaF32,
+            texels.as_mut_ptr().cast(),
+        );
+        texels
+    };
+    Ok((read(0), read(1)))
+}
+
+fn rasterize_program() -> Result<LinkedProgram, ShaderCompilationError> {
+    Program::new().attach_and_link(vec![
+        Shader::new(RASTERIZE_VERTEX_SHADER.to_owned(), ShaderType::Vertex),
+        Shader::new(RASTERIZE_FRAGMENT_SHADER.to_owned(), ShaderType::Fragment),
+    ])
+}
+
+/// The light `light` contributes to a surface at `origin` facing `normal`, matching the
+/// attenuation of [`Light::GLSL`]
+fn direct_light(mesh: &TriangleMesh, light: &Light, origin: Vec3, normal: Vec3) -> Vec3 {
+    let (to_light, distance, radiance) = match *light {
+        Light::Directional {
+            direction,
+            color,
+            intensity,
+        } => (glm::normalize(-direction), f32::INFINITY, color * intensity),
+        Light::Point {
+            position,
+            color,
+            intensity,
+            radius,
+        } => {
+            let offset = position - origin;
+            let distance = glm::length(offset);
+            if distance >= radius || distance <= 0.0 {
+                return glm::vec3(0.0, 0.0, 0.0);
+            }
+            let falloff = (1.0 - (distance / radius).powi(4)).clamp(0.0, 1.0);
+            let attenuation = falloff * falloff / (distance * distance + 1.0);
+            (
+                offset / distance,
+                distance,
+                color * (intensity * attenuation),
+            )
+        }
+    };
+
+    let cosine = glm::dot(normal, to_light);
+    if cosine <= 0.0 {
+        return glm::vec3(0.0, 0.0, 0.0);
+    }
+    let ray = Ray {
+        origin,
+        direction: to_light,
+    };
+    let occluded = mesh
+        .intersect_ray(&ray)
+        .is_some_and(|hit| hit.hit.distance < distance);
+    if occluded {
+        glm::vec3(0.0, 0.0, 0.0)
+    } else {
+        radiance * cosine
+    }
+}
+
+/// The fraction of cosine-weighted rays from `origin` that escape to the sky
+fn sky_visibility(
+    mesh: &TriangleMesh,
+    origin: Vec3,
+    normal: Vec3,
+    texel: usize,
+    settings: &BakeSettings,
+) -> f32 {
+    if settings.sky_samples == 0 {
+        return 0.0;
+    }
+
+    // An orthonormal basis around the normal
+    let helper = if normal.x.abs() < 0.9 {
+        glm::vec3(1.0, 0.0, 0.0)
+    } else {
+        glm::vec3(0.0, 1.0, 0.0)
+    };
+    let tangent = glm::normalize(glm::cross(helper, normal));
+    let bitangent = glm::cross(normal, tangent);
+
+    // Hammersley points, rotated per texel so that neighbouring texels don't share patterns
+    let rotation = (texel as u32).wrapping_mul(2_654_435_769) as f32 / u32::MAX as f32;
+    let visible = (0..settings.sky_samples)
+        .filter(|&sample| {
+            let u = (sample as f32 + 0.5) / settings.sky_samples as f32;
+            let v = (sample.reverse_bits() as f32 / u32::MAX as f32 + rotation).fract();
+            let radius = u.sqrt();
+            let angle = v * std::f32::consts::TAU;
+            let direction = tangent * (radius * angle.cos())
+                + bitangent * (radius * angle.sin())
+                + normal * (1.0 - u).max(0.0).sqrt();
+            let ray = Ray {
+                origin,
+                direction: glm::normalize(direction),
+            };
+            mesh.intersect_ray(&ray).is_none()
+        })
+        .count();
+    visible as f32 / settings.sky_samples as f32
+}
+
+/// Grows the covered texels into their uncovered neighbours `settings.dilation` times
+fn dilate(texels: &mut [Vec3], covered: &mut [bool], settings: &BakeSettings) {
+    let (width, height) = (settings.width as i64, settings.height as i64);
+    for _ in 0..settings.dilation {
+        let mut grown = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let index = (y * width + x) as usize;
+                if covered[index] {
+                    continue;
+                }
+
+                let neighbours = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .iter()
+                    .map(|&(dx, dy)| (x + dx, y + dy))
+                    .filter(|&(nx, ny)| (0..width).contains(&nx) && (0..height).contains(&ny))
+                    .map(|(nx, ny)| (ny * width + nx) as usize)
+                    .filter(|&neighbour| covered[neighbour])
+                    .collect::<Vec<_>>();
+                if !neighbours.is_empty() {
+                    let sum = neighbours
+                        .iter()
+                        .fold(glm::vec3(0.0, 0.0, 0.0), |sum, &n| sum + texels[n]);
+                    grown.push((index, sum / neighbours.len() as f32));
+                }
+            }
+        }
+
+        if grown.is_empty() {
+            break;
+        }
+        for (index, texel) in grown {
+            texels[index] = texel;
+            covered[index] = true;
+        }
+    }
+}
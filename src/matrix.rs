@@ -1,3 +1,6 @@
+use crate::renderer::{ClipDepth, Rect};
+use glm::GenSquareMat;
+
 /// Constructs an orthographic projection matrix.
 pub fn ortho(
     left: f32,
@@ -19,3 +22,89 @@ pub fn ortho(
         ),
     )
 }
+
+/// A half-line starting at `origin` and extending along `direction`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: glm::Vec3,
+    /// The direction of the ray, normalized
+    pub direction: glm::Vec3,
+}
+
+impl Ray {
+    /// Returns the point `distance` units along the ray
+    pub fn at(&self, distance: f32) -> glm::Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Returns the distance along the ray at which it crosses the plane through `point` with
+    /// the given `normal`, or `None` if it runs parallel to or away from the plane
+    pub fn intersect_plane(&self, point: glm::Vec3, normal: glm::Vec3) -> Option<f32> {
+        let denominator = glm::dot(normal, self.direction);
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let distance = glm::dot(normal, point - self.origin) / denominator;
+        (distance >= 0.0).then_some(distance)
+    }
+}
+
+/// Converts a point in window coordinates back to world space, the inverse of the vertex
+/// transformation and viewport mapping.
+///
+/// `window` holds the pixel position relative to the bottom left of the window and a depth
+/// buffer value between `0` and `1`; `clip_depth` is the convention set with
+/// [`crate::Renderer::clip_control`].
+///
+/// # Returns
+///
+/// The world-space position, or `None` if `view_projection` isn't invertible or the point
+/// lies on the camera plane.
+pub fn unproject(
+    window: glm::Vec3,
+    view_projection: glm::Mat4,
+    viewport: Rect,
+    clip_depth: ClipDepth,
+) -> Option<glm::Vec3> {
+    let ndc_z = match clip_depth {
+        ClipDepth::NegativeOneToOne => window.z * 2.0 - 1.0,
+        ClipDepth::ZeroToOne => window.z,
+    };
+    let ndc = glm::vec4(
+        (window.x - viewport.x as f32) / viewport.width as f32 * 2.0 - 1.0,
+        (window.y - viewport.y as f32) / viewport.height as f32 * 2.0 - 1.0,
+        ndc_z,
+        1.0,
+    );
+
+    let point = view_projection.inverse()? * ndc;
+    (point.w.abs() > f32::EPSILON).then(|| glm::vec3(point.x, point.y, point.z) / point.w)
+}
+
+/// Returns the ray from the near plane through the pixel at `window`, relative to the bottom
+/// left of the window, e.g. for picking. See [`unproject`].
+pub fn ray_through(
+    window: glm::Vec2,
+    view_projection: glm::Mat4,
+    viewport: Rect,
+    clip_depth: ClipDepth,
+) -> Option<Ray> {
+    let near = unproject(
+        glm::vec3(window.x, window.y, 0.0),
+        view_projection,
+        viewport,
+        clip_depth,
+    )?;
+    let far = unproject(
+        glm::vec3(window.x, window.y, 1.0),
+        view_projection,
+        viewport,
+        clip_depth,
+    )?;
+
+    Some(Ray {
+        origin: near,
+        direction: glm::normalize(far - near),
+    })
+}
@@ -0,0 +1,155 @@
+use crate::{
+    buffer::{BufferUsage, VertexBuffer},
+    draw_parameters::DrawParameters,
+    renderer::{DrawError, DrawMode, Renderer},
+    shader::{LinkedProgram, Program, Shader, ShaderCompilationError, ShaderType, Vertex},
+    uniforms::Uniforms,
+};
+use glm::Vec2;
+
+/// A trait representing any primitive graphical object
+pub trait Primitive {
+    type Vertex: Vertex;
+
+    /// Turns [`self`] into a mesh by creating a vertex buffer and specifying a [`DrawMode`]
+    fn into_mesh(self) -> Mesh<Self::Vertex>;
+    fn buffer(&self) -> &VertexBuffer<Self::Vertex>;
+    fn buffer_mut(&mut self) -> &mut VertexBuffer<Self::Vertex>;
+    fn draw_mode(&self) -> DrawMode;
+}
+
+/// A single triangle covering the whole viewport, for post-processing and other full-screen
+/// passes.
+///
+/// Pair it with [`FullscreenQuad::VERTEX_SHADER`], which passes texture coordinates to the
+/// fragment stage as `in vec2 uv`.
+pub struct FullscreenQuad {
+    vertex_buffer: VertexBuffer<Vec2>,
+}
+
+impl Default for FullscreenQuad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FullscreenQuad {
+    /// A vertex shader for the triangle that outputs `uv` in the range `[0, 1]`
+    pub const VERTEX_SHADER: &'static str = r#"
+        #version 460 core
+        layout(location = 0) in vec2 position;
+
+        out vec2 uv;
+
+        void main() {
+            uv = position * 0.5 + 0.5;
+            gl_Position = vec4(position, 0, 1);
+        }
+    "#;
+
+    /// Constructs the full-screen triangle
+    pub fn new() -> Self {
+        Self {
+            vertex_buffer: VertexBuffer::new(
+                &Self::calculate_vertices(),
+                None,
+                BufferUsage::StaticDraw,
+            ),
+        }
+    }
+
+    fn calculate_vertices() -> [Vec2; 3] {
+        [
+            glm::vec2(-1.0, -1.0),
+            glm::vec2(3.0, -1.0),
+            glm::vec2(-1.0, 3.0),
+        ]
+    }
+
+    /// Links `fragment_source` together with [`FullscreenQuad::VERTEX_SHADER`]
+    pub fn program(fragment_source: &str) -> Result<LinkedProgram, ShaderCompilationError> {
+        Program::new().attach_and_link(vec![
+            Shader::new(Self::VERTEX_SHADER.to_owned(), ShaderType::Vertex),
+            Shader::new(fragment_source.to_owned(), ShaderType::Fragment),
+        ])
+    }
+
+    /// Draws the triangle with `shader_program`
+    pub fn draw(
+        &self,
+        renderer: &Renderer,
+        shader_program: &LinkedProgram,
+        uniforms: &Uniforms,
+    ) -> Result<(), DrawError> {
+        renderer.draw(self.buffer(), shader_program, self.draw_mode(), uniforms)
+    }
+}
+
+impl Primitive for FullscreenQuad {
+    type Vertex = Vec2;
+
+    fn into_mesh(self) -> Mesh<Self::Vertex> {
+        Mesh {
+            buffer: VertexBuffer::new(&Self::calculate_vertices(), None, BufferUsage::StaticDraw),
+            draw_mode: self.draw_mode(),
+        }
+    }
+
+    fn buffer(&self) -> &VertexBuffer<Self::Vertex> {
+        &self.vertex_buffer
+    }
+
+    fn buffer_mut(&mut self) -> &mut VertexBuffer<Self::Vertex> {
+        &mut self.vertex_buffer
+    }
+
+    fn draw_mode(&self) -> DrawMode {
+        DrawMode::Triangles
+    }
+}
+
+pub struct Mesh<V> {
+    pub(crate) buffer: VertexBuffer<V>,
+    pub(crate) draw_mode: DrawMode,
+}
+
+impl<V> Mesh<V>
+where
+    V: Vertex,
+{
+    pub fn buffer(&self) -> &VertexBuffer<V> {
+        &self.buffer
+    }
+
+    /// Returns the mode the mesh is drawn with
+    pub fn draw_mode(&self) -> DrawMode {
+        self.draw_mode
+    }
+
+    pub fn draw(
+        &self,
+        renderer: &mut Renderer,
+        shader_program: &LinkedProgram,
+        uniforms: &Uniforms,
+    ) -> Result<(), DrawError> {
+        renderer.draw(self.buffer(), shader_program, self.draw_mode, uniforms)
+    }
+
+    /// Draws the mesh with `parameters` applied for the duration of the draw, e.g. to draw a
+    /// single instanced or multisample-masked mesh. See [`Renderer::draw_with_parameters`].
+    pub fn draw_with_parameters(
+        &self,
+        renderer: &Renderer,
+        shader_program: &LinkedProgram,
+        uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError> {
+        renderer.draw_with_parameters(
+            self.buffer(),
+            shader_program,
+            self.draw_mode,
+            uniforms,
+            parameters,
+        )
+    }
+}
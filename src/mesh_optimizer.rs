@@ -0,0 +1,425 @@
+//! Reordering of indexed triangle meshes for faster rendering, in the spirit of
+//! meshoptimizer. Run [`optimize_mesh`] once when a mesh is loaded; the result renders the
+//! same triangles with fewer vertex shader invocations, less overdraw and better memory
+//! locality.
+
+use glm::Vec3;
+
+/// The number of vertices the vertex cache optimization models, which suits the post-transform
+/// caches of current GPUs
+const CACHE_SIZE: usize = 32;
+/// The number of most recently used vertices that get the same score, so that the triangle
+/// just emitted isn't preferred over its neighbours
+const LAST_TRIANGLE_VERTICES: usize = 3;
+
+/// Runs [`optimize_vertex_cache`], [`optimize_overdraw`] and [`optimize_vertex_fetch`] in that
+/// order, using `position` to find the position of a vertex.
+///
+/// # Returns
+///
+/// The vertices reordered by first use, without unused vertices, and the indices referring to
+/// them.
+///
+/// # Panics
+/// This function panics if `indices` doesn't hold whole triangles or refers to a vertex
+/// past the end of `vertices`.
+pub fn optimize_mesh<V, F>(vertices: &[V], indices: &[u32], position: F) -> (Vec<V>, Vec<u32>)
+where
+    V: Clone,
+    F: Fn(&V) -> Vec3,
+{
+    let mut indices = indices.to_vec();
+    optimize_vertex_cache(&mut indices, vertices.len());
+    let positions = vertices.iter().map(position).collect::<Vec<_>>();
+    optimize_overdraw(&mut indices, &positions);
+    let vertices = optimize_vertex_fetch(vertices, &mut indices);
+    (vertices, indices)
+}
+
+/// Returns the average number of vertices transformed per triangle when drawing `indices`
+/// with a FIFO vertex cache of `cache_size` vertices, between `0.5` for a perfect order and
+/// `3.0` for one without any reuse
+pub fn average_cache_miss_ratio(indices: &[u32], vertex_count: usize, cache_size: usize) -> f32 {
+    if indices.len() < 3 {
+        return 0.0;
+    }
+
+    let mut cache = FifoCache::new(vertex_count, cache_size);
+    let misses = indices.iter().filter(|&&index| cache.access(index)).count();
+    misses as f32 / (indices.len() / 3) as f32
+}
+
+/// Reorders the triangles of `indices` so that consecutive triangles share vertices, which the
+/// GPU then only transforms once. Uses Tom Forsyth's linear-speed vertex cache optimization.
+///
+/// # Panics
+/// This function panics if `indices` doesn't hold whole triangles or refers to a vertex at
+/// or past `vertex_count`.
+pub fn optimize_vertex_cache(indices: &mut [u32], vertex_count: usize) {
+    assert!(
+        indices.len().is_multiple_of(3),
+        "{} indices don't form whole triangles",
+        indices.len()
+    );
+    assert!(
+        indices.iter().all(|&index| (index as usize) < vertex_count),
+        "Index out of range for a mesh of {vertex_count} vertices"
+    );
+
+    let triangle_count = indices.len() / 3;
+    let mut vertex_triangles = vec![Vec::new(); vertex_count];
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        for &vertex in corners {
+            vertex_triangles[vertex as usize].push(triangle);
+        }
+    }
+
+    let mut cache_position = vec![None; vertex_count];
+    let mut vertex_scores = vertex_triangles
+        .iter()
+        .map(|triangles| vertex_score(None, triangles.len()))
+        .collect::<Vec<_>>();
+    let mut triangle_scores = indices
+        .chunks_exact(3)
+        .map(|corners| corners.iter().map(|&v| vertex_scores[v as usize]).sum())
+        .collect::<Vec<f32>>();
+    let mut emitted = vec![false; triangle_count];
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut order = Vec::with_capacity(triangle_count);
+    let mut next_unemitted = 0;
+
+    while order.len() < triangle_count {
+        // The best triangle touching the cache, or the next one in input order on a restart
+        let best = cache
+            .iter()
+            .flat_map(|&vertex| vertex_triangles[vertex as usize].iter().copied())
+            .max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+        let triangle = match best {
+            Some(triangle) => triangle,
+            None => {
+                while emitted[next_unemitted] {
+                    next_unemitted += 1;
+                }
+                next_unemitted
+            }
+        };
+
+        emitted[triangle] = true;
+        order.push(triangle);
+        let corners = [
+            indices[triangle * 3],
+            indices[triangle * 3 + 1],
+            indices[triangle * 3 + 2],
+        ];
+        for &vertex in &corners {
+            let triangles = &mut vertex_triangles[vertex as usize];
+            if let Some(position) = triangles.iter().position(|&t| t == triangle) {
+                triangles.swap_remove(position);
+            }
+        }
+
+        // Move the corners to the front of the cache, pushing the least recent vertices out
+        cache.retain(|vertex| !corners.contains(vertex));
+        cache.splice(0..0, corners);
+        let evicted = cache.split_off(cache.len().min(CACHE_SIZE));
+
+        for (position, &vertex) in cache.iter().enumerate() {
+            cache_position[vertex as usize] = Some(position);
+        }
+        for &vertex in &evicted {
+            cache_position[vertex as usize] = None;
+        }
+        for &vertex in cache.iter().chain(&evicted) {
+            let vertex = vertex as usize;
+            let score = vertex_score(cache_position[vertex], vertex_triangles[vertex].len());
+            let delta = score - vertex_scores[vertex];
+            vertex_scores[vertex] = score;
+            for &triangle in &vertex_triangles[vertex] {
+                triangle_scores[triangle] += delta;
+            }
+        }
+    }
+
+    let reordered = order
+        .iter()
+        .flat_map(|&triangle| indices[triangle * 3..triangle * 3 + 3].to_vec())
+        .collect::<Vec<_>>();
+    indices.copy_from_slice(&reordered);
+}
+
+/// The score of a vertex at `cache_position` that `remaining` triangles still use
+fn vertex_score(cache_position: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        None => 0.0,
+        Some(position) if position < LAST_TRIANGLE_VERTICES => 0.75,
+        Some(position) => {
+            let scale = 1.0 / (CACHE_SIZE - LAST_TRIANGLE_VERTICES) as f32;
+            (1.0 - (position - LAST_TRIANGLE_VERTICES) as f32 * scale).powf(1.5)
+        }
+    };
+    // Vertices with few triangles left are finished off first to avoid leaving them stranded
+    cache_score + 2.0 / (remaining as f32).sqrt()
+}
+
+/// Reorders clusters of triangles so that the ones facing outwards are drawn first, which
+/// lets the depth test reject more of the ones behind them. Clusters are formed where the
+/// vertex cache restarts, so this keeps most of the benefit of [`optimize_vertex_cache`],
+/// which should be run first.
+///
+/// # Panics
+/// This function panics if `indices` doesn't hold whole triangles or refers to a vertex past
+/// the end of `positions`.
+pub fn optimize_overdraw(indices: &mut [u32], positions: &[Vec3]) {
+    assert!(
+        indices.len().is_multiple_of(3),
+        "{} indices don't form whole triangles",
+        indices.len()
+    );
+
+    let mut cache = FifoCache::new(positions.len(), CACHE_SIZE);
+    let mut clusters: Vec<(usize, usize)> = Vec::new();
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        let misses = corners
+            .iter()
+            .filter(|&&vertex| cache.access(vertex))
+            .count();
+        match clusters.last_mut() {
+            Some((_, end)) if misses < 3 => *end = triangle + 1,
+            _ => clusters.push((triangle, triangle + 1)),
+        }
+    }
+
+    // The area-weighted centroid and normal of each cluster and of the whole mesh
+    let triangle_geometry = |triangle: usize| {
+        let [a, b, c] = [0, 1, 2].map(|corner| positions[indices[triangle * 3 + corner] as usize]);
+        let normal = glm::cross(b - a, c - a);
+        let area = glm::length(normal);
+        (normal, (a + b + c) * (area / 3.0), area)
+    };
+    let zero = glm::vec3(0.0, 0.0, 0.0);
+    let mut mesh_centroid = zero;
+    let mut mesh_area = 0.0;
+    let mut cluster_geometry = Vec::with_capacity(clusters.len());
+    for &(start, end) in &clusters {
+        let (mut normal, mut centroid, mut area) = (zero, zero, 0.0);
+        for triangle in start..end {
+            let (triangle_normal, weighted_centroid, triangle_area) = triangle_geometry(triangle);
+            normal = normal + triangle_normal;
+            centroid = centroid + weighted_centroid;
+            area += triangle_area;
+        }
+        mesh_centroid = mesh_centroid + centroid;
+        mesh_area += area;
+        cluster_geometry.push((normal, centroid, area));
+    }
+    if mesh_area > 0.0 {
+        mesh_centroid = mesh_centroid / mesh_area;
+    }
+
+    let keys = cluster_geometry
+        .iter()
+        .map(|&(normal, centroid, area)| {
+            if area <= 0.0 || glm::length(normal) <= 0.0 {
+                return 0.0;
+            }
+            glm::dot(centroid / area - mesh_centroid, glm::normalize(normal))
+        })
+        .collect::<Vec<f32>>();
+    let mut order = (0..clusters.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| keys[b].total_cmp(&keys[a]));
+
+    let reordered = order
+        .iter()
+        .flat_map(|&cluster| {
+            let (start, end) = clusters[cluster];
+            indices[start * 3..end * 3].to_vec()
+        })
+        .collect::<Vec<_>>();
+    indices.copy_from_slice(&reordered);
+}
+
+/// Reorders `vertices` by their first use in `indices` and drops unused ones, so that the
+/// GPU fetches vertex data mostly sequentially. `indices` is updated to match.
+///
+/// # Returns
+///
+/// The reordered vertices.
+///
+/// # Panics
+/// This function panics if `indices` refers to a vertex past the end of `vertices`.
+pub fn optimize_vertex_fetch<V: Clone>(vertices: &[V], indices: &mut [u32]) -> Vec<V> {
+    let mut remap = vec![None; vertices.len()];
+    let mut reordered = Vec::with_capacity(vertices.len());
+    for index in indices.iter_mut() {
+        let new_index = *remap[*index as usize].get_or_insert_with(|| {
+            reordered.push(vertices[*index as usize].clone());
+            (reordered.len() - 1) as u32
+        });
+        *index = new_index;
+    }
+    reordered
+}
+
+/// A simulated FIFO post-transform vertex cache
+struct FifoCache {
+    /// The time each vertex was last added to the cache
+    added: Vec<Option<usize>>,
+    time: usize,
+    size: usize,
+}
+
+impl FifoCache {
+    fn new(vertex_count: usize, size: usize) -> Self {
+        Self {
+            added: vec![None; vertex_count],
+            time: 0,
+            size,
+        }
+    }
+
+    /// Accesses `vertex`, returning whether it missed the cache
+    fn access(&mut self, vertex: u32) -> bool {
+        let added = &mut self.added[vertex as usize];
+        let hit = added.is_some_and(|added| self.time - added < self.size);
+        if !hit {
+            *added = Some(self.time);
+            self.time += 1;
+        }
+        !hit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid of `size` by `size` quads on a bumpy surface, with its triangles shuffled so
+    /// the input order has poor locality
+    fn shuffled_grid(size: u32) -> (Vec<Vec3>, Vec<u32>) {
+        let positions = (0..=size)
+            .flat_map(|y| {
+                (0..=size).map(move |x| {
+                    let (x, y) = (x as f32, y as f32);
+                    glm::vec3(x, y, (x * 0.7).sin() + (y * 0.3).cos())
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut triangles = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                let corner = y * (size + 1) + x;
+                triangles.push([corner, corner + 1, corner + size + 1]);
+                triangles.push([corner + 1, corner + size + 2, corner + size + 1]);
+            }
+        }
+        // A fixed stride coprime with the triangle count visits every triangle once
+        let count = triangles.len();
+        let indices = (0..count)
+            .flat_map(|i| triangles[i * 97 % count])
+            .collect::<Vec<_>>();
+        (positions, indices)
+    }
+
+    /// The triangles of `indices` as sorted corner lists, so that two index buffers drawing
+    /// the same triangles in any order compare equal
+    fn sorted_triangles(indices: &[u32], vertex: impl Fn(u32) -> [u32; 3]) -> Vec<[[u32; 3]; 3]> {
+        let mut triangles = indices
+            .chunks_exact(3)
+            .map(|corners| [vertex(corners[0]), vertex(corners[1]), vertex(corners[2])])
+            .collect::<Vec<_>>();
+        triangles.sort_unstable();
+        triangles
+    }
+
+    fn bits(position: Vec3) -> [u32; 3] {
+        [
+            position.x.to_bits(),
+            position.y.to_bits(),
+            position.z.to_bits(),
+        ]
+    }
+
+    #[test]
+    fn vertex_cache_permutes_triangles_and_reduces_misses() {
+        let (positions, input) = shuffled_grid(16);
+        let mut indices = input.clone();
+        optimize_vertex_cache(&mut indices, positions.len());
+
+        assert_eq!(
+            sorted_triangles(&indices, |v| bits(positions[v as usize])),
+            sorted_triangles(&input, |v| bits(positions[v as usize]))
+        );
+        let before = average_cache_miss_ratio(&input, positions.len(), 16);
+        let after = average_cache_miss_ratio(&indices, positions.len(), 16);
+        assert!(after < before, "miss ratio went from {before} to {after}");
+        assert!(
+            after < 1.0,
+            "miss ratio {after} is worse than expected for a grid"
+        );
+    }
+
+    #[test]
+    fn overdraw_permutes_triangles() {
+        let (positions, input) = shuffled_grid(16);
+        let mut indices = input.clone();
+        optimize_vertex_cache(&mut indices, positions.len());
+        let cache_optimized = indices.clone();
+        optimize_overdraw(&mut indices, &positions);
+
+        assert_eq!(
+            sorted_triangles(&indices, |v| bits(positions[v as usize])),
+            sorted_triangles(&cache_optimized, |v| bits(positions[v as usize]))
+        );
+    }
+
+    #[test]
+    fn vertex_fetch_orders_vertices_by_first_use() {
+        let (positions, input) = shuffled_grid(8);
+        let mut vertices = positions.clone();
+        // An unused vertex, which must be dropped
+        vertices.push(glm::vec3(100.0, 100.0, 100.0));
+        let mut indices = input.clone();
+        let reordered = optimize_vertex_fetch(&vertices, &mut indices);
+
+        assert_eq!(reordered.len(), positions.len());
+        assert_eq!(
+            sorted_triangles(&indices, |v| bits(reordered[v as usize])),
+            sorted_triangles(&input, |v| bits(vertices[v as usize]))
+        );
+        let mut next = 0;
+        for &index in &indices {
+            assert!(index <= next, "vertex {index} is used before vertex {next}");
+            if index == next {
+                next += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn optimize_mesh_preserves_triangles() {
+        let (positions, input) = shuffled_grid(12);
+        let (vertices, indices) = optimize_mesh(&positions, &input, |&position| position);
+
+        assert_eq!(
+            sorted_triangles(&indices, |v| bits(vertices[v as usize])),
+            sorted_triangles(&input, |v| bits(positions[v as usize]))
+        );
+    }
+
+    #[test]
+    fn cache_miss_ratio_bounds() {
+        assert_eq!(average_cache_miss_ratio(&[], 0, 16), 0.0);
+        assert_eq!(average_cache_miss_ratio(&[0, 1, 2], 3, 16), 3.0);
+        assert_eq!(average_cache_miss_ratio(&[0, 1, 2, 0, 1, 2], 3, 16), 1.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn partial_triangles_panic() {
+        optimize_vertex_cache(&mut [0, 1], 2);
+    }
+}
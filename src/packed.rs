@@ -0,0 +1,271 @@
+use crate::types::{AttributeFormat, AttributeType, Pod};
+use glm::{Vec3, Vec4};
+
+/// Quantizes `value` in `[-1, 1]` to a signed normalized integer with `max` as its largest
+/// value
+fn snorm(value: f32, max: f32) -> i32 {
+    (value.clamp(-1.0, 1.0) * max).round() as i32
+}
+
+/// A unit vector stored as two signed normalized 16-bit integers by projecting it onto an
+/// octahedron, a third of the size of three floats with an error well below a hundredth of a
+/// degree.
+///
+/// Shaders read the attribute as a `vec2` and decode it with the `decode_octahedral` function
+/// in [`OctahedralNormal::GLSL`].
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct OctahedralNormal(pub [i16; 2]);
+
+// `#[repr(transparent)]` over a `Pod` type
+unsafe impl Pod for OctahedralNormal {}
+
+impl OctahedralNormal {
+    /// GLSL source of `vec3 decode_octahedral(vec2 encoded)`, to be pasted after the
+    /// `#version` directive
+    pub const GLSL: &'static str = r#"
+        vec3 decode_octahedral(vec2 encoded) {
+            vec3 normal = vec3(encoded, 1.0 - abs(encoded.x) - abs(encoded.y));
+            float fold = max(-normal.z, 0.0);
+            normal.xy += mix(vec2(fold), vec2(-fold), greaterThanEqual(normal.xy, vec2(0.0)));
+            return normalize(normal);
+        }
+    "#;
+
+    /// Encodes `normal`, which doesn't need to be normalized but must not be zero
+    pub fn encode(normal: Vec3) -> Self {
+        let sign = |value: f32| if value >= 0.0 { 1.0 } else { -1.0 };
+        let normal = normal / (normal.x.abs() + normal.y.abs() + normal.z.abs());
+        let (x, y) = if normal.z >= 0.0 {
+            (normal.x, normal.y)
+        } else {
+            // The lower half is folded over the diagonals of the upper one
+            (
+                (1.0 - normal.y.abs()) * sign(normal.x),
+                (1.0 - normal.x.abs()) * sign(normal.y),
+            )
+        };
+        Self([x, y].map(|value| snorm(value, i16::MAX as f32) as i16))
+    }
+
+    /// Decodes the unit vector, as `decode_octahedral` does on the GPU
+    pub fn decode(self) -> Vec3 {
+        let [x, y] = self
+            .0
+            .map(|value| (value as f32 / i16::MAX as f32).max(-1.0));
+        let mut normal = glm::vec3(x, y, 1.0 - x.abs() - y.abs());
+        let fold = (-normal.z).max(0.0);
+        normal.x += if normal.x >= 0.0 { -fold } else { fold };
+        normal.y += if normal.y >= 0.0 { -fold } else { fold };
+        glm::normalize(normal)
+    }
+}
+
+impl AttributeType for OctahedralNormal {
+    const FORMAT: AttributeFormat = AttributeFormat::new(gl::SHORT, 2, true);
+}
+
+/// A tangent and the handedness of its bitangent packed into 10, 10, 10 and 2 signed
+/// normalized bits (`GL_INT_2_10_10_10_REV`), a quarter of the size of four floats.
+///
+/// Shaders read the attribute as a `vec4` whose `w` is `1.0` or `-1.0`, so the bitangent is
+/// `cross(normal, tangent.xyz) * tangent.w`.
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PackedTangent(pub u32);
+
+// `#[repr(transparent)]` over a `Pod` type
+unsafe impl Pod for PackedTangent {}
+
+impl PackedTangent {
+    /// Encodes `tangent`, whose components must lie in `[-1, 1]`, and the sign of
+    /// `handedness`
+    pub fn encode(tangent: Vec3, handedness: f32) -> Self {
+        let w = if handedness < 0.0 { -1.0 } else { 1.0 };
+        Self(pack_snorm_2_10_10_10(glm::vec4(
+            tangent.x, tangent.y, tangent.z, w,
+        )))
+    }
+
+    /// Decodes the tangent and handedness, as the GPU does
+    pub fn decode(self) -> Vec4 {
+        unpack_snorm_2_10_10_10(self.0)
+    }
+}
+
+impl AttributeType for PackedTangent {
+    const FORMAT: AttributeFormat = AttributeFormat::SNORM_2_10_10_10_REV;
+}
+
+/// Packs four values in `[-1, 1]` into the `GL_INT_2_10_10_10_REV` layout, with `x` in the
+/// lowest bits and only `-1`, `0` and `1` representable for `w`
+pub fn pack_snorm_2_10_10_10(value: Vec4) -> u32 {
+    let field = |value: f32, bits: u32, shift: u32| {
+        let max = ((1 << (bits - 1)) - 1) as f32;
+        ((snorm(value, max) as u32) & ((1 << bits) - 1)) << shift
+    };
+    field(value.x, 10, 0) | field(value.y, 10, 10) | field(value.z, 10, 20) | field(value.w, 2, 30)
+}
+
+/// Unpacks four values from the `GL_INT_2_10_10_10_REV` layout, the inverse of
+/// [`pack_snorm_2_10_10_10`]
+pub fn unpack_snorm_2_10_10_10(packed: u32) -> Vec4 {
+    let field = |bits: u32, shift: u32| {
+        // Shifting the field to the top and back sign-extends it
+        let value = ((packed << (32 - bits - shift)) as i32) >> (32 - bits);
+        (value as f32 / ((1 << (bits - 1)) - 1) as f32).max(-1.0)
+    };
+    glm::vec4(field(10, 0), field(10, 10), field(10, 20), field(2, 30))
+}
+
+/// An RGBA color stored as four normalized bytes, a quarter of the size of four floats.
+///
+/// Components are quantized linearly, so colors should be stored in the space the shader
+/// works in.
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PackedColor(pub [u8; 4]);
+
+// `#[repr(transparent)]` over a `Pod` type
+unsafe impl Pod for PackedColor {}
+
+impl PackedColor {
+    /// Encodes `color`, clamping its components to `[0, 1]`
+    pub fn encode(color: Vec4) -> Self {
+        Self(
+            [color.x, color.y, color.z, color.w]
+                .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8),
+        )
+    }
+
+    /// Decodes the color, as the GPU does
+    pub fn decode(self) -> Vec4 {
+        let [r, g, b, a] = self.0.map(|value| value as f32 / 255.0);
+        glm::vec4(r, g, b, a)
+    }
+}
+
+impl AttributeType for PackedColor {
+    const FORMAT: AttributeFormat = AttributeFormat::new(gl::UNSIGNED_BYTE, 4, true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec4, b: Vec4, epsilon: f32) {
+        let difference = a - b;
+        assert!(
+            [difference.x, difference.y, difference.z, difference.w]
+                .iter()
+                .all(|d| d.abs() <= epsilon),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn octahedral_normals_round_trip() {
+        let normals = [
+            glm::vec3(0.0, 0.0, 1.0),
+            glm::vec3(0.0, 0.0, -1.0),
+            glm::vec3(1.0, 0.0, 0.0),
+            glm::vec3(0.0, -1.0, 0.0),
+            glm::vec3(1.0, 2.0, 3.0),
+            glm::vec3(-1.0, 2.0, -3.0),
+            glm::vec3(-0.3, -0.5, -0.8),
+            glm::vec3(0.7, -0.1, -0.2),
+        ];
+        for normal in normals {
+            let normal = glm::normalize(normal);
+            let decoded = OctahedralNormal::encode(normal).decode();
+            let angle = glm::length(glm::cross(normal, decoded)).atan2(glm::dot(normal, decoded));
+            // Within a hundredth of a degree
+            assert!(
+                angle.to_degrees() < 0.01,
+                "{normal:?} decoded to {decoded:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn octahedral_normals_need_not_be_normalized() {
+        assert_eq!(
+            OctahedralNormal::encode(glm::vec3(0.0, 5.0, -5.0)),
+            OctahedralNormal::encode(glm::normalize(glm::vec3(0.0, 1.0, -1.0)))
+        );
+        assert_eq!(
+            OctahedralNormal::encode(glm::vec3(0.0, 0.0, -1.0)),
+            OctahedralNormal([i16::MAX, i16::MAX])
+        );
+    }
+
+    #[test]
+    fn octahedral_decoding_clamps_the_most_negative_value() {
+        assert_eq!(
+            OctahedralNormal([i16::MIN, 0]).decode(),
+            OctahedralNormal([-i16::MAX, 0]).decode()
+        );
+    }
+
+    #[test]
+    fn snorm_2_10_10_10_layout() {
+        assert_eq!(
+            pack_snorm_2_10_10_10(glm::vec4(1.0, -1.0, 0.0, -1.0)),
+            511 | 0x201 << 10 | 0b11 << 30
+        );
+        assert_eq!(pack_snorm_2_10_10_10(glm::vec4(0.0, 0.0, 0.0, 0.0)), 0);
+        assert_close(
+            unpack_snorm_2_10_10_10(511 | 0x201 << 10 | 0b01 << 30),
+            glm::vec4(1.0, -1.0, 0.0, 1.0),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn snorm_2_10_10_10_round_trips_and_saturates() {
+        let value = glm::vec4(0.25, -0.5, 0.75, 1.0);
+        assert_close(
+            unpack_snorm_2_10_10_10(pack_snorm_2_10_10_10(value)),
+            value,
+            0.5 / 511.0,
+        );
+
+        assert_eq!(
+            pack_snorm_2_10_10_10(glm::vec4(2.0, -3.0, 0.5, 4.0)),
+            pack_snorm_2_10_10_10(glm::vec4(1.0, -1.0, 0.5, 1.0))
+        );
+        // The most negative field values decode to -1 like the next one up
+        assert_close(
+            unpack_snorm_2_10_10_10(0x200 | 0b10 << 30),
+            glm::vec4(-1.0, 0.0, 0.0, -1.0),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn tangents_keep_the_sign_of_their_handedness() {
+        assert_close(
+            PackedTangent::encode(glm::vec3(1.0, 0.0, -1.0), -0.5).decode(),
+            glm::vec4(1.0, 0.0, -1.0, -1.0),
+            0.0,
+        );
+        assert_eq!(
+            PackedTangent::encode(glm::vec3(0.0, 1.0, 0.0), 0.0)
+                .decode()
+                .w,
+            1.0
+        );
+    }
+
+    #[test]
+    fn colors_saturate_and_round_trip() {
+        assert_eq!(
+            PackedColor::encode(glm::vec4(2.0, -1.0, 0.5, 1.0)),
+            PackedColor([255, 0, 128, 255])
+        );
+        for value in 0..=u8::MAX {
+            let color = PackedColor([value, 0, u8::MAX - value, value]);
+            assert_eq!(PackedColor::encode(color.decode()), color);
+        }
+    }
+}
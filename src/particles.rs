@@ -0,0 +1,403 @@
+use crate::{
+    bind::{BindGuard, BindTarget},
+    buffer::{BufferTarget, ShaderStorageBuffer},
+    compute::{ComputeProgram, ComputeProgramError},
+    context::Unsupported,
+    diagnostics::diagnostic,
+    draw_parameters,
+    shader::{LinkedProgram, Program, Shader, ShaderType},
+    surface::{Surface, ViewportGuard},
+    uniforms,
+};
+use glm::{Mat4, Vec3, Vec4};
+
+/// The shader storage binding points used while simulating
+const INPUT_BINDING: u32 = 0;
+const OUTPUT_BINDING: u32 = 1;
+const COMMAND_BINDING: u32 = 2;
+
+/// A particle as laid out in the particle storage blocks (`std430`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+struct GpuParticle {
+    position_age: [f32; 4],
+    velocity_lifetime: [f32; 4],
+}
+
+/// The arguments of `glDrawArraysIndirect`, whose instance count doubles as the number of
+/// live particles in a buffer
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct DrawCommand {
+    count: u32,
+    instance_count: u32,
+    first: u32,
+    base_instance: u32,
+}
+
+impl DrawCommand {
+    /// A billboard quad drawn once per live particle
+    const EMPTY: Self = Self {
+        count: 4,
+        instance_count: 0,
+        first: 0,
+        base_instance: 0,
+    };
+}
+
+/// Where and how a [`GpuParticleSystem`] spawns its particles
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ParticleEmitter {
+    /// The center of the box particles spawn in
+    pub position: Vec3,
+    /// Half the size of the box particles spawn in
+    pub spread: f32,
+    /// The average initial velocity in units per second
+    pub velocity: Vec3,
+    /// The largest random offset added to each component of the initial velocity
+    pub velocity_spread: f32,
+    /// The acceleration applied to every particle
+    pub gravity: Vec3,
+    /// How long particles live, in seconds
+    pub lifetime: f32,
+    /// The number of particles spawned per second
+    pub rate: f32,
+    /// The size of a particle in world units when spawned and when it dies
+    pub start_size: f32,
+    pub end_size: f32,
+    /// The color of a particle when spawned and when it dies, faded in between
+    pub start_color: Vec4,
+    pub end_color: Vec4,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            position: glm::vec3(0.0, 0.0, 0.0),
+            spread: 0.1,
+            velocity: glm::vec3(0.0, 1.0, 0.0),
+            velocity_spread: 0.5,
+            gravity: glm::vec3(0.0, -9.81, 0.0),
+            lifetime: 2.0,
+            rate: 100.0,
+            start_size: 0.1,
+            end_size: 0.0,
+            start_color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            end_color: glm::vec4(1.0, 1.0, 1.0, 0.0),
+        }
+    }
+}
+
+/// A particle system that lives entirely on the GPU, for particle counts the CPU can't keep
+/// up with.
+///
+/// Particles are kept in two storage buffers. Every [`GpuParticleSystem::update`] runs a
+/// compute pass that ages the live particles of one buffer, moves the survivors and newly
+/// spawned particles into the other, and counts them in the instance count of an indirect
+/// draw command. [`GpuParticleSystem::draw`] then draws a camera-facing quad per particle
+/// with that command, so the particle count never travels back to the CPU.
+pub struct GpuParticleSystem {
+    emitter: ParticleEmitter,
+    capacity: u32,
+    particles: [ShaderStorageBuffer<GpuParticle>; 2],
+    commands: ShaderStorageBuffer<DrawCommand>,
+    /// The buffer holding the live particles
+    current: usize,
+    simulation: ComputeProgram,
+    rendering: LinkedProgram,
+    vao: u32,
+    /// Particles still to be spawned, carried over between updates
+    pending: f32,
+    seed: u32,
+}
+
+impl GpuParticleSystem {
+    const PARTICLE_GLSL: &'static str = r#"
+        struct Particle {
+            vec4 position_age;
+            vec4 velocity_lifetime;
+        };
+    "#;
+
+    const SIMULATION_SHADER: &'static str = r#"
+        layout(local_size_x = 64) in;
+
+        layout(std430, binding = 0) readonly buffer Input {
+            Particle input_particles[];
+        };
+
+        layout(std430, binding = 1) writeonly buffer Output {
+            Particle output_particles[];
+        };
+
+        layout(std430, binding = 2) buffer Commands {
+            uvec4 commands[];
+        };
+
+        uniform uint u_input;
+        uniform uint u_output;
+        uniform uint u_capacity;
+        uniform uint u_spawn;
+        uniform uint u_seed;
+        uniform float u_delta;
+        uniform vec3 u_position;
+        uniform float u_spread;
+        uniform vec3 u_velocity;
+        uniform float u_velocity_spread;
+        uniform vec3 u_gravity;
+        uniform float u_lifetime;
+
+        float random(inout uint state) {
+            state = state * 747796405u + 2891336453u;
+            uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+            return float((word >> 22u) ^ word) / 4294967295.0;
+        }
+
+        vec3 random_offset(inout uint state) {
+            return vec3(random(state), random(state), random(state)) * 2.0 - 1.0;
+        }
+
+        void emit(Particle particle) {
+            uint index = atomicAdd(commands[u_output].y, 1u);
+            if (index < u_capacity) {
+                output_particles[index] = particle;
+            } else {
+                // Undo the allocation so the count never exceeds the capacity
+                atomicAdd(commands[u_output].y, 0xFFFFFFFFu);
+            }
+        }
+
+        void main() {
+            uint id = gl_GlobalInvocationID.x;
+            uint alive = commands[u_input].y;
+            if (id < alive) {
+                Particle particle = input_particles[id];
+                particle.position_age.w += u_delta;
+                if (particle.position_age.w < particle.velocity_lifetime.w) {
+                    particle.velocity_lifetime.xyz += u_gravity * u_delta;
+                    particle.position_age.xyz += particle.velocity_lifetime.xyz * u_delta;
+                    emit(particle);
+                }
+            } else if (id - alive < u_spawn) {
+                uint state = id * 1973u + u_seed * 9277u + 1u;
+                Particle particle;
+                particle.position_age = vec4(u_position + random_offset(state) * u_spread, 0.0);
+                particle.velocity_lifetime = vec4(
+                    u_velocity + random_offset(state) * u_velocity_spread,
+                    u_lifetime
+                );
+                emit(particle);
+            }
+        }
+    "#;
+
+    const VERTEX_SHADER: &'static str = r#"
+        layout(std430, binding = 0) readonly buffer Particles {
+            Particle particles[];
+        };
+
+        uniform mat4 u_view;
+        uniform mat4 u_projection;
+        uniform float u_start_size;
+        uniform float u_end_size;
+        uniform vec4 u_start_color;
+        uniform vec4 u_end_color;
+
+        out vec4 particle_color;
+        out vec2 uv;
+
+        void main() {
+            Particle particle = particles[gl_InstanceID];
+            float t = clamp(particle.position_age.w / particle.velocity_lifetime.w, 0.0, 1.0);
+
+            uv = vec2(gl_VertexID & 1, gl_VertexID >> 1);
+            vec4 center = u_view * vec4(particle.position_age.xyz, 1.0);
+            center.xy += (uv - 0.5) * mix(u_start_size, u_end_size, t);
+            gl_Position = u_projection * center;
+            particle_color = mix(u_start_color, u_end_color, t);
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &'static str = r#"
+        #version 460 core
+
+        in vec4 particle_color;
+        in vec2 uv;
+
+        out vec4 color;
+
+        void main() {
+            float distance = length(uv * 2.0 - 1.0);
+            if (distance > 1.0) {
+                discard;
+            }
+            color = particle_color * vec4(1.0, 1.0, 1.0, 1.0 - distance * distance);
+        }
+    "#;
+
+    /// Creates a system of at most `capacity` particles spawned by `emitter`
+    pub fn new(capacity: u32, emitter: ParticleEmitter) -> Result<Self, Unsupported> {
+        let simulation = match ComputeProgram::new(format!(
+            "#version 460 core\n{}{}",
+            Self::PARTICLE_GLSL,
+            Self::SIMULATION_SHADER
+        )) {
+            Ok(program) => program,
+            Err(ComputeProgramError::Unsupported(error)) => return Err(error),
+            Err(ComputeProgramError::Compilation(error)) => {
+                panic!("Built-in particle simulation shader failed to compile: {error}")
+            }
+        };
+        #[allow(clippy::expect_used)]
+        let rendering = Program::new()
+            .attach_and_link(vec![
+                Shader::new(
+                    format!(
+                        "#version 460 core\n{}{}",
+                        Self::PARTICLE_GLSL,
+                        Self::VERTEX_SHADER
+                    ),
+                    ShaderType::Vertex,
+                ),
+                Shader::new(Self::FRAGMENT_SHADER.to_owned(), ShaderType::Fragment),
+            ])
+            .expect("Built-in particle shader failed to compile");
+
+        // Quads are generated from gl_VertexID, but drawing still needs a vertex array
+        let mut vao = 0;
+        unsafe { gl::GenVertexArrays(1, &mut vao) };
+
+        Ok(Self {
+            emitter,
+            capacity,
+            particles: [
+                ShaderStorageBuffer::empty(capacity.max(1) as usize),
+                ShaderStorageBuffer::empty(capacity.max(1) as usize),
+            ],
+            commands: ShaderStorageBuffer::new(&[DrawCommand::EMPTY; 2]),
+            current: 0,
+            simulation,
+            rendering,
+            vao,
+            pending: 0.0,
+            seed: 0,
+        })
+    }
+
+    pub fn emitter(&self) -> &ParticleEmitter {
+        &self.emitter
+    }
+
+    pub fn emitter_mut(&mut self) -> &mut ParticleEmitter {
+        &mut self.emitter
+    }
+
+    /// Returns the largest number of particles alive at once
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Spawns `count` particles at once during the next update, on top of the emitter's rate
+    pub fn burst(&mut self, count: u32) {
+        self.pending += count as f32;
+    }
+
+    /// Removes every particle
+    pub fn clear(&mut self) {
+        self.commands.set_data(&[DrawCommand::EMPTY; 2]);
+        self.pending = 0.0;
+    }
+
+    /// Advances the simulation by `delta` seconds and spawns new particles
+    pub fn update(&mut self, delta: f32) {
+        self.pending += self.emitter.rate * delta;
+        let spawn = self.pending.floor();
+        self.pending -= spawn;
+        self.seed = self.seed.wrapping_add(1);
+
+        let (input, output) = (self.current, 1 - self.current);
+        self.commands.set_sub_data(output, &[DrawCommand::EMPTY]);
+        self.particles[input].bind_to(INPUT_BINDING);
+        self.particles[output].bind_to(OUTPUT_BINDING);
+        self.commands.bind_to(COMMAND_BINDING);
+
+        let program = self.simulation.program();
+        let emitter = &self.emitter;
+        let uniforms = uniforms!(program => {
+            "u_input": input as u32,
+            "u_output": output as u32,
+            "u_capacity": self.capacity,
+            "u_spawn": spawn as u32,
+            "u_seed": self.seed,
+            "u_delta": delta,
+            "u_position": emitter.position,
+            "u_spread": emitter.spread,
+            "u_velocity": emitter.velocity,
+            "u_velocity_spread": emitter.velocity_spread,
+            "u_gravity": emitter.gravity,
+            "u_lifetime": emitter.lifetime
+        });
+
+        if let Err(error) = self
+            .simulation
+            .dispatch_invocations((self.capacity, 1, 1), &uniforms)
+        {
+            diagnostic!(Error, Draw, "Failed to simulate particles: {error}");
+        }
+        unsafe { gl::MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT | gl::COMMAND_BARRIER_BIT) };
+        self.current = output;
+    }
+
+    /// Draws the live particles into `target` as seen through `view` and `projection`, alpha
+    /// blended and without writing depth
+    pub fn draw<S: Surface>(&self, target: &S, view: Mat4, projection: Mat4) {
+        let program = &self.rendering;
+        let emitter = &self.emitter;
+        let uniforms = uniforms!(program => {
+            "u_view": view,
+            "u_projection": projection,
+            "u_start_size": emitter.start_size,
+            "u_end_size": emitter.end_size,
+            "u_start_color": emitter.start_color,
+            "u_end_color": emitter.end_color
+        });
+
+        let _target_binding = target.bind_target();
+        let _viewport = ViewportGuard::new(target.dimensions());
+        let _binding = BindGuard::with_targets([
+            Some((BindTarget::VertexArray, self.vao)),
+            Some((
+                BindTarget::Buffer(BufferTarget::DrawIndirect),
+                self.commands.buffer().id(),
+            )),
+        ]);
+        let _program_binding = program.bind_scoped();
+        if let Err(error) = uniforms.upload_all() {
+            diagnostic!(Error, Draw, "Failed to draw particles: {error}");
+            return;
+        }
+        self.particles[self.current].bind_to(INPUT_BINDING);
+
+        let offset = self.current * std::mem::size_of::<DrawCommand>();
+        unsafe {
+            let blend = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+
+            gl::DrawArraysIndirect(gl::TRIANGLE_STRIP, offset as *const _);
+
+            gl::DepthMask(gl::TRUE);
+            if !blend {
+                gl::Disable(gl::BLEND);
+            }
+        };
+        draw_parameters::invalidate_state_cache();
+    }
+}
+
+impl Drop for GpuParticleSystem {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteVertexArrays(1, &self.vao) };
+    }
+}
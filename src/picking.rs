@@ -0,0 +1,118 @@
+use crate::{
+    buffer::VertexBufferSlice,
+    framebuffer::{Framebuffer, FramebufferError},
+    renderer::{DrawError, DrawMode, Rect, Renderer},
+    shader::{LinkedProgram, Vertex},
+    surface::Surface,
+    texture::{PixelFormat, Texture2D, TextureFormat},
+    types::IndexType,
+    uniforms::Uniforms,
+};
+
+/// Renders object IDs into an off-screen integer target so the object under a pixel can be
+/// looked up with [`Picker::pick`].
+///
+/// Programs drawn through [`Picker::draw`] receive the ID as `uniform uint u_object_id` and
+/// are expected to write it to their first output, as [`Picker::FRAGMENT_SHADER`] does.
+/// The ID `0` is reserved for pixels not covered by any object.
+#[derive(Debug)]
+pub struct Picker {
+    framebuffer: Framebuffer,
+}
+
+impl Picker {
+    /// A fragment shader writing `u_object_id` to the ID target
+    pub const FRAGMENT_SHADER: &'static str = r#"
+        #version 460 core
+
+        uniform uint u_object_id;
+
+        out uint object_id;
+
+        void main() {
+            object_id = u_object_id;
+        }
+    "#;
+
+    /// Creates a picker whose ID target has the given size, usually that of the window
+    pub fn new(width: u32, height: u32) -> Result<Self, FramebufferError> {
+        Ok(Self {
+            framebuffer: Self::create_framebuffer(width, height)?,
+        })
+    }
+
+    fn create_framebuffer(width: u32, height: u32) -> Result<Framebuffer, FramebufferError> {
+        Framebuffer::new(
+            vec![Texture2D::new(width, height, TextureFormat::R32UI)],
+            Some(Texture2D::new(width, height, TextureFormat::Depth24)),
+        )
+    }
+
+    /// Recreates the ID target with a new size, discarding its contents
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferError> {
+        self.framebuffer = Self::create_framebuffer(width, height)?;
+        Ok(())
+    }
+
+    /// Returns the width and height of the ID target in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.framebuffer.dimensions()
+    }
+
+    /// Returns the framebuffer IDs are rendered into
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// Resets every pixel to the reserved ID `0` and the depth to `1.0`
+    pub fn clear(&mut self) {
+        let _binding = self.framebuffer.bind_target();
+        let id = [0u32; 4];
+        let depth = 1.0f32;
+        unsafe {
+            gl::ClearBufferuiv(gl::COLOR, 0, id.as_ptr());
+            gl::ClearBufferfv(gl::DEPTH, 0, &depth);
+        };
+    }
+
+    /// Draws a buffer into the ID target, tagging every covered pixel with `id`.
+    ///
+    /// `id` is uploaded to the `u_object_id` uniform of `shader_program` before drawing. See
+    /// [`Renderer::draw`].
+    pub fn draw<'a, V: Vertex + 'a, I: IndexType + 'a>(
+        &mut self,
+        buffer: impl Into<VertexBufferSlice<'a, V, I>>,
+        shader_program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+        id: u32,
+    ) -> Result<(), DrawError> {
+        debug_assert_ne!(id, 0, "the object ID 0 is reserved for empty pixels");
+
+        shader_program.set_uniform("u_object_id", id);
+        self.framebuffer
+            .draw(buffer, shader_program, mode, uniforms)
+    }
+
+    /// Returns the ID of the object covering the pixel at `(x, y)`, or `None` if no object
+    /// covers it or it lies outside the target.
+    ///
+    /// Coordinates are in window space, with the origin at the top-left corner as reported
+    /// by the windowing system.
+    pub fn pick(&self, renderer: &Renderer, x: u32, y: u32) -> Option<u32> {
+        let (width, height) = self.dimensions();
+        if x >= width || y >= height {
+            return None;
+        }
+
+        let data = renderer.read_framebuffer_pixels(
+            &self.framebuffer,
+            0,
+            Rect::new(x as i32, (height - 1 - y) as i32, 1, 1),
+            PixelFormat::RedU32,
+        );
+        let id = u32::from_ne_bytes(data[..4].try_into().ok()?);
+
+        (id != 0).then_some(id)
+    }
+}
@@ -0,0 +1,727 @@
+use crate::{
+    diagnostics::diagnostic,
+    frame_graph::FrameDescription,
+    framebuffer::{Framebuffer, MipChain, RenderTargetPool, TargetDescriptor},
+    mesh::FullscreenQuad,
+    renderer::{DrawError, Renderer},
+    shader::{LinkedProgram, ShaderCompilationError},
+    surface::{Surface, ViewportGuard},
+    texture::{Texture2D, Texture3D, TextureFormat},
+    uniforms::{Uniform, Uniforms},
+};
+
+/// A single fragment-shader pass of an [`EffectChain`]
+pub struct Effect {
+    program: LinkedProgram,
+    uniforms: Uniforms,
+    enabled: bool,
+}
+
+impl Effect {
+    /// Returns the program of this pass, e.g. to resolve uniform locations
+    pub fn program(&self) -> &LinkedProgram {
+        &self.program
+    }
+
+    /// Sets additional uniforms uploaded whenever this pass runs
+    pub fn set_uniforms(&mut self, uniforms: Uniforms) {
+        self.uniforms = uniforms;
+    }
+
+    /// Enables or disables the pass. Disabled passes are skipped by [`EffectChain::apply`].
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns whether the pass is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// A sequence of full-screen fragment-shader passes applied to a texture.
+///
+/// Passes render into targets acquired from a [`RenderTargetPool`], which are released as soon
+/// as the next pass has read them. Only the output of the last pass is kept until the next
+/// [`EffectChain::apply`]. Chains sharing a pool through [`EffectChain::set_pool`] reuse each
+/// other's intermediate targets.
+///
+/// Every pass's fragment shader receives:
+///
+/// - `in vec2 uv` - the texture coordinate of the fragment
+/// - `uniform sampler2D u_input` - the output of the previous pass (or the chain's input)
+/// - `uniform vec2 u_texel_size` - the size of a single texel of the chain's targets
+pub struct EffectChain {
+    pool: RenderTargetPool,
+    output: Option<Framebuffer>,
+    passes: Vec<Effect>,
+    quad: FullscreenQuad,
+    descriptor: TargetDescriptor,
+}
+
+impl EffectChain {
+    /// A fast approximate anti-aliasing (FXAA) pass for images rendered without multisampling.
+    ///
+    /// Edges are detected from the luma of the input, which should be in display (gamma)
+    /// space, so the pass belongs after tone mapping. Added with [`EffectChain::add_fxaa`].
+    pub const FXAA_SHADER: &'static str = r#"
+        #version 460 core
+
+        in vec2 uv;
+
+        uniform sampler2D u_input;
+        uniform vec2 u_texel_size;
+
+        out vec4 color;
+
+        const float EDGE_THRESHOLD = 1.0 / 8.0;
+        const float EDGE_THRESHOLD_MIN = 1.0 / 24.0;
+        const float SUBPIXEL_QUALITY = 0.75;
+        const int SEARCH_STEPS = 12;
+
+        float luma(vec3 rgb) {
+            return dot(rgb, vec3(0.299, 0.587, 0.114));
+        }
+
+        float luma_at(vec2 position) {
+            return luma(textureLod(u_input, position, 0.0).rgb);
+        }
+
+        void main() {
+            vec4 center = textureLod(u_input, uv, 0.0);
+            float luma_center = luma(center.rgb);
+            float luma_n = luma_at(uv + vec2(0.0, u_texel_size.y));
+            float luma_s = luma_at(uv - vec2(0.0, u_texel_size.y));
+            float luma_e = luma_at(uv + vec2(u_texel_size.x, 0.0));
+            float luma_w = luma_at(uv - vec2(u_texel_size.x, 0.0));
+
+            float luma_min = min(luma_center, min(min(luma_n, luma_s), min(luma_e, luma_w)));
+            float luma_max = max(luma_center, max(max(luma_n, luma_s), max(luma_e, luma_w)));
+            float range = luma_max - luma_min;
+            if (range < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD)) {
+                color = center;
+                return;
+            }
+
+            float luma_ne = luma_at(uv + u_texel_size);
+            float luma_sw = luma_at(uv - u_texel_size);
+            float luma_nw = luma_at(uv + vec2(-u_texel_size.x, u_texel_size.y));
+            float luma_se = luma_at(uv + vec2(u_texel_size.x, -u_texel_size.y));
+
+            float luma_ns = luma_n + luma_s;
+            float luma_ew = luma_e + luma_w;
+            float luma_corners = luma_ne + luma_sw + luma_nw + luma_se;
+
+            float edge_horizontal = abs(-2.0 * luma_w + luma_nw + luma_sw)
+                + 2.0 * abs(-2.0 * luma_center + luma_ns)
+                + abs(-2.0 * luma_e + luma_ne + luma_se);
+            float edge_vertical = abs(-2.0 * luma_n + luma_nw + luma_ne)
+                + 2.0 * abs(-2.0 * luma_center + luma_ew)
+                + abs(-2.0 * luma_s + luma_sw + luma_se);
+            bool horizontal = edge_horizontal >= edge_vertical;
+
+            float luma_positive = horizontal ? luma_n : luma_e;
+            float luma_negative = horizontal ? luma_s : luma_w;
+            float gradient_positive = abs(luma_positive - luma_center);
+            float gradient_negative = abs(luma_negative - luma_center);
+
+            float step_length = horizontal ? u_texel_size.y : u_texel_size.x;
+            float luma_local;
+            float gradient;
+            if (gradient_negative >= gradient_positive) {
+                step_length = -step_length;
+                luma_local = 0.5 * (luma_negative + luma_center);
+                gradient = 0.25 * gradient_negative;
+            } else {
+                luma_local = 0.5 * (luma_positive + luma_center);
+                gradient = 0.25 * gradient_positive;
+            }
+
+            vec2 edge_uv = uv;
+            if (horizontal) {
+                edge_uv.y += 0.5 * step_length;
+            } else {
+                edge_uv.x += 0.5 * step_length;
+            }
+
+            vec2 offset = horizontal ? vec2(u_texel_size.x, 0.0) : vec2(0.0, u_texel_size.y);
+            vec2 uv_positive = edge_uv + offset;
+            vec2 uv_negative = edge_uv - offset;
+            float delta_positive = luma_at(uv_positive) - luma_local;
+            float delta_negative = luma_at(uv_negative) - luma_local;
+            bool done_positive = abs(delta_positive) >= gradient;
+            bool done_negative = abs(delta_negative) >= gradient;
+
+            for (int i = 0; i < SEARCH_STEPS && !(done_positive && done_negative); i++) {
+                if (!done_positive) {
+                    uv_positive += offset;
+                    delta_positive = luma_at(uv_positive) - luma_local;
+                    done_positive = abs(delta_positive) >= gradient;
+                }
+                if (!done_negative) {
+                    uv_negative -= offset;
+                    delta_negative = luma_at(uv_negative) - luma_local;
+                    done_negative = abs(delta_negative) >= gradient;
+                }
+            }
+
+            float distance_positive = horizontal ? uv_positive.x - uv.x : uv_positive.y - uv.y;
+            float distance_negative = horizontal ? uv.x - uv_negative.x : uv.y - uv_negative.y;
+            bool positive_closer = distance_positive < distance_negative;
+            float closest = min(distance_positive, distance_negative);
+            float edge_length = distance_positive + distance_negative;
+
+            bool center_smaller = luma_center < luma_local;
+            bool correct_variation = ((positive_closer ? delta_positive : delta_negative) < 0.0)
+                != center_smaller;
+            float edge_offset = correct_variation ? 0.5 - closest / edge_length : 0.0;
+
+            float luma_average = (2.0 * (luma_ns + luma_ew) + luma_corners) / 12.0;
+            float subpixel = clamp(abs(luma_average - luma_center) / range, 0.0, 1.0);
+            subpixel = (-2.0 * subpixel + 3.0) * subpixel * subpixel;
+            float subpixel_offset = subpixel * subpixel * SUBPIXEL_QUALITY;
+
+            vec2 final_uv = uv;
+            float final_offset = max(edge_offset, subpixel_offset) * step_length;
+            if (horizontal) {
+                final_uv.y += final_offset;
+            } else {
+                final_uv.x += final_offset;
+            }
+            color = textureLod(u_input, final_uv, 0.0);
+        }
+    "#;
+
+    /// A pass mapping every color through a 3D lookup table, added with
+    /// [`EffectChain::add_color_grading`].
+    ///
+    /// Besides the usual inputs it receives `uniform sampler3D u_lut`, `uniform float
+    /// u_lut_size` and the input range of the table as `uniform vec3 u_domain_min` and
+    /// `uniform vec3 u_domain_max`.
+    pub const COLOR_GRADING_SHADER: &'static str = r#"
+        #version 460 core
+
+        in vec2 uv;
+
+        uniform sampler2D u_input;
+        uniform sampler3D u_lut;
+        uniform float u_lut_size;
+        uniform vec3 u_domain_min;
+        uniform vec3 u_domain_max;
+
+        out vec4 color;
+
+        void main() {
+            vec4 input_color = texture(u_input, uv);
+            vec3 coord = clamp(
+                (input_color.rgb - u_domain_min) / (u_domain_max - u_domain_min),
+                0.0,
+                1.0
+            );
+            // Map [0, 1] onto the centers of the first and last texels
+            coord = coord * ((u_lut_size - 1.0) / u_lut_size) + 0.5 / u_lut_size;
+            color = vec4(texture(u_lut, coord).rgb, input_color.a);
+        }
+    "#;
+
+    /// Creates an empty effect chain whose intermediate targets have the given size and format
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Self {
+        Self {
+            pool: RenderTargetPool::new(),
+            output: None,
+            passes: vec![],
+            quad: FullscreenQuad::new(),
+            descriptor: TargetDescriptor::new(width, height, format),
+        }
+    }
+
+    /// Makes the chain acquire its targets from `pool`, e.g. a pool shared with other chains
+    pub fn set_pool(&mut self, pool: RenderTargetPool) {
+        if let Some(output) = self.output.take() {
+            self.pool.release(output);
+        }
+        self.pool = pool;
+    }
+
+    /// Returns the pool the chain acquires its targets from
+    pub fn pool(&self) -> &RenderTargetPool {
+        &self.pool
+    }
+
+    /// Compiles `fragment_source` into a new pass appended to the end of the chain.
+    ///
+    /// # Returns
+    ///
+    /// The index of the new pass.
+    pub fn add_pass(&mut self, fragment_source: &str) -> Result<usize, ShaderCompilationError> {
+        let program = FullscreenQuad::program(fragment_source)?;
+        self.passes.push(Effect {
+            program,
+            uniforms: Uniforms::default(),
+            enabled: true,
+        });
+        Ok(self.passes.len() - 1)
+    }
+
+    /// Appends a pass applying [`EffectChain::FXAA_SHADER`] to the end of the chain.
+    ///
+    /// # Returns
+    ///
+    /// The index of the new pass.
+    pub fn add_fxaa(&mut self) -> usize {
+        #[allow(clippy::expect_used)]
+        self.add_pass(Self::FXAA_SHADER)
+            .expect("Built-in FXAA shader failed to compile")
+    }
+
+    /// Appends a pass grading colors with `lut` to the end of the chain. See
+    /// [`EffectChain::COLOR_GRADING_SHADER`].
+    ///
+    /// The pass refers to the texture of `lut`, which must be kept alive as long as the pass
+    /// is used.
+    ///
+    /// # Returns
+    ///
+    /// The index of the new pass.
+    pub fn add_color_grading(&mut self, lut: &ColorLut) -> usize {
+        #[allow(clippy::expect_used)]
+        let index = self
+            .add_pass(Self::COLOR_GRADING_SHADER)
+            .expect("Built-in color grading shader failed to compile");
+
+        let program = &self.passes[index].program;
+        let uniforms = crate::uniforms!(program => {
+            "u_lut": lut.texture().binding(),
+            "u_lut_size": lut.size() as f32,
+            "u_domain_min": lut.domain_min(),
+            "u_domain_max": lut.domain_max()
+        });
+        self.passes[index].set_uniforms(uniforms);
+        index
+    }
+
+    /// Returns the pass at `index`
+    pub fn pass(&self, index: usize) -> Option<&Effect> {
+        self.passes.get(index)
+    }
+
+    /// Returns the pass at `index` mutably
+    pub fn pass_mut(&mut self, index: usize) -> Option<&mut Effect> {
+        self.passes.get_mut(index)
+    }
+
+    /// Returns the number of passes in the chain
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Returns whether the chain contains no passes
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Returns the size of the chain's intermediate targets
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.descriptor.width, self.descriptor.height)
+    }
+
+    /// Changes the size of the intermediate targets, e.g. after the window was resized.
+    ///
+    /// Targets of the old size, including the last output, go back to the pool and stay there
+    /// until [`RenderTargetPool::trim`] is called.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if self.dimensions() != (width, height) {
+            self.descriptor.width = width;
+            self.descriptor.height = height;
+            if let Some(output) = self.output.take() {
+                self.pool.release(output);
+            }
+        }
+    }
+
+    /// Describes the enabled passes into `frame`, reading the attachment at index `input`
+    /// first, as [`EffectChain::apply`] would run them.
+    ///
+    /// # Returns
+    ///
+    /// The index of the attachment holding the output of the last pass, or `input` if no pass
+    /// is enabled.
+    pub fn describe(&self, frame: &mut FrameDescription, input: usize) -> usize {
+        let mut last = input;
+        for (index, _) in self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|(_, pass)| pass.enabled)
+        {
+            let target = frame.add_attachment(&format!("effect {index} target"), self.descriptor);
+            frame.add_pass(&format!("effect {index}"), &[last], &[target]);
+            last = target;
+        }
+        last
+    }
+
+    /// Runs every enabled pass in order, starting with `input`.
+    ///
+    /// # Returns
+    ///
+    /// The texture holding the output of the last pass, or `input` if no pass ran.
+    pub fn apply<'a>(&'a mut self, renderer: &Renderer, input: &'a Texture2D) -> &'a Texture2D {
+        let (width, height) = self.dimensions();
+        let _viewport = ViewportGuard::new((width, height));
+
+        if let Some(previous) = self.output.take() {
+            self.pool.release(previous);
+        }
+
+        let mut last: Option<Framebuffer> = None;
+        for pass in self.passes.iter_mut().filter(|pass| pass.enabled) {
+            let framebuffer = match self.pool.acquire(self.descriptor) {
+                Ok(framebuffer) => framebuffer,
+                Err(err) => {
+                    diagnostic!(
+                        Error,
+                        Resource,
+                        "Failed to create post-processing target: {err:?}"
+                    );
+                    break;
+                }
+            };
+            #[allow(clippy::unwrap_used)]
+            let source = last
+                .as_ref()
+                .map_or(input, |last| last.color_attachment(0).unwrap());
+            let binding = framebuffer.bind_scoped();
+
+            pass.program.set_uniform(
+                "u_texel_size",
+                glm::vec2(1.0 / width as f32, 1.0 / height as f32),
+            );
+            let input_location = pass.program.get_uniform_location("u_input");
+            pass.uniforms
+                .data
+                .push((input_location, source.binding().into_value()));
+            if let Err(err) = self.quad.draw(renderer, &pass.program, &pass.uniforms) {
+                diagnostic!(Error, Draw, "Failed to draw post-processing pass: {err}");
+            }
+            pass.uniforms.data.pop();
+
+            drop(binding);
+            if let Some(previous) = last.replace(framebuffer) {
+                self.pool.release(previous);
+            }
+        }
+
+        self.output = last;
+        #[allow(clippy::unwrap_used)]
+        self.output
+            .as_ref()
+            .map_or(input, |output| output.color_attachment(0).unwrap())
+    }
+}
+
+/// An error that occurred while loading a [`ColorLut`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum LutError {
+    /// A line of a `.cube` file couldn't be parsed
+    Parse {
+        /// The line number, starting at `1`
+        line: usize,
+        /// The offending line
+        content: String,
+    },
+    /// A `.cube` file has no `LUT_3D_SIZE` or declares an unusable size
+    InvalidSize,
+    /// The number of table entries doesn't match the size of the table
+    WrongEntryCount { expected: usize, found: usize },
+    /// A strip image isn't `size * size` pixels wide and `size` pixels tall
+    InvalidStripDimensions { width: u32, height: u32 },
+}
+
+impl std::fmt::Display for LutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LutError::Parse { line, content } => {
+                write!(f, "Failed to parse line {line} of LUT: {content:?}")
+            }
+            LutError::InvalidSize => write!(f, "LUT doesn't declare a valid LUT_3D_SIZE"),
+            LutError::WrongEntryCount { expected, found } => {
+                write!(f, "Expected {expected} LUT entries, found {found}")
+            }
+            LutError::InvalidStripDimensions { width, height } => write!(
+                f,
+                "A {width}x{height} image isn't a LUT strip of size * size by size pixels"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LutError {}
+
+/// A 3D color lookup table for color grading, applied with
+/// [`EffectChain::add_color_grading`]
+#[derive(Debug)]
+pub struct ColorLut {
+    texture: Texture3D,
+    size: u32,
+    domain_min: glm::Vec3,
+    domain_max: glm::Vec3,
+}
+
+impl ColorLut {
+    /// Creates a lookup table of `size` entries per axis from RGB entries with red varying
+    /// fastest and blue slowest, covering inputs from `0` to `1`.
+    ///
+    /// # Panics
+    /// This function panics if `entries` doesn't hold exactly `size³` entries.
+    pub fn new(size: u32, entries: &[glm::Vec3]) -> Self {
+        Self::with_domain(
+            size,
+            entries,
+            glm::vec3(0.0, 0.0, 0.0),
+            glm::vec3(1.0, 1.0, 1.0),
+        )
+    }
+
+    fn with_domain(
+        size: u32,
+        entries: &[glm::Vec3],
+        domain_min: glm::Vec3,
+        domain_max: glm::Vec3,
+    ) -> Self {
+        assert_eq!(
+            entries.len(),
+            (size as usize).pow(3),
+            "A LUT of size {size} needs {} entries",
+            (size as usize).pow(3)
+        );
+
+        let data = entries
+            .iter()
+            .flat_map(|entry| [entry.x, entry.y, entry.z, 1.0])
+            .flat_map(f32::to_ne_bytes)
+            .collect::<Vec<_>>();
+        Self {
+            texture: Texture3D::with_data(size, size, size, TextureFormat::Rgba16F, &data),
+            size,
+            domain_min,
+            domain_max,
+        }
+    }
+
+    /// Parses a lookup table in the Adobe/Resolve `.cube` format
+    pub fn from_cube(source: &str) -> Result<Self, LutError> {
+        let mut size = None;
+        let mut domain_min = glm::vec3(0.0, 0.0, 0.0);
+        let mut domain_max = glm::vec3(1.0, 1.0, 1.0);
+        let mut entries = vec![];
+
+        for (index, line) in source.lines().enumerate() {
+            let error = || LutError::Parse {
+                line: index + 1,
+                content: line.to_owned(),
+            };
+            let parse_vec3 = |values: &[&str]| -> Result<glm::Vec3, LutError> {
+                match values {
+                    [r, g, b] => Ok(glm::vec3(
+                        r.parse().map_err(|_| error())?,
+                        g.parse().map_err(|_| error())?,
+                        b.parse().map_err(|_| error())?,
+                    )),
+                    _ => Err(error()),
+                }
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words[0] {
+                "TITLE" => {}
+                "LUT_1D_SIZE" => return Err(LutError::InvalidSize),
+                "LUT_3D_SIZE" => {
+                    let parsed = words.get(1).and_then(|word| word.parse::<u32>().ok());
+                    size = Some(
+                        parsed
+                            .filter(|size| *size >= 2)
+                            .ok_or(LutError::InvalidSize)?,
+                    );
+                }
+                "DOMAIN_MIN" => domain_min = parse_vec3(&words[1..])?,
+                "DOMAIN_MAX" => domain_max = parse_vec3(&words[1..])?,
+                "LUT_3D_INPUT_RANGE" => {
+                    let parse = |word: Option<&&str>| {
+                        word.and_then(|word| word.parse::<f32>().ok())
+                            .ok_or_else(error)
+                    };
+                    let (min, max) = (parse(words.get(1))?, parse(words.get(2))?);
+                    domain_min = glm::vec3(min, min, min);
+                    domain_max = glm::vec3(max, max, max);
+                }
+                _ => entries.push(parse_vec3(&words)?),
+            }
+        }
+
+        let size = size.ok_or(LutError::InvalidSize)?;
+        let expected = (size as usize).pow(3);
+        if entries.len() != expected {
+            return Err(LutError::WrongEntryCount {
+                expected,
+                found: entries.len(),
+            });
+        }
+
+        Ok(Self::with_domain(size, &entries, domain_min, domain_max))
+    }
+
+    /// Creates a lookup table from an RGBA8 strip image, e.g. a decoded PNG, that is
+    /// `size * size` pixels wide and `size` pixels tall.
+    ///
+    /// The strip holds `size` square slices side by side, one per blue value. Within a slice,
+    /// red increases to the right and green increases with the row, starting at the first row
+    /// of `pixels`.
+    ///
+    /// # Panics
+    /// This function panics if `pixels` is too small for an image of the given size.
+    pub fn from_strip(width: u32, height: u32, pixels: &[u8]) -> Result<Self, LutError> {
+        let size = height;
+        if size < 2 || width != size * size {
+            return Err(LutError::InvalidStripDimensions { width, height });
+        }
+        let expected = width as usize * height as usize * 4;
+        assert!(
+            pixels.len() >= expected,
+            "Expected at least {expected} bytes of strip data, got {}",
+            pixels.len()
+        );
+
+        let size = size as usize;
+        let mut entries = Vec::with_capacity(size.pow(3));
+        for blue in 0..size {
+            for green in 0..size {
+                for red in 0..size {
+                    let offset = (green * size * size + blue * size + red) * 4;
+                    let pixel = &pixels[offset..offset + 3];
+                    entries.push(glm::vec3(
+                        pixel[0] as f32 / 255.0,
+                        pixel[1] as f32 / 255.0,
+                        pixel[2] as f32 / 255.0,
+                    ));
+                }
+            }
+        }
+
+        Ok(Self::new(height, &entries))
+    }
+
+    /// Returns the number of entries along each axis
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the input color mapped to the first entry of the table
+    pub fn domain_min(&self) -> glm::Vec3 {
+        self.domain_min
+    }
+
+    /// Returns the input color mapped to the last entry of the table
+    pub fn domain_max(&self) -> glm::Vec3 {
+        self.domain_max
+    }
+
+    /// Returns the 3D texture holding the table
+    pub fn texture(&self) -> &Texture3D {
+        &self.texture
+    }
+}
+
+/// A full-screen fragment-shader pass run once per mip level of a [`MipChain`], each time
+/// reading from a neighbouring level.
+///
+/// Every run of the pass's fragment shader receives:
+///
+/// - `in vec2 uv` - the texture coordinate of the fragment
+/// - `uniform sampler2D u_input` - the chain's texture, restricted to the level being read
+/// - `uniform vec2 u_texel_size` - the size of a single texel of the level being read
+/// - `uniform int u_source_level` - the index of the level being read
+pub struct MipPass {
+    program: LinkedProgram,
+    uniforms: Uniforms,
+    quad: FullscreenQuad,
+}
+
+impl MipPass {
+    /// Compiles `fragment_source` into a new mip pass
+    pub fn new(fragment_source: &str) -> Result<Self, ShaderCompilationError> {
+        Ok(Self {
+            program: FullscreenQuad::program(fragment_source)?,
+            uniforms: Uniforms::default(),
+            quad: FullscreenQuad::new(),
+        })
+    }
+
+    /// Returns the program of this pass, e.g. to resolve uniform locations
+    pub fn program(&self) -> &LinkedProgram {
+        &self.program
+    }
+
+    /// Sets additional uniforms uploaded for every level
+    pub fn set_uniforms(&mut self, uniforms: Uniforms) {
+        self.uniforms = uniforms;
+    }
+
+    /// Renders every level after the base level from the level above it, from largest to
+    /// smallest, e.g. to build a bloom downsample chain or a hierarchical depth buffer
+    pub fn downsample(&mut self, renderer: &Renderer, chain: &MipChain) -> Result<(), DrawError> {
+        let result =
+            (1..chain.levels()).try_for_each(|level| self.run(renderer, chain, level - 1, level));
+        chain.texture().set_sampled_levels(0..=chain.levels() - 1);
+        result
+    }
+
+    /// Renders every level except the smallest from the level below it, from smallest to
+    /// largest, e.g. for the upsampling half of a bloom chain.
+    ///
+    /// The output replaces the previous contents of each level.
+    pub fn upsample(&mut self, renderer: &Renderer, chain: &MipChain) -> Result<(), DrawError> {
+        let result = (0..chain.levels().saturating_sub(1))
+            .rev()
+            .try_for_each(|level| self.run(renderer, chain, level + 1, level));
+        chain.texture().set_sampled_levels(0..=chain.levels() - 1);
+        result
+    }
+
+    fn run(
+        &mut self,
+        renderer: &Renderer,
+        chain: &MipChain,
+        source: u32,
+        target: u32,
+    ) -> Result<(), DrawError> {
+        let texture = chain.texture();
+        // Sampling only the source level keeps the target level out of the sampled range,
+        // which would otherwise form a feedback loop
+        texture.set_sampled_levels(source..=source);
+
+        #[allow(clippy::unwrap_used)]
+        let surface = chain.level(target).unwrap();
+        let _binding = surface.bind_target();
+        let _viewport = ViewportGuard::new(surface.dimensions());
+
+        let (width, height) = texture.level_dimensions(source);
+        self.program.set_uniform(
+            "u_texel_size",
+            glm::vec2(1.0 / width as f32, 1.0 / height as f32),
+        );
+        self.program.set_uniform("u_source_level", source as i32);
+        let input_location = self.program.get_uniform_location("u_input");
+        self.uniforms
+            .data
+            .push((input_location, texture.binding().into_value()));
+        let result = self.quad.draw(renderer, &self.program, &self.uniforms);
+        self.uniforms.data.pop();
+        result
+    }
+}
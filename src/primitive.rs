@@ -1,22 +1,11 @@
+pub use crate::mesh::{FullscreenQuad, Mesh, Primitive};
 use crate::{
-    buffer::VertexBuffer,
-    renderer::{DrawMode, Renderer},
-    shader::{self, Program, Vertex, VertexAttributeSpec},
-    uniforms::Uniforms,
+    buffer::{BufferUsage, VertexBuffer},
+    renderer::DrawMode,
+    shader::{self, Vertex, VertexAttributeSpec, VertexFormat},
 };
 use glm::{Vec3, Vec4};
 
-/// A trait representing any primitive graphical object
-pub trait Primitive {
-    type Vertex: Vertex;
-
-    /// Turns [`self`] into a mesh by creating a vertex buffer and specifying a [`DrawMode`]
-    fn into_mesh(self) -> Mesh<Self::Vertex>;
-    fn buffer(&self) -> &VertexBuffer<Self::Vertex>;
-    fn buffer_mut(&mut self) -> &mut VertexBuffer<Self::Vertex>;
-    fn draw_mode(&self) -> DrawMode;
-}
-
 macro_rules! colour_vertex {
     ( $x: expr, $y: expr, $z: expr; $r: expr, $g: expr, $b: expr, $a: expr ) => {{
         crate::primitive::ColorVertex {
@@ -59,7 +48,7 @@ impl Circle {
     /// Constructs a new circle from a center, radius and number of segments
     pub fn new(center: Vec3, radius: f32, segments: i32) -> Self {
         let vertices = Self::calculate_vertices(center, radius, segments);
-        let buffer = VertexBuffer::new(&vertices, None);
+        let buffer = VertexBuffer::new(&vertices, None, BufferUsage::DynamicDraw);
 
         Self {
             center,
@@ -76,7 +65,8 @@ impl Circle {
     pub fn set_center(&mut self, center: Vec3) -> Vec3 {
         let old = std::mem::replace(&mut self.center, center);
         let vertices = Self::calculate_vertices(self.center, self.radius, self.segments);
-        self.buffer_mut().update_buffer(&vertices, None);
+        self.buffer_mut()
+            .update_buffer(&vertices, None, BufferUsage::DynamicDraw);
         old
     }
 }
@@ -88,7 +78,7 @@ impl Primitive for Circle {
         let vertices = Self::calculate_vertices(self.center, self.radius, self.segments);
 
         Mesh {
-            buffer: VertexBuffer::new(&vertices, None),
+            buffer: VertexBuffer::new(&vertices, None, BufferUsage::StaticDraw),
             draw_mode: self.draw_mode(),
         }
     }
@@ -125,7 +115,7 @@ impl Square {
         Self {
             position,
             side_length,
-            vertex_buffer: VertexBuffer::new(&vertices, Some(&indices)),
+            vertex_buffer: VertexBuffer::new(&vertices, Some(&indices), BufferUsage::DynamicDraw),
         }
     }
 
@@ -138,7 +128,8 @@ impl Square {
     pub fn set_position(&mut self, position: Vec3) -> glm::Vec3 {
         let old = std::mem::replace(&mut self.position, position);
         let (vertices, indices) = Self::calculate_vertices(self.position, self.side_length);
-        self.buffer_mut().update_buffer(&vertices, Some(&indices));
+        self.buffer_mut()
+            .update_buffer(&vertices, Some(&indices), BufferUsage::DynamicDraw);
         old
     }
 
@@ -151,7 +142,8 @@ impl Square {
     pub fn set_side_length(&mut self, side_length: f32) -> f32 {
         let old = std::mem::replace(&mut self.side_length, side_length);
         let (vertices, indices) = Self::calculate_vertices(self.position, self.side_length);
-        self.buffer_mut().update_buffer(&vertices, Some(&indices));
+        self.buffer_mut()
+            .update_buffer(&vertices, Some(&indices), BufferUsage::DynamicDraw);
         old
     }
 
@@ -206,7 +198,7 @@ impl Primitive for Square {
     fn into_mesh(self) -> Mesh<Self::Vertex> {
         let (vertices, indices) = Self::calculate_vertices(self.position, self.side_length);
         Mesh {
-            buffer: VertexBuffer::new(&vertices, Some(&indices)),
+            buffer: VertexBuffer::new(&vertices, Some(&indices), BufferUsage::StaticDraw),
             draw_mode: DrawMode::Triangles,
         }
     }
@@ -224,24 +216,6 @@ impl Primitive for Square {
     }
 }
 
-pub struct Mesh<V> {
-    buffer: VertexBuffer<V>,
-    draw_mode: DrawMode,
-}
-
-impl<V> Mesh<V>
-where
-    V: Vertex,
-{
-    pub fn buffer(&self) -> &VertexBuffer<V> {
-        &self.buffer
-    }
-
-    pub fn draw(&self, renderer: &mut Renderer, shader_program: &Program, uniforms: &Uniforms) {
-        renderer.draw(self.buffer(), shader_program, self.draw_mode, uniforms)
-    }
-}
-
 #[derive(Debug, Copy, Clone)]
 pub struct ColorVertex {
     pub position: Vec3,
@@ -262,25 +236,102 @@ impl From<ColorVertex> for crate::buffer::VertexData {
     }
 }
 
+impl crate::buffer::FromVertexData for ColorVertex {
+    fn from_vertex_data(data: &[u8]) -> Self {
+        let [x, y, z, r, g, b, a] = crate::buffer::read_floats(data);
+        Self {
+            position: glm::vec3(x, y, z),
+            color: glm::vec4(r, g, b, a),
+        }
+    }
+}
+
 impl Vertex for ColorVertex {
     fn get_vertex_spec() -> shader::VertexAttributeSpec {
-        VertexAttributeSpec {
-            layouts: vec![
-                (
-                    3,
-                    gl::FLOAT,
-                    gl::FALSE,
-                    7 * std::mem::size_of::<f32>() as i32,
-                    0,
-                ),
-                (
-                    4,
-                    gl::FLOAT,
-                    gl::FALSE,
-                    7 * std::mem::size_of::<f32>() as i32,
-                    3 * std::mem::size_of::<f32>(),
-                ),
+        VertexAttributeSpec::builder()
+            .attribute(VertexFormat::Float32x3)
+            .attribute(VertexFormat::Float32x4)
+            .build()
+    }
+}
+
+/// A [`ColorVertex`] storing its color as normalized bytes, which shrinks a vertex from 28 to
+/// 16 bytes. Shaders read the color as a `vec4` in `[0, 1]` just the same, so both vertex
+/// types work with the same programs.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CompactColorVertex {
+    pub position: Vec3,
+    /// The red, green, blue and alpha components, where `255` is `1.0`
+    pub color: [u8; 4],
+}
+
+impl CompactColorVertex {
+    /// Creates a vertex, quantizing each component of `color` from `[0, 1]` to a byte
+    pub fn new(position: Vec3, color: Vec4) -> Self {
+        let quantize = |component: f32| (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Self {
+            position,
+            color: [
+                quantize(color.x),
+                quantize(color.y),
+                quantize(color.z),
+                quantize(color.w),
             ],
         }
     }
+
+    /// Returns the color with components in `[0, 1]`
+    pub fn color_f32(&self) -> Vec4 {
+        let [r, g, b, a] = self.color.map(|component| component as f32 / 255.0);
+        glm::vec4(r, g, b, a)
+    }
+}
+
+impl From<ColorVertex> for CompactColorVertex {
+    fn from(vertex: ColorVertex) -> Self {
+        Self::new(vertex.position, vertex.color)
+    }
+}
+
+impl From<CompactColorVertex> for ColorVertex {
+    fn from(vertex: CompactColorVertex) -> Self {
+        Self {
+            position: vertex.position,
+            color: vertex.color_f32(),
+        }
+    }
+}
+
+impl From<CompactColorVertex> for crate::buffer::VertexData {
+    fn from(vertex: CompactColorVertex) -> crate::buffer::VertexData {
+        let mut data = vertex
+            .position
+            .as_array()
+            .iter()
+            .flat_map(|f| f.to_ne_bytes())
+            .collect::<Vec<_>>();
+        data.extend_from_slice(&vertex.color);
+        crate::buffer::VertexData { data }
+    }
+}
+
+impl crate::buffer::FromVertexData for CompactColorVertex {
+    fn from_vertex_data(data: &[u8]) -> Self {
+        let [x, y, z] = crate::buffer::read_floats(data);
+        let mut color = [0; 4];
+        color.copy_from_slice(&data[12..16]);
+        Self {
+            position: glm::vec3(x, y, z),
+            color,
+        }
+    }
+}
+
+impl Vertex for CompactColorVertex {
+    fn get_vertex_spec() -> shader::VertexAttributeSpec {
+        VertexAttributeSpec::builder()
+            .attribute(VertexFormat::Float32x3)
+            .attribute(VertexFormat::Unorm8x4)
+            .build()
+    }
 }
@@ -0,0 +1,394 @@
+use crate::diagnostics::diagnostic;
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The measured CPU and GPU time of a single profiler scope
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeTiming {
+    /// The name the scope was opened with
+    pub name: String,
+    /// How many scopes enclose this one, starting at `0` for top-level scopes
+    pub depth: usize,
+    /// The CPU time spent between opening and closing the scope
+    pub cpu_time: Duration,
+    /// The GPU time spent executing the commands issued inside the scope
+    pub gpu_time: Duration,
+}
+
+/// The timings of a whole frame, available a few frames after it was submitted
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FrameTimings {
+    /// The index of the frame, counted by [`Profiler::begin_frame`]
+    pub frame_index: u64,
+    /// The CPU time between [`Profiler::begin_frame`] and [`Profiler::end_frame`]
+    pub cpu_time: Duration,
+    /// The GPU time spent executing the frame's commands
+    pub gpu_time: Duration,
+    /// The scopes opened during the frame, in the order they were opened
+    pub scopes: Vec<ScopeTiming>,
+}
+
+/// Upper limits on the time a frame may take. `None` leaves that side unlimited.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct FrameBudget {
+    pub cpu: Option<Duration>,
+    pub gpu: Option<Duration>,
+}
+
+/// Passed to the callback registered with [`Profiler::on_budget_exceeded`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    /// The timings of the frame that went over budget
+    pub timings: FrameTimings,
+    /// The budget that was exceeded
+    pub budget: FrameBudget,
+    /// Whether the CPU time exceeded [`FrameBudget::cpu`]
+    pub cpu_exceeded: bool,
+    /// Whether the GPU time exceeded [`FrameBudget::gpu`]
+    pub gpu_exceeded: bool,
+}
+
+impl BudgetExceeded {
+    /// Returns the scopes of the frame, most expensive first. Scopes are ordered by GPU time
+    /// if the GPU budget was exceeded and by CPU time otherwise.
+    pub fn offending_scopes(&self) -> Vec<&ScopeTiming> {
+        let mut scopes = self.timings.scopes.iter().collect::<Vec<_>>();
+        if self.gpu_exceeded {
+            scopes.sort_by_key(|scope| Reverse(scope.gpu_time));
+        } else {
+            scopes.sort_by_key(|scope| Reverse(scope.cpu_time));
+        }
+        scopes
+    }
+}
+
+type BudgetCallback = Box<dyn FnMut(&BudgetExceeded)>;
+
+/// A pair of `GL_TIMESTAMP` queries bracketing a span of commands
+#[derive(Debug)]
+struct QueryPair {
+    start: u32,
+    end: u32,
+}
+
+impl QueryPair {
+    fn elapsed(&self) -> Duration {
+        let (mut start, mut end) = (0u64, 0u64);
+        unsafe {
+            gl::GetQueryObjectui64v(self.start, gl::QUERY_RESULT, &mut start);
+            gl::GetQueryObjectui64v(self.end, gl::QUERY_RESULT, &mut end);
+        };
+        Duration::from_nanos(end.saturating_sub(start))
+    }
+}
+
+#[derive(Debug)]
+struct PendingScope {
+    name: String,
+    depth: usize,
+    queries: QueryPair,
+    started: Instant,
+    cpu_time: Duration,
+}
+
+#[derive(Debug)]
+struct PendingFrame {
+    index: u64,
+    queries: QueryPair,
+    started: Instant,
+    cpu_time: Duration,
+    scopes: Vec<PendingScope>,
+}
+
+#[derive(Default)]
+struct ProfilerState {
+    frame_count: u64,
+    current: Option<PendingFrame>,
+    open_scopes: Vec<usize>,
+    pending: VecDeque<PendingFrame>,
+    free_queries: Vec<u32>,
+    last_timings: Option<FrameTimings>,
+    budget: FrameBudget,
+    callback: Option<BudgetCallback>,
+}
+
+impl ProfilerState {
+    fn timestamp(&mut self) -> u32 {
+        let query = self.free_queries.pop().unwrap_or_else(|| {
+            let mut query = 0;
+            unsafe { gl::GenQueries(1, &mut query) };
+            query
+        });
+        unsafe { gl::QueryCounter(query, gl::TIMESTAMP) };
+        query
+    }
+
+    fn release(&mut self, queries: QueryPair) {
+        self.free_queries.extend([queries.start, queries.end]);
+    }
+
+    /// Resolves every pending frame whose queries have completed, without blocking
+    fn collect(&mut self) {
+        while let Some(frame) = self.pending.front() {
+            let mut available = 0;
+            unsafe {
+                gl::GetQueryObjectiv(
+                    frame.queries.end,
+                    gl::QUERY_RESULT_AVAILABLE,
+                    &mut available,
+                )
+            };
+            if available == 0 {
+                break;
+            }
+
+            let Some(frame) = self.pending.pop_front() else {
+                break;
+            };
+            let timings = FrameTimings {
+                frame_index: frame.index,
+                cpu_time: frame.cpu_time,
+                gpu_time: frame.queries.elapsed(),
+                scopes: frame
+                    .scopes
+                    .iter()
+                    .map(|scope| ScopeTiming {
+                        name: scope.name.clone(),
+                        depth: scope.depth,
+                        cpu_time: scope.cpu_time,
+                        gpu_time: scope.queries.elapsed(),
+                    })
+                    .collect(),
+            };
+
+            self.release(frame.queries);
+            for scope in frame.scopes {
+                self.release(scope.queries);
+            }
+
+            self.check_budget(&timings);
+            self.last_timings = Some(timings);
+        }
+    }
+
+    fn check_budget(&mut self, timings: &FrameTimings) {
+        let cpu_exceeded = self.budget.cpu.is_some_and(|cpu| timings.cpu_time > cpu);
+        let gpu_exceeded = self.budget.gpu.is_some_and(|gpu| timings.gpu_time > gpu);
+        if !cpu_exceeded && !gpu_exceeded {
+            return;
+        }
+
+        if let Some(callback) = self.callback.as_mut() {
+            callback(&BudgetExceeded {
+                timings: timings.clone(),
+                budget: self.budget,
+                cpu_exceeded,
+                gpu_exceeded,
+            });
+        }
+    }
+}
+
+/// Measures the CPU and GPU time of frames and of named scopes within them, using
+/// `GL_TIMESTAMP` queries.
+///
+/// GPU timings are read back without stalling, so they become available a few frames after
+/// the frame they belong to. A callback registered with [`Profiler::on_budget_exceeded`] is
+/// fired for each frame exceeding the [`FrameBudget`], so applications can reduce quality.
+pub struct Profiler {
+    state: RefCell<ProfilerState>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    /// Constructs a profiler with no budget
+    pub fn new() -> Self {
+        Self {
+            state: RefCell::new(ProfilerState::default()),
+        }
+    }
+
+    /// Sets the budget checked against every completed frame
+    pub fn set_budget(&mut self, budget: FrameBudget) {
+        self.state.get_mut().budget = budget;
+    }
+
+    /// Returns the budget checked against every completed frame
+    pub fn budget(&self) -> FrameBudget {
+        self.state.borrow().budget
+    }
+
+    /// Registers `callback` to be called for every frame exceeding the budget, replacing any
+    /// previous callback
+    pub fn on_budget_exceeded<F>(&mut self, callback: F)
+    where
+        F: FnMut(&BudgetExceeded) + 'static,
+    {
+        self.state.get_mut().callback = Some(Box::new(callback));
+    }
+
+    /// Starts measuring a new frame, collecting the results of earlier frames that have
+    /// completed on the GPU
+    pub fn begin_frame(&mut self) {
+        let state = self.state.get_mut();
+        state.collect();
+
+        if state.current.is_some() {
+            diagnostic!(
+                Warning,
+                Usage,
+                "Profiler::begin_frame called without ending the previous frame"
+            );
+            self.end_frame();
+        }
+
+        let state = self.state.get_mut();
+        let start = state.timestamp();
+        state.current = Some(PendingFrame {
+            index: state.frame_count,
+            queries: QueryPair { start, end: 0 },
+            started: Instant::now(),
+            cpu_time: Duration::ZERO,
+            scopes: vec![],
+        });
+        state.frame_count += 1;
+    }
+
+    /// Finishes measuring the current frame. Its timings become available once the GPU has
+    /// executed it.
+    pub fn end_frame(&mut self) {
+        let state = self.state.get_mut();
+        if !state.open_scopes.is_empty() {
+            diagnostic!(
+                Warning,
+                Usage,
+                "Profiler::end_frame called with profiler scopes still open"
+            );
+        }
+
+        let end = state.timestamp();
+        if let Some(mut frame) = state.current.take() {
+            frame.queries.end = end;
+            frame.cpu_time = frame.started.elapsed();
+            state.pending.push_back(frame);
+        } else {
+            state.free_queries.push(end);
+            diagnostic!(
+                Warning,
+                Usage,
+                "Profiler::end_frame called without a frame in progress"
+            );
+        }
+    }
+
+    /// Opens a named scope that measures the commands issued until the returned guard is
+    /// dropped. Scopes may be nested.
+    pub fn scope(&self, name: &str) -> ProfileScope<'_> {
+        let mut state = self.state.borrow_mut();
+        let start = state.timestamp();
+        let depth = state.open_scopes.len();
+
+        let index = match state.current.as_mut() {
+            Some(frame) => {
+                frame.scopes.push(PendingScope {
+                    name: name.to_string(),
+                    depth,
+                    queries: QueryPair { start, end: 0 },
+                    started: Instant::now(),
+                    cpu_time: Duration::ZERO,
+                });
+                Some(frame.scopes.len() - 1)
+            }
+            None => {
+                diagnostic!(
+                    Warning,
+                    Usage,
+                    "Profiler scope {name:?} opened outside of a frame"
+                );
+                state.free_queries.push(start);
+                None
+            }
+        };
+
+        if let Some(index) = index {
+            state.open_scopes.push(index);
+        }
+
+        ProfileScope {
+            profiler: self,
+            index,
+        }
+    }
+
+    /// Returns the timings of the most recent frame that has completed on the GPU
+    pub fn last_timings(&self) -> Option<FrameTimings> {
+        self.state.borrow().last_timings.clone()
+    }
+
+    fn close_scope(&self, index: usize) {
+        let mut state = self.state.borrow_mut();
+        let end = state.timestamp();
+        state.open_scopes.retain(|open| *open != index);
+
+        match state.current.as_mut() {
+            Some(frame) => {
+                let scope = &mut frame.scopes[index];
+                scope.queries.end = end;
+                scope.cpu_time = scope.started.elapsed();
+            }
+            None => state.free_queries.push(end),
+        }
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        let state = self.state.get_mut();
+        let mut queries = std::mem::take(&mut state.free_queries);
+        for frame in state.pending.drain(..).chain(state.current.take()) {
+            queries.extend([frame.queries.start, frame.queries.end]);
+            for scope in frame.scopes {
+                queries.extend([scope.queries.start, scope.queries.end]);
+            }
+        }
+
+        queries.retain(|query| *query != 0);
+        unsafe { gl::DeleteQueries(queries.len() as i32, queries.as_ptr()) };
+    }
+}
+
+impl std::fmt::Debug for Profiler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Profiler")
+            .field("frame_count", &state.frame_count)
+            .field("pending_frames", &state.pending.len())
+            .field("budget", &state.budget)
+            .finish()
+    }
+}
+
+/// A profiler scope, closed when dropped. Obtained from [`Profiler::scope`].
+#[must_use = "the scope is closed as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct ProfileScope<'a> {
+    profiler: &'a Profiler,
+    index: Option<usize>,
+}
+
+impl Drop for ProfileScope<'_> {
+    fn drop(&mut self) {
+        if let Some(index) = self.index {
+            self.profiler.close_scope(index);
+        }
+    }
+}
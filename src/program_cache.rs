@@ -0,0 +1,186 @@
+use crate::{
+    diagnostics::diagnostic,
+    shader::{
+        GeometryInput, LinkedProgram, Program, ProgramBinary, Shader, ShaderCompilationError,
+    },
+};
+use std::{
+    ffi::CStr,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Identifies cache files written by [`ProgramCache`]
+const MAGIC: &[u8; 4] = b"GLPB";
+
+/// Caches linked programs on disk, keyed by the sources of their shaders and the driver that
+/// linked them, so that later runs load them without compiling anything.
+///
+/// Entries of other drivers are simply never looked up, and entries the driver rejects are
+/// relinked and overwritten. Failing to read or write the cache only logs a warning.
+#[derive(Debug, Clone)]
+pub struct ProgramCache {
+    directory: PathBuf,
+    driver: String,
+}
+
+impl ProgramCache {
+    /// Creates a cache storing its entries in `directory`, which is created when the first
+    /// entry is saved. The driver is identified through the current context.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        let driver = [gl::VENDOR, gl::RENDERER, gl::VERSION]
+            .map(|name| {
+                let string = unsafe { gl::GetString(name) };
+                if string.is_null() {
+                    String::new()
+                } else {
+                    unsafe { CStr::from_ptr(string.cast()) }
+                        .to_string_lossy()
+                        .into_owned()
+                }
+            })
+            .join("\n");
+
+        Self {
+            directory: directory.into(),
+            driver,
+        }
+    }
+
+    /// Returns the directory entries are stored in
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Loads the program made of `shaders` from the cache, or links it and saves it on a
+    /// miss. Shaders loaded from SPIR-V have no source to key the cache with, so programs
+    /// using them are always linked.
+    pub fn link<S>(&self, shaders: Vec<Shader<S>>) -> Result<LinkedProgram, ShaderCompilationError>
+    where
+        S: AsRef<str>,
+    {
+        let Some(path) = self.entry_path(&shaders) else {
+            return Program::new().attach_and_link(shaders);
+        };
+
+        if let Some(binary) = self.load(&path) {
+            match Program::from_binary(&binary) {
+                Ok(program) => return Ok(program),
+                Err(error) => {
+                    diagnostic!(Debug, Shader, "Relinking rejected program binary: {error}")
+                }
+            }
+        }
+
+        let program = Program::new().attach_and_link(shaders)?;
+        if let Some(binary) = program.binary() {
+            if let Err(error) = self.save(&path, &binary) {
+                diagnostic!(
+                    Warning,
+                    Shader,
+                    "Failed to cache program binary at {path:?}: {error}"
+                );
+            }
+        }
+        Ok(program)
+    }
+
+    /// Removes every entry from the cache
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.directory) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry_path<S: AsRef<str>>(&self, shaders: &[Shader<S>]) -> Option<PathBuf> {
+        let mut hash = Fnv1a::default();
+        hash.write(self.driver.as_bytes());
+        for shader in shaders {
+            hash.write(format!("\0{:?}\0", shader.shader_type()).as_bytes());
+            hash.write(shader.source()?.as_bytes());
+        }
+        Some(self.directory.join(format!("{:016x}.bin", hash.0)))
+    }
+
+    fn load(&self, path: &Path) -> Option<ProgramBinary> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    diagnostic!(
+                        Warning,
+                        Shader,
+                        "Failed to read cached program binary {path:?}: {error}"
+                    );
+                }
+                return None;
+            }
+        };
+
+        if bytes.len() < 10 || &bytes[..4] != MAGIC {
+            return None;
+        }
+        let (header, data) = bytes.split_at(10);
+        Some(ProgramBinary {
+            format: u32::from_le_bytes([header[4], header[5], header[6], header[7]]),
+            data: data.to_vec(),
+            geometry_input: decode_geometry_input(header[8])?,
+            tessellated: header[9] != 0,
+        })
+    }
+
+    fn save(&self, path: &Path, binary: &ProgramBinary) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        let mut file = fs::File::create(path)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&binary.format.to_le_bytes())?;
+        file.write_all(&[
+            encode_geometry_input(binary.geometry_input),
+            binary.tessellated as u8,
+        ])?;
+        file.write_all(&binary.data)
+    }
+}
+
+fn encode_geometry_input(input: Option<GeometryInput>) -> u8 {
+    match input {
+        None => 0,
+        Some(GeometryInput::Points) => 1,
+        Some(GeometryInput::Lines) => 2,
+        Some(GeometryInput::LinesAdjacency) => 3,
+        Some(GeometryInput::Triangles) => 4,
+        Some(GeometryInput::TrianglesAdjacency) => 5,
+    }
+}
+
+/// The inverse of [`encode_geometry_input`], or `None` for an unknown value
+fn decode_geometry_input(value: u8) -> Option<Option<GeometryInput>> {
+    match value {
+        0 => Some(None),
+        1 => Some(Some(GeometryInput::Points)),
+        2 => Some(Some(GeometryInput::Lines)),
+        3 => Some(Some(GeometryInput::LinesAdjacency)),
+        4 => Some(Some(GeometryInput::Triangles)),
+        5 => Some(Some(GeometryInput::TrianglesAdjacency)),
+        _ => None,
+    }
+}
+
+/// The 64-bit FNV-1a hash, which unlike `DefaultHasher` is stable across Rust releases
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
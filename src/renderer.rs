@@ -1,15 +1,42 @@
 use crate::{
-    buffer::VertexBuffer,
-    shader::{Program, Vertex},
+    bind::{BindGuard, BindTarget},
+    buffer::{
+        IndexBuffer, PendingRead, PixelPackBuffer, UniformBuffer, VertexBuffer, VertexBufferSlice,
+    },
+    camera::Camera,
+    context::{self, Limits, Unsupported},
+    diagnostics::diagnostic,
+    draw_parameters::{self, DrawParameters},
+    drawable::Drawable,
+    environment::{Background, BackgroundPass, Fog, ENVIRONMENT_BINDING},
+    ext,
+    frame::{Frame, FrameStats},
+    framebuffer::Framebuffer,
+    globals::{Globals, GLOBALS_BINDING},
+    shader::{GeometryInput, LinkedProgram, Vertex},
+    sync::MemoryBarrier,
+    texture::PixelFormat,
+    types::IndexType,
     uniforms::Uniforms,
 };
-use glm::Vec4;
+use glm::{Mat4, Vec4};
 use std::{
+    ops::Range,
     os::raw::c_void,
     ptr::{null, slice_from_raw_parts},
+    time::Instant,
 };
 
-#[derive(Debug, Copy, Clone)]
+fn identity() -> Mat4 {
+    glm::Matrix4::new(
+        glm::vec4(1.0, 0.0, 0.0, 0.0),
+        glm::vec4(0.0, 1.0, 0.0, 0.0),
+        glm::vec4(0.0, 0.0, 1.0, 0.0),
+        glm::vec4(0.0, 0.0, 0.0, 1.0),
+    )
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum DrawMode {
     Triangles,
     TriangleStrip,
@@ -18,6 +45,19 @@ pub enum DrawMode {
     Lines,
     LineStrip,
     LineLoop,
+    /// Lines with an extra vertex on either end, for geometry shaders
+    LinesAdjacency,
+    /// A line strip with an extra vertex on either end, for geometry shaders
+    LineStripAdjacency,
+    /// Triangles with a vertex beyond every edge, interleaved with the corners, for geometry
+    /// shaders such as silhouette extraction
+    TrianglesAdjacency,
+    /// A triangle strip with a vertex beyond every outer edge, for geometry shaders
+    TriangleStripAdjacency,
+    /// Patches of `vertices` control points each, for tessellation shaders
+    Patches {
+        vertices: u32,
+    },
 }
 
 impl From<DrawMode> for u32 {
@@ -30,10 +70,218 @@ impl From<DrawMode> for u32 {
             DrawMode::Lines => gl::LINES,
             DrawMode::LineStrip => gl::LINE_STRIP,
             DrawMode::LineLoop => gl::LINE_LOOP,
+            DrawMode::LinesAdjacency => gl::LINES_ADJACENCY,
+            DrawMode::LineStripAdjacency => gl::LINE_STRIP_ADJACENCY,
+            DrawMode::TrianglesAdjacency => gl::TRIANGLES_ADJACENCY,
+            DrawMode::TriangleStripAdjacency => gl::TRIANGLE_STRIP_ADJACENCY,
+            DrawMode::Patches { .. } => gl::PATCHES,
+        }
+    }
+}
+
+impl DrawMode {
+    /// Checks that `count` vertices (or indices) form whole primitives of this mode. Drawing
+    /// nothing is always valid.
+    pub fn validate(self, count: usize) -> Result<(), DrawError> {
+        if count == 0 {
+            return Ok(());
+        }
+
+        let (multiple, minimum) = match self {
+            DrawMode::Points => (1, 1),
+            DrawMode::Lines => (2, 2),
+            DrawMode::LineStrip | DrawMode::LineLoop => (1, 2),
+            DrawMode::Triangles => (3, 3),
+            DrawMode::TriangleStrip | DrawMode::TriangleFan => (1, 3),
+            DrawMode::LinesAdjacency => (4, 4),
+            DrawMode::LineStripAdjacency => (1, 4),
+            DrawMode::TrianglesAdjacency => (6, 6),
+            DrawMode::TriangleStripAdjacency => (2, 6),
+            DrawMode::Patches { vertices: 0 } => return Err(DrawError::InvalidPatchSize(0)),
+            DrawMode::Patches { vertices } => (vertices as usize, vertices as usize),
+        };
+
+        if count < minimum {
+            Err(DrawError::TooFewVertices {
+                mode: self,
+                count,
+                minimum,
+            })
+        } else if !count.is_multiple_of(multiple) {
+            Err(DrawError::IncompletePrimitive {
+                mode: self,
+                count,
+                multiple,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A draw call that was rejected before reaching OpenGL, which would have silently dropped
+/// some or all of its primitives
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawError {
+    /// The vertex (or index) count isn't a multiple of the number of vertices per primitive
+    IncompletePrimitive {
+        mode: DrawMode,
+        count: usize,
+        multiple: usize,
+    },
+    /// There are fewer vertices (or indices) than a single primitive needs
+    TooFewVertices {
+        mode: DrawMode,
+        count: usize,
+        minimum: usize,
+    },
+    /// The patch size is zero or larger than `GL_MAX_PATCH_VERTICES`
+    InvalidPatchSize(u32),
+    /// A program with tessellation shaders was used with a mode other than
+    /// [`DrawMode::Patches`]
+    PatchesRequired(DrawMode),
+    /// [`DrawMode::Patches`] was used with a program without a tessellation evaluation shader
+    TessellationRequired,
+    /// The program's geometry shader doesn't accept the primitives of `mode`
+    GeometryInputMismatch {
+        mode: DrawMode,
+        input: GeometryInput,
+    },
+    /// The program reads an attribute of type `ty` at `location` that the vertex layout
+    /// doesn't provide. Only checked in debug builds.
+    MissingAttribute { location: u32, ty: u32 },
+    /// The program reads an integer or double attribute of type `ty` at `location`, but
+    /// vertex layouts only provide floats. Only checked in debug builds.
+    AttributeTypeMismatch { location: u32, ty: u32 },
+    /// A [`crate::command::CommandList`] refers to a program or buffer owned by a different
+    /// [`crate::command::Resources`] than the one it was submitted to
+    ForeignResource,
+    /// The uniforms use more distinct textures than the `available` texture units
+    OutOfTextureUnits { available: u32 },
+}
+
+impl std::fmt::Display for DrawError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawError::IncompletePrimitive {
+                mode,
+                count,
+                multiple,
+            } => write!(
+                f,
+                "{mode:?} needs a multiple of {multiple} vertices, but {count} were submitted"
+            ),
+            DrawError::TooFewVertices {
+                mode,
+                count,
+                minimum,
+            } => write!(
+                f,
+                "{mode:?} needs at least {minimum} vertices, but {count} were submitted"
+            ),
+            DrawError::InvalidPatchSize(size) => write!(f, "{size} is not a valid patch size"),
+            DrawError::PatchesRequired(mode) => write!(
+                f,
+                "{mode:?} can't be drawn with tessellation shaders, which only accept patches"
+            ),
+            DrawError::TessellationRequired => write!(
+                f,
+                "Patches can only be drawn with a tessellation evaluation shader"
+            ),
+            DrawError::GeometryInputMismatch { mode, input } => write!(
+                f,
+                "{mode:?} can't be drawn with a geometry shader taking {input:?} as input"
+            ),
+            DrawError::MissingAttribute { location, ty } => write!(
+                f,
+                "The program reads a {} attribute at location {location}, which the vertex \
+                 layout doesn't provide",
+                crate::types::glsl_type_name(*ty)
+            ),
+            DrawError::AttributeTypeMismatch { location, ty } => write!(
+                f,
+                "The program reads a {} attribute at location {location}, but vertex layouts \
+                 only provide floats",
+                crate::types::glsl_type_name(*ty)
+            ),
+            DrawError::ForeignResource => write!(
+                f,
+                "The command list refers to a resource owned by another registry"
+            ),
+            DrawError::OutOfTextureUnits { available } => write!(
+                f,
+                "The uniforms use more textures than the {available} available texture units"
+            ),
         }
     }
 }
 
+impl std::error::Error for DrawError {}
+
+/// A rectangle of pixels with its origin at the bottom left
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Constructs a new rectangle
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// The window-space position of the clip-space origin, set with [`Renderer::clip_control`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ClipOrigin {
+    /// Clip-space `(-1, -1)` maps to the lower-left corner, as is the OpenGL default
+    #[default]
+    LowerLeft,
+    /// Clip-space `(-1, -1)` maps to the upper-left corner, as in Vulkan and Direct3D
+    UpperLeft,
+}
+
+impl From<ClipOrigin> for u32 {
+    fn from(origin: ClipOrigin) -> u32 {
+        match origin {
+            ClipOrigin::LowerLeft => gl::LOWER_LEFT,
+            ClipOrigin::UpperLeft => gl::UPPER_LEFT,
+        }
+    }
+}
+
+/// The clip-space depth range mapped to the depth buffer, set with [`Renderer::clip_control`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ClipDepth {
+    /// Depth ranges from `-1` to `1`, as is the OpenGL default
+    #[default]
+    NegativeOneToOne,
+    /// Depth ranges from `0` to `1`, as in Vulkan and Direct3D. Combined with a floating-point
+    /// depth buffer, a depth clear of `0` and a `GREATER` depth test, this gives reversed-Z
+    /// its full precision.
+    ZeroToOne,
+}
+
+impl From<ClipDepth> for u32 {
+    fn from(depth: ClipDepth) -> u32 {
+        match depth {
+            ClipDepth::NegativeOneToOne => gl::NEGATIVE_ONE_TO_ONE,
+            ClipDepth::ZeroToOne => gl::ZERO_TO_ONE,
+        }
+    }
+}
+
+/// Which faces are culled, set globally with [`Renderer::cull_faces`] or per draw with
+/// [`DrawParameters::culling`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CullingMode {
     Clockwise,
     CounterClockwise,
@@ -42,15 +290,41 @@ pub enum CullingMode {
 
 /// A struct for abstracting OpenGL draw calls
 pub struct Renderer {
-    clear_color: Vec4,
+    background: Background,
+    background_pass: Option<BackgroundPass>,
+    background_camera: Mat4,
     clear_depth: f64,
+    frame_count: u64,
+    last_frame: Option<FrameStats>,
+    clip_control: (ClipOrigin, ClipDepth),
+    fog: Fog,
+    environment: Option<UniformBuffer<Fog>>,
+    globals: Globals,
+    globals_buffer: Option<UniformBuffer<Globals>>,
+    /// The instant at which the time was `time_offset` seconds
+    time_origin: Instant,
+    time_offset: f32,
+    /// The time at which the previous frame began
+    previous_frame_time: Option<f32>,
 }
 
 impl Default for Renderer {
     fn default() -> Self {
         Self {
-            clear_color: glm::vec4(0.0, 0.0, 0.0, 1.0),
+            background: Background::default(),
+            background_pass: None,
+            background_camera: identity(),
             clear_depth: 0.0,
+            frame_count: 0,
+            last_frame: None,
+            clip_control: Default::default(),
+            fog: Fog::default(),
+            environment: None,
+            globals: Globals::default(),
+            globals_buffer: None,
+            time_origin: Instant::now(),
+            time_offset: 0.0,
+            previous_frame_time: None,
         }
     }
 }
@@ -66,14 +340,146 @@ impl Renderer {
         };
 
         Self {
-            clear_color: Vec4::new(0.0, 0.0, 0.0, 0.0),
+            background: Background::Color(Vec4::new(0.0, 0.0, 0.0, 0.0)),
+            background_pass: None,
+            background_camera: identity(),
             clear_depth: 0.0,
+            frame_count: 0,
+            last_frame: None,
+            clip_control: Default::default(),
+            fog: Fog::default(),
+            environment: None,
+            globals: Globals::default(),
+            globals_buffer: None,
+            time_origin: Instant::now(),
+            time_offset: 0.0,
+            previous_frame_time: None,
+        }
+    }
+
+    /// Sets the fog applied by shaders that include [`Fog::GLSL`], uploading it to the
+    /// environment uniform block at [`ENVIRONMENT_BINDING`]
+    pub fn set_fog(&mut self, fog: Fog) {
+        self.fog = fog;
+        match self.environment {
+            Some(ref mut environment) => environment.set(&fog),
+            None => self.environment = Some(UniformBuffer::new(&fog)),
+        }
+
+        #[allow(clippy::unwrap_used)]
+        self.environment
+            .as_ref()
+            .unwrap()
+            .bind_to(ENVIRONMENT_BINDING);
+    }
+
+    /// Returns the fog last set with [`Renderer::set_fog`]
+    pub fn fog(&self) -> &Fog {
+        &self.fog
+    }
+
+    /// Sets the camera whose matrices and position are exposed to shaders that include
+    /// [`Globals::GLSL`], uploading it to the globals uniform block at [`GLOBALS_BINDING`]
+    pub fn set_camera(&mut self, camera: &Camera) {
+        self.globals.set_camera(camera);
+        self.upload_globals();
+    }
+
+    /// Returns the time in seconds exposed to shaders as `time`, which starts at `0` when the
+    /// renderer is created and advances in real time
+    pub fn time(&self) -> f32 {
+        self.time_offset + self.time_origin.elapsed().as_secs_f32()
+    }
+
+    /// Sets the time exposed to shaders, from which it keeps advancing in real time, e.g. to
+    /// restart animations. Takes effect at the next [`Renderer::begin_frame`].
+    pub fn set_time(&mut self, seconds: f32) {
+        self.time_origin = Instant::now();
+        self.time_offset = seconds;
+        self.previous_frame_time = None;
+    }
+
+    /// Returns the values last uploaded to the globals uniform block
+    pub fn globals(&self) -> &Globals {
+        &self.globals
+    }
+
+    fn upload_globals(&mut self) {
+        match self.globals_buffer {
+            Some(ref mut buffer) => buffer.set(&self.globals),
+            None => self.globals_buffer = Some(UniformBuffer::new(&self.globals)),
         }
+
+        #[allow(clippy::unwrap_used)]
+        self.globals_buffer
+            .as_ref()
+            .unwrap()
+            .bind_to(GLOBALS_BINDING);
+    }
+
+    /// Begins a new frame. Clears and draws issued through the returned [`Frame`] are
+    /// counted towards its statistics.
+    ///
+    /// The globals uniform block is updated with the frame index, the current viewport, the
+    /// time and the time since the previous frame.
+    pub fn begin_frame(&mut self) -> Frame<'_> {
+        let frame_index = self.frame_count;
+        let time = self.time();
+        self.globals.time = time;
+        self.globals.delta_time = self
+            .previous_frame_time
+            .map_or(0.0, |previous| (time - previous).max(0.0));
+        self.previous_frame_time = Some(time);
+
+        let mut viewport = [0; 4];
+        unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+        self.globals.viewport_size = glm::vec2(viewport[2] as f32, viewport[3] as f32);
+        self.globals.frame_index = frame_index as u32;
+        self.upload_globals();
+        Frame::new(self, frame_index)
+    }
+
+    /// Returns the statistics of the most recently finished frame
+    pub fn last_frame_stats(&self) -> Option<&FrameStats> {
+        self.last_frame.as_ref()
+    }
+
+    /// Returns the number of frames finished so far
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub(crate) fn end_frame(&mut self, stats: FrameStats) {
+        self.frame_count += 1;
+        self.last_frame = Some(stats);
     }
 
-    /// Sets the clear color for the renderer.
+    /// Sets the clear color for the renderer. This is a shorthand for setting a
+    /// [`Background::Color`].
     pub fn clear_color(&mut self, color: Vec4) {
-        self.clear_color = color;
+        self.set_background(Background::Color(color));
+    }
+
+    /// Sets what [`Renderer::clear`] fills the pixel buffer with
+    pub fn set_background(&mut self, background: Background) {
+        self.background_pass = BackgroundPass::new(&background);
+        self.background = background;
+    }
+
+    /// Returns the background last set with [`Renderer::set_background`]
+    pub fn background(&self) -> &Background {
+        &self.background
+    }
+
+    /// Sets the camera through which skyboxes and panoramas are seen. Only the rotation of
+    /// `view` is used, so the background stays infinitely far away.
+    ///
+    /// # Panics
+    /// This function panics if the resulting view-projection matrix isn't invertible.
+    pub fn set_background_camera(&mut self, view: Mat4, projection: Mat4) {
+        let mut rotation = view;
+        rotation[3] = glm::vec4(0.0, 0.0, 0.0, 1.0);
+        self.background_camera = glm::inverse(&(projection * rotation));
     }
 
     /// Sets the clear depth for the renderer
@@ -87,6 +493,34 @@ impl Renderer {
         self.clear_depth = depth;
     }
 
+    /// Sets the clip-space origin and depth convention with `glClipControl`.
+    ///
+    /// # Returns
+    ///
+    /// An error, leaving the conventions unchanged, if the context doesn't support OpenGL 4.5
+    /// or `GL_ARB_clip_control`.
+    pub fn clip_control(
+        &mut self,
+        origin: ClipOrigin,
+        depth: ClipDepth,
+    ) -> Result<(), Unsupported> {
+        if !context::supports_clip_control() {
+            return Err(Unsupported {
+                feature: "glClipControl",
+            });
+        }
+
+        unsafe { gl::ClipControl(origin.into(), depth.into()) };
+        self.clip_control = (origin, depth);
+        Ok(())
+    }
+
+    /// Returns the clip-space origin and depth convention last set with
+    /// [`Renderer::clip_control`]
+    pub fn clip_conventions(&self) -> (ClipOrigin, ClipDepth) {
+        self.clip_control
+    }
+
     /// Loads the function table for OpenGL.
     ///
     /// Must be called before constructing a renderer or any other object in this library
@@ -94,25 +528,16 @@ impl Renderer {
     where
         F: FnMut(&'static str) -> *const c_void,
     {
-        gl::load_with(load_with);
+        let mut load_with = load_with;
+        gl::load_with(&mut load_with);
+        ext::load_with(load_with);
+        // The functions may belong to a different context than the recorded state
+        context::load_capabilities();
+        draw_parameters::invalidate_state_cache();
     }
 
     pub fn cull_faces(&mut self, culling_mode: CullingMode) {
-        match culling_mode {
-            CullingMode::Clockwise => unsafe {
-                gl::Enable(gl::CULL_FACE);
-                gl::CullFace(gl::BACK);
-                gl::FrontFace(gl::CCW);
-            },
-            CullingMode::CounterClockwise => unsafe {
-                gl::Enable(gl::CULL_FACE);
-                gl::CullFace(gl::BACK);
-                gl::FrontFace(gl::CW);
-            },
-            CullingMode::None => {
-                unsafe { gl::Disable(gl::CULL_FACE) };
-            }
-        }
+        draw_parameters::set_culling(culling_mode);
     }
 
     extern "system" fn debug_callback(
@@ -136,82 +561,477 @@ impl Renderer {
         let message = String::from_utf8(message).expect("Debug message was invalid String");
 
         if ty == gl::DEBUG_TYPE_ERROR {
-            log::error!(
-                "Debug Callback: ** GL ERROR ** type = {ty}, severity = {severity}, message = {message}\n",
+            diagnostic!(
+                Error,
+                Gl,
+                "Debug Callback: ** GL ERROR ** type = {ty}, severity = {severity}, \
+                 message = {message}\n",
             );
         } else {
-            log::debug!(
+            diagnostic!(
+                Debug,
+                Gl,
                 "Debug Callback: type = {ty}, severity = {severity}, message = {message}\n",
             );
         }
     }
 
-    /// Clears the pixel buffer currently being drawn to
+    /// Clears the pixel buffer currently being drawn to and fills it with the background.
+    /// See [`Renderer::set_background`].
     pub fn clear(&self) {
+        let clear_color = self.background.clear_color();
         unsafe {
-            gl::ClearColor(
-                self.clear_color.x,
-                self.clear_color.y,
-                self.clear_color.z,
-                self.clear_color.w,
-            );
+            gl::ClearColor(clear_color.x, clear_color.y, clear_color.z, clear_color.w);
             gl::ClearDepth(self.clear_depth);
 
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         };
+
+        if let Some(ref pass) = self.background_pass {
+            // The background is behind everything, so it's drawn without touching depth
+            let depth_test = unsafe { gl::IsEnabled(gl::DEPTH_TEST) } == gl::TRUE;
+            unsafe { gl::Disable(gl::DEPTH_TEST) };
+            draw_parameters::invalidate_state_cache();
+            if let Err(error) = pass.draw(self, &self.background, self.background_camera) {
+                diagnostic!(Error, Draw, "Failed to draw the background: {error}");
+            }
+            if depth_test {
+                unsafe { gl::Enable(gl::DEPTH_TEST) };
+            }
+            draw_parameters::invalidate_state_cache();
+        }
+    }
+
+    /// Draws a buffer to the screen.
+    ///
+    /// # Returns
+    ///
+    /// An error, without drawing anything, if the buffer doesn't hold whole primitives of
+    /// `mode`.
+    pub fn draw<'a, V: Vertex + 'a, I: IndexType + 'a>(
+        &self,
+        buffer: impl Into<VertexBufferSlice<'a, V, I>>,
+        shader_program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+    ) -> Result<(), DrawError> {
+        draw_vertex_buffer(
+            buffer.into(),
+            shader_program,
+            mode,
+            uniforms,
+            &DrawParameters::default(),
+        )
+    }
+
+    /// Draws the vertices of `buffer` to the screen using the indices in `indices`, ignoring
+    /// any indices stored in `buffer` itself. See [`Renderer::draw`].
+    pub fn draw_indexed<V: Vertex, I: IndexType, J: IndexType>(
+        &self,
+        buffer: &VertexBuffer<V, I>,
+        indices: &IndexBuffer<J>,
+        shader_program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+    ) -> Result<(), DrawError> {
+        let _buffer_binding = buffer.bind_scoped();
+        let _index_binding = indices.bind_scoped();
+        issue_draw::<V>(
+            0..indices.len(),
+            Some((indices.index_type(), std::mem::size_of::<J>())),
+            shader_program,
+            mode,
+            uniforms,
+            &DrawParameters::default(),
+        )
     }
 
-    /// Draws a buffer to the screen
-    pub fn draw<V: Vertex>(
+    /// Draws a buffer to the screen with `parameters` applied for the duration of the draw.
+    /// See [`Renderer::draw`].
+    pub fn draw_with_parameters<'a, V: Vertex + 'a, I: IndexType + 'a>(
         &self,
-        buffer: &VertexBuffer<V>,
-        shader_program: &Program,
+        buffer: impl Into<VertexBufferSlice<'a, V, I>>,
+        shader_program: &LinkedProgram,
         mode: DrawMode,
         uniforms: &Uniforms,
+        parameters: &DrawParameters,
+    ) -> Result<(), DrawError> {
+        draw_vertex_buffer(buffer.into(), shader_program, mode, uniforms, parameters)
+    }
+
+    /// Draws `drawable` with its program, uniforms and parameters. Unlike [`Renderer::draw`],
+    /// this accepts trait objects as well as concrete drawables, so scenes can be built from
+    /// different kinds of renderables.
+    pub fn submit<D: Drawable + ?Sized>(&self, drawable: &D) -> Result<(), DrawError> {
+        drawable.geometry().draw_geometry(
+            drawable.program(),
+            drawable.uniforms(),
+            &drawable.parameters(),
+        )
+    }
+
+    /// Makes shader writes to storage buffers and images issued so far visible to the reads
+    /// in `barriers` issued afterwards, e.g. [`MemoryBarrier::VERTEX_ATTRIB_ARRAY`] before
+    /// drawing vertices a compute shader wrote. Does nothing if `barriers` is empty.
+    pub fn memory_barrier(&self, barriers: MemoryBarrier) {
+        if !barriers.is_empty() {
+            unsafe { gl::MemoryBarrier(barriers.into()) };
+        }
+    }
+
+    /// Reads a rectangle of pixels from the back buffer of the default framebuffer.
+    ///
+    /// Rows are returned bottom to top, as OpenGL stores them.
+    pub fn read_pixels(&self, rect: Rect, format: PixelFormat) -> Vec<u8> {
+        let mut data = vec![0u8; Self::read_size(rect, format)];
+        Self::read_pixels_into(0, gl::BACK, rect, format, data.as_mut_ptr().cast());
+        data
+    }
+
+    /// Reads a rectangle of pixels from color attachment `attachment` of `framebuffer`.
+    ///
+    /// Rows are returned bottom to top, as OpenGL stores them.
+    pub fn read_framebuffer_pixels(
+        &self,
+        framebuffer: &Framebuffer,
+        attachment: u32,
+        rect: Rect,
+        format: PixelFormat,
+    ) -> Vec<u8> {
+        let mut data = vec![0u8; Self::read_size(rect, format)];
+        Self::read_pixels_into(
+            framebuffer.id(),
+            Self::read_buffer(attachment, format),
+            rect,
+            format,
+            data.as_mut_ptr().cast(),
+        );
+        data
+    }
+
+    /// Starts an asynchronous read of the default framebuffer's back buffer into `buffer`.
+    ///
+    /// Unlike [`Renderer::read_pixels`], this doesn't wait for rendering to finish.
+    /// Call [`PendingRead::finish_read`] on the result, ideally a frame or two later.
+    ///
+    /// # Panics
+    /// This function panics if `buffer` is too small to hold the requested pixels.
+    pub fn start_read<'a>(
+        &self,
+        buffer: &'a PixelPackBuffer,
+        rect: Rect,
+        format: PixelFormat,
+    ) -> PendingRead<'a> {
+        Self::start_read_from(buffer, 0, gl::BACK, rect, format)
+    }
+
+    /// Starts an asynchronous read of color attachment `attachment` of `framebuffer` into
+    /// `buffer`.
+    ///
+    /// # Panics
+    /// This function panics if `buffer` is too small to hold the requested pixels.
+    pub fn start_framebuffer_read<'a>(
+        &self,
+        buffer: &'a PixelPackBuffer,
+        framebuffer: &Framebuffer,
+        attachment: u32,
+        rect: Rect,
+        format: PixelFormat,
+    ) -> PendingRead<'a> {
+        Self::start_read_from(
+            buffer,
+            framebuffer.id(),
+            Self::read_buffer(attachment, format),
+            rect,
+            format,
+        )
+    }
+
+    fn start_read_from(
+        buffer: &PixelPackBuffer,
+        fbo: u32,
+        read_buffer: u32,
+        rect: Rect,
+        format: PixelFormat,
+    ) -> PendingRead<'_> {
+        let len = Self::read_size(rect, format);
+        assert!(
+            len <= buffer.size(),
+            "Pixel pack buffer of {} bytes is too small for a read of {len} bytes",
+            buffer.size()
+        );
+
+        {
+            let _binding = buffer.bind_scoped();
+            // With a pixel pack buffer bound, the destination is an offset into that buffer
+            Self::read_pixels_into(fbo, read_buffer, rect, format, std::ptr::null_mut());
+        }
+
+        PendingRead::new(buffer, len)
+    }
+
+    fn read_size(rect: Rect, format: PixelFormat) -> usize {
+        rect.width as usize * rect.height as usize * format.bytes_per_pixel()
+    }
+
+    fn read_buffer(attachment: u32, format: PixelFormat) -> u32 {
+        match format {
+            PixelFormat::Depth | PixelFormat::DepthStencil => gl::NONE,
+            _ => gl::COLOR_ATTACHMENT0 + attachment,
+        }
+    }
+
+    pub(crate) fn read_pixels_into(
+        fbo: u32,
+        read_buffer: u32,
+        rect: Rect,
+        format: PixelFormat,
+        destination: *mut c_void,
     ) {
-        buffer.bind();
-        shader_program.bind();
-        uniforms.upload_all();
-
-        let vertex_spec = <V as Vertex>::get_vertex_spec();
-        for i in 0..vertex_spec.layouts.len() {
-            unsafe {
-                gl::EnableVertexAttribArray(i as u32);
+        let _binding = BindGuard::new(BindTarget::ReadFramebuffer, fbo);
+        unsafe {
+            // The read buffer belongs to the framebuffer and the pack alignment to the context,
+            // so both are put back for whoever reads next
+            let mut previous_read_buffer = 0;
+            let mut previous_alignment = 0;
+            if read_buffer != gl::NONE {
+                gl::GetIntegerv(gl::READ_BUFFER, &mut previous_read_buffer);
+                gl::ReadBuffer(read_buffer);
+            }
+            gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut previous_alignment);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                rect.x,
+                rect.y,
+                rect.width as i32,
+                rect.height as i32,
+                format.format(),
+                format.ty(),
+                destination,
+            );
+            gl::PixelStorei(gl::PACK_ALIGNMENT, previous_alignment);
+            if read_buffer != gl::NONE {
+                gl::ReadBuffer(previous_read_buffer as u32);
             }
+        };
+    }
+}
+
+/// Issues the draw call for `slice` to whatever framebuffer is currently bound
+pub(crate) fn draw_vertex_buffer<V: Vertex, I: IndexType>(
+    slice: VertexBufferSlice<'_, V, I>,
+    shader_program: &LinkedProgram,
+    mode: DrawMode,
+    uniforms: &Uniforms,
+    parameters: &DrawParameters,
+) -> Result<(), DrawError> {
+    let buffer = slice.buffer();
+    let _buffer_binding = buffer.bind_scoped();
+    let indices = buffer
+        .has_indices()
+        .then(|| (buffer.index_type(), std::mem::size_of::<I>()));
+    issue_draw::<V>(
+        slice.range(),
+        indices,
+        shader_program,
+        mode,
+        uniforms,
+        parameters,
+    )
+}
+
+/// Issues a draw call for the currently bound vertex array, drawing the indices in `range`
+/// from the bound index buffer if `indices` (an index type and the size of an index) is given,
+/// and the vertices in `range` otherwise
+fn issue_draw<V: Vertex>(
+    range: Range<usize>,
+    indices: Option<(u32, usize)>,
+    shader_program: &LinkedProgram,
+    mode: DrawMode,
+    uniforms: &Uniforms,
+    parameters: &DrawParameters,
+) -> Result<(), DrawError> {
+    let count = range.len();
+    mode.validate(count)?;
+    if let DrawMode::Patches { vertices: size } = mode {
+        if size > Limits::current().max_patch_vertices {
+            return Err(DrawError::InvalidPatchSize(size));
         }
+    }
 
-        vertex_spec
-            .layouts
-            .iter()
-            .enumerate()
-            .for_each(|(index, layout)| unsafe {
-                let (size, ty, normalized, stride, offset) = *layout;
-                gl::VertexAttribPointer(
-                    index as u32,
-                    size,
-                    ty,
-                    normalized,
-                    stride,
-                    offset as *const c_void,
-                )
-            });
+    match (
+        matches!(mode, DrawMode::Patches { .. }),
+        shader_program.is_tessellated(),
+    ) {
+        (true, false) => return Err(DrawError::TessellationRequired),
+        (false, true) => return Err(DrawError::PatchesRequired(mode)),
+        _ => {}
+    }
+    if let Some(input) = shader_program.geometry_input() {
+        if !input.accepts(mode) {
+            return Err(DrawError::GeometryInputMismatch { mode, input });
+        }
+    }
+
+    let vertex_spec = <V as Vertex>::get_vertex_spec();
+    if cfg!(debug_assertions) {
+        shader_program.check_vertex_layout(&vertex_spec)?;
+    }
+
+    let _state = parameters.apply(mode);
+    let _program_binding = shader_program.bind_scoped();
+    shader_program.apply_subroutines();
+    shader_program.report_uniform_mismatches(uniforms);
+    uniforms.upload_all()?;
+
+    for i in 0..vertex_spec.layouts.len() {
+        unsafe {
+            gl::EnableVertexAttribArray(i as u32);
+        }
+    }
 
-        if buffer.has_indices() {
-            unsafe {
-                gl::DrawElements(
+    vertex_spec
+        .layouts
+        .iter()
+        .enumerate()
+        .for_each(|(index, layout)| unsafe {
+            let (size, ty, normalized, stride, offset, divisor) = *layout;
+            gl::VertexAttribPointer(
+                index as u32,
+                size,
+                ty,
+                normalized,
+                stride,
+                offset as *const c_void,
+            );
+            gl::VertexAttribDivisor(index as u32, divisor);
+        });
+
+    let instances = parameters.instances.unwrap_or(1) as i32;
+    let base_instance = parameters.base_instance;
+    let plain = parameters.instances.is_none() && base_instance == 0;
+    if let Some((index_type, index_size)) = indices {
+        // With an index buffer bound, the pointer is a byte offset into that buffer
+        let offset = (range.start * index_size) as *const c_void;
+        unsafe {
+            if plain && parameters.base_vertex == 0 {
+                gl::DrawElements(mode.into(), count as i32, index_type, offset)
+            } else {
+                gl::DrawElementsInstancedBaseVertexBaseInstance(
                     mode.into(),
-                    buffer.index_count() as i32,
-                    gl::UNSIGNED_INT,
-                    null(),
+                    count as i32,
+                    index_type,
+                    offset,
+                    instances,
+                    parameters.base_vertex,
+                    base_instance,
                 )
             }
-        } else {
-            unsafe { gl::DrawArrays(mode.into(), 0, buffer.vertex_count() as i32) };
         }
-        for i in 0..vertex_spec.layouts.len() {
-            unsafe {
-                gl::DisableVertexAttribArray(i as u32);
+    } else {
+        unsafe {
+            if plain {
+                gl::DrawArrays(mode.into(), range.start as i32, count as i32)
+            } else {
+                gl::DrawArraysInstancedBaseInstance(
+                    mode.into(),
+                    range.start as i32,
+                    count as i32,
+                    instances,
+                    base_instance,
+                )
             }
+        };
+    }
+    for i in 0..vertex_spec.layouts.len() {
+        unsafe {
+            gl::VertexAttribDivisor(i as u32, 0);
+            gl::DisableVertexAttribArray(i as u32);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_primitives_are_valid() {
+        for (mode, count) in [
+            (DrawMode::Points, 1),
+            (DrawMode::Lines, 4),
+            (DrawMode::LineStrip, 3),
+            (DrawMode::LineLoop, 2),
+            (DrawMode::Triangles, 9),
+            (DrawMode::TriangleStrip, 4),
+            (DrawMode::TriangleFan, 7),
+            (DrawMode::LinesAdjacency, 8),
+            (DrawMode::LineStripAdjacency, 5),
+            (DrawMode::TrianglesAdjacency, 12),
+            (DrawMode::TriangleStripAdjacency, 8),
+            (DrawMode::Patches { vertices: 3 }, 6),
+        ] {
+            assert_eq!(mode.validate(count), Ok(()), "{mode:?} with {count}");
         }
     }
+
+    #[test]
+    fn drawing_nothing_is_valid() {
+        assert_eq!(DrawMode::Triangles.validate(0), Ok(()));
+        assert_eq!(DrawMode::Patches { vertices: 4 }.validate(0), Ok(()));
+    }
+
+    #[test]
+    fn partial_primitives_are_rejected() {
+        for (mode, count, multiple) in [
+            (DrawMode::Lines, 3, 2),
+            (DrawMode::Triangles, 4, 3),
+            (DrawMode::Triangles, 8, 3),
+            (DrawMode::LinesAdjacency, 6, 4),
+            (DrawMode::TrianglesAdjacency, 7, 6),
+            (DrawMode::TriangleStripAdjacency, 7, 2),
+            (DrawMode::Patches { vertices: 4 }, 6, 4),
+        ] {
+            assert_eq!(
+                mode.validate(count),
+                Err(DrawError::IncompletePrimitive {
+                    mode,
+                    count,
+                    multiple
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn too_few_vertices_are_rejected() {
+        for (mode, count, minimum) in [
+            (DrawMode::Lines, 1, 2),
+            (DrawMode::LineStrip, 1, 2),
+            (DrawMode::Triangles, 2, 3),
+            (DrawMode::TriangleFan, 2, 3),
+            (DrawMode::LineStripAdjacency, 3, 4),
+            (DrawMode::TriangleStripAdjacency, 4, 6),
+            (DrawMode::Patches { vertices: 16 }, 8, 16),
+        ] {
+            assert_eq!(
+                mode.validate(count),
+                Err(DrawError::TooFewVertices {
+                    mode,
+                    count,
+                    minimum
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn empty_patches_are_rejected() {
+        assert_eq!(
+            DrawMode::Patches { vertices: 0 }.validate(3),
+            Err(DrawError::InvalidPatchSize(0))
+        );
+    }
 }
@@ -1,14 +1,30 @@
+use crate::{
+    bind::{BindGuard, BindTarget},
+    context::{self, Limits, Unsupported},
+    diagnostics::diagnostic,
+    ext,
+    include::{self, IncludeDirectory, IncludeError, IncludeResolver},
+    renderer::{DrawError, DrawMode},
+    types::{AttributeFormat, AttributeType},
+    uniforms::{self, Uniform, UniformHandle, Uniforms},
+};
 use std::{
+    cell::RefCell,
+    collections::HashMap,
     ffi::{c_char, CString},
+    fmt::Write,
     io::Read,
+    path::{Path, PathBuf},
 };
 
 /// An abstraction for the concept of a Vertex Attribute Array
 /// Usage of this struct outside of the library is currently unsafe, since
 /// the memory safety of the GPU buffer associated depends on the user supplying correct values.
+/// Prefer [`VertexAttributeSpec::builder`], which computes strides and offsets itself.
 #[derive(Debug, Default, Clone)]
 pub struct VertexAttributeSpec {
-    pub(crate) layouts: Vec<(i32, u32, u8, i32, usize)>,
+    /// `(count, type, normalized, stride, offset, divisor)` for every attribute
+    pub(crate) layouts: Vec<(i32, u32, u8, i32, usize, u32)>,
 }
 
 impl VertexAttributeSpec {
@@ -17,6 +33,12 @@ impl VertexAttributeSpec {
         Self { layouts: vec![] }
     }
 
+    /// Starts a specification of tightly packed attributes described by their
+    /// [`VertexFormat`]s
+    pub fn builder() -> VertexAttributeBuilder {
+        VertexAttributeBuilder::default()
+    }
+
     /// Adds a layout to the vertex specification
     ///
     /// # Safety
@@ -40,6 +62,23 @@ impl VertexAttributeSpec {
         normalized: bool,
         stride: i32,
         offset: usize,
+    ) {
+        self.push_instance_layout(count, ty, normalized, stride, offset, 0);
+    }
+
+    /// Adds a layout that advances once every `divisor` instances instead of once per vertex,
+    /// e.g. a per-instance transform. A `divisor` of `0` makes it a regular per-vertex layout.
+    ///
+    /// # Safety
+    /// See [`VertexAttributeSpec::push_layout`].
+    pub unsafe fn push_instance_layout(
+        &mut self,
+        count: i32,
+        ty: u32,
+        normalized: bool,
+        stride: i32,
+        offset: usize,
+        divisor: u32,
     ) {
         self.layouts.push((
             count,
@@ -47,8 +86,181 @@ impl VertexAttributeSpec {
             if normalized { gl::TRUE } else { gl::FALSE },
             stride,
             offset,
+            divisor,
         ));
     }
+
+    /// Returns the number of bytes a single vertex occupies in the vertex buffer
+    pub(crate) fn vertex_size(&self) -> usize {
+        self.layouts
+            .iter()
+            .map(|&(count, ty, _, stride, offset, _)| {
+                if stride > 0 {
+                    stride as usize
+                } else {
+                    // A stride of 0 means the attributes are tightly packed
+                    offset + AttributeFormat::new(ty, count, false).size()
+                }
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// The type and component count of a vertex attribute as stored in a vertex buffer.
+///
+/// `Unorm` and `Snorm` formats are normalized to `[0, 1]` and `[-1, 1]` respectively, and
+/// every format is read as floats by the shader.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VertexFormat {
+    Float32,
+    Float32x2,
+    Float32x3,
+    Float32x4,
+    Float16x2,
+    Float16x4,
+    Unorm8x2,
+    Unorm8x4,
+    Snorm8x2,
+    Snorm8x4,
+    Unorm16x2,
+    Unorm16x4,
+    Snorm16x2,
+    Snorm16x4,
+    /// Four components of 10, 10, 10 and 2 bits packed into a `u32`,
+    /// `GL_UNSIGNED_INT_2_10_10_10_REV`
+    Unorm10_10_10_2,
+    /// Four signed components of 10, 10, 10 and 2 bits packed into a `u32`,
+    /// `GL_INT_2_10_10_10_REV`
+    Snorm10_10_10_2,
+}
+
+impl VertexFormat {
+    /// Returns how the attribute is passed to `glVertexAttribPointer`
+    pub fn attribute_format(self) -> AttributeFormat {
+        let (ty, components) = match self {
+            VertexFormat::Float32 => (gl::FLOAT, 1),
+            VertexFormat::Float32x2 => (gl::FLOAT, 2),
+            VertexFormat::Float32x3 => (gl::FLOAT, 3),
+            VertexFormat::Float32x4 => (gl::FLOAT, 4),
+            VertexFormat::Float16x2 => (gl::HALF_FLOAT, 2),
+            VertexFormat::Float16x4 => (gl::HALF_FLOAT, 4),
+            VertexFormat::Unorm8x2 => (gl::UNSIGNED_BYTE, 2),
+            VertexFormat::Unorm8x4 => (gl::UNSIGNED_BYTE, 4),
+            VertexFormat::Snorm8x2 => (gl::BYTE, 2),
+            VertexFormat::Snorm8x4 => (gl::BYTE, 4),
+            VertexFormat::Unorm16x2 => (gl::UNSIGNED_SHORT, 2),
+            VertexFormat::Unorm16x4 => (gl::UNSIGNED_SHORT, 4),
+            VertexFormat::Snorm16x2 => (gl::SHORT, 2),
+            VertexFormat::Snorm16x4 => (gl::SHORT, 4),
+            VertexFormat::Unorm10_10_10_2 => return AttributeFormat::UNORM_2_10_10_10_REV,
+            VertexFormat::Snorm10_10_10_2 => return AttributeFormat::SNORM_2_10_10_10_REV,
+        };
+        AttributeFormat::new(ty, components, !matches!(ty, gl::FLOAT | gl::HALF_FLOAT))
+    }
+
+    /// Returns the number of components
+    pub fn components(self) -> i32 {
+        self.attribute_format().components
+    }
+
+    /// Returns the OpenGL type of each component, or of the whole attribute for packed
+    /// formats
+    pub fn component_type(self) -> u32 {
+        self.attribute_format().ty
+    }
+
+    /// Returns whether integer components are normalized
+    pub fn normalized(self) -> bool {
+        self.attribute_format().normalized
+    }
+
+    /// Returns the number of bytes the attribute occupies
+    pub fn size(self) -> usize {
+        self.attribute_format().size()
+    }
+}
+
+impl From<VertexFormat> for AttributeFormat {
+    fn from(format: VertexFormat) -> Self {
+        format.attribute_format()
+    }
+}
+
+/// Builds a [`VertexAttributeSpec`] of attributes laid out one after another in the order
+/// they're added, assigned locations from `0`.
+///
+/// ```no_run
+/// use glium2::shader::{VertexAttributeSpec, VertexFormat};
+///
+/// // A position followed by a normalized RGBA8 color, 16 bytes per vertex
+/// let spec = VertexAttributeSpec::builder()
+///     .attribute(VertexFormat::Float32x3)
+///     .attribute(VertexFormat::Unorm8x4)
+///     .build();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct VertexAttributeBuilder {
+    /// `(format, offset, divisor)` for every attribute
+    attributes: Vec<(AttributeFormat, usize, u32)>,
+    stride: usize,
+}
+
+impl VertexAttributeBuilder {
+    /// Adds a per-vertex attribute after the previous one
+    pub fn attribute(self, format: impl Into<AttributeFormat>) -> Self {
+        self.instance_attribute(format, 0)
+    }
+
+    /// Adds a per-vertex attribute stored as the Rust type `T` after the previous one
+    pub fn attribute_of<T: AttributeType>(self) -> Self {
+        self.attribute(T::FORMAT)
+    }
+
+    /// Adds an attribute after the previous one that advances once every `divisor`
+    /// instances. See [`VertexAttributeSpec::push_instance_layout`].
+    pub fn instance_attribute(mut self, format: impl Into<AttributeFormat>, divisor: u32) -> Self {
+        let format = format.into();
+        self.attributes.push((format, self.stride, divisor));
+        self.stride += format.size();
+        self
+    }
+
+    /// Skips `bytes` bytes, e.g. padding between fields of a `#[repr(C)]` struct
+    pub fn padding(mut self, bytes: usize) -> Self {
+        self.stride += bytes;
+        self
+    }
+
+    /// Returns the number of bytes a vertex occupies so far
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Finishes the specification
+    pub fn build(self) -> VertexAttributeSpec {
+        let stride = self.stride as i32;
+        VertexAttributeSpec {
+            layouts: self
+                .attributes
+                .into_iter()
+                .map(|(format, offset, divisor)| {
+                    (
+                        format.components,
+                        format.ty,
+                        if format.normalized {
+                            gl::TRUE
+                        } else {
+                            gl::FALSE
+                        },
+                        stride,
+                        offset,
+                        divisor,
+                    )
+                })
+                .collect(),
+        }
+    }
 }
 
 /// A trait representing a single vertex usable in an OpenGL buffer
@@ -57,28 +269,17 @@ pub trait Vertex: Into<crate::buffer::VertexData> + Clone {
     fn get_vertex_spec() -> VertexAttributeSpec;
 }
 
-/// The linking state of a GLSL program
-pub enum ProgramState {
-    /// The program has not been linked
-    Unlinked,
-    /// The program has been linked
-    Linked,
-    /// An error occurred during the linking process
-    LinkerError(String),
-}
-
-/// A GLSL shader program
+/// An unlinked GLSL shader program.
+///
+/// Linking consumes the program and produces a [`LinkedProgram`], which is the only kind of
+/// program that can be used for drawing.
 pub struct Program {
     id: u32,
-    linked: ProgramState,
 }
 
 impl Default for Program {
     fn default() -> Self {
-        Self {
-            id: unsafe { gl::CreateProgram() },
-            linked: ProgramState::Unlinked,
-        }
+        Self::new()
     }
 }
 
@@ -87,24 +288,96 @@ impl Program {
     pub fn new() -> Self {
         Self {
             id: unsafe { gl::CreateProgram() },
-            linked: ProgramState::Unlinked,
         }
     }
 
     /// Attaches shaders and links program
     pub fn attach_and_link<S>(
-        &mut self,
+        self,
         shaders: Vec<Shader<S>>,
-    ) -> Result<(), ShaderCompilationError>
+    ) -> Result<LinkedProgram, ShaderCompilationError>
     where
         S: AsRef<str>,
     {
+        let has_stage = |stage| shaders.iter().any(|shader| shader.shader_type == stage);
+        let has_geometry = has_stage(ShaderType::Geometry);
+        let tessellated = has_stage(ShaderType::TessEvaluation);
         for ref mut shader in shaders {
             shader.compile()?;
             unsafe { gl::AttachShader(self.id, shader.id) };
         }
 
-        unsafe { gl::LinkProgram(self.id) };
+        unsafe {
+            // Lets the linked program be saved with `LinkedProgram::binary`
+            gl::ProgramParameteri(
+                self.id,
+                gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                gl::TRUE as i32,
+            );
+            gl::LinkProgram(self.id)
+        };
+        self.check_link_status()?;
+
+        // Linking checks that the geometry shader declares its layouts; draws check that the
+        // input matches the primitives
+        let geometry_input = has_geometry
+            .then(|| {
+                let mut input = 0;
+                unsafe { gl::GetProgramiv(self.id, gl::GEOMETRY_INPUT_TYPE, &mut input) };
+                GeometryInput::from_gl(input as u32)
+            })
+            .flatten();
+
+        let id = self.id;
+        // The linked program takes over ownership of the OpenGL object
+        std::mem::forget(self);
+
+        Ok(LinkedProgram::new(id, geometry_input, tessellated))
+    }
+
+    /// Links a program from a vertex and a fragment shader read from files with
+    /// [`Shader::from_file`]
+    pub fn from_files(
+        vertex: impl AsRef<Path>,
+        fragment: impl AsRef<Path>,
+    ) -> Result<LinkedProgram, ShaderFileError> {
+        let shaders = vec![
+            Shader::from_file(vertex, Some(ShaderType::Vertex))?,
+            Shader::from_file(fragment, Some(ShaderType::Fragment))?,
+        ];
+        Ok(Self::new().attach_and_link(shaders)?)
+    }
+
+    /// Loads a program from a binary saved with [`LinkedProgram::binary`], skipping
+    /// compilation and linking.
+    ///
+    /// # Returns
+    ///
+    /// An error if the driver rejects the binary, which happens whenever the driver or GPU
+    /// changed since it was saved. The program must then be linked from source again.
+    pub fn from_binary(binary: &ProgramBinary) -> Result<LinkedProgram, ShaderCompilationError> {
+        let program = Self::new();
+        unsafe {
+            gl::ProgramBinary(
+                program.id,
+                binary.format,
+                binary.data.as_ptr().cast(),
+                binary.data.len() as i32,
+            )
+        };
+        program.check_link_status()?;
+
+        let id = program.id;
+        std::mem::forget(program);
+        Ok(LinkedProgram::new(
+            id,
+            binary.geometry_input,
+            binary.tessellated,
+        ))
+    }
+
+    /// Returns the info log as an error if linking failed
+    fn check_link_status(&self) -> Result<(), ShaderCompilationError> {
         let mut link_status = 0;
         unsafe {
             gl::GetProgramiv(
@@ -113,39 +386,623 @@ impl Program {
                 std::ptr::addr_of_mut!(link_status),
             );
         };
+        if link_status == gl::TRUE as i32 {
+            return Ok(());
+        }
+
+        let mut info_log_length = 0;
+        unsafe {
+            gl::GetProgramiv(
+                self.id,
+                gl::INFO_LOG_LENGTH,
+                std::ptr::addr_of_mut!(info_log_length),
+            )
+        };
+        let mut buffer = Vec::with_capacity(info_log_length.max(0) as usize);
+        let mut bytes_written = 0;
+        unsafe {
+            gl::GetProgramInfoLog(
+                self.id,
+                info_log_length,
+                std::ptr::addr_of_mut!(bytes_written),
+                buffer.as_mut_ptr_range().start,
+            );
+            buffer.set_len(bytes_written as usize);
+        };
+
+        Err(ShaderCompilationError(
+            String::from_utf8(buffer.into_iter().map(|c| c as u8).collect::<Vec<_>>())
+                .expect("Info log is not a valid String"),
+        ))
+    }
+}
+
+/// A linked program in the driver's own binary format, see [`LinkedProgram::binary`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramBinary {
+    /// The driver-specific format of `data`
+    pub format: u32,
+    pub data: Vec<u8>,
+    /// The input of the program's geometry shader, which can't be queried from a binary
+    pub geometry_input: Option<GeometryInput>,
+    /// Whether the program has a tessellation evaluation shader
+    pub tessellated: bool,
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        // SAFETY: We are being dropped, so we can destroy the program we correspond with
+        unsafe { gl::DeleteProgram(self.id) };
+    }
+}
+
+/// Something that identifies a uniform of a [`LinkedProgram`], either by name or by location
+pub trait UniformLocation {
+    /// Resolves the location of the uniform in `program`
+    fn resolve(&self, program: &LinkedProgram) -> i32;
+}
+
+impl UniformLocation for i32 {
+    fn resolve(&self, _program: &LinkedProgram) -> i32 {
+        *self
+    }
+}
 
-        if link_status != gl::TRUE as i32 {
-            let mut info_log_length = 0;
+impl UniformLocation for &str {
+    fn resolve(&self, program: &LinkedProgram) -> i32 {
+        program.get_uniform_location(self)
+    }
+}
+
+impl UniformLocation for String {
+    fn resolve(&self, program: &LinkedProgram) -> i32 {
+        program.get_uniform_location(self)
+    }
+}
+
+/// An active uniform of a program, as reported by `glGetActiveUniform`. Members of uniform
+/// blocks aren't included, as they have no location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveUniform {
+    /// The name of the uniform. Arrays are named after their first element, e.g. `lights[0]`.
+    pub name: String,
+    /// The OpenGL type of the uniform, e.g. `GL_FLOAT_VEC3`
+    pub ty: u32,
+    /// The number of array elements, or `1` if the uniform isn't an array
+    pub size: i32,
+    /// The location of the uniform
+    pub location: i32,
+}
+
+impl ActiveUniform {
+    /// Returns the GLSL name of the uniform's type, e.g. `vec3`
+    pub fn type_name(&self) -> &'static str {
+        crate::types::glsl_type_name(self.ty)
+    }
+
+    /// Returns whether the uniform is declared as an array
+    pub fn is_array(&self) -> bool {
+        self.size > 1 || self.name.ends_with("[0]")
+    }
+}
+
+/// An active vertex attribute of a program, as reported by `glGetActiveAttrib`. Built-in
+/// inputs such as `gl_VertexID` aren't included, as they have no location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveAttribute {
+    /// The name of the attribute
+    pub name: String,
+    /// The OpenGL type of the attribute, e.g. `GL_FLOAT_VEC3`
+    pub ty: u32,
+    /// The number of array elements, or `1` if the attribute isn't an array
+    pub size: i32,
+    /// The first location of the attribute
+    pub location: i32,
+}
+
+impl ActiveAttribute {
+    /// Returns the GLSL name of the attribute's type, e.g. `vec3`
+    pub fn type_name(&self) -> &'static str {
+        crate::types::glsl_type_name(self.ty)
+    }
+
+    /// Returns the number of consecutive locations the attribute occupies, e.g. `4` for a
+    /// `mat4`
+    pub fn location_count(&self) -> u32 {
+        let columns = match self.ty {
+            gl::FLOAT_MAT2 | gl::FLOAT_MAT2x3 | gl::FLOAT_MAT2x4 => 2,
+            gl::DOUBLE_MAT2 | gl::DOUBLE_MAT2x3 | gl::DOUBLE_MAT2x4 => 2,
+            gl::FLOAT_MAT3 | gl::FLOAT_MAT3x2 | gl::FLOAT_MAT3x4 => 3,
+            gl::DOUBLE_MAT3 | gl::DOUBLE_MAT3x2 | gl::DOUBLE_MAT3x4 => 3,
+            gl::FLOAT_MAT4 | gl::FLOAT_MAT4x2 | gl::FLOAT_MAT4x3 => 4,
+            gl::DOUBLE_MAT4 | gl::DOUBLE_MAT4x2 | gl::DOUBLE_MAT4x3 => 4,
+            _ => 1,
+        };
+        columns * self.size.max(1) as u32
+    }
+
+    /// Returns whether the attribute reads floats, as every layout of a
+    /// [`VertexAttributeSpec`] provides them. Integer and double attributes would read
+    /// garbage.
+    pub fn reads_floats(&self) -> bool {
+        matches!(
+            self.ty,
+            gl::FLOAT
+                | gl::FLOAT_VEC2
+                | gl::FLOAT_VEC3
+                | gl::FLOAT_VEC4
+                | gl::FLOAT_MAT2
+                | gl::FLOAT_MAT2x3
+                | gl::FLOAT_MAT2x4
+                | gl::FLOAT_MAT3
+                | gl::FLOAT_MAT3x2
+                | gl::FLOAT_MAT3x4
+                | gl::FLOAT_MAT4
+                | gl::FLOAT_MAT4x2
+                | gl::FLOAT_MAT4x3
+        )
+    }
+}
+
+/// A value whose type doesn't match the type of the uniform it is uploaded to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformMismatch {
+    /// The name of the uniform
+    pub name: String,
+    /// The location of the uniform
+    pub location: i32,
+    /// The OpenGL type declared in the shader
+    pub expected: u32,
+    /// The OpenGL type of the supplied value
+    pub actual: u32,
+    /// The number of array elements declared in the shader
+    pub expected_len: usize,
+    /// The number of array elements supplied
+    pub actual_len: usize,
+}
+
+impl std::fmt::Display for UniformMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "uniform `{}` (location {}) is declared as {}[{}] but was given {}[{}]",
+            self.name,
+            self.location,
+            crate::types::glsl_type_name(self.expected),
+            self.expected_len,
+            crate::types::glsl_type_name(self.actual),
+            self.actual_len,
+        )
+    }
+}
+
+/// A successfully linked GLSL shader program, obtained from [`Program::attach_and_link`]
+pub struct LinkedProgram {
+    id: u32,
+    uniform_locations: RefCell<HashMap<String, i32>>,
+    active_uniforms: Vec<ActiveUniform>,
+    active_attributes: Vec<ActiveAttribute>,
+    geometry_input: Option<GeometryInput>,
+    tessellated: bool,
+    /// The subroutine selected for every subroutine uniform location of each stage, keyed by
+    /// the stage's shader type enum
+    subroutines: RefCell<HashMap<u32, Vec<u32>>>,
+}
+
+impl LinkedProgram {
+    fn new(id: u32, geometry_input: Option<GeometryInput>, tessellated: bool) -> Self {
+        let active_uniforms = Self::query_active_uniforms(id);
+        let uniform_locations = active_uniforms
+            .iter()
+            .flat_map(|uniform| {
+                // Arrays are reported as `name[0]`, but can also be looked up as `name`
+                let base = uniform.name.strip_suffix("[0]").map(str::to_owned);
+                std::iter::once((uniform.name.clone(), uniform.location))
+                    .chain(base.map(|base| (base, uniform.location)))
+            })
+            .collect();
+
+        Self {
+            id,
+            uniform_locations: RefCell::new(uniform_locations),
+            active_uniforms,
+            active_attributes: Self::query_active_attributes(id),
+            geometry_input,
+            tessellated,
+            subroutines: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the primitive type accepted by the program's geometry shader, or `None` if it
+    /// has none
+    pub fn geometry_input(&self) -> Option<GeometryInput> {
+        self.geometry_input
+    }
+
+    /// Returns whether the program has a tessellation evaluation shader, and so only draws
+    /// [`DrawMode::Patches`]
+    pub fn is_tessellated(&self) -> bool {
+        self.tessellated
+    }
+
+    /// Returns the program in the driver's binary format, which can be saved and loaded with
+    /// [`Program::from_binary`] to skip compilation on later runs, or `None` if the driver
+    /// doesn't support program binaries
+    pub fn binary(&self) -> Option<ProgramBinary> {
+        let mut length = 0;
+        unsafe { gl::GetProgramiv(self.id, gl::PROGRAM_BINARY_LENGTH, &mut length) };
+        if length <= 0 {
+            return None;
+        }
+
+        let mut data = vec![0u8; length as usize];
+        let mut written = 0;
+        let mut format = 0;
+        unsafe {
+            gl::GetProgramBinary(
+                self.id,
+                length,
+                &mut written,
+                &mut format,
+                data.as_mut_ptr().cast(),
+            )
+        };
+        data.truncate(written.max(0) as usize);
+
+        (!data.is_empty()).then_some(ProgramBinary {
+            format,
+            data,
+            geometry_input: self.geometry_input,
+            tessellated: self.tessellated,
+        })
+    }
+
+    fn query_active_uniforms(id: u32) -> Vec<ActiveUniform> {
+        let mut count = 0;
+        let mut max_length = 0;
+        unsafe {
+            gl::GetProgramiv(id, gl::ACTIVE_UNIFORMS, &mut count);
+            gl::GetProgramiv(id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+        };
+
+        (0..count as u32)
+            .filter_map(|index| {
+                let mut name = vec![0u8; max_length.max(1) as usize];
+                let mut length = 0;
+                let mut size = 0;
+                let mut ty = 0;
+                unsafe {
+                    gl::GetActiveUniform(
+                        id,
+                        index,
+                        max_length,
+                        &mut length,
+                        &mut size,
+                        &mut ty,
+                        name.as_mut_ptr().cast(),
+                    );
+                };
+                name.truncate(length as usize);
+                let name = String::from_utf8(name).ok()?;
+                let c_name = CString::new(name.as_str()).ok()?;
+                let location = unsafe { gl::GetUniformLocation(id, c_name.as_ptr()) };
+
+                // Members of uniform blocks don't have a location
+                (location >= 0).then_some(ActiveUniform {
+                    name,
+                    ty,
+                    size,
+                    location,
+                })
+            })
+            .collect()
+    }
+
+    fn query_active_attributes(id: u32) -> Vec<ActiveAttribute> {
+        let mut count = 0;
+        let mut max_length = 0;
+        unsafe {
+            gl::GetProgramiv(id, gl::ACTIVE_ATTRIBUTES, &mut count);
+            gl::GetProgramiv(id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_length);
+        };
+
+        let mut attributes = (0..count as u32)
+            .filter_map(|index| {
+                let mut name = vec![0u8; max_length.max(1) as usize];
+                let mut length = 0;
+                let mut size = 0;
+                let mut ty = 0;
+                unsafe {
+                    gl::GetActiveAttrib(
+                        id,
+                        index,
+                        max_length,
+                        &mut length,
+                        &mut size,
+                        &mut ty,
+                        name.as_mut_ptr().cast(),
+                    );
+                };
+                name.truncate(length as usize);
+                let name = String::from_utf8(name).ok()?;
+                let c_name = CString::new(name.as_str()).ok()?;
+                let location = unsafe { gl::GetAttribLocation(id, c_name.as_ptr()) };
+
+                (location >= 0).then_some(ActiveAttribute {
+                    name,
+                    ty,
+                    size,
+                    location,
+                })
+            })
+            .collect::<Vec<_>>();
+        attributes.sort_by_key(|attribute| attribute.location);
+        attributes
+    }
+
+    /// Returns every active vertex attribute of the program, sorted by location
+    pub fn active_attributes(&self) -> &[ActiveAttribute] {
+        &self.active_attributes
+    }
+
+    /// Checks that `spec` provides every attribute the program reads, in a type it can read.
+    ///
+    /// # Returns
+    ///
+    /// The first attribute that isn't provided or would read garbage.
+    pub fn check_vertex_layout(&self, spec: &VertexAttributeSpec) -> Result<(), DrawError> {
+        for attribute in &self.active_attributes {
+            let location = attribute.location as u32;
+            if !attribute.reads_floats() {
+                return Err(DrawError::AttributeTypeMismatch {
+                    location,
+                    ty: attribute.ty,
+                });
+            }
+            let end = location + attribute.location_count();
+            if end as usize > spec.layouts.len() {
+                return Err(DrawError::MissingAttribute {
+                    location,
+                    ty: attribute.ty,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns every active uniform of the program outside of uniform blocks, in the order the
+    /// driver reports them
+    pub fn active_uniforms(&self) -> &[ActiveUniform] {
+        &self.active_uniforms
+    }
+
+    /// Returns the active uniform at `location`, if any
+    pub(crate) fn active_uniform_at(&self, location: i32) -> Option<&ActiveUniform> {
+        self.active_uniforms
+            .iter()
+            .find(|uniform| uniform.location == location)
+    }
+
+    /// Checks that the type of `value` matches the active uniform at `location`
+    pub(crate) fn check_uniform(
+        &self,
+        location: i32,
+        value: &dyn Uniform,
+    ) -> Option<UniformMismatch> {
+        let actual = value.gl_type()?;
+        let uniform = self.active_uniform_at(location)?;
+        let expected_len = uniform.size.max(1) as usize;
+        let actual_len = value.array_len();
+        (!uniforms::is_compatible(actual, uniform.ty) || actual_len > expected_len).then(|| {
+            UniformMismatch {
+                name: uniform.name.clone(),
+                location,
+                expected: uniform.ty,
+                actual,
+                expected_len,
+                actual_len,
+            }
+        })
+    }
+
+    /// Cross-checks every value in `uniforms` against the types of the program's active
+    /// uniforms.
+    ///
+    /// Values whose type can't be determined, and locations that aren't active, are skipped.
+    pub fn validate_uniforms(&self, uniforms: &Uniforms) -> Result<(), Vec<UniformMismatch>> {
+        let mismatches = uniforms
+            .data
+            .iter()
+            .filter_map(|(location, value)| self.check_uniform(*location, value))
+            .collect::<Vec<_>>();
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+
+    /// Returns the index of the subroutine called `name` in `stage`, or `None` if it isn't
+    /// active
+    pub fn subroutine_index(&self, stage: ShaderType, name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        let index = unsafe { gl::GetSubroutineIndex(self.id, stage.gl_stage(), name.as_ptr()) };
+        (index != gl::INVALID_INDEX).then_some(index)
+    }
+
+    /// Returns the location of the subroutine uniform called `name` in `stage`, or `None` if
+    /// it isn't active
+    pub fn subroutine_uniform_location(&self, stage: ShaderType, name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        let location =
+            unsafe { gl::GetSubroutineUniformLocation(self.id, stage.gl_stage(), name.as_ptr()) };
+        u32::try_from(location).ok()
+    }
+
+    /// Makes the subroutine uniform called `uniform` in `stage` call the subroutine called
+    /// `subroutine`, e.g. to switch the lighting model of an uber-shader without switching
+    /// programs.
+    ///
+    /// OpenGL forgets the selected subroutines whenever a program is bound, so the selection
+    /// is stored in the program and applied every time it is bound for a draw or dispatch.
+    /// Until set, each subroutine uniform calls the first subroutine compatible with it.
+    ///
+    /// # Returns
+    ///
+    /// Whether both the uniform and the subroutine are active in `stage`.
+    pub fn set_subroutine(&self, stage: ShaderType, uniform: &str, subroutine: &str) -> bool {
+        let (Some(location), Some(index)) = (
+            self.subroutine_uniform_location(stage, uniform),
+            self.subroutine_index(stage, subroutine),
+        ) else {
+            return false;
+        };
+
+        let mut subroutines = self.subroutines.borrow_mut();
+        let selection = subroutines
+            .entry(stage.gl_stage())
+            .or_insert_with(|| self.default_subroutines(stage));
+        match selection.get_mut(location as usize) {
+            Some(selected) => {
+                *selected = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Selects the subroutine of every subroutine uniform location in `stage` at once, by
+    /// index. See [`LinkedProgram::set_subroutine`].
+    ///
+    /// # Panics
+    /// This function panics if `indices` doesn't hold exactly one index per active
+    /// subroutine uniform location of `stage`.
+    pub fn set_subroutines(&self, stage: ShaderType, indices: &[u32]) {
+        let locations = self.subroutine_uniform_locations(stage);
+        assert_eq!(
+            indices.len(),
+            locations,
+            "The {stage:?} stage has {locations} subroutine uniform locations, but {} \
+             subroutines were given",
+            indices.len()
+        );
+        self.subroutines
+            .borrow_mut()
+            .insert(stage.gl_stage(), indices.to_vec());
+    }
+
+    /// Returns the number of subroutine uniform locations `stage` has, counting every element
+    /// of subroutine uniform arrays
+    pub fn subroutine_uniform_locations(&self, stage: ShaderType) -> usize {
+        let mut locations = 0;
+        unsafe {
+            gl::GetProgramStageiv(
+                self.id,
+                stage.gl_stage(),
+                gl::ACTIVE_SUBROUTINE_UNIFORM_LOCATIONS,
+                &mut locations,
+            )
+        };
+        locations.max(0) as usize
+    }
+
+    /// Returns the first subroutine compatible with each subroutine uniform location of
+    /// `stage`
+    fn default_subroutines(&self, stage: ShaderType) -> Vec<u32> {
+        let stage_type = stage.gl_stage();
+        let mut selection = vec![0; self.subroutine_uniform_locations(stage)];
+        let mut uniforms = 0;
+        unsafe {
+            gl::GetProgramStageiv(
+                self.id,
+                stage_type,
+                gl::ACTIVE_SUBROUTINE_UNIFORMS,
+                &mut uniforms,
+            )
+        };
+
+        for uniform in 0..uniforms.max(0) as u32 {
+            let (mut compatible_count, mut size) = (0, 0);
             unsafe {
-                gl::GetProgramiv(
+                gl::GetActiveSubroutineUniformiv(
+                    self.id,
+                    stage_type,
+                    uniform,
+                    gl::NUM_COMPATIBLE_SUBROUTINES,
+                    &mut compatible_count,
+                );
+                gl::GetActiveSubroutineUniformiv(
                     self.id,
-                    gl::INFO_LOG_LENGTH,
-                    std::ptr::addr_of_mut!(info_log_length),
+                    stage_type,
+                    uniform,
+                    gl::UNIFORM_SIZE,
+                    &mut size,
+                );
+            };
+            if compatible_count <= 0 {
+                continue;
+            }
+            let mut compatible = vec![0; compatible_count as usize];
+            unsafe {
+                gl::GetActiveSubroutineUniformiv(
+                    self.id,
+                    stage_type,
+                    uniform,
+                    gl::COMPATIBLE_SUBROUTINES,
+                    compatible.as_mut_ptr(),
                 )
             };
-            let mut buffer = Vec::with_capacity(info_log_length as usize);
-            let mut bytes_written = 0;
+
+            let mut name = [0 as c_char; 256];
+            let mut length = 0;
             unsafe {
-                gl::GetProgramInfoLog(
+                gl::GetActiveSubroutineUniformName(
                     self.id,
-                    info_log_length,
-                    std::ptr::addr_of_mut!(bytes_written),
-                    buffer.as_mut_ptr_range().start,
-                );
-                buffer.set_len(bytes_written as usize);
+                    stage_type,
+                    uniform,
+                    name.len() as i32,
+                    &mut length,
+                    name.as_mut_ptr(),
+                )
+            };
+            let location =
+                unsafe { gl::GetSubroutineUniformLocation(self.id, stage_type, name.as_ptr()) };
+            let Ok(location) = usize::try_from(location) else {
+                continue;
             };
 
-            let error = ShaderCompilationError(
-                String::from_utf8(buffer.into_iter().map(|c| c as u8).collect::<Vec<_>>())
-                    .expect("Info log is not a valid String"),
-            );
+            for element in location..location + size.max(1) as usize {
+                if let Some(selected) = selection.get_mut(element) {
+                    *selected = compatible[0] as u32;
+                }
+            }
+        }
+        selection
+    }
 
-            self.linked = ProgramState::LinkerError(error.0.clone());
-            return Err(error);
+    /// Applies the subroutines selected with [`LinkedProgram::set_subroutine`]. The program
+    /// must be bound.
+    pub(crate) fn apply_subroutines(&self) {
+        for (stage, indices) in self.subroutines.borrow().iter() {
+            unsafe { gl::UniformSubroutinesuiv(*stage, indices.len() as i32, indices.as_ptr()) };
         }
+    }
 
-        self.linked = ProgramState::Linked;
-        Ok(())
+    /// Logs every uniform type mismatch in debug builds
+    pub(crate) fn report_uniform_mismatches(&self, uniforms: &Uniforms) {
+        if cfg!(debug_assertions) {
+            if let Err(mismatches) = self.validate_uniforms(uniforms) {
+                mismatches.iter().for_each(|mismatch| {
+                    diagnostic!(Error, Shader, "Uniform type mismatch: {mismatch}")
+                });
+            }
+        }
+    }
+
+    /// Returns the OpenGL name of the program
+    pub(crate) fn id(&self) -> u32 {
+        self.id
     }
 
     /// Sets `self` as the currently active program to be used for drawing.
@@ -153,22 +1010,164 @@ impl Program {
         unsafe { gl::UseProgram(self.id) };
     }
 
+    /// Makes `self` the active program until the returned guard is dropped, after which the
+    /// previously active program is restored
+    pub fn bind_scoped(&self) -> BindGuard<'_> {
+        BindGuard::new(BindTarget::Program, self.id)
+    }
+
+    /// Returns the location of the uniform called `name`, or `-1` if it isn't active.
+    ///
+    /// Locations are cached after the first lookup.
     pub fn get_uniform_location(&self, name: &str) -> i32 {
+        if let Some(location) = self.uniform_locations.borrow().get(name) {
+            return *location;
+        }
+
         let name_bytes = name
             .as_bytes()
             .bytes()
             .map(|b| b.unwrap() as i8)
             .chain(vec![0])
             .collect::<Vec<_>>();
-        unsafe { gl::GetUniformLocation(self.id, name_bytes.as_ptr_range().start.cast()) }
+        let location =
+            unsafe { gl::GetUniformLocation(self.id, name_bytes.as_ptr_range().start.cast()) };
+        self.uniform_locations
+            .borrow_mut()
+            .insert(name.to_owned(), location);
+        location
+    }
+
+    /// Sets a single uniform of this program, identified by name or location.
+    ///
+    /// Uses `glProgramUniform*` where available, so the program doesn't have to be bound.
+    /// Otherwise the program is bound for the duration of the upload.
+    pub fn set_uniform<L, U>(&self, location: L, value: U)
+    where
+        L: UniformLocation,
+        U: Uniform,
+    {
+        let location = location.resolve(self);
+        if location < 0 {
+            diagnostic!(
+                Debug,
+                Shader,
+                "Ignoring value {value:?} for inactive uniform"
+            );
+            return;
+        }
+
+        if cfg!(debug_assertions) {
+            if let Some(mismatch) = self.check_uniform(location, &value) {
+                diagnostic!(Error, Shader, "Uniform type mismatch: {mismatch}");
+            }
+        }
+
+        uniforms::upload_to(self.id, location, &value);
+    }
+
+    /// Resolves the uniform called `name` once, returning a handle that only accepts values
+    /// of type `T`.
+    ///
+    /// The handle must not be used after the program has been dropped.
+    pub fn uniform<T>(&self, name: &str) -> UniformHandle<T>
+    where
+        T: Uniform,
+    {
+        UniformHandle::new(self.id, self.get_uniform_location(name))
+    }
+
+    /// Returns the index of the uniform block called `name`, or `None` if it isn't active
+    pub fn uniform_block_index(&self, name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        let index = unsafe { gl::GetUniformBlockIndex(self.id, name.as_ptr()) };
+        (index != gl::INVALID_INDEX).then_some(index)
+    }
+
+    /// Returns the size in bytes the program expects for the uniform block called `name`, or
+    /// `None` if it isn't active
+    pub fn uniform_block_size(&self, name: &str) -> Option<usize> {
+        let index = self.uniform_block_index(name)?;
+        let mut size = 0;
+        unsafe {
+            gl::GetActiveUniformBlockiv(self.id, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size)
+        };
+        usize::try_from(size).ok()
+    }
+
+    /// Makes the uniform block called `name` read from the uniform buffer bound to binding
+    /// point `binding`, e.g. with [`crate::buffer::UniformBuffer::bind_to`].
+    ///
+    /// # Returns
+    ///
+    /// Whether the block is active in the program.
+    pub fn bind_uniform_block(&self, name: &str, binding: u32) -> bool {
+        match self.uniform_block_index(name) {
+            Some(index) => {
+                unsafe { gl::UniformBlockBinding(self.id, index, binding) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the binding point the uniform block called `name` reads from, or `None` if it
+    /// isn't active
+    pub fn uniform_block_binding(&self, name: &str) -> Option<u32> {
+        let index = self.uniform_block_index(name)?;
+        let mut binding = 0;
+        unsafe {
+            gl::GetActiveUniformBlockiv(self.id, index, gl::UNIFORM_BLOCK_BINDING, &mut binding)
+        };
+        u32::try_from(binding).ok()
+    }
+
+    /// Binds every uniform block named in `bindings` to its binding point, e.g. chained
+    /// directly after [`Program::attach_and_link`].
+    ///
+    /// Blocks that aren't active in the program are skipped. See
+    /// [`LinkedProgram::bind_uniform_block`].
+    pub fn with_uniform_blocks(self, bindings: &[(&str, u32)]) -> Self {
+        for &(name, binding) in bindings {
+            if !self.bind_uniform_block(name, binding) {
+                diagnostic!(
+                    Debug,
+                    Shader,
+                    "Skipping binding of inactive uniform block {name:?}"
+                );
+            }
+        }
+        self
     }
 
-    pub fn state(&self) -> &ProgramState {
-        &self.linked
+    /// Returns the index of the shader storage block called `name`, or `None` if it isn't
+    /// active
+    pub fn storage_block_index(&self, name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        let index = unsafe {
+            gl::GetProgramResourceIndex(self.id, gl::SHADER_STORAGE_BLOCK, name.as_ptr())
+        };
+        (index != gl::INVALID_INDEX).then_some(index)
+    }
+
+    /// Makes the shader storage block called `name` access the storage buffer bound to
+    /// binding point `binding`, e.g. with [`crate::buffer::ShaderStorageBuffer::bind_to`].
+    ///
+    /// # Returns
+    ///
+    /// Whether the block is active in the program.
+    pub fn bind_storage_block(&self, name: &str, binding: u32) -> bool {
+        match self.storage_block_index(name) {
+            Some(index) => {
+                unsafe { gl::ShaderStorageBlockBinding(self.id, index, binding) };
+                true
+            }
+            None => false,
+        }
     }
 }
 
-impl Drop for Program {
+impl Drop for LinkedProgram {
     fn drop(&mut self) {
         // SAFETY: We are being dropped, so we can destroy the program we correspond with
         unsafe { gl::DeleteProgram(self.id) };
@@ -195,17 +1194,243 @@ where
 #[derive(Debug, Clone)]
 pub struct ShaderCompilationError(String);
 
+impl std::fmt::Display for ShaderCompilationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ShaderCompilationError {}
+
+/// An error loading a shader with [`Shader::from_file`] or [`Program::from_files`]
+#[derive(Debug, Clone)]
+pub enum ShaderFileError {
+    /// The file couldn't be read
+    Io { path: PathBuf, error: String },
+    /// No stage was given and none could be inferred from the file's extension
+    UnknownStage(PathBuf),
+    /// An `#include` in the file couldn't be expanded
+    Include(IncludeError),
+    /// A shader failed to compile or the program failed to link
+    Compilation(ShaderCompilationError),
+}
+
+impl std::fmt::Display for ShaderFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "Failed to read {}: {error}", path.display()),
+            Self::UnknownStage(path) => {
+                write!(f, "Cannot infer the shader stage of {}", path.display())
+            }
+            Self::Include(error) => write!(f, "{error}"),
+            Self::Compilation(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for ShaderFileError {}
+
+impl From<IncludeError> for ShaderFileError {
+    fn from(error: IncludeError) -> Self {
+        ShaderFileError::Include(error)
+    }
+}
+
+impl From<ShaderCompilationError> for ShaderFileError {
+    fn from(error: ShaderCompilationError) -> Self {
+        ShaderFileError::Compilation(error)
+    }
+}
+
+/// A value of a SPIR-V specialization constant, stored as the 32 bits OpenGL expects
+pub trait SpecializationValue {
+    /// Returns the bits of the value
+    fn to_bits(self) -> u32;
+}
+
+impl SpecializationValue for bool {
+    fn to_bits(self) -> u32 {
+        self as u32
+    }
+}
+
+impl SpecializationValue for u32 {
+    fn to_bits(self) -> u32 {
+        self
+    }
+}
+
+impl SpecializationValue for i32 {
+    fn to_bits(self) -> u32 {
+        self as u32
+    }
+}
+
+impl SpecializationValue for f32 {
+    fn to_bits(self) -> u32 {
+        f32::to_bits(self)
+    }
+}
+
+/// The values of SPIR-V specialization constants, declared in GLSL with
+/// `layout(constant_id = N) const`. Constants that aren't set keep their default values.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SpecializationConstants {
+    /// `(constant_id, bits)` for every constant
+    values: Vec<(u32, u32)>,
+}
+
+impl SpecializationConstants {
+    /// Constructs an empty set of constants
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets constant `constant_id` to `value`, replacing any earlier value
+    pub fn with(mut self, constant_id: u32, value: impl SpecializationValue) -> Self {
+        self.set(constant_id, value);
+        self
+    }
+
+    /// Sets constant `constant_id` to `value`, replacing any earlier value
+    pub fn set(&mut self, constant_id: u32, value: impl SpecializationValue) {
+        let bits = value.to_bits();
+        match self.values.iter_mut().find(|(id, _)| *id == constant_id) {
+            Some((_, existing)) => *existing = bits,
+            None => self.values.push((constant_id, bits)),
+        }
+    }
+}
+
+/// An error that occurred while loading a SPIR-V shader with [`Shader::from_spirv`]
+#[derive(Debug, Clone)]
+pub enum SpirvError {
+    /// The context can't load SPIR-V modules
+    Unsupported(Unsupported),
+    /// The module isn't a whole number of little-endian SPIR-V words
+    InvalidModule,
+    /// The entry point name contains a nul byte
+    InvalidEntryPoint,
+    /// Specialization failed, e.g. because the entry point doesn't exist
+    Specialization(ShaderCompilationError),
+}
+
+impl std::fmt::Display for SpirvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpirvError::Unsupported(error) => write!(f, "{error}"),
+            SpirvError::InvalidModule => write!(f, "The data is not a valid SPIR-V module"),
+            SpirvError::InvalidEntryPoint => {
+                write!(f, "The entry point name contains a nul byte")
+            }
+            SpirvError::Specialization(error) => {
+                write!(f, "Failed to specialize SPIR-V shader: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpirvError {}
+
+impl From<Unsupported> for SpirvError {
+    fn from(error: Unsupported) -> Self {
+        SpirvError::Unsupported(error)
+    }
+}
+
 pub struct Shader<S>
 where
     S: AsRef<str>,
 {
     id: u32,
+    shader_type: ShaderType,
     state: ShaderState<S>,
+    /// The files the source was assembled from by [`Shader::with_includes`]
+    files: Vec<String>,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ShaderType {
     Vertex,
+    /// Runs once per patch drawn with [`DrawMode::Patches`], deciding how finely it's
+    /// tessellated. Optional; the default tessellation levels are used without one.
+    TessControl,
+    /// Runs once per vertex generated by the tessellator, positioning it within the patch,
+    /// e.g. to displace terrain. Required to draw [`DrawMode::Patches`].
+    TessEvaluation,
+    /// Runs once per primitive between the vertex and fragment stages, and may emit any
+    /// number of new primitives. See [`GeometryInput`].
+    Geometry,
     Fragment,
+    Compute,
+}
+
+impl ShaderType {
+    /// Returns the OpenGL shader type enum of the stage, e.g. `GL_VERTEX_SHADER`
+    pub(crate) fn gl_stage(self) -> u32 {
+        match self {
+            ShaderType::Fragment => gl::FRAGMENT_SHADER,
+            ShaderType::Vertex => gl::VERTEX_SHADER,
+            ShaderType::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderType::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderType::Geometry => gl::GEOMETRY_SHADER,
+            ShaderType::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+
+    /// Returns the stage conventionally stored in files with extension `extension`, as used
+    /// by glslang: `vert`, `tesc`, `tese`, `geom`, `frag` and `comp`
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "vert" => Some(Self::Vertex),
+            "tesc" => Some(Self::TessControl),
+            "tese" => Some(Self::TessEvaluation),
+            "geom" => Some(Self::Geometry),
+            "frag" => Some(Self::Fragment),
+            "comp" => Some(Self::Compute),
+            _ => None,
+        }
+    }
+}
+
+/// The primitive type a geometry shader accepts, declared with `layout(<type>) in;`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GeometryInput {
+    Points,
+    Lines,
+    LinesAdjacency,
+    Triangles,
+    TrianglesAdjacency,
+}
+
+impl GeometryInput {
+    fn from_gl(input: u32) -> Option<Self> {
+        match input {
+            gl::POINTS => Some(Self::Points),
+            gl::LINES => Some(Self::Lines),
+            gl::LINES_ADJACENCY => Some(Self::LinesAdjacency),
+            gl::TRIANGLES => Some(Self::Triangles),
+            gl::TRIANGLES_ADJACENCY => Some(Self::TrianglesAdjacency),
+            _ => None,
+        }
+    }
+
+    /// Returns whether primitives drawn with `mode` reach the geometry shader as this type.
+    /// Patches are always accepted, as their type is decided by the tessellation stages.
+    pub fn accepts(self, mode: DrawMode) -> bool {
+        match mode {
+            DrawMode::Points => self == Self::Points,
+            DrawMode::Lines | DrawMode::LineStrip | DrawMode::LineLoop => self == Self::Lines,
+            DrawMode::LinesAdjacency | DrawMode::LineStripAdjacency => self == Self::LinesAdjacency,
+            DrawMode::Triangles | DrawMode::TriangleStrip | DrawMode::TriangleFan => {
+                self == Self::Triangles
+            }
+            DrawMode::TrianglesAdjacency | DrawMode::TriangleStripAdjacency => {
+                self == Self::TrianglesAdjacency
+            }
+            DrawMode::Patches { .. } => true,
+        }
+    }
 }
 
 impl<S> Shader<S>
@@ -213,16 +1438,86 @@ where
     S: AsRef<str>,
 {
     pub fn new(source: S, shader_type: ShaderType) -> Self {
-        let id = unsafe {
-            gl::CreateShader(match shader_type {
-                ShaderType::Fragment => gl::FRAGMENT_SHADER,
-                ShaderType::Vertex => gl::VERTEX_SHADER,
-            })
-        };
-
         Self {
-            id,
+            id: create_shader(shader_type),
+            shader_type,
             state: ShaderState::Uncompiled(source),
+            files: Vec::new(),
+        }
+    }
+
+    /// Loads the entry point `entry_point` of a precompiled SPIR-V `module`, e.g. produced by
+    /// glslang or shaderc, with the specialization constants in `constants`.
+    ///
+    /// The shader is specialized right away, so it is ready to be linked with shaders of
+    /// either kind.
+    pub fn from_spirv(
+        module: &[u8],
+        shader_type: ShaderType,
+        entry_point: &str,
+        constants: &SpecializationConstants,
+    ) -> Result<Self, SpirvError> {
+        /// The first word of every SPIR-V module
+        const MAGIC: u32 = 0x0723_0203;
+
+        if !context::supports_spirv() {
+            return Err(Unsupported {
+                feature: "GL_ARB_gl_spirv",
+            }
+            .into());
+        }
+        let magic = module
+            .get(..4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+        if !module.len().is_multiple_of(4) || magic != Some(MAGIC) {
+            return Err(SpirvError::InvalidModule);
+        }
+        let entry_point = CString::new(entry_point).map_err(|_| SpirvError::InvalidEntryPoint)?;
+
+        // Owning the shader right away deletes it again if specialization fails
+        let shader = Self {
+            id: create_shader(shader_type),
+            shader_type,
+            state: ShaderState::Compiled,
+            files: Vec::new(),
+        };
+        let (indices, values): (Vec<u32>, Vec<u32>) = constants.values.iter().copied().unzip();
+        unsafe {
+            gl::ShaderBinary(
+                1,
+                &shader.id,
+                ext::SHADER_BINARY_FORMAT_SPIR_V,
+                module.as_ptr().cast(),
+                module.len() as i32,
+            );
+            ext::specialize_shader(
+                shader.id,
+                entry_point.as_ptr(),
+                indices.len() as u32,
+                indices.as_ptr(),
+                values.as_ptr(),
+            );
+        };
+
+        let mut compile_status = 0;
+        unsafe { gl::GetShaderiv(shader.id, gl::COMPILE_STATUS, &mut compile_status) };
+        if compile_status == gl::FALSE as i32 {
+            return Err(SpirvError::Specialization(info_log(shader.id)));
+        }
+        Ok(shader)
+    }
+
+    /// Returns the shader's stage
+    pub fn shader_type(&self) -> ShaderType {
+        self.shader_type
+    }
+
+    /// Returns the GLSL source of the shader, or `None` if it was already compiled or
+    /// loaded from SPIR-V
+    pub fn source(&self) -> Option<&str> {
+        match self.state {
+            ShaderState::Uncompiled(ref source) => Some(source.as_ref()),
+            _ => None,
         }
     }
 
@@ -231,54 +1526,8 @@ where
             ShaderState::Compiled => Ok(()),
             ShaderState::CompilationError(ref err) => Err(err.clone()),
             ShaderState::Uncompiled(ref source) => {
-                let len_ptr = source.as_ref().len() as i32;
-                let source_cstring = CString::new(source.as_ref().as_bytes())
-                    .expect("Source code is not a valid CString");
-                let source_ptr: *const *const c_char = &source_cstring.as_ptr();
-
-                // SAFETY: source_ptr and len_ptr are both valid pointers at this time.
-                // OpenGL should not write to these, so it doesn't matter that the underlying
-                // references are shared.
-                unsafe { gl::ShaderSource(self.id, 1, source_ptr, std::ptr::addr_of!(len_ptr)) };
-                let mut compile_status = 0;
-
-                // SAFETY: compile_status has not been dropped yet, and this function call won't
-                // outlive it.
-                unsafe {
-                    gl::GetShaderiv(
-                        self.id,
-                        gl::COMPILE_STATUS,
-                        std::ptr::addr_of_mut!(compile_status),
-                    )
-                }
-                if compile_status != gl::FALSE as i32 {
-                    let mut info_log_length = 0;
-                    unsafe {
-                        gl::GetShaderiv(
-                            self.id,
-                            gl::INFO_LOG_LENGTH,
-                            std::ptr::addr_of_mut!(info_log_length),
-                        )
-                    };
-
-                    let mut bytes_written = 0;
-                    let mut log_buffer = vec![0i8; info_log_length as usize];
-                    unsafe {
-                        gl::GetShaderInfoLog(
-                            self.id,
-                            info_log_length,
-                            std::ptr::addr_of_mut!(bytes_written),
-                            log_buffer.as_mut_ptr_range().start,
-                        );
-                    };
-
-                    let error = ShaderCompilationError(
-                        String::from_utf8(
-                            log_buffer.into_iter().map(|c| c as u8).collect::<Vec<_>>(),
-                        )
-                        .expect("Shader info log is not a valid String"),
-                    );
-
+                if let Err(mut error) = compile_source(self.id, source.as_ref()) {
+                    error.0 = name_source_strings(error.0, &self.files);
                     self.state = ShaderState::CompilationError(error.clone());
                     return Err(error);
                 }
@@ -290,6 +1539,184 @@ where
     }
 }
 
+impl Shader<String> {
+    /// Creates a shader from `source`, the contents of the file `name`, after expanding its
+    /// `#include "file"` directives with `resolver`. See [`include::expand_includes`].
+    ///
+    /// Compilation errors name the file, or list the file behind each source string number in
+    /// the log if others were included.
+    pub fn with_includes<R>(
+        source: &str,
+        name: &str,
+        shader_type: ShaderType,
+        resolver: &R,
+    ) -> Result<Self, IncludeError>
+    where
+        R: IncludeResolver + ?Sized,
+    {
+        let expanded = include::expand_includes(source, name, resolver)?;
+        let mut shader = Self::new(expanded.source, shader_type);
+        shader.files = expanded.files;
+        Ok(shader)
+    }
+
+    /// Reads a shader from the file at `path`, expanding `#include` directives relative to
+    /// its directory. Without a `shader_type`, the stage is inferred from the extension with
+    /// [`ShaderType::from_extension`].
+    ///
+    /// Compilation errors name the file.
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        shader_type: Option<ShaderType>,
+    ) -> Result<Self, ShaderFileError> {
+        let path = path.as_ref();
+        let shader_type = shader_type
+            .or_else(|| {
+                path.extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(ShaderType::from_extension)
+            })
+            .ok_or_else(|| ShaderFileError::UnknownStage(path.to_owned()))?;
+        let source = std::fs::read_to_string(path).map_err(|error| ShaderFileError::Io {
+            path: path.to_owned(),
+            error: error.to_string(),
+        })?;
+
+        let directory = IncludeDirectory::new(path.parent().unwrap_or(Path::new("")));
+        let shader = Self::with_includes(
+            &source,
+            &path.display().to_string(),
+            shader_type,
+            &directory,
+        )?;
+        Ok(shader)
+    }
+
+    /// Creates a shader from `source` with a `#define NAME VALUE` line for each pair in
+    /// `defines` inserted after its `#version` directive, so that permutations of one source
+    /// such as lit and unlit variants can be compiled. Line numbers in errors are unaffected.
+    ///
+    /// An empty value defines the name without one, for `#ifdef` checks.
+    pub fn with_defines(source: &str, shader_type: ShaderType, defines: &[(&str, &str)]) -> Self {
+        let defines = defines
+            .iter()
+            .fold(String::new(), |mut lines, (name, value)| {
+                let _ = writeln!(lines, "#define {name} {value}");
+                lines
+            });
+        Self::new(inject_defines(source, &defines), shader_type)
+    }
+}
+
+/// Compiles `source` as a throwaway shader of `shader_type`, returning the info log if it
+/// fails to compile
+pub(crate) fn check_compiles(
+    source: &str,
+    shader_type: ShaderType,
+) -> Result<(), ShaderCompilationError> {
+    let id = create_shader(shader_type);
+    let result = compile_source(id, source);
+    unsafe { gl::DeleteShader(id) };
+    result
+}
+
+/// Compiles `source` into shader `id`, with the limits of the current context defined (see
+/// [`Limits::defines`]).
+///
+/// # Returns
+///
+/// The info log if the source doesn't compile.
+fn compile_source(id: u32, source: &str) -> Result<(), ShaderCompilationError> {
+    let source = inject_defines(source, &Limits::current().defines());
+    let source = CString::new(source).map_err(|_| {
+        ShaderCompilationError("Shader source contains a null character".to_owned())
+    })?;
+
+    let mut compile_status = 0;
+    // SAFETY: the source outlives the calls, and OpenGL reads it up to its null terminator
+    unsafe {
+        gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
+        gl::CompileShader(id);
+        gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut compile_status);
+    }
+    if compile_status == gl::FALSE as i32 {
+        Err(info_log(id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Attributes the info `log` of a shader assembled from `files` to them: a single file
+/// prefixes the log, while several are listed by the source string number the log refers to
+/// them with
+fn name_source_strings(mut log: String, files: &[String]) -> String {
+    match files {
+        [] => log,
+        [file] => format!("{file}: {log}"),
+        files => {
+            log.push_str("\nSource strings:");
+            for (number, file) in files.iter().enumerate() {
+                let _ = write!(log, "\n  {number}: {file}");
+            }
+            log
+        }
+    }
+}
+
+fn create_shader(shader_type: ShaderType) -> u32 {
+    unsafe { gl::CreateShader(shader_type.gl_stage()) }
+}
+
+/// Reads the info log of shader `id` into an error
+fn info_log(id: u32) -> ShaderCompilationError {
+    let mut info_log_length = 0;
+    unsafe {
+        gl::GetShaderiv(
+            id,
+            gl::INFO_LOG_LENGTH,
+            std::ptr::addr_of_mut!(info_log_length),
+        )
+    };
+
+    let mut bytes_written = 0;
+    let mut log_buffer = vec![0i8; info_log_length.max(0) as usize];
+    unsafe {
+        gl::GetShaderInfoLog(
+            id,
+            info_log_length,
+            std::ptr::addr_of_mut!(bytes_written),
+            log_buffer.as_mut_ptr_range().start,
+        );
+    };
+    log_buffer.truncate(bytes_written.max(0) as usize);
+
+    ShaderCompilationError(
+        String::from_utf8(log_buffer.into_iter().map(|c| c as u8).collect::<Vec<_>>())
+            .expect("Shader info log is not a valid String"),
+    )
+}
+
+/// Inserts `defines` right after the `#version` directive of `source`, or before everything
+/// if it has none, followed by a `#line` directive so that errors report the original line
+/// numbers
+fn inject_defines(source: &str, defines: &str) -> String {
+    let mut offset = 0;
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        offset += line.len();
+        if line.trim_start().starts_with("#version") {
+            let separator = if line.ends_with('\n') { "" } else { "\n" };
+            return format!(
+                "{}{separator}{defines}#line {}\n{}",
+                &source[..offset],
+                index + 2,
+                &source[offset..]
+            );
+        }
+    }
+
+    format!("{defines}#line 1\n{source}")
+}
+
 impl<S> Drop for Shader<S>
 where
     S: AsRef<str>,
@@ -299,3 +1726,34 @@ where
         unsafe { gl::DeleteShader(self.id) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_files_prefix_the_log() {
+        assert_eq!(
+            name_source_strings("0(3) : error C0000: syntax error".to_owned(), &[]),
+            "0(3) : error C0000: syntax error"
+        );
+        assert_eq!(
+            name_source_strings(
+                "0(3) : error C0000: syntax error".to_owned(),
+                &["lit.frag".to_owned()]
+            ),
+            "lit.frag: 0(3) : error C0000: syntax error"
+        );
+    }
+
+    #[test]
+    fn included_files_are_listed_by_source_string() {
+        let files = ["lit.frag".to_owned(), "lib/light.glsl".to_owned()];
+
+        assert_eq!(
+            name_source_strings("1(2) : error C1008: undefined variable".to_owned(), &files),
+            "1(2) : error C1008: undefined variable\nSource strings:\n  0: lit.frag\n  1: \
+             lib/light.glsl"
+        );
+    }
+}
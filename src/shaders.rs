@@ -0,0 +1,197 @@
+//! Ready-made programs for common materials, so that simple applications don't need to embed
+//! GLSL. Each function documents the vertex attributes and the uniforms its program expects.
+
+use crate::{
+    globals::Globals,
+    shader::{LinkedProgram, Program, Shader, ShaderCompilationError, ShaderType},
+};
+
+/// A vertex shader transforming `layout(location = 0) in vec3 position` by `u_mvp`
+pub const TRANSFORM_VERTEX_SHADER: &str = r#"
+    #version 460 core
+    layout(location = 0) in vec3 position;
+
+    uniform mat4 u_mvp;
+
+    void main() {
+        gl_Position = u_mvp * vec4(position, 1.0);
+    }
+"#;
+
+const FLAT_COLOR_FRAGMENT_SHADER: &str = r#"
+    #version 460 core
+
+    uniform vec4 u_color;
+
+    out vec4 color;
+
+    void main() {
+        color = u_color;
+    }
+"#;
+
+const VERTEX_COLOR_VERTEX_SHADER: &str = r#"
+    #version 460 core
+    layout(location = 0) in vec3 position;
+    layout(location = 1) in vec4 vertex_color;
+
+    uniform mat4 u_mvp;
+
+    out vec4 v_color;
+
+    void main() {
+        v_color = vertex_color;
+        gl_Position = u_mvp * vec4(position, 1.0);
+    }
+"#;
+
+const VERTEX_COLOR_FRAGMENT_SHADER: &str = r#"
+    #version 460 core
+
+    in vec4 v_color;
+
+    out vec4 color;
+
+    void main() {
+        color = v_color;
+    }
+"#;
+
+const TEXTURED_QUAD_VERTEX_SHADER: &str = r#"
+    #version 460 core
+    layout(location = 0) in vec2 position;
+
+    uniform mat4 u_transform;
+
+    out vec2 uv;
+
+    void main() {
+        uv = position * 0.5 + 0.5;
+        gl_Position = u_transform * vec4(position, 0.0, 1.0);
+    }
+"#;
+
+const TEXTURED_QUAD_FRAGMENT_SHADER: &str = r#"
+    #version 460 core
+
+    in vec2 uv;
+
+    uniform sampler2D u_texture;
+    uniform vec4 u_tint;
+
+    out vec4 color;
+
+    void main() {
+        color = texture(u_texture, uv) * u_tint;
+    }
+"#;
+
+const BLINN_PHONG_VERTEX_SHADER: &str = r#"
+    layout(location = 0) in vec3 position;
+    layout(location = 1) in vec3 normal;
+
+    uniform mat4 u_model;
+
+    out vec3 world_position;
+    out vec3 world_normal;
+
+    void main() {
+        vec4 world = u_model * vec4(position, 1.0);
+        world_position = world.xyz;
+        world_normal = mat3(transpose(inverse(u_model))) * normal;
+        gl_Position = view_projection * world;
+    }
+"#;
+
+const BLINN_PHONG_FRAGMENT_SHADER: &str = r#"
+    in vec3 world_position;
+    in vec3 world_normal;
+
+    uniform vec3 u_light_direction;
+    uniform vec3 u_light_color;
+    uniform vec3 u_ambient;
+    uniform vec3 u_diffuse;
+    uniform vec3 u_specular;
+    uniform float u_shininess;
+
+    out vec4 color;
+
+    void main() {
+        vec3 n = normalize(world_normal);
+        vec3 l = normalize(-u_light_direction);
+        vec3 v = normalize(camera_position - world_position);
+        vec3 h = normalize(l + v);
+
+        float diffuse = max(dot(n, l), 0.0);
+        float specular = diffuse > 0.0 ? pow(max(dot(n, h), 0.0), u_shininess) : 0.0;
+        vec3 lit = u_ambient * u_diffuse
+            + (u_diffuse * diffuse + u_specular * specular) * u_light_color;
+        color = vec4(lit, 1.0);
+    }
+"#;
+
+fn link(vertex: String, fragment: String) -> Result<LinkedProgram, ShaderCompilationError> {
+    Program::new().attach_and_link(vec![
+        Shader::new(vertex, ShaderType::Vertex),
+        Shader::new(fragment, ShaderType::Fragment),
+    ])
+}
+
+/// Prepends the `#version` directive and the globals block to `source`
+fn with_globals(source: &str) -> String {
+    format!("#version 460 core\n{}{source}", Globals::GLSL)
+}
+
+/// A program filling geometry with a single color.
+///
+/// Attributes: `vec3` position at location `0`.
+///
+/// Uniforms: `u_mvp` (`mat4`) and `u_color` (`vec4`).
+pub fn flat_color() -> Result<LinkedProgram, ShaderCompilationError> {
+    link(
+        TRANSFORM_VERTEX_SHADER.to_owned(),
+        FLAT_COLOR_FRAGMENT_SHADER.to_owned(),
+    )
+}
+
+/// A program coloring geometry with interpolated vertex colors, matching the layout of
+/// [`crate::primitive::ColorVertex`] and [`crate::primitive::CompactColorVertex`].
+///
+/// Attributes: `vec3` position at location `0` and `vec4` color at location `1`.
+///
+/// Uniforms: `u_mvp` (`mat4`).
+pub fn vertex_color() -> Result<LinkedProgram, ShaderCompilationError> {
+    link(
+        VERTEX_COLOR_VERTEX_SHADER.to_owned(),
+        VERTEX_COLOR_FRAGMENT_SHADER.to_owned(),
+    )
+}
+
+/// A program drawing a texture on a quad spanning `[-1, 1]`, such as two triangles of
+/// [`glm::Vec2`] vertices, with texture coordinates derived from the positions.
+///
+/// Attributes: `vec2` position at location `0`.
+///
+/// Uniforms: `u_transform` (`mat4`), `u_texture` (`sampler2D`) and `u_tint` (`vec4`),
+/// multiplied with the texture.
+pub fn textured_quad() -> Result<LinkedProgram, ShaderCompilationError> {
+    link(
+        TEXTURED_QUAD_VERTEX_SHADER.to_owned(),
+        TEXTURED_QUAD_FRAGMENT_SHADER.to_owned(),
+    )
+}
+
+/// A program shading geometry with the Blinn-Phong model under a single directional light,
+/// seen through the camera set with [`crate::Renderer::set_camera`].
+///
+/// Attributes: `vec3` position at location `0` and `vec3` normal at location `1`.
+///
+/// Uniforms: `u_model` (`mat4`), `u_light_direction` (`vec3`, the direction the light
+/// travels in), `u_light_color` (`vec3`), `u_ambient` (`vec3`), `u_diffuse` (`vec3`),
+/// `u_specular` (`vec3`) and `u_shininess` (`float`).
+pub fn blinn_phong() -> Result<LinkedProgram, ShaderCompilationError> {
+    link(
+        with_globals(BLINN_PHONG_VERTEX_SHADER),
+        with_globals(BLINN_PHONG_FRAGMENT_SHADER),
+    )
+}
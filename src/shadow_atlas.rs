@@ -0,0 +1,408 @@
+use crate::{
+    bind::{BindGuard, BindTarget},
+    framebuffer::{Framebuffer, FramebufferError},
+    renderer::Rect,
+    surface::{Region, Surface},
+    texture::{Filter, Texture2D, TextureBinding, TextureFormat},
+};
+use glm::Mat4;
+use std::collections::HashMap;
+
+/// A shadow map allocated in a [`ShadowAtlas`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShadowMapId(u32);
+
+#[derive(Debug, Copy, Clone)]
+struct Allocation {
+    tile: Tile,
+    view_projection: Option<Mat4>,
+}
+
+/// A square tile handed out by a [`BuddyAllocator`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Tile {
+    x: u32,
+    y: u32,
+    level: u32,
+}
+
+/// Hands out power-of-two square tiles of a square area: a tile is split in four when a
+/// smaller one is needed, and freed tiles merge back with their neighbours.
+#[derive(Debug, Clone)]
+struct BuddyAllocator {
+    size: u32,
+    /// The bottom left corners of the free tiles of each level, where level `0` is the whole
+    /// area and each level halves the tile size
+    free: Vec<Vec<(u32, u32)>>,
+}
+
+impl BuddyAllocator {
+    /// Creates an allocator for an area of `size` by `size` whose smallest tiles are
+    /// `min_tile` wide and high. Both must be powers of two, with `min_tile <= size`.
+    fn new(size: u32, min_tile: u32) -> Self {
+        let levels = (size / min_tile).trailing_zeros() + 1;
+        let mut free = vec![Vec::new(); levels as usize];
+        free[0].push((0, 0));
+        Self { size, free }
+    }
+
+    /// Returns a free tile of at least `size` by `size`, or `None` if there is no room
+    fn allocate(&mut self, size: u32) -> Option<Tile> {
+        // Checked before rounding up, which overflows above 2^31
+        if size > self.size {
+            return None;
+        }
+        let size = size.max(1).next_power_of_two();
+        let level = (self.size / size)
+            .trailing_zeros()
+            .min(self.free.len() as u32 - 1);
+        let (x, y) = self.take(level)?;
+        Some(Tile { x, y, level })
+    }
+
+    /// Returns a free tile of `level`, splitting larger tiles as needed
+    fn take(&mut self, level: u32) -> Option<(u32, u32)> {
+        if let Some(tile) = self.free[level as usize].pop() {
+            return Some(tile);
+        }
+        if level == 0 {
+            return None;
+        }
+
+        let (x, y) = self.take(level - 1)?;
+        let half = self.tile_size(level);
+        // Keeps the first quarter and frees the other three
+        self.free[level as usize].extend([(x + half, y), (x, y + half), (x + half, y + half)]);
+        Some((x, y))
+    }
+
+    /// Frees `tile`, merging it with its neighbours where they are free too
+    fn free(&mut self, tile: Tile) {
+        let Tile {
+            mut x,
+            mut y,
+            mut level,
+        } = tile;
+        while level > 0 {
+            let parent_size = self.tile_size(level - 1);
+            let (parent_x, parent_y) = (x - x % parent_size, y - y % parent_size);
+            let half = parent_size / 2;
+            let siblings = [
+                (parent_x, parent_y),
+                (parent_x + half, parent_y),
+                (parent_x, parent_y + half),
+                (parent_x + half, parent_y + half),
+            ];
+            let free = &mut self.free[level as usize];
+            let all_free = siblings
+                .iter()
+                .filter(|&&tile| tile != (x, y))
+                .all(|tile| free.contains(tile));
+            if !all_free {
+                break;
+            }
+
+            free.retain(|tile| !siblings.contains(tile));
+            (x, y, level) = (parent_x, parent_y, level - 1);
+        }
+        self.free[level as usize].push((x, y));
+    }
+
+    /// Frees every tile
+    fn clear(&mut self) {
+        self.free.iter_mut().for_each(Vec::clear);
+        self.free[0].push((0, 0));
+    }
+
+    /// The width and height of the tiles of `level`
+    fn tile_size(&self, level: u32) -> u32 {
+        self.size >> level
+    }
+}
+
+/// Packs the shadow maps of many lights into square tiles of a single depth texture, so that
+/// they are all rendered into one framebuffer and sampled through one `sampler2DShadow`.
+///
+/// Tiles have power-of-two sizes and are handed out by a buddy allocator: a tile is split in
+/// four when a smaller one is needed, and freed tiles merge back with their neighbours.
+///
+/// Each light renders its shadow map through [`ShadowAtlas::region`] with its own view and
+/// projection, registered with [`ShadowAtlas::set_view_projection`]. Shaders then sample the
+/// atlas with the matrix from [`ShadowAtlas::shadow_matrix`], which already accounts for the
+/// tile.
+pub struct ShadowAtlas {
+    framebuffer: Framebuffer,
+    size: u32,
+    tiles: BuddyAllocator,
+    allocations: HashMap<u32, Allocation>,
+    next_id: u32,
+}
+
+impl ShadowAtlas {
+    /// Creates an empty atlas of `size` by `size` texels whose smallest tiles are `min_tile`
+    /// texels wide and high.
+    ///
+    /// # Panics
+    /// This function panics if `size` or `min_tile` isn't a power of two, or if `min_tile` is
+    /// larger than `size`.
+    pub fn new(size: u32, min_tile: u32) -> Result<Self, FramebufferError> {
+        assert!(
+            size.is_power_of_two() && min_tile.is_power_of_two() && min_tile <= size,
+            "The atlas size {size} and the tile size {min_tile} must be powers of two, with \
+             the tile no larger than the atlas"
+        );
+
+        let mut depth = Texture2D::new(size, size, TextureFormat::Depth32F);
+        depth.set_filter(Filter::Linear, Filter::Linear);
+        {
+            let _binding = BindGuard::new(BindTarget::Texture2D, depth.id());
+            unsafe {
+                gl::TexParameteri(
+                    gl::TEXTURE_2D,
+                    gl::TEXTURE_COMPARE_MODE,
+                    gl::COMPARE_REF_TO_TEXTURE as i32,
+                );
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+            };
+        }
+        let framebuffer = Framebuffer::new(vec![], Some(depth))?;
+
+        Ok(Self {
+            framebuffer,
+            size,
+            tiles: BuddyAllocator::new(size, min_tile),
+            allocations: HashMap::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Allocates a tile of at least `size` by `size` texels, rounded up to a power of two and
+    /// no smaller than the atlas's smallest tile.
+    ///
+    /// # Returns
+    ///
+    /// The new shadow map, or `None` if the atlas has no free space for a tile of that size.
+    pub fn allocate(&mut self, size: u32) -> Option<ShadowMapId> {
+        let tile = self.tiles.allocate(size)?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.allocations.insert(
+            id,
+            Allocation {
+                tile,
+                view_projection: None,
+            },
+        );
+        Some(ShadowMapId(id))
+    }
+
+    /// Frees the tile of `id`, merging it with its neighbours where they are free too
+    pub fn free(&mut self, id: ShadowMapId) {
+        if let Some(allocation) = self.allocations.remove(&id.0) {
+            self.tiles.free(allocation.tile);
+        }
+    }
+
+    /// Frees every tile
+    pub fn clear(&mut self) {
+        self.allocations.clear();
+        self.tiles.clear();
+    }
+
+    /// Returns the texels covered by the tile of `id`, or `None` if it was freed
+    pub fn viewport(&self, id: ShadowMapId) -> Option<Rect> {
+        self.allocations.get(&id.0).map(|allocation| {
+            let Tile { x, y, level } = allocation.tile;
+            let size = self.tiles.tile_size(level);
+            Rect::new(x as i32, y as i32, size, size)
+        })
+    }
+
+    /// Returns the tile of `id` as a surface to clear and render the shadow map into, or
+    /// `None` if it was freed
+    pub fn region(&self, id: ShadowMapId) -> Option<Region<'_, Framebuffer>> {
+        self.viewport(id).map(|rect| self.framebuffer.region(rect))
+    }
+
+    /// Sets the view and projection the shadow map of `id` is rendered with
+    pub fn set_view_projection(&mut self, id: ShadowMapId, view_projection: Mat4) {
+        if let Some(allocation) = self.allocations.get_mut(&id.0) {
+            allocation.view_projection = Some(view_projection);
+        }
+    }
+
+    /// Returns the view and projection the shadow map of `id` is rendered with, if set
+    pub fn view_projection(&self, id: ShadowMapId) -> Option<Mat4> {
+        self.allocations
+            .get(&id.0)
+            .and_then(|allocation| allocation.view_projection)
+    }
+
+    /// Returns the matrix transforming world space positions into the texture coordinates and
+    /// depth to compare against when sampling the shadow map of `id` from the atlas, or `None`
+    /// if it has no view and projection set
+    pub fn shadow_matrix(&self, id: ShadowMapId) -> Option<Mat4> {
+        let view_projection = self.view_projection(id)?;
+        let rect = self.viewport(id)?;
+        let scale = rect.width as f32 / self.size as f32;
+        let offset_x = rect.x as f32 / self.size as f32;
+        let offset_y = rect.y as f32 / self.size as f32;
+
+        // Maps clip space [-1, 1] into the tile and depth into [0, 1]
+        let tile = glm::Matrix4::new(
+            glm::vec4(scale * 0.5, 0.0, 0.0, 0.0),
+            glm::vec4(0.0, scale * 0.5, 0.0, 0.0),
+            glm::vec4(0.0, 0.0, 0.5, 0.0),
+            glm::vec4(offset_x + scale * 0.5, offset_y + scale * 0.5, 0.5, 1.0),
+        );
+        Some(tile * view_projection)
+    }
+
+    /// Clears every shadow map to the far plane, e.g. at the start of a frame
+    pub fn clear_depth(&mut self) {
+        let _binding = self.framebuffer.bind_scoped();
+        unsafe {
+            gl::ClearDepth(1.0);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        };
+    }
+
+    /// Returns the framebuffer holding the atlas
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// Returns the depth texture holding the atlas, which compares depths when sampled
+    pub fn texture(&self) -> &Texture2D {
+        self.framebuffer
+            .depth_attachment()
+            .expect("The atlas framebuffer always has a depth attachment")
+    }
+
+    /// Returns a binding that can be passed as the value of a `sampler2DShadow` uniform
+    pub fn binding(&self) -> TextureBinding {
+        self.texture().binding()
+    }
+
+    /// Returns the width and height of the atlas in texels
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the number of allocated shadow maps
+    pub fn len(&self) -> usize {
+        self.allocations.len()
+    }
+
+    /// Returns whether no shadow maps are allocated
+    pub fn is_empty(&self) -> bool {
+        self.allocations.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile_size(allocator: &BuddyAllocator, tile: Tile) -> u32 {
+        allocator.tile_size(tile.level)
+    }
+
+    fn overlap(allocator: &BuddyAllocator, a: Tile, b: Tile) -> bool {
+        let (a_size, b_size) = (tile_size(allocator, a), tile_size(allocator, b));
+        a.x < b.x + b_size && b.x < a.x + a_size && a.y < b.y + b_size && b.y < a.y + a_size
+    }
+
+    #[test]
+    fn splits_into_quarters() {
+        let mut allocator = BuddyAllocator::new(1024, 128);
+        let tiles: Vec<_> = (0..4).map(|_| allocator.allocate(512).unwrap()).collect();
+
+        assert!(tiles.iter().all(|tile| tile.level == 1));
+        for (index, &a) in tiles.iter().enumerate() {
+            for &b in &tiles[index + 1..] {
+                assert!(!overlap(&allocator, a, b), "{a:?} overlaps {b:?}");
+            }
+        }
+        assert_eq!(allocator.allocate(512), None);
+        assert_eq!(allocator.allocate(1), None);
+    }
+
+    #[test]
+    fn rounds_sizes_up_to_tiles() {
+        let mut allocator = BuddyAllocator::new(1024, 128);
+
+        assert_eq!(allocator.allocate(300).map(|tile| tile.level), Some(1));
+        assert_eq!(allocator.allocate(0).map(|tile| tile.level), Some(3));
+        assert_eq!(allocator.allocate(1024), None);
+        assert_eq!(allocator.allocate(1025), None);
+        assert_eq!(allocator.allocate(u32::MAX), None);
+        assert_eq!(allocator.allocate((1 << 31) + 1), None);
+    }
+
+    #[test]
+    fn freed_tiles_merge_back() {
+        let mut allocator = BuddyAllocator::new(1024, 128);
+        let tiles: Vec<_> = (0..64).map(|_| allocator.allocate(128).unwrap()).collect();
+        assert_eq!(allocator.allocate(128), None);
+
+        // Frees in an order that leaves every parent partially free until its last child
+        for tile in tiles
+            .iter()
+            .step_by(2)
+            .chain(tiles.iter().skip(1).step_by(2))
+        {
+            allocator.free(*tile);
+        }
+
+        assert_eq!(allocator.free[0], [(0, 0)]);
+        assert!(allocator.free[1..].iter().all(Vec::is_empty));
+        assert_eq!(
+            allocator.allocate(1024),
+            Some(Tile {
+                x: 0,
+                y: 0,
+                level: 0
+            })
+        );
+    }
+
+    #[test]
+    fn fragmentation_keeps_only_partially_used_tiles_split() {
+        let mut allocator = BuddyAllocator::new(1024, 128);
+        let small = allocator.allocate(128).unwrap();
+        let medium = allocator.allocate(256).unwrap();
+        let large = allocator.allocate(512).unwrap();
+
+        assert!(!overlap(&allocator, small, medium));
+        assert!(!overlap(&allocator, medium, large));
+        assert!(!overlap(&allocator, small, large));
+        // Two large quarters remain, plus the rest of the quarter holding the smaller tiles
+        assert_eq!(allocator.free[1].len(), 2);
+        assert_eq!(allocator.free[2].len(), 2);
+        assert_eq!(allocator.free[3].len(), 3);
+
+        allocator.free(medium);
+        assert_eq!(allocator.free[2].len(), 3);
+        assert_eq!(allocator.free[3].len(), 3);
+
+        // The last tile in the first quarter merges every level back up
+        allocator.free(small);
+        assert_eq!(allocator.free[1].len(), 3);
+        assert!(allocator.free[2..].iter().all(Vec::is_empty));
+
+        allocator.free(large);
+        assert_eq!(allocator.free[0], [(0, 0)]);
+    }
+
+    #[test]
+    fn clear_frees_everything() {
+        let mut allocator = BuddyAllocator::new(256, 64);
+        while allocator.allocate(64).is_some() {}
+
+        allocator.clear();
+
+        assert_eq!(allocator.allocate(256).map(|tile| tile.level), Some(0));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    bind::{BindGuard, BindTarget},
+    framebuffer::FramebufferError,
+    surface::Surface,
+    texture::{Texture2DArray, TextureFormat},
+};
+use glm::Mat4;
+
+/// One of the two eyes of a stereo view
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl Eye {
+    /// Both eyes, in the order of their layers
+    pub const BOTH: [Eye; 2] = [Eye::Left, Eye::Right];
+
+    /// The layer of a [`StereoTarget`] this eye renders into
+    pub fn layer(self) -> u32 {
+        match self {
+            Eye::Left => 0,
+            Eye::Right => 1,
+        }
+    }
+}
+
+/// Derives per-eye view matrices from a single view matrix
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StereoRig {
+    /// The distance between the eyes in world units
+    pub eye_separation: f32,
+}
+
+impl StereoRig {
+    /// Constructs a rig with the given distance between the eyes
+    pub fn new(eye_separation: f32) -> Self {
+        Self { eye_separation }
+    }
+
+    /// Returns the view matrix of `eye`, offset sideways from the head's view matrix `view`
+    pub fn view(&self, eye: Eye, view: Mat4) -> Mat4 {
+        let offset = match eye {
+            Eye::Left => self.eye_separation / 2.0,
+            Eye::Right => -self.eye_separation / 2.0,
+        };
+
+        let translation = Mat4::new(
+            glm::vec4(1.0, 0.0, 0.0, 0.0),
+            glm::vec4(0.0, 1.0, 0.0, 0.0),
+            glm::vec4(0.0, 0.0, 1.0, 0.0),
+            glm::vec4(offset, 0.0, 0.0, 1.0),
+        );
+        translation * view
+    }
+}
+
+/// A render target with a color and depth layer for each eye.
+///
+/// Each eye is rendered in its own pass through [`StereoTarget::eye`]. The results can be
+/// sampled from [`StereoTarget::color`] as a `sampler2DArray`, e.g. with
+/// [`StereoTarget::ANAGLYPH_SHADER`].
+#[derive(Debug)]
+pub struct StereoTarget {
+    color: Texture2DArray,
+    depth: Texture2DArray,
+    framebuffers: [u32; 2],
+}
+
+impl StereoTarget {
+    /// A fragment shader for [`crate::mesh::FullscreenQuad::program`] that combines both
+    /// eyes into a red-cyan anaglyph. The target's color texture is sampled through
+    /// `uniform sampler2DArray u_eyes`.
+    pub const ANAGLYPH_SHADER: &'static str = r#"
+        #version 460 core
+
+        in vec2 uv;
+
+        uniform sampler2DArray u_eyes;
+
+        out vec4 color;
+
+        void main() {
+            vec4 left = texture(u_eyes, vec3(uv, 0));
+            vec4 right = texture(u_eyes, vec3(uv, 1));
+            color = vec4(left.r, right.g, right.b, 1);
+        }
+    "#;
+
+    /// Creates a stereo target whose layers have the given size and color format
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Result<Self, FramebufferError> {
+        if format.is_depth() {
+            return Err(FramebufferError::InvalidAttachmentFormat);
+        }
+
+        let color = Texture2DArray::new(width, height, 2, format);
+        let depth = Texture2DArray::new(width, height, 2, TextureFormat::Depth24);
+
+        let mut framebuffers = [0; 2];
+        unsafe { gl::GenFramebuffers(2, framebuffers.as_mut_ptr()) };
+        let target = Self {
+            color,
+            depth,
+            framebuffers,
+        };
+
+        for eye in Eye::BOTH {
+            let _binding = target.bind_eye(eye);
+            let status = unsafe {
+                gl::FramebufferTextureLayer(
+                    gl::DRAW_FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    target.color.id(),
+                    0,
+                    eye.layer() as i32,
+                );
+                gl::FramebufferTextureLayer(
+                    gl::DRAW_FRAMEBUFFER,
+                    gl::DEPTH_ATTACHMENT,
+                    target.depth.id(),
+                    0,
+                    eye.layer() as i32,
+                );
+                gl::CheckFramebufferStatus(gl::DRAW_FRAMEBUFFER)
+            };
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(FramebufferError::Incomplete(status));
+            }
+        }
+
+        Ok(target)
+    }
+
+    fn bind_eye(&self, eye: Eye) -> BindGuard<'_> {
+        BindGuard::pair(
+            BindTarget::DrawFramebuffer,
+            BindTarget::ReadFramebuffer,
+            self.framebuffers[eye.layer() as usize],
+        )
+    }
+
+    /// Returns a surface rendering into the layers of `eye`
+    pub fn eye(&self, eye: Eye) -> EyeSurface<'_> {
+        EyeSurface { target: self, eye }
+    }
+
+    /// Returns the color texture, with the left eye in layer `0` and the right eye in layer `1`
+    pub fn color(&self) -> &Texture2DArray {
+        &self.color
+    }
+
+    /// Returns the depth texture, with the same layers as [`StereoTarget::color`]
+    pub fn depth(&self) -> &Texture2DArray {
+        &self.depth
+    }
+
+    /// Returns the width and height of each eye's layer in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.color.dimensions()
+    }
+}
+
+impl Drop for StereoTarget {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteFramebuffers(2, self.framebuffers.as_ptr()) };
+    }
+}
+
+/// The layers of a single eye of a [`StereoTarget`], obtained through [`StereoTarget::eye`]
+#[derive(Debug)]
+pub struct EyeSurface<'a> {
+    target: &'a StereoTarget,
+    eye: Eye,
+}
+
+impl EyeSurface<'_> {
+    /// Returns the eye this surface renders
+    pub fn eye(&self) -> Eye {
+        self.eye
+    }
+}
+
+impl Surface for EyeSurface<'_> {
+    fn bind_target(&self) -> BindGuard<'_> {
+        self.target.bind_eye(self.eye)
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.target.dimensions()
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    bind::{BindGuard, BindTarget},
+    buffer::VertexBufferSlice,
+    draw_parameters::{self, DrawParameters},
+    framebuffer::{Framebuffer, MultisampleFramebuffer},
+    renderer::{self, DrawError, DrawMode, Rect},
+    shader::{LinkedProgram, Vertex},
+    types::IndexType,
+    uniforms::Uniforms,
+};
+use glm::Vec4;
+
+/// Something that can be rendered to, such as the window or an off-screen [`Framebuffer`].
+///
+/// Rendering code written against this trait doesn't need to know where it draws.
+pub trait Surface {
+    /// Binds the surface as the target of draw and read operations until the returned guard
+    /// is dropped
+    fn bind_target(&self) -> BindGuard<'_>;
+
+    /// Returns the width and height of the surface in pixels
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Clears the whole surface to `color` and `depth`
+    fn clear(&mut self, color: Vec4, depth: f64) {
+        let _binding = self.bind_target();
+        unsafe {
+            gl::ClearColor(color.x, color.y, color.z, color.w);
+            gl::ClearDepth(depth);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        };
+    }
+
+    /// Draws a buffer to the surface, using a viewport covering the whole surface. See
+    /// [`crate::Renderer::draw`].
+    fn draw<'a, V: Vertex + 'a, I: IndexType + 'a>(
+        &mut self,
+        buffer: impl Into<VertexBufferSlice<'a, V, I>>,
+        shader_program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+    ) -> Result<(), DrawError> {
+        let _binding = self.bind_target();
+        let _viewport = ViewportGuard::new(self.dimensions());
+        renderer::draw_vertex_buffer(
+            buffer.into(),
+            shader_program,
+            mode,
+            uniforms,
+            &DrawParameters::default(),
+        )
+    }
+
+    /// Returns the part of the surface covered by `rect`, which can be cleared and drawn to
+    /// on its own
+    fn region(&self, rect: Rect) -> Region<'_, Self>
+    where
+        Self: Sized,
+    {
+        Region::new(self, rect)
+    }
+
+    /// Splits the surface into a grid of `columns` by `rows` equally sized regions, e.g. for
+    /// split-screen rendering.
+    ///
+    /// # Returns
+    ///
+    /// The regions row by row, starting at the top left.
+    fn split(&self, columns: u32, rows: u32) -> Vec<Region<'_, Self>>
+    where
+        Self: Sized,
+    {
+        let (width, height) = self.dimensions();
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let (cell_width, cell_height) = (width / columns, height / rows);
+
+        (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                // Window coordinates start at the bottom
+                let y = height - (row + 1) * cell_height;
+                self.region(Rect::new(
+                    (column * cell_width) as i32,
+                    y as i32,
+                    cell_width,
+                    cell_height,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// A rectangular part of a [`Surface`], obtained through [`Surface::region`] or
+/// [`Surface::split`].
+///
+/// Draws use a viewport covering the region, and both draws and clears are scissored to it,
+/// so each region can show its own camera.
+pub struct Region<'a, S> {
+    surface: &'a S,
+    rect: Rect,
+}
+
+impl<'a, S: Surface> Region<'a, S> {
+    /// Creates a region covering `rect` of `surface`
+    pub fn new(surface: &'a S, rect: Rect) -> Self {
+        Self { surface, rect }
+    }
+
+    /// Returns the rectangle covered by the region, in window coordinates of the surface
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Returns the width of the region divided by its height, for building a projection
+    pub fn aspect_ratio(&self) -> f32 {
+        self.rect.width as f32 / self.rect.height.max(1) as f32
+    }
+}
+
+impl<S: Surface> Surface for Region<'_, S> {
+    fn bind_target(&self) -> BindGuard<'_> {
+        self.surface.bind_target()
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.rect.width, self.rect.height)
+    }
+
+    fn clear(&mut self, color: Vec4, depth: f64) {
+        let _binding = self.bind_target();
+        let _scissor = ScissorGuard::new(self.rect);
+        unsafe {
+            gl::ClearColor(color.x, color.y, color.z, color.w);
+            gl::ClearDepth(depth);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        };
+    }
+
+    fn draw<'a, V: Vertex + 'a, I: IndexType + 'a>(
+        &mut self,
+        buffer: impl Into<VertexBufferSlice<'a, V, I>>,
+        shader_program: &LinkedProgram,
+        mode: DrawMode,
+        uniforms: &Uniforms,
+    ) -> Result<(), DrawError> {
+        let _binding = self.bind_target();
+        let _viewport = ViewportGuard::with_rect(self.rect);
+        let _scissor = ScissorGuard::new(self.rect);
+        renderer::draw_vertex_buffer(
+            buffer.into(),
+            shader_program,
+            mode,
+            uniforms,
+            &DrawParameters::default(),
+        )
+    }
+}
+
+/// The framebuffer provided by the windowing system
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DefaultFramebuffer {
+    width: u32,
+    height: u32,
+}
+
+impl DefaultFramebuffer {
+    /// Constructs a handle to the default framebuffer, which has the size of the window
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Updates the size of the default framebuffer, e.g. after the window was resized
+    pub fn set_dimensions(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+}
+
+impl Surface for DefaultFramebuffer {
+    fn bind_target(&self) -> BindGuard<'_> {
+        BindGuard::pair(BindTarget::DrawFramebuffer, BindTarget::ReadFramebuffer, 0)
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl Surface for Framebuffer {
+    fn bind_target(&self) -> BindGuard<'_> {
+        self.bind_scoped()
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        Framebuffer::dimensions(self)
+    }
+}
+
+impl Surface for MultisampleFramebuffer {
+    fn bind_target(&self) -> BindGuard<'_> {
+        self.bind_scoped()
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        MultisampleFramebuffer::dimensions(self)
+    }
+}
+
+/// Sets the viewport and restores the previous one when dropped
+pub(crate) struct ViewportGuard {
+    previous: [i32; 4],
+}
+
+impl ViewportGuard {
+    pub(crate) fn new((width, height): (u32, u32)) -> Self {
+        Self::with_rect(Rect::new(0, 0, width, height))
+    }
+
+    pub(crate) fn with_rect(rect: Rect) -> Self {
+        let previous = draw_parameters::replace_viewport([
+            rect.x,
+            rect.y,
+            rect.width as i32,
+            rect.height as i32,
+        ]);
+        Self { previous }
+    }
+}
+
+impl Drop for ViewportGuard {
+    fn drop(&mut self) {
+        draw_parameters::replace_viewport(self.previous);
+    }
+}
+
+/// Restricts rendering to a rectangle and restores the previous scissor state when dropped
+struct ScissorGuard {
+    enabled: bool,
+    previous: [i32; 4],
+}
+
+impl ScissorGuard {
+    fn new(rect: Rect) -> Self {
+        let (enabled, previous) = draw_parameters::replace_scissor(
+            true,
+            [rect.x, rect.y, rect.width as i32, rect.height as i32],
+        );
+        Self { enabled, previous }
+    }
+}
+
+impl Drop for ScissorGuard {
+    fn drop(&mut self) {
+        draw_parameters::replace_scissor(self.enabled, self.previous);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::diagnostics::diagnostic;
+use gl::types::GLsync;
+use std::{
+    ops::{BitOr, BitOrAssign},
+    time::Duration,
+};
+
+/// The kinds of reads that must see earlier shader writes to buffers and images, combined
+/// with `|` and passed to [`crate::Renderer::memory_barrier`].
+///
+/// Each flag names how the data is read *afterwards*, e.g. [`MemoryBarrier::VERTEX_ATTRIB_ARRAY`]
+/// to draw vertices a compute shader wrote to a storage buffer.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MemoryBarrier(u32);
+
+impl MemoryBarrier {
+    /// No synchronization
+    pub const NONE: Self = Self(0);
+    /// Vertex attributes sourced from buffers, `GL_VERTEX_ATTRIB_ARRAY_BARRIER_BIT`
+    pub const VERTEX_ATTRIB_ARRAY: Self = Self(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    /// Indices sourced from buffers, `GL_ELEMENT_ARRAY_BARRIER_BIT`
+    pub const ELEMENT_ARRAY: Self = Self(gl::ELEMENT_ARRAY_BARRIER_BIT);
+    /// Uniform blocks, `GL_UNIFORM_BARRIER_BIT`
+    pub const UNIFORM: Self = Self(gl::UNIFORM_BARRIER_BIT);
+    /// Texture sampling, `GL_TEXTURE_FETCH_BARRIER_BIT`
+    pub const TEXTURE_FETCH: Self = Self(gl::TEXTURE_FETCH_BARRIER_BIT);
+    /// Image loads and stores, `GL_SHADER_IMAGE_ACCESS_BARRIER_BIT`
+    pub const SHADER_IMAGE_ACCESS: Self = Self(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    /// Indirect draw and dispatch commands, `GL_COMMAND_BARRIER_BIT`
+    pub const COMMAND: Self = Self(gl::COMMAND_BARRIER_BIT);
+    /// Pixel transfers through pixel pack and unpack buffers, `GL_PIXEL_BUFFER_BARRIER_BIT`
+    pub const PIXEL_BUFFER: Self = Self(gl::PIXEL_BUFFER_BARRIER_BIT);
+    /// Texture uploads and read-backs, `GL_TEXTURE_UPDATE_BARRIER_BIT`
+    pub const TEXTURE_UPDATE: Self = Self(gl::TEXTURE_UPDATE_BARRIER_BIT);
+    /// Buffer copies, read-backs and mappings, `GL_BUFFER_UPDATE_BARRIER_BIT`
+    pub const BUFFER_UPDATE: Self = Self(gl::BUFFER_UPDATE_BARRIER_BIT);
+    /// Framebuffer attachments, `GL_FRAMEBUFFER_BARRIER_BIT`
+    pub const FRAMEBUFFER: Self = Self(gl::FRAMEBUFFER_BARRIER_BIT);
+    /// Atomic counter buffers, `GL_ATOMIC_COUNTER_BARRIER_BIT`
+    pub const ATOMIC_COUNTER: Self = Self(gl::ATOMIC_COUNTER_BARRIER_BIT);
+    /// Shader storage blocks, `GL_SHADER_STORAGE_BARRIER_BIT`
+    pub const SHADER_STORAGE: Self = Self(gl::SHADER_STORAGE_BARRIER_BIT);
+    /// Every kind of read, `GL_ALL_BARRIER_BITS`
+    pub const ALL: Self = Self(gl::ALL_BARRIER_BITS);
+
+    /// Returns the raw `GLbitfield` passed to `glMemoryBarrier`
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns whether every flag in `other` is set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns whether no flag is set
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for MemoryBarrier {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for MemoryBarrier {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl From<MemoryBarrier> for u32 {
+    fn from(barrier: MemoryBarrier) -> u32 {
+        barrier.0
+    }
+}
+
+/// A GPU fence that becomes signaled once all previously submitted commands have completed
+#[derive(Debug)]
+pub struct Fence {
+    sync: GLsync,
+}
+
+impl Default for Fence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fence {
+    /// Inserts a new fence into the command stream
+    pub fn new() -> Self {
+        let sync = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        Self { sync }
+    }
+
+    /// Returns whether the GPU has passed the fence, without blocking
+    pub fn is_signaled(&self) -> bool {
+        let status = unsafe { gl::ClientWaitSync(self.sync, 0, 0) };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+
+    /// Blocks until the GPU has passed the fence or `timeout` elapses.
+    ///
+    /// # Returns
+    ///
+    /// Whether the fence was signaled before the timeout.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let status = unsafe {
+            gl::ClientWaitSync(
+                self.sync,
+                gl::SYNC_FLUSH_COMMANDS_BIT,
+                timeout.as_nanos().min(u64::MAX as u128) as u64,
+            )
+        };
+
+        match status {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => true,
+            gl::WAIT_FAILED => {
+                diagnostic!(Error, Gl, "Waiting on fence failed");
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Blocks until the GPU has passed the fence
+    pub fn wait(&self) {
+        while !self.wait_timeout(Duration::from_millis(100)) {}
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        // SAFETY: We are being dropped, so we can destroy the sync object we correspond with
+        unsafe { gl::DeleteSync(self.sync) };
+    }
+}
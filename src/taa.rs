@@ -0,0 +1,260 @@
+use crate::{
+    framebuffer::{Framebuffer, FramebufferError},
+    mesh::FullscreenQuad,
+    renderer::{DrawError, Renderer},
+    shader::LinkedProgram,
+    surface::ViewportGuard,
+    texture::{Texture2D, TextureFormat},
+    uniforms,
+};
+use glm::{Mat4, Vec2};
+
+/// Returns element `index` (starting at `1`) of the Halton sequence with base `base`
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// A repeating sequence of sub-pixel offsets by which the projection is jittered every frame,
+/// so that consecutive frames sample different points of each pixel.
+///
+/// The offsets follow the Halton (2, 3) sequence, which covers the pixel evenly after only a
+/// few frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct JitterSequence {
+    index: u32,
+    length: u32,
+}
+
+impl Default for JitterSequence {
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl JitterSequence {
+    /// Creates a sequence that repeats after `length` frames
+    ///
+    /// # Panics
+    /// This function panics if `length` is `0`.
+    pub fn new(length: u32) -> Self {
+        assert!(length > 0, "A jitter sequence needs at least one offset");
+        Self { index: 0, length }
+    }
+
+    /// Returns the offset of the current frame in pixels, in the range `[-0.5, 0.5]`
+    pub fn offset(&self) -> Vec2 {
+        glm::vec2(
+            halton(self.index + 1, 2) - 0.5,
+            halton(self.index + 1, 3) - 0.5,
+        )
+    }
+
+    /// Moves on to the offset of the next frame
+    pub fn advance(&mut self) {
+        self.index = (self.index + 1) % self.length;
+    }
+
+    /// Restarts the sequence from its first offset
+    pub fn reset(&mut self) {
+        self.index = 0;
+    }
+
+    /// Returns `projection` shifted by the current offset for a target of the given size.
+    ///
+    /// Works for both perspective and orthographic projections. Only the projection used for
+    /// rasterization should be jittered; velocities are computed from unjittered positions.
+    pub fn apply(&self, projection: Mat4, (width, height): (u32, u32)) -> Mat4 {
+        let offset = self.offset();
+        let ndc_x = 2.0 * offset.x / width as f32;
+        let ndc_y = 2.0 * offset.y / height as f32;
+
+        // Adds the offset multiplied by clip-space w, so it survives the perspective divide
+        let mut jittered = projection;
+        for column in 0..4 {
+            let w = jittered[column][3];
+            jittered[column][0] += ndc_x * w;
+            jittered[column][1] += ndc_y * w;
+        }
+        jittered
+    }
+}
+
+/// Accumulates jittered frames into an anti-aliased image by blending each frame with the
+/// reprojected result of the previous ones.
+///
+/// Every frame, render the scene with a projection jittered by a [`JitterSequence`] into a
+/// color target and a velocity target, then pass both to [`TemporalResolve::resolve`]. The
+/// velocity target (e.g. [`TextureFormat::Rg16F`]) holds the screen-space motion of every
+/// pixel since the previous frame, in texture coordinates, as computed by
+/// [`TemporalResolve::VELOCITY_FUNCTIONS`].
+///
+/// The reprojected history is clamped to the colors around each pixel in the current frame,
+/// which rejects history that was occluded or changed.
+pub struct TemporalResolve {
+    history: [Framebuffer; 2],
+    latest: usize,
+    history_valid: bool,
+    program: LinkedProgram,
+    quad: FullscreenQuad,
+    format: TextureFormat,
+    blend_factor: f32,
+}
+
+impl TemporalResolve {
+    /// GLSL functions for writing the velocity target, to be inserted after the `#version`
+    /// directive of a fragment shader.
+    ///
+    /// `taa_velocity` takes the clip-space position of the fragment in the current and the
+    /// previous frame, both computed with unjittered projections, and returns the value to
+    /// write to the velocity target.
+    pub const VELOCITY_FUNCTIONS: &'static str = r#"
+        vec2 taa_velocity(vec4 current_clip, vec4 previous_clip) {
+            vec2 current_uv = current_clip.xy / current_clip.w * 0.5 + 0.5;
+            vec2 previous_uv = previous_clip.xy / previous_clip.w * 0.5 + 0.5;
+            return current_uv - previous_uv;
+        }
+    "#;
+
+    const RESOLVE_SHADER: &'static str = r#"
+        #version 460 core
+
+        in vec2 uv;
+
+        uniform sampler2D u_current;
+        uniform sampler2D u_history;
+        uniform sampler2D u_velocity;
+        uniform vec2 u_texel_size;
+        uniform float u_blend_factor;
+        uniform bool u_history_valid;
+
+        out vec4 color;
+
+        void main() {
+            vec4 current = texture(u_current, uv);
+            vec2 history_uv = uv - texture(u_velocity, uv).xy;
+            bool offscreen = any(lessThan(history_uv, vec2(0.0)))
+                || any(greaterThan(history_uv, vec2(1.0)));
+            if (!u_history_valid || offscreen) {
+                color = current;
+                return;
+            }
+
+            vec4 neighborhood_min = current;
+            vec4 neighborhood_max = current;
+            for (int y = -1; y <= 1; y++) {
+                for (int x = -1; x <= 1; x++) {
+                    vec4 neighbor = texture(u_current, uv + vec2(x, y) * u_texel_size);
+                    neighborhood_min = min(neighborhood_min, neighbor);
+                    neighborhood_max = max(neighborhood_max, neighbor);
+                }
+            }
+
+            vec4 history = clamp(texture(u_history, history_uv), neighborhood_min, neighborhood_max);
+            color = mix(history, current, u_blend_factor);
+        }
+    "#;
+
+    /// Creates a resolve pass whose history has the given size and format
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Result<Self, FramebufferError> {
+        #[allow(clippy::expect_used)]
+        let program = FullscreenQuad::program(Self::RESOLVE_SHADER)
+            .expect("Built-in TAA resolve shader failed to compile");
+
+        Ok(Self {
+            history: Self::create_history(width, height, format)?,
+            latest: 0,
+            history_valid: false,
+            program,
+            quad: FullscreenQuad::new(),
+            format,
+            blend_factor: 0.1,
+        })
+    }
+
+    fn create_history(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Result<[Framebuffer; 2], FramebufferError> {
+        Ok([
+            Framebuffer::new(vec![Texture2D::new(width, height, format)], None)?,
+            Framebuffer::new(vec![Texture2D::new(width, height, format)], None)?,
+        ])
+    }
+
+    /// Recreates the history with a new size, e.g. after the window was resized
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferError> {
+        if self.dimensions() != (width, height) {
+            self.history = Self::create_history(width, height, self.format)?;
+            self.reset();
+        }
+        Ok(())
+    }
+
+    /// Discards the accumulated history, e.g. after a camera cut
+    pub fn reset(&mut self) {
+        self.history_valid = false;
+    }
+
+    /// Sets how much of the current frame is blended into the history, between `0` and `1`.
+    ///
+    /// Lower values give smoother edges but more ghosting. The default is `0.1`.
+    pub fn set_blend_factor(&mut self, blend_factor: f32) {
+        self.blend_factor = blend_factor.clamp(0.0, 1.0);
+    }
+
+    /// Returns how much of the current frame is blended into the history
+    pub fn blend_factor(&self) -> f32 {
+        self.blend_factor
+    }
+
+    /// Returns the size of the history in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.history[0].dimensions()
+    }
+
+    /// Blends `current` into the history, reprojected with `velocity`.
+    ///
+    /// # Returns
+    ///
+    /// The anti-aliased image, which becomes the history of the next frame.
+    pub fn resolve(
+        &mut self,
+        renderer: &Renderer,
+        current: &Texture2D,
+        velocity: &Texture2D,
+    ) -> Result<&Texture2D, DrawError> {
+        let target = 1 - self.latest;
+        let (width, height) = self.dimensions();
+
+        {
+            #[allow(clippy::unwrap_used)]
+            let history = self.history[self.latest].color_attachment(0).unwrap();
+            let _binding = self.history[target].bind_scoped();
+            let _viewport = ViewportGuard::new((width, height));
+
+            let program = &self.program;
+            let uniforms = uniforms!(program => {
+                "u_current": current.binding(),
+                "u_history": history.binding(),
+                "u_velocity": velocity.binding(),
+                "u_texel_size": glm::vec2(1.0 / width as f32, 1.0 / height as f32),
+                "u_blend_factor": self.blend_factor,
+                "u_history_valid": self.history_valid as i32
+            });
+            self.quad.draw(renderer, program, &uniforms)?;
+        }
+
+        self.latest = target;
+        self.history_valid = true;
+        #[allow(clippy::unwrap_used)]
+        Ok(self.history[target].color_attachment(0).unwrap())
+    }
+}
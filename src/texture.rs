@@ -0,0 +1,1373 @@
+use crate::{
+    context::{self, Limits, Unsupported},
+    uniforms::{Uniform, UniformValue},
+};
+use std::ptr::null;
+
+/// The internal storage format of a texture
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    R16F,
+    Rg16F,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+    /// Packed unsigned floats with 11, 11 and 10 bits, a compact format for HDR targets that
+    /// don't need alpha
+    R11FG11FB10F,
+    R32UI,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+}
+
+impl TextureFormat {
+    /// The sized internal format passed to OpenGL
+    pub fn internal_format(self) -> u32 {
+        match self {
+            TextureFormat::R8 => gl::R8,
+            TextureFormat::Rg8 => gl::RG8,
+            TextureFormat::Rgb8 => gl::RGB8,
+            TextureFormat::Rgba8 => gl::RGBA8,
+            TextureFormat::R16F => gl::R16F,
+            TextureFormat::Rg16F => gl::RG16F,
+            TextureFormat::Rgba16F => gl::RGBA16F,
+            TextureFormat::R32F => gl::R32F,
+            TextureFormat::Rgba32F => gl::RGBA32F,
+            TextureFormat::R11FG11FB10F => gl::R11F_G11F_B10F,
+            TextureFormat::R32UI => gl::R32UI,
+            TextureFormat::Depth24 => gl::DEPTH_COMPONENT24,
+            TextureFormat::Depth32F => gl::DEPTH_COMPONENT32F,
+            TextureFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+        }
+    }
+
+    /// The pixel format used when transferring data of this format
+    pub fn pixel_format(self) -> PixelFormat {
+        match self {
+            TextureFormat::R8 => PixelFormat::Red,
+            TextureFormat::Rg8 => PixelFormat::Rg,
+            TextureFormat::Rgb8 => PixelFormat::Rgb,
+            TextureFormat::Rgba8 => PixelFormat::Rgba,
+            TextureFormat::R16F | TextureFormat::R32F => PixelFormat::RedF32,
+            TextureFormat::Rg16F => PixelFormat::RgF32,
+            TextureFormat::Rgba16F | TextureFormat::Rgba32F => PixelFormat::RgbaF32,
+            TextureFormat::R11FG11FB10F => PixelFormat::RgbF32,
+            TextureFormat::R32UI => PixelFormat::RedU32,
+            TextureFormat::Depth24 | TextureFormat::Depth32F => PixelFormat::Depth,
+            TextureFormat::Depth24Stencil8 => PixelFormat::DepthStencil,
+        }
+    }
+
+    /// The pixel format used when transferring half-precision data of this format, or `None`
+    /// if it isn't a floating-point color format
+    pub fn half_pixel_format(self) -> Option<PixelFormat> {
+        match self {
+            TextureFormat::R16F | TextureFormat::R32F => Some(PixelFormat::RedF16),
+            TextureFormat::Rg16F => Some(PixelFormat::RgF16),
+            TextureFormat::R11FG11FB10F => Some(PixelFormat::RgbF16),
+            TextureFormat::Rgba16F | TextureFormat::Rgba32F => Some(PixelFormat::RgbaF16),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this is a depth (or depth-stencil) format
+    pub fn is_depth(self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Depth24 | TextureFormat::Depth32F | TextureFormat::Depth24Stencil8
+        )
+    }
+
+    /// Returns whether this format contains a stencil component
+    pub fn has_stencil(self) -> bool {
+        matches!(self, TextureFormat::Depth24Stencil8)
+    }
+
+    /// Returns the size of a texel in bytes, or `None` if the format can't be used for image
+    /// load/store
+    pub fn image_texel_size(self) -> Option<usize> {
+        match self {
+            TextureFormat::R8 => Some(1),
+            TextureFormat::Rg8 | TextureFormat::R16F => Some(2),
+            TextureFormat::Rgba8
+            | TextureFormat::Rg16F
+            | TextureFormat::R32F
+            | TextureFormat::R11FG11FB10F
+            | TextureFormat::R32UI => Some(4),
+            TextureFormat::Rgba16F => Some(8),
+            TextureFormat::Rgba32F => Some(16),
+            TextureFormat::Rgb8
+            | TextureFormat::Depth24
+            | TextureFormat::Depth32F
+            | TextureFormat::Depth24Stencil8 => None,
+        }
+    }
+}
+
+/// The layout of pixel data transferred between the CPU and the GPU
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    /// One unsigned byte per pixel
+    Red,
+    /// Two unsigned bytes per pixel
+    Rg,
+    /// Three unsigned bytes per pixel
+    Rgb,
+    /// Four unsigned bytes per pixel
+    Rgba,
+    /// Four unsigned bytes per pixel in BGRA order
+    Bgra,
+    /// One `f32` per pixel
+    RedF32,
+    /// Two `f32`s per pixel
+    RgF32,
+    /// Three `f32`s per pixel
+    RgbF32,
+    /// Four `f32`s per pixel
+    RgbaF32,
+    /// One half-precision float per pixel
+    RedF16,
+    /// Two half-precision floats per pixel
+    RgF16,
+    /// Three half-precision floats per pixel
+    RgbF16,
+    /// Four half-precision floats per pixel
+    RgbaF16,
+    /// One `u32` per pixel, for integer textures
+    RedU32,
+    /// One `f32` depth value per pixel
+    Depth,
+    /// A packed 24-bit depth and 8-bit stencil value per pixel
+    DepthStencil,
+}
+
+impl PixelFormat {
+    /// The OpenGL pixel format
+    pub fn format(self) -> u32 {
+        match self {
+            PixelFormat::Red | PixelFormat::RedF32 | PixelFormat::RedF16 => gl::RED,
+            PixelFormat::Rg | PixelFormat::RgF32 | PixelFormat::RgF16 => gl::RG,
+            PixelFormat::Rgb | PixelFormat::RgbF32 | PixelFormat::RgbF16 => gl::RGB,
+            PixelFormat::Rgba | PixelFormat::RgbaF32 | PixelFormat::RgbaF16 => gl::RGBA,
+            PixelFormat::Bgra => gl::BGRA,
+            PixelFormat::RedU32 => gl::RED_INTEGER,
+            PixelFormat::Depth => gl::DEPTH_COMPONENT,
+            PixelFormat::DepthStencil => gl::DEPTH_STENCIL,
+        }
+    }
+
+    /// The OpenGL type of each component
+    pub fn ty(self) -> u32 {
+        match self {
+            PixelFormat::Red
+            | PixelFormat::Rg
+            | PixelFormat::Rgb
+            | PixelFormat::Rgba
+            | PixelFormat::Bgra => gl::UNSIGNED_BYTE,
+            PixelFormat::RedF32
+            | PixelFormat::RgF32
+            | PixelFormat::RgbF32
+            | PixelFormat::RgbaF32
+            | PixelFormat::Depth => gl::FLOAT,
+            PixelFormat::RedF16
+            | PixelFormat::RgF16
+            | PixelFormat::RgbF16
+            | PixelFormat::RgbaF16 => gl::HALF_FLOAT,
+            PixelFormat::RedU32 => gl::UNSIGNED_INT,
+            PixelFormat::DepthStencil => gl::UNSIGNED_INT_24_8,
+        }
+    }
+
+    /// The number of bytes occupied by a single pixel
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Red => 1,
+            PixelFormat::Rg | PixelFormat::RedF16 => 2,
+            PixelFormat::Rgb => 3,
+            PixelFormat::Rgba | PixelFormat::Bgra | PixelFormat::RgF16 => 4,
+            PixelFormat::RedF32 | PixelFormat::RedU32 | PixelFormat::Depth => 4,
+            PixelFormat::DepthStencil => 4,
+            PixelFormat::RgbF16 => 6,
+            PixelFormat::RgF32 | PixelFormat::RgbaF16 => 8,
+            PixelFormat::RgbF32 => 12,
+            PixelFormat::RgbaF32 => 16,
+        }
+    }
+}
+
+/// Texture sampling filter
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+impl From<Filter> for i32 {
+    fn from(filter: Filter) -> i32 {
+        match filter {
+            Filter::Nearest => gl::NEAREST as i32,
+            Filter::Linear => gl::LINEAR as i32,
+        }
+    }
+}
+
+/// Texture coordinate wrapping behaviour
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl From<Wrap> for i32 {
+    fn from(wrap: Wrap) -> i32 {
+        match wrap {
+            Wrap::Repeat => gl::REPEAT as i32,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT as i32,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE as i32,
+        }
+    }
+}
+
+/// A reference to a texture that can be passed as the value of a sampler uniform.
+///
+/// Texture units are assigned automatically when the binding is uploaded through
+/// [`crate::uniforms::Uniforms`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TextureBinding {
+    /// The texture target, e.g. `GL_TEXTURE_2D`
+    pub target: u32,
+    /// The OpenGL name of the texture
+    pub id: u32,
+}
+
+impl TextureBinding {
+    /// Binds the texture to texture unit `unit`
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(self.target, self.id);
+        };
+    }
+}
+
+impl Uniform for TextureBinding {
+    /// Binds the texture to unit `0`. Prefer passing textures through
+    /// [`crate::uniforms::Uniforms`], which assigns a unit to each texture.
+    fn upload(&self, location: i32) {
+        self.bind(0);
+        0i32.upload(location);
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        self.bind(0);
+        0i32.upload_to_program(program, location);
+    }
+
+    fn texture(&self) -> Option<TextureBinding> {
+        Some(*self)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Texture(self)
+    }
+}
+
+/// Assigns texture units to the textures used by a single draw call.
+///
+/// A texture used by several samplers is only bound once.
+#[derive(Debug)]
+pub struct TextureUnits {
+    first_unit: u32,
+    /// `GL_MAX_COMBINED_TEXTURE_IMAGE_UNITS`, queried when the allocator is created
+    max_units: u32,
+    assigned: Vec<TextureBinding>,
+}
+
+impl Default for TextureUnits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextureUnits {
+    /// Constructs an allocator handing out units starting at `0`
+    pub fn new() -> Self {
+        Self::starting_at(0)
+    }
+
+    /// Constructs an allocator handing out units starting at `first_unit`, leaving lower
+    /// units to be managed by hand
+    pub fn starting_at(first_unit: u32) -> Self {
+        Self {
+            first_unit,
+            max_units: Limits::current().max_combined_texture_image_units,
+            assigned: vec![],
+        }
+    }
+
+    /// Returns the unit `texture` is bound to, binding it to the next free unit if it hasn't
+    /// been assigned one yet.
+    ///
+    /// # Returns
+    ///
+    /// The unit, or `None` without binding anything if every unit the context provides is
+    /// taken.
+    pub fn assign(&mut self, texture: TextureBinding) -> Option<u32> {
+        if let Some(index) = self.assigned.iter().position(|t| *t == texture) {
+            return Some(self.first_unit + index as u32);
+        }
+
+        let unit = self.first_unit + self.assigned.len() as u32;
+        if unit >= self.max_units {
+            return None;
+        }
+
+        texture.bind(unit);
+        self.assigned.push(texture);
+        Some(unit)
+    }
+
+    /// Returns the number of texture units the context provides, including those below the
+    /// first unit handed out
+    pub fn max_units(&self) -> u32 {
+        self.max_units
+    }
+
+    /// Returns the number of units assigned so far
+    pub fn len(&self) -> usize {
+        self.assigned.len()
+    }
+
+    /// Returns whether no units have been assigned
+    pub fn is_empty(&self) -> bool {
+        self.assigned.is_empty()
+    }
+
+    /// Forgets every assignment, e.g. between draw calls
+    pub fn reset(&mut self) {
+        self.assigned.clear();
+    }
+}
+
+/// How a shader may access a texture bound as an image, which must agree with the
+/// `readonly` and `writeonly` qualifiers of the GLSL image uniform
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ImageAccess {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+impl From<ImageAccess> for u32 {
+    fn from(access: ImageAccess) -> u32 {
+        match access {
+            ImageAccess::ReadOnly => gl::READ_ONLY,
+            ImageAccess::WriteOnly => gl::WRITE_ONLY,
+            ImageAccess::ReadWrite => gl::READ_WRITE,
+        }
+    }
+}
+
+/// An image binding that was rejected before reaching OpenGL
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageBindError {
+    /// The context doesn't support image load/store
+    Unsupported(Unsupported),
+    /// The format can't be used for image load/store
+    InvalidFormat(TextureFormat),
+    /// The format's texels differ in size from those of the texture, so it can't be used to
+    /// reinterpret the texture
+    IncompatibleFormat {
+        texture: TextureFormat,
+        image: TextureFormat,
+    },
+    /// The mip level doesn't exist in the texture
+    LevelOutOfRange { level: u32, levels: u32 },
+    /// The image unit is at or above `GL_MAX_IMAGE_UNITS`
+    UnitOutOfRange { unit: u32, limit: u32 },
+}
+
+impl std::fmt::Display for ImageBindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageBindError::Unsupported(error) => write!(f, "{error}"),
+            ImageBindError::InvalidFormat(format) => {
+                write!(f, "{format:?} can't be used for image load/store")
+            }
+            ImageBindError::IncompatibleFormat { texture, image } => write!(
+                f,
+                "A {texture:?} texture can't be bound as a {image:?} image"
+            ),
+            ImageBindError::LevelOutOfRange { level, levels } => write!(
+                f,
+                "Mip level {level} is out of range for a texture with {levels} levels"
+            ),
+            ImageBindError::UnitOutOfRange { unit, limit } => write!(
+                f,
+                "Image unit {unit} is out of range, only {limit} are available"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImageBindError {}
+
+impl From<Unsupported> for ImageBindError {
+    fn from(error: Unsupported) -> Self {
+        ImageBindError::Unsupported(error)
+    }
+}
+
+/// A texture level to bind to an image unit
+struct ImageLevel {
+    id: u32,
+    level: u32,
+    levels: u32,
+    /// Whether every layer is bound, for array, cube and 3D textures
+    layered: bool,
+    format: TextureFormat,
+}
+
+impl ImageLevel {
+    /// Binds the level to image unit `unit` after checking that `format` can reinterpret it
+    fn bind(
+        &self,
+        unit: u32,
+        access: ImageAccess,
+        format: TextureFormat,
+    ) -> Result<(), ImageBindError> {
+        if !context::supports_image_load_store() {
+            return Err(Unsupported {
+                feature: "GL_ARB_shader_image_load_store",
+            }
+            .into());
+        }
+
+        let size = format
+            .image_texel_size()
+            .ok_or(ImageBindError::InvalidFormat(format))?;
+        if self.format.image_texel_size() != Some(size) {
+            return Err(ImageBindError::IncompatibleFormat {
+                texture: self.format,
+                image: format,
+            });
+        }
+        if self.level >= self.levels {
+            return Err(ImageBindError::LevelOutOfRange {
+                level: self.level,
+                levels: self.levels,
+            });
+        }
+
+        let mut limit = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_IMAGE_UNITS, &mut limit) };
+        let limit = limit.max(0) as u32;
+        if unit >= limit {
+            return Err(ImageBindError::UnitOutOfRange { unit, limit });
+        }
+
+        unsafe {
+            gl::BindImageTexture(
+                unit,
+                self.id,
+                self.level as i32,
+                self.layered as u8,
+                0,
+                access.into(),
+                format.internal_format(),
+            )
+        };
+        Ok(())
+    }
+}
+
+/// Makes shader writes to images visible to image loads issued afterwards, e.g. between two
+/// compute dispatches
+pub fn image_barrier() {
+    unsafe { gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT) };
+}
+
+/// Makes shader writes to images visible to texture sampling issued afterwards, e.g. before
+/// drawing with a texture a compute shader filled
+pub fn texture_fetch_barrier() {
+    unsafe { gl::MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT) };
+}
+
+/// A two-dimensional OpenGL texture
+#[derive(Debug)]
+pub struct Texture2D {
+    id: u32,
+    width: u32,
+    height: u32,
+    levels: u32,
+    format: TextureFormat,
+}
+
+impl Texture2D {
+    /// Allocates an uninitialized texture
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Self {
+        Self::allocate(width, height, 1, format, null())
+    }
+
+    /// Allocates an uninitialized texture with `levels` mip levels, each half the size of the
+    /// previous one.
+    ///
+    /// # Panics
+    /// This function panics if `levels` is `0` or greater than
+    /// [`Texture2D::max_levels`] for the given size.
+    pub fn with_mipmaps(width: u32, height: u32, levels: u32, format: TextureFormat) -> Self {
+        let max_levels = Self::max_levels(width, height);
+        assert!(
+            (1..=max_levels).contains(&levels),
+            "A {width}x{height} texture can have between 1 and {max_levels} mip levels, got {levels}"
+        );
+
+        Self::allocate(width, height, levels, format, null())
+    }
+
+    /// Returns the number of mip levels of a complete mip chain for a texture of the given
+    /// size, down to and including the 1x1 level
+    pub fn max_levels(width: u32, height: u32) -> u32 {
+        u32::BITS - width.max(height).max(1).leading_zeros()
+    }
+
+    /// Creates a texture from tightly packed pixel data laid out according to
+    /// [`TextureFormat::pixel_format`].
+    ///
+    /// # Panics
+    /// This function panics if `data` is too small for a texture of the given size.
+    pub fn with_data(width: u32, height: u32, format: TextureFormat, data: &[u8]) -> Self {
+        let expected = width as usize * height as usize * format.pixel_format().bytes_per_pixel();
+        assert!(
+            data.len() >= expected,
+            "Expected at least {expected} bytes of texture data, got {}",
+            data.len()
+        );
+
+        Self::allocate(width, height, 1, format, data.as_ptr().cast())
+    }
+
+    /// Creates a texture from half-precision floats, e.g. HDR image data, without widening it
+    /// to `f32` first.
+    ///
+    /// # Panics
+    /// This function panics if `format` isn't a floating-point color format or `data` holds
+    /// fewer components than the texture needs.
+    #[cfg(feature = "half")]
+    pub fn with_f16_data(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[half::f16],
+    ) -> Self {
+        let pixel_format = format
+            .half_pixel_format()
+            .unwrap_or_else(|| panic!("{format:?} textures can't be created from f16 data"));
+        let expected = width as usize * height as usize * pixel_format.bytes_per_pixel();
+        assert!(
+            std::mem::size_of_val(data) >= expected,
+            "Expected at least {expected} bytes of texture data, got {}",
+            std::mem::size_of_val(data)
+        );
+
+        Self::allocate_with(width, height, 1, format, pixel_format, data.as_ptr().cast())
+    }
+
+    fn allocate(
+        width: u32,
+        height: u32,
+        levels: u32,
+        format: TextureFormat,
+        data: *const std::os::raw::c_void,
+    ) -> Self {
+        Self::allocate_with(width, height, levels, format, format.pixel_format(), data)
+    }
+
+    /// Allocates the texture, uploading `data` laid out as `pixel_format` to the base level
+    fn allocate_with(
+        width: u32,
+        height: u32,
+        levels: u32,
+        format: TextureFormat,
+        pixel_format: PixelFormat,
+        data: *const std::os::raw::c_void,
+    ) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, std::ptr::addr_of_mut!(id));
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            for level in 0..levels {
+                let (level_width, level_height) = mip_dimensions((width, height), level);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    level as i32,
+                    format.internal_format() as i32,
+                    level_width as i32,
+                    level_height as i32,
+                    0,
+                    pixel_format.format(),
+                    pixel_format.ty(),
+                    if level == 0 { data } else { null() },
+                );
+            }
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, levels as i32 - 1);
+
+            let filter = if format == TextureFormat::R32UI {
+                Filter::Nearest
+            } else {
+                Filter::Linear
+            };
+            let min_filter = match (filter, levels) {
+                (_, 1) => filter.into(),
+                (Filter::Nearest, _) => gl::NEAREST_MIPMAP_NEAREST as i32,
+                (Filter::Linear, _) => gl::LINEAR_MIPMAP_LINEAR as i32,
+            };
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter.into());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, Wrap::ClampToEdge.into());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, Wrap::ClampToEdge.into());
+        };
+
+        Self {
+            id,
+            width,
+            height,
+            levels,
+            format,
+        }
+    }
+
+    /// Binds the texture to texture unit `unit`
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+        };
+    }
+
+    /// Binds the base level to image unit `unit` for image load/store, accessed as `format`.
+    /// See [`Texture2D::bind_image_level`].
+    pub fn bind_image(
+        &self,
+        unit: u32,
+        access: ImageAccess,
+        format: TextureFormat,
+    ) -> Result<(), ImageBindError> {
+        self.bind_image_level(unit, 0, access, format)
+    }
+
+    /// Binds mip level `level` to image unit `unit` for image load/store, accessed as
+    /// `format`, which must have texels of the same size as the texture's format
+    pub fn bind_image_level(
+        &self,
+        unit: u32,
+        level: u32,
+        access: ImageAccess,
+        format: TextureFormat,
+    ) -> Result<(), ImageBindError> {
+        ImageLevel {
+            id: self.id,
+            level,
+            levels: self.levels,
+            layered: false,
+            format: self.format,
+        }
+        .bind(unit, access, format)
+    }
+
+    /// Fills every mip level below the base level by downsampling the base level
+    pub fn generate_mipmaps(&mut self) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        };
+    }
+
+    /// Restricts sampling to the mip levels in `levels`, so that other levels can be rendered
+    /// to while this texture is being sampled
+    #[cfg(feature = "passes")]
+    pub(crate) fn set_sampled_levels(&self, levels: std::ops::RangeInclusive<u32>) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_BASE_LEVEL,
+                *levels.start() as i32,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, *levels.end() as i32);
+        };
+    }
+
+    /// Returns a binding that can be passed as the value of a `sampler2D` uniform
+    pub fn binding(&self) -> TextureBinding {
+        TextureBinding {
+            target: gl::TEXTURE_2D,
+            id: self.id,
+        }
+    }
+
+    /// Sets the minification and magnification filters
+    pub fn set_filter(&mut self, min: Filter, mag: Filter) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min.into());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag.into());
+        };
+    }
+
+    /// Sets the wrapping behaviour along both axes
+    pub fn set_wrap(&mut self, wrap: Wrap) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap.into());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap.into());
+        };
+    }
+
+    /// Returns the OpenGL name of the texture
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the width of the texture in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the texture in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the width and height of the texture in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the number of mip levels of the texture
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    /// Returns the width and height of mip level `level` in pixels
+    pub fn level_dimensions(&self, level: u32) -> (u32, u32) {
+        mip_dimensions((self.width, self.height), level)
+    }
+
+    /// Returns the internal format of the texture
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+/// Returns the size of mip level `level` of an image whose base level has the given size
+fn mip_dimensions((width, height): (u32, u32), level: u32) -> (u32, u32) {
+    (
+        width.checked_shr(level).unwrap_or(0).max(1),
+        height.checked_shr(level).unwrap_or(0).max(1),
+    )
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        // SAFETY: We are being dropped, so we can destroy the texture we correspond with
+        unsafe { gl::DeleteTextures(1, &self.id) };
+    }
+}
+
+/// An array of two-dimensional textures of the same size and format, sampled in GLSL with
+/// `sampler2DArray`
+#[derive(Debug)]
+pub struct Texture2DArray {
+    id: u32,
+    width: u32,
+    height: u32,
+    layers: u32,
+    format: TextureFormat,
+}
+
+impl Texture2DArray {
+    /// Creates an uninitialized texture array with `layers` layers
+    pub fn new(width: u32, height: u32, layers: u32, format: TextureFormat) -> Self {
+        let mut id = 0;
+        let pixel_format = format.pixel_format();
+        unsafe {
+            gl::GenTextures(1, std::ptr::addr_of_mut!(id));
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                format.internal_format() as i32,
+                width as i32,
+                height as i32,
+                layers as i32,
+                0,
+                pixel_format.format(),
+                pixel_format.ty(),
+                null(),
+            );
+
+            let filter = if format == TextureFormat::R32UI {
+                Filter::Nearest
+            } else {
+                Filter::Linear
+            };
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, filter.into());
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, filter.into());
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_WRAP_S,
+                Wrap::ClampToEdge.into(),
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_WRAP_T,
+                Wrap::ClampToEdge.into(),
+            );
+        };
+
+        Self {
+            id,
+            width,
+            height,
+            layers,
+            format,
+        }
+    }
+
+    /// Binds the texture array to texture unit `unit`
+    pub fn bind(&self, unit: u32) {
+        self.binding().bind(unit);
+    }
+
+    /// Binds every layer to image unit `unit` for image load/store as an `image2DArray`,
+    /// accessed as `format`, which must have texels of the same size as the texture's format
+    pub fn bind_image(
+        &self,
+        unit: u32,
+        access: ImageAccess,
+        format: TextureFormat,
+    ) -> Result<(), ImageBindError> {
+        ImageLevel {
+            id: self.id,
+            level: 0,
+            levels: 1,
+            layered: true,
+            format: self.format,
+        }
+        .bind(unit, access, format)
+    }
+
+    /// Returns a binding that can be passed as the value of a `sampler2DArray` uniform
+    pub fn binding(&self) -> TextureBinding {
+        TextureBinding {
+            target: gl::TEXTURE_2D_ARRAY,
+            id: self.id,
+        }
+    }
+
+    /// Returns the OpenGL name of the texture
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the width and height of each layer in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the number of layers
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    /// Returns the internal format of the texture
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+impl Drop for Texture2DArray {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.id) };
+    }
+}
+
+/// A three-dimensional texture, sampled in GLSL with `sampler3D`, e.g. for color lookup tables
+/// or volumetric data
+#[derive(Debug)]
+pub struct Texture3D {
+    id: u32,
+    width: u32,
+    height: u32,
+    depth: u32,
+    format: TextureFormat,
+}
+
+impl Texture3D {
+    /// Allocates an uninitialized texture
+    pub fn new(width: u32, height: u32, depth: u32, format: TextureFormat) -> Self {
+        Self::allocate(width, height, depth, format, null())
+    }
+
+    /// Creates a texture from tightly packed pixel data laid out according to
+    /// [`TextureFormat::pixel_format`], with `x` varying fastest and `z` slowest.
+    ///
+    /// # Panics
+    /// This function panics if `data` is too small for a texture of the given size.
+    pub fn with_data(
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+        data: &[u8],
+    ) -> Self {
+        let expected = width as usize
+            * height as usize
+            * depth as usize
+            * format.pixel_format().bytes_per_pixel();
+        assert!(
+            data.len() >= expected,
+            "Expected at least {expected} bytes of texture data, got {}",
+            data.len()
+        );
+
+        Self::allocate(width, height, depth, format, data.as_ptr().cast())
+    }
+
+    fn allocate(
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+        data: *const std::os::raw::c_void,
+    ) -> Self {
+        let mut id = 0;
+        let pixel_format = format.pixel_format();
+        unsafe {
+            gl::GenTextures(1, std::ptr::addr_of_mut!(id));
+            gl::BindTexture(gl::TEXTURE_3D, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage3D(
+                gl::TEXTURE_3D,
+                0,
+                format.internal_format() as i32,
+                width as i32,
+                height as i32,
+                depth as i32,
+                0,
+                pixel_format.format(),
+                pixel_format.ty(),
+                data,
+            );
+
+            let filter = if format == TextureFormat::R32UI {
+                Filter::Nearest
+            } else {
+                Filter::Linear
+            };
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, filter.into());
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, filter.into());
+        };
+
+        let texture = Self {
+            id,
+            width,
+            height,
+            depth,
+            format,
+        };
+        texture.apply_wrap(Wrap::ClampToEdge);
+        texture
+    }
+
+    /// Binds the texture to texture unit `unit`
+    pub fn bind(&self, unit: u32) {
+        self.binding().bind(unit);
+    }
+
+    /// Binds every slice to image unit `unit` for image load/store as an `image3D`,
+    /// accessed as `format`, which must have texels of the same size as the texture's format
+    pub fn bind_image(
+        &self,
+        unit: u32,
+        access: ImageAccess,
+        format: TextureFormat,
+    ) -> Result<(), ImageBindError> {
+        ImageLevel {
+            id: self.id,
+            level: 0,
+            levels: 1,
+            layered: true,
+            format: self.format,
+        }
+        .bind(unit, access, format)
+    }
+
+    /// Returns a binding that can be passed as the value of a `sampler3D` uniform
+    pub fn binding(&self) -> TextureBinding {
+        TextureBinding {
+            target: gl::TEXTURE_3D,
+            id: self.id,
+        }
+    }
+
+    /// Sets the minification and magnification filters
+    pub fn set_filter(&mut self, min: Filter, mag: Filter) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_3D, self.id);
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, min.into());
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, mag.into());
+        };
+    }
+
+    /// Sets the wrapping behaviour along all three axes
+    pub fn set_wrap(&mut self, wrap: Wrap) {
+        self.apply_wrap(wrap);
+    }
+
+    fn apply_wrap(&self, wrap: Wrap) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_3D, self.id);
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_S, wrap.into());
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_T, wrap.into());
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_R, wrap.into());
+        };
+    }
+
+    /// Returns the OpenGL name of the texture
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the width, height and depth of the texture in pixels
+    pub fn dimensions(&self) -> (u32, u32, u32) {
+        (self.width, self.height, self.depth)
+    }
+
+    /// Returns the internal format of the texture
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+impl Drop for Texture3D {
+    fn drop(&mut self) {
+        // SAFETY: We are being dropped, so we can destroy the texture we correspond with
+        unsafe { gl::DeleteTextures(1, &self.id) };
+    }
+}
+
+/// A cube map texture, e.g. for skyboxes and reflections, with six square faces
+pub struct TextureCube {
+    id: u32,
+    size: u32,
+    format: TextureFormat,
+}
+
+impl TextureCube {
+    /// Allocates an uninitialized cube map whose faces are `size` pixels wide and high
+    pub fn new(size: u32, format: TextureFormat) -> Self {
+        Self::allocate(size, format, [null(); 6])
+    }
+
+    /// Creates a cube map from tightly packed pixel data for each face, laid out according to
+    /// [`TextureFormat::pixel_format`], in the order `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`.
+    ///
+    /// # Panics
+    /// This function panics if the data of any face is too small for a face of the given size.
+    pub fn with_faces(size: u32, format: TextureFormat, faces: [&[u8]; 6]) -> Self {
+        let expected = size as usize * size as usize * format.pixel_format().bytes_per_pixel();
+        for face in faces {
+            assert!(
+                face.len() >= expected,
+                "Expected at least {expected} bytes of data per face, got {}",
+                face.len()
+            );
+        }
+
+        Self::allocate(size, format, faces.map(|face| face.as_ptr().cast()))
+    }
+
+    fn allocate(size: u32, format: TextureFormat, faces: [*const std::os::raw::c_void; 6]) -> Self {
+        let mut id = 0;
+        let pixel_format = format.pixel_format();
+        unsafe {
+            gl::GenTextures(1, std::ptr::addr_of_mut!(id));
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            for (face, data) in faces.into_iter().enumerate() {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                    0,
+                    format.internal_format() as i32,
+                    size as i32,
+                    size as i32,
+                    0,
+                    pixel_format.format(),
+                    pixel_format.ty(),
+                    data,
+                );
+            }
+
+            let wrap = Wrap::ClampToEdge.into();
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, wrap);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, wrap);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, wrap);
+        };
+
+        let mut texture = Self { id, size, format };
+        texture.set_filter(Filter::Linear, Filter::Linear);
+        texture
+    }
+
+    /// Binds the texture to texture unit `unit`
+    pub fn bind(&self, unit: u32) {
+        self.binding().bind(unit);
+    }
+
+    /// Binds every face to image unit `unit` for image load/store as an `imageCube`,
+    /// accessed as `format`, which must have texels of the same size as the texture's format
+    pub fn bind_image(
+        &self,
+        unit: u32,
+        access: ImageAccess,
+        format: TextureFormat,
+    ) -> Result<(), ImageBindError> {
+        ImageLevel {
+            id: self.id,
+            level: 0,
+            levels: 1,
+            layered: true,
+            format: self.format,
+        }
+        .bind(unit, access, format)
+    }
+
+    /// Returns a binding that can be passed as the value of a `samplerCube` uniform
+    pub fn binding(&self) -> TextureBinding {
+        TextureBinding {
+            target: gl::TEXTURE_CUBE_MAP,
+            id: self.id,
+        }
+    }
+
+    /// Sets the minification and magnification filters
+    pub fn set_filter(&mut self, min: Filter, mag: Filter) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, min.into());
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, mag.into());
+        };
+    }
+
+    /// Returns the OpenGL name of the texture
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the width and height of each face in pixels
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the internal format of the texture
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+impl Drop for TextureCube {
+    fn drop(&mut self) {
+        // SAFETY: We are being dropped, so we can destroy the texture we correspond with
+        unsafe { gl::DeleteTextures(1, &self.id) };
+    }
+}
+
+/// An array of cube maps of the same size and format, sampled in GLSL with
+/// `samplerCubeArray` (or `samplerCubeArrayShadow`) using a `vec4` of the direction and the
+/// cube's index, e.g. for many point light shadow maps or reflection probes bound at once.
+///
+/// Each face of each cube is a layer of the array, see [`TextureCubeArray::layer`].
+#[derive(Debug)]
+pub struct TextureCubeArray {
+    id: u32,
+    size: u32,
+    cubes: u32,
+    format: TextureFormat,
+}
+
+impl TextureCubeArray {
+    /// Allocates `cubes` uninitialized cube maps whose faces are `size` pixels wide and high
+    pub fn new(size: u32, cubes: u32, format: TextureFormat) -> Self {
+        let mut id = 0;
+        let pixel_format = format.pixel_format();
+        unsafe {
+            gl::GenTextures(1, std::ptr::addr_of_mut!(id));
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, id);
+            gl::TexImage3D(
+                gl::TEXTURE_CUBE_MAP_ARRAY,
+                0,
+                format.internal_format() as i32,
+                size as i32,
+                size as i32,
+                (cubes * 6) as i32,
+                0,
+                pixel_format.format(),
+                pixel_format.ty(),
+                null(),
+            );
+
+            let wrap = Wrap::ClampToEdge.into();
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_S, wrap);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_T, wrap);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_R, wrap);
+        };
+
+        let mut texture = Self {
+            id,
+            size,
+            cubes,
+            format,
+        };
+        let filter = if format == TextureFormat::R32UI {
+            Filter::Nearest
+        } else {
+            Filter::Linear
+        };
+        texture.set_filter(filter, filter);
+        texture
+    }
+
+    /// Returns the array layer holding `face` of cube `cube`, e.g. for attaching it to a
+    /// framebuffer with `glFramebufferTextureLayer`. Faces are in the order `+X`, `-X`, `+Y`,
+    /// `-Y`, `+Z`, `-Z`.
+    ///
+    /// # Panics
+    /// This function panics if `cube` or `face` is out of range.
+    pub fn layer(&self, cube: u32, face: u32) -> u32 {
+        assert!(
+            cube < self.cubes && face < 6,
+            "Face {face} of cube {cube} is out of range for an array of {} cubes",
+            self.cubes
+        );
+        cube * 6 + face
+    }
+
+    /// Replaces the pixels of `face` of cube `cube` with tightly packed `data`, laid out
+    /// according to [`TextureFormat::pixel_format`].
+    ///
+    /// # Panics
+    /// This function panics if `cube` or `face` is out of range, or if `data` is too small for
+    /// a face.
+    pub fn upload_face(&mut self, cube: u32, face: u32, data: &[u8]) {
+        let layer = self.layer(cube, face);
+        let pixel_format = self.format.pixel_format();
+        let expected = self.size as usize * self.size as usize * pixel_format.bytes_per_pixel();
+        assert!(
+            data.len() >= expected,
+            "Expected at least {expected} bytes of data per face, got {}",
+            data.len()
+        );
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, self.id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage3D(
+                gl::TEXTURE_CUBE_MAP_ARRAY,
+                0,
+                0,
+                0,
+                layer as i32,
+                self.size as i32,
+                self.size as i32,
+                1,
+                pixel_format.format(),
+                pixel_format.ty(),
+                data.as_ptr().cast(),
+            );
+        };
+    }
+
+    /// Binds the texture to texture unit `unit`
+    pub fn bind(&self, unit: u32) {
+        self.binding().bind(unit);
+    }
+
+    /// Binds every face of every cube to image unit `unit` for image load/store as an
+    /// `imageCubeArray`, accessed as `format`, which must have texels of the same size as the
+    /// texture's format
+    pub fn bind_image(
+        &self,
+        unit: u32,
+        access: ImageAccess,
+        format: TextureFormat,
+    ) -> Result<(), ImageBindError> {
+        ImageLevel {
+            id: self.id,
+            level: 0,
+            levels: 1,
+            layered: true,
+            format: self.format,
+        }
+        .bind(unit, access, format)
+    }
+
+    /// Returns a binding that can be passed as the value of a `samplerCubeArray` uniform
+    pub fn binding(&self) -> TextureBinding {
+        TextureBinding {
+            target: gl::TEXTURE_CUBE_MAP_ARRAY,
+            id: self.id,
+        }
+    }
+
+    /// Sets the minification and magnification filters
+    pub fn set_filter(&mut self, min: Filter, mag: Filter) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, self.id);
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP_ARRAY,
+                gl::TEXTURE_MIN_FILTER,
+                min.into(),
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP_ARRAY,
+                gl::TEXTURE_MAG_FILTER,
+                mag.into(),
+            );
+        };
+    }
+
+    /// Returns the OpenGL name of the texture
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the width and height of each face in pixels
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the number of cube maps
+    pub fn cubes(&self) -> u32 {
+        self.cubes
+    }
+
+    /// Returns the internal format of the texture
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+impl Drop for TextureCubeArray {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.id) };
+    }
+}
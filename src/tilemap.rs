@@ -0,0 +1,499 @@
+use crate::{
+    buffer::{VertexBuffer, VertexData},
+    renderer::{DrawError, DrawMode},
+    shader::{
+        LinkedProgram, Program, Shader, ShaderType, Vertex, VertexAttributeSpec, VertexFormat,
+    },
+    surface::Surface,
+    texture::{Texture2D, TextureFormat},
+    uniforms,
+};
+use glm::{Mat4, Vec2};
+use std::rc::Rc;
+
+/// The number of tiles along each side of a chunk
+pub const CHUNK_SIZE: u32 = 32;
+
+/// A texture holding equally sized tiles in a grid, numbered row by row from the top left
+/// starting at `0`.
+///
+/// Tile data is expected top row first, as images are usually stored.
+#[derive(Debug)]
+pub struct TileAtlas {
+    texture: Texture2D,
+    tile_size: (u32, u32),
+    columns: u32,
+    rows: u32,
+}
+
+impl TileAtlas {
+    /// Creates an atlas of the tiles of `tile_width` by `tile_height` pixels in `texture`.
+    /// Leftover pixels on the right and bottom edges are ignored.
+    ///
+    /// # Panics
+    /// This function panics if the tile size is `0` or larger than the texture.
+    pub fn new(texture: Texture2D, tile_width: u32, tile_height: u32) -> Self {
+        let (width, height) = texture.dimensions();
+        assert!(
+            tile_width > 0 && tile_height > 0 && tile_width <= width && tile_height <= height,
+            "A {tile_width}x{tile_height} tile doesn't fit a {width}x{height} atlas"
+        );
+
+        Self {
+            texture,
+            tile_size: (tile_width, tile_height),
+            columns: width / tile_width,
+            rows: height / tile_height,
+        }
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    pub fn texture_mut(&mut self) -> &mut Texture2D {
+        &mut self.texture
+    }
+
+    /// Returns the width and height of a tile in pixels
+    pub fn tile_size(&self) -> (u32, u32) {
+        self.tile_size
+    }
+
+    /// Returns the number of tile columns and rows
+    pub fn grid(&self) -> (u32, u32) {
+        (self.columns, self.rows)
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Returns the texture coordinates of the top left and bottom right corner of `tile`
+    pub fn tile_uv(&self, tile: u32) -> (Vec2, Vec2) {
+        let size = self.tile_uv_size();
+        let origin = glm::vec2(
+            (tile % self.columns) as f32 * size.x,
+            (tile / self.columns) as f32 * size.y,
+        );
+        (origin, origin + size)
+    }
+
+    /// Returns the size of a tile in texture coordinates
+    fn tile_uv_size(&self) -> Vec2 {
+        let (width, height) = self.texture.dimensions();
+        glm::vec2(
+            self.tile_size.0 as f32 / width as f32,
+            self.tile_size.1 as f32 / height as f32,
+        )
+    }
+}
+
+/// How a [`Tilemap`] gets its tiles to the GPU
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum TilemapMode {
+    /// Every chunk is a static vertex buffer with a quad per tile. Cheap to draw, but every
+    /// change rebuilds the chunk's buffer.
+    #[default]
+    Meshes,
+    /// Every chunk is a single quad looking its tiles up in a texture of tile indices. Cheap
+    /// to change, at the cost of a lookup per fragment; suited to maps edited every frame.
+    IndexTexture,
+}
+
+/// The GPU resources of a chunk, rebuilt when its tiles change
+#[derive(Default)]
+struct Chunk {
+    dirty: bool,
+    mesh: Option<VertexBuffer<TileVertex>>,
+    indices: Option<Texture2D>,
+}
+
+/// A grid of tiles from a [`TileAtlas`], split into chunks of [`CHUNK_SIZE`] by [`CHUNK_SIZE`]
+/// tiles that are only rebuilt when their tiles change.
+///
+/// Tile `(x, y)` covers `x * tile_width` to `(x + 1) * tile_width` horizontally and
+/// `y * tile_height` to `(y + 1) * tile_height` vertically in world space, so row `0` is on
+/// top with a Y-down projection such as `matrix::ortho(0.0, width, -1.0, 1.0, 0.0, height)`,
+/// as in most map editors. Layers are separate tilemaps drawn back to front, usually sharing
+/// an atlas.
+pub struct Tilemap {
+    atlas: Rc<TileAtlas>,
+    width: u32,
+    height: u32,
+    tile_size: Vec2,
+    mode: TilemapMode,
+    /// `0` for empty cells and the atlas index plus one otherwise
+    tiles: Vec<u32>,
+    chunks: Vec<Chunk>,
+    program: LinkedProgram,
+}
+
+impl Tilemap {
+    const VERTEX_SHADER: &'static str = r#"
+        #version 460 core
+        layout(location = 0) in vec2 position;
+        layout(location = 1) in vec2 uv;
+
+        uniform mat4 u_view_projection;
+
+        out vec2 tile_uv;
+
+        void main() {
+            tile_uv = uv;
+            gl_Position = u_view_projection * vec4(position, 0.0, 1.0);
+        }
+    "#;
+
+    const MESH_FRAGMENT_SHADER: &'static str = r#"
+        #version 460 core
+
+        uniform sampler2D u_atlas;
+
+        in vec2 tile_uv;
+
+        out vec4 color;
+
+        void main() {
+            color = texture(u_atlas, tile_uv);
+            if (color.a == 0.0) {
+                discard;
+            }
+        }
+    "#;
+
+    /// For index textures `tile_uv` holds the position in tiles from the chunk's top left
+    const INDEX_FRAGMENT_SHADER: &'static str = r#"
+        #version 460 core
+
+        uniform usampler2D u_tiles;
+        uniform sampler2D u_atlas;
+        uniform uint u_atlas_columns;
+        uniform vec2 u_tile_uv_size;
+
+        in vec2 tile_uv;
+
+        out vec4 color;
+
+        void main() {
+            ivec2 cell = min(ivec2(tile_uv), textureSize(u_tiles, 0) - 1);
+            uint tile = texelFetch(u_tiles, cell, 0).r;
+            if (tile == 0u) {
+                discard;
+            }
+
+            tile -= 1u;
+            vec2 origin = vec2(tile % u_atlas_columns, tile / u_atlas_columns) * u_tile_uv_size;
+            vec2 uv = origin + fract(tile_uv) * u_tile_uv_size;
+            color = textureGrad(u_atlas, uv, dFdx(tile_uv * u_tile_uv_size), dFdy(tile_uv * u_tile_uv_size));
+            if (color.a == 0.0) {
+                discard;
+            }
+        }
+    "#;
+
+    /// Creates an empty map of `width` by `height` tiles, each `tile_size` world units large
+    pub fn new(
+        atlas: Rc<TileAtlas>,
+        width: u32,
+        height: u32,
+        tile_size: Vec2,
+        mode: TilemapMode,
+    ) -> Self {
+        let fragment_shader = match mode {
+            TilemapMode::Meshes => Self::MESH_FRAGMENT_SHADER,
+            TilemapMode::IndexTexture => Self::INDEX_FRAGMENT_SHADER,
+        };
+        #[allow(clippy::expect_used)]
+        let program = Program::new()
+            .attach_and_link(vec![
+                Shader::new(Self::VERTEX_SHADER, ShaderType::Vertex),
+                Shader::new(fragment_shader, ShaderType::Fragment),
+            ])
+            .expect("Built-in tilemap shader failed to compile");
+
+        let chunk_count = width.div_ceil(CHUNK_SIZE) * height.div_ceil(CHUNK_SIZE);
+        Self {
+            atlas,
+            width,
+            height,
+            tile_size,
+            mode,
+            tiles: vec![0; width as usize * height as usize],
+            chunks: (0..chunk_count).map(|_| Chunk::default()).collect(),
+            program,
+        }
+    }
+
+    /// Creates a map from `tiles`, given row by row from the top left, where `None` marks
+    /// empty cells.
+    ///
+    /// # Panics
+    /// This function panics if there isn't exactly one tile per cell.
+    pub fn with_tiles(
+        atlas: Rc<TileAtlas>,
+        width: u32,
+        height: u32,
+        tile_size: Vec2,
+        mode: TilemapMode,
+        tiles: &[Option<u32>],
+    ) -> Self {
+        assert_eq!(
+            tiles.len(),
+            width as usize * height as usize,
+            "Expected one tile per cell of a {width}x{height} map"
+        );
+
+        let mut map = Self::new(atlas, width, height, tile_size, mode);
+        map.tiles = tiles
+            .iter()
+            .map(|tile| tile.map_or(0, |tile| tile + 1))
+            .collect();
+        for chunk in &mut map.chunks {
+            chunk.dirty = true;
+        }
+        map
+    }
+
+    pub fn atlas(&self) -> &Rc<TileAtlas> {
+        &self.atlas
+    }
+
+    /// Returns the width and height of the map in tiles
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the size of a tile in world units
+    pub fn tile_size(&self) -> Vec2 {
+        self.tile_size
+    }
+
+    pub fn mode(&self) -> TilemapMode {
+        self.mode
+    }
+
+    /// Returns the atlas index of the tile at `(x, y)`, or `None` if the cell is empty or
+    /// outside the map
+    pub fn get(&self, x: u32, y: u32) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles[self.cell_index(x, y)].checked_sub(1)
+    }
+
+    /// Places the atlas tile `tile` at `(x, y)`, or clears the cell if it's `None`
+    ///
+    /// # Panics
+    /// This function panics if `(x, y)` is outside the map.
+    pub fn set(&mut self, x: u32, y: u32, tile: Option<u32>) {
+        assert!(
+            x < self.width && y < self.height,
+            "Tile ({x}, {y}) is outside the {}x{} map",
+            self.width,
+            self.height
+        );
+
+        let index = self.cell_index(x, y);
+        let value = tile.map_or(0, |tile| tile + 1);
+        if self.tiles[index] != value {
+            self.tiles[index] = value;
+            let chunk = self.chunk_index(x / CHUNK_SIZE, y / CHUNK_SIZE);
+            self.chunks[chunk].dirty = true;
+        }
+    }
+
+    /// Places `tile` in every cell
+    pub fn fill(&mut self, tile: Option<u32>) {
+        self.tiles.fill(tile.map_or(0, |tile| tile + 1));
+        for chunk in &mut self.chunks {
+            chunk.dirty = true;
+        }
+    }
+
+    /// Returns the cell containing the world-space position `position`, or `None` if it lies
+    /// outside the map, e.g. for mouse picking with [`crate::camera::Camera::screen_to_world`]
+    pub fn cell_at(&self, position: Vec2) -> Option<(u32, u32)> {
+        let x = (position.x / self.tile_size.x).floor();
+        let y = (position.y / self.tile_size.y).floor();
+        (x >= 0.0 && y >= 0.0 && x < self.width as f32 && y < self.height as f32)
+            .then_some((x as u32, y as u32))
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    fn chunk_index(&self, chunk_x: u32, chunk_y: u32) -> usize {
+        (chunk_y * self.width.div_ceil(CHUNK_SIZE) + chunk_x) as usize
+    }
+
+    /// Rebuilds the GPU resources of the chunks whose tiles changed
+    fn update_chunks(&mut self) {
+        let columns = self.width.div_ceil(CHUNK_SIZE);
+        for index in 0..self.chunks.len() {
+            if !self.chunks[index].dirty {
+                continue;
+            }
+
+            let chunk_x = index as u32 % columns;
+            let chunk_y = index as u32 / columns;
+            let x_range = chunk_x * CHUNK_SIZE..((chunk_x + 1) * CHUNK_SIZE).min(self.width);
+            let y_range = chunk_y * CHUNK_SIZE..((chunk_y + 1) * CHUNK_SIZE).min(self.height);
+            let chunk = match self.mode {
+                TilemapMode::Meshes => Chunk {
+                    dirty: false,
+                    mesh: self.build_mesh(x_range, y_range),
+                    indices: None,
+                },
+                TilemapMode::IndexTexture => self.build_index_texture(x_range, y_range),
+            };
+            self.chunks[index] = chunk;
+        }
+    }
+
+    fn build_mesh(
+        &self,
+        x_range: std::ops::Range<u32>,
+        y_range: std::ops::Range<u32>,
+    ) -> Option<VertexBuffer<TileVertex>> {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for y in y_range {
+            for x in x_range.clone() {
+                let Some(tile) = self.get(x, y) else {
+                    continue;
+                };
+
+                let (uv_min, uv_max) = self.atlas.tile_uv(tile);
+                let min = glm::vec2(x as f32 * self.tile_size.x, y as f32 * self.tile_size.y);
+                let max = min + self.tile_size;
+                let first = vertices.len() as u32;
+                vertices.extend([
+                    TileVertex::new(min, uv_min),
+                    TileVertex::new(glm::vec2(max.x, min.y), glm::vec2(uv_max.x, uv_min.y)),
+                    TileVertex::new(max, uv_max),
+                    TileVertex::new(glm::vec2(min.x, max.y), glm::vec2(uv_min.x, uv_max.y)),
+                ]);
+                indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+            }
+        }
+
+        (!vertices.is_empty()).then(|| VertexBuffer::new_static(&vertices, Some(&indices)))
+    }
+
+    fn build_index_texture(
+        &self,
+        x_range: std::ops::Range<u32>,
+        y_range: std::ops::Range<u32>,
+    ) -> Chunk {
+        let (width, height) = (x_range.len() as u32, y_range.len() as u32);
+        let mut data = Vec::with_capacity((width * height) as usize * 4);
+        for y in y_range.clone() {
+            let row = self.cell_index(x_range.start, y);
+            for tile in &self.tiles[row..row + width as usize] {
+                data.extend_from_slice(&tile.to_ne_bytes());
+            }
+        }
+        if data.chunks_exact(4).all(|tile| tile == [0; 4]) {
+            return Chunk::default();
+        }
+
+        let min = glm::vec2(
+            x_range.start as f32 * self.tile_size.x,
+            y_range.start as f32 * self.tile_size.y,
+        );
+        let max = min
+            + glm::vec2(
+                width as f32 * self.tile_size.x,
+                height as f32 * self.tile_size.y,
+            );
+        let (columns, rows) = (width as f32, height as f32);
+        let quad = [
+            TileVertex::new(min, glm::vec2(0.0, 0.0)),
+            TileVertex::new(glm::vec2(max.x, min.y), glm::vec2(columns, 0.0)),
+            TileVertex::new(max, glm::vec2(columns, rows)),
+            TileVertex::new(glm::vec2(min.x, max.y), glm::vec2(0.0, rows)),
+        ];
+
+        Chunk {
+            dirty: false,
+            mesh: Some(VertexBuffer::new_static(&quad, Some(&[0, 1, 2, 0, 2, 3]))),
+            indices: Some(Texture2D::with_data(
+                width,
+                height,
+                TextureFormat::R32UI,
+                &data,
+            )),
+        }
+    }
+
+    /// Draws the map into `target`, first rebuilding the chunks whose tiles changed
+    pub fn draw<S: Surface>(
+        &mut self,
+        target: &mut S,
+        view_projection: Mat4,
+    ) -> Result<(), DrawError> {
+        self.update_chunks();
+
+        let program = &self.program;
+        let atlas = self.atlas.texture.binding();
+        for chunk in &self.chunks {
+            let Some(ref mesh) = chunk.mesh else {
+                continue;
+            };
+
+            let uniforms = match chunk.indices {
+                Some(ref indices) => uniforms!(program => {
+                    "u_view_projection": view_projection,
+                    "u_atlas": atlas,
+                    "u_tiles": indices.binding(),
+                    "u_atlas_columns": self.atlas.columns,
+                    "u_tile_uv_size": self.atlas.tile_uv_size()
+                }),
+                None => uniforms!(program => {
+                    "u_view_projection": view_projection,
+                    "u_atlas": atlas
+                }),
+            };
+            target.draw(mesh, program, DrawMode::Triangles, &uniforms)?;
+        }
+        Ok(())
+    }
+}
+
+/// A corner of a tile quad
+#[derive(Debug, Copy, Clone)]
+struct TileVertex {
+    position: Vec2,
+    uv: Vec2,
+}
+
+impl TileVertex {
+    fn new(position: Vec2, uv: Vec2) -> Self {
+        Self { position, uv }
+    }
+}
+
+impl From<TileVertex> for VertexData {
+    fn from(vertex: TileVertex) -> VertexData {
+        VertexData {
+            data: vertex
+                .position
+                .as_array()
+                .iter()
+                .chain(vertex.uv.as_array())
+                .flat_map(|f| f.to_ne_bytes())
+                .collect(),
+        }
+    }
+}
+
+impl Vertex for TileVertex {
+    fn get_vertex_spec() -> VertexAttributeSpec {
+        VertexAttributeSpec::builder()
+            .attribute(VertexFormat::Float32x2)
+            .attribute(VertexFormat::Float32x2)
+            .build()
+    }
+}
@@ -0,0 +1,528 @@
+use crate::tilemap::{TileAtlas, Tilemap, TilemapMode};
+use std::{io::Read, rc::Rc};
+use xml::{
+    attribute::OwnedAttribute,
+    reader::{EventReader, XmlEvent},
+};
+
+/// The flip and rotation flags Tiled stores in the top bits of a global tile ID
+const FLAG_BITS: u32 = 0xF000_0000;
+
+/// An error that occurred while importing a Tiled map
+#[derive(Debug)]
+pub enum TmxError {
+    /// The file isn't well-formed XML
+    Xml(xml::reader::Error),
+    /// The file has no `<map>` element
+    NoMap,
+    /// A required attribute is missing
+    MissingAttribute {
+        element: String,
+        attribute: &'static str,
+    },
+    /// An attribute doesn't hold a valid value
+    InvalidAttribute {
+        attribute: &'static str,
+        value: String,
+    },
+    /// The map is infinite, which is stored in chunks that aren't supported
+    InfiniteMap,
+    /// Layer data uses an encoding or compression other than CSV, uncompressed base64 or
+    /// `<tile>` elements
+    UnsupportedEncoding(String),
+    /// Layer data couldn't be decoded
+    InvalidData { layer: String },
+    /// A layer holds a different number of tiles than its size implies
+    WrongTileCount {
+        layer: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for TmxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TmxError::Xml(error) => write!(f, "Failed to parse TMX file: {error}"),
+            TmxError::NoMap => write!(f, "TMX file has no <map> element"),
+            TmxError::MissingAttribute { element, attribute } => {
+                write!(f, "<{element}> is missing the {attribute:?} attribute")
+            }
+            TmxError::InvalidAttribute { attribute, value } => {
+                write!(f, "{value:?} is not a valid value for {attribute:?}")
+            }
+            TmxError::InfiniteMap => write!(f, "Infinite TMX maps are not supported"),
+            TmxError::UnsupportedEncoding(encoding) => {
+                write!(f, "TMX layer data encoding {encoding:?} is not supported")
+            }
+            TmxError::InvalidData { layer } => {
+                write!(f, "The data of TMX layer {layer:?} is invalid")
+            }
+            TmxError::WrongTileCount {
+                layer,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Expected {expected} tiles in TMX layer {layer:?}, found {found}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TmxError {}
+
+impl From<xml::reader::Error> for TmxError {
+    fn from(error: xml::reader::Error) -> Self {
+        TmxError::Xml(error)
+    }
+}
+
+/// A tileset referenced by a Tiled map. Images aren't loaded; [`TmxTileset::image`] names
+/// the file to build a [`TileAtlas`] from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TmxTileset {
+    /// The global tile ID of the tileset's first tile
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    /// The path of the tileset image, relative to the map
+    pub image: Option<String>,
+    /// The path of an external `.tsx` tileset, whose other fields are left at their defaults
+    pub source: Option<String>,
+}
+
+impl TmxTileset {
+    /// Returns the atlas index of the global tile ID `gid`, or `None` if it's empty or belongs
+    /// to another tileset
+    pub fn local_id(&self, gid: u32) -> Option<u32> {
+        let id = gid.checked_sub(self.first_gid)?;
+        (gid != 0 && (self.tile_count == 0 || id < self.tile_count)).then_some(id)
+    }
+}
+
+/// A tile layer of a Tiled map
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TmxLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Global tile IDs row by row from the top left with the flip flags cleared, where `0`
+    /// marks empty cells
+    pub tiles: Vec<u32>,
+}
+
+impl TmxLayer {
+    /// Creates a tilemap of the layer's tiles from `tileset`, whose image `atlas` was loaded
+    /// from, sized like the tiles of `map`. Tiles of other tilesets are left empty.
+    pub fn to_tilemap(
+        &self,
+        map: &TmxMap,
+        tileset: &TmxTileset,
+        atlas: Rc<TileAtlas>,
+        mode: TilemapMode,
+    ) -> Tilemap {
+        let tiles = self
+            .tiles
+            .iter()
+            .map(|&gid| tileset.local_id(gid))
+            .collect::<Vec<_>>();
+        Tilemap::with_tiles(
+            atlas,
+            self.width,
+            self.height,
+            glm::vec2(map.tile_width as f32, map.tile_height as f32),
+            mode,
+            &tiles,
+        )
+    }
+}
+
+/// A finite, orthogonal map in the `.tmx` format of the Tiled map editor
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TmxMap {
+    /// The size of the map in tiles
+    pub width: u32,
+    pub height: u32,
+    /// The size of a tile in pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tilesets: Vec<TmxTileset>,
+    /// The tile layers from back to front; object and image layers are skipped
+    pub layers: Vec<TmxLayer>,
+}
+
+impl TmxMap {
+    /// Parses a `.tmx` file. Layer data may be stored as CSV, uncompressed base64 or `<tile>`
+    /// elements.
+    pub fn parse(reader: impl Read) -> Result<Self, TmxError> {
+        let mut map = None;
+        let mut data: Option<LayerData> = None;
+        // The names of the elements enclosing the current one
+        let mut parents: Vec<String> = Vec::new();
+
+        for event in EventReader::new(reader) {
+            match event? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    let element = name.local_name.as_str();
+                    let parent = parents.last().map(String::as_str);
+                    let attribute = |attribute| find_attribute(&attributes, attribute);
+                    let required = |attribute| required_attribute(&attributes, element, attribute);
+                    match element {
+                        "map" => {
+                            if attribute("infinite") == Some("1") {
+                                return Err(TmxError::InfiniteMap);
+                            }
+                            map = Some(TmxMap {
+                                width: required("width")?,
+                                height: required("height")?,
+                                tile_width: required("tilewidth")?,
+                                tile_height: required("tileheight")?,
+                                ..Default::default()
+                            });
+                        }
+                        "tileset" => {
+                            let map = map.as_mut().ok_or(TmxError::NoMap)?;
+                            let tileset = match attribute("source") {
+                                Some(source) => TmxTileset {
+                                    first_gid: required("firstgid")?,
+                                    source: Some(source.to_owned()),
+                                    ..Default::default()
+                                },
+                                None => TmxTileset {
+                                    first_gid: required("firstgid")?,
+                                    name: attribute("name").unwrap_or_default().to_owned(),
+                                    tile_width: required("tilewidth")?,
+                                    tile_height: required("tileheight")?,
+                                    columns: required("columns")?,
+                                    tile_count: required("tilecount")?,
+                                    ..Default::default()
+                                },
+                            };
+                            map.tilesets.push(tileset);
+                        }
+                        // Image layers and tiles of image collections have images of their own
+                        "image" if parent == Some("tileset") => {
+                            if let Some(tileset) =
+                                map.as_mut().and_then(|map| map.tilesets.last_mut())
+                            {
+                                tileset.image = attribute("source").map(str::to_owned);
+                            }
+                        }
+                        "layer" => {
+                            let map = map.as_mut().ok_or(TmxError::NoMap)?;
+                            map.layers.push(TmxLayer {
+                                name: attribute("name").unwrap_or_default().to_owned(),
+                                width: required("width")?,
+                                height: required("height")?,
+                                tiles: Vec::new(),
+                            });
+                        }
+                        "data" => {
+                            if let Some(compression) = attribute("compression") {
+                                return Err(TmxError::UnsupportedEncoding(compression.to_owned()));
+                            }
+                            data = Some(match attribute("encoding") {
+                                None => LayerData::Elements(Vec::new()),
+                                Some("csv") => LayerData::Csv(String::new()),
+                                Some("base64") => LayerData::Base64(String::new()),
+                                Some(encoding) => {
+                                    return Err(TmxError::UnsupportedEncoding(encoding.to_owned()))
+                                }
+                            });
+                        }
+                        "chunk" => return Err(TmxError::InfiniteMap),
+                        "tile" => {
+                            if let Some(LayerData::Elements(ref mut tiles)) = data {
+                                tiles.push(attribute("gid").map_or(Ok(0), |_| required("gid"))?);
+                            }
+                        }
+                        _ => {}
+                    }
+                    parents.push(name.local_name);
+                }
+                XmlEvent::Characters(text) => {
+                    if let Some(
+                        LayerData::Csv(ref mut buffer) | LayerData::Base64(ref mut buffer),
+                    ) = data
+                    {
+                        buffer.push_str(&text)
+                    }
+                }
+                XmlEvent::EndElement { name } => {
+                    parents.pop();
+                    if name.local_name != "data" {
+                        continue;
+                    }
+                    let map = map.as_mut().ok_or(TmxError::NoMap)?;
+                    if let (Some(layer), Some(data)) = (map.layers.last_mut(), data.take()) {
+                        layer.tiles = data.decode(&layer.name)?;
+                        let expected = layer.width as usize * layer.height as usize;
+                        if layer.tiles.len() != expected {
+                            return Err(TmxError::WrongTileCount {
+                                layer: layer.name.clone(),
+                                expected,
+                                found: layer.tiles.len(),
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        map.ok_or(TmxError::NoMap)
+    }
+
+    /// Returns the tileset the global tile ID `gid` belongs to
+    pub fn tileset_for(&self, gid: u32) -> Option<&TmxTileset> {
+        self.tilesets
+            .iter()
+            .filter(|tileset| tileset.first_gid <= gid & !FLAG_BITS)
+            .max_by_key(|tileset| tileset.first_gid)
+    }
+
+    /// Returns the tile layer called `name`
+    pub fn layer(&self, name: &str) -> Option<&TmxLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+}
+
+/// The contents of a `<data>` element being read
+enum LayerData {
+    Elements(Vec<u32>),
+    Csv(String),
+    Base64(String),
+}
+
+impl LayerData {
+    /// Returns the global tile IDs with their flags cleared
+    fn decode(self, layer: &str) -> Result<Vec<u32>, TmxError> {
+        let invalid = || TmxError::InvalidData {
+            layer: layer.to_owned(),
+        };
+        let tiles = match self {
+            LayerData::Elements(tiles) => tiles,
+            LayerData::Csv(text) => text
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<u32>().map_err(|_| invalid()))
+                .collect::<Result<_, _>>()?,
+            LayerData::Base64(text) => {
+                let bytes = decode_base64(text.trim()).ok_or_else(invalid)?;
+                if bytes.len() % 4 != 0 {
+                    return Err(invalid());
+                }
+                bytes
+                    .chunks_exact(4)
+                    .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                    .collect()
+            }
+        };
+        Ok(tiles.into_iter().map(|gid| gid & !FLAG_BITS).collect())
+    }
+}
+
+fn find_attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .map(|attribute| attribute.value.as_str())
+}
+
+fn required_attribute(
+    attributes: &[OwnedAttribute],
+    element: &str,
+    attribute: &'static str,
+) -> Result<u32, TmxError> {
+    let value =
+        find_attribute(attributes, attribute).ok_or_else(|| TmxError::MissingAttribute {
+            element: element.to_owned(),
+            attribute,
+        })?;
+    value.parse().map_err(|_| TmxError::InvalidAttribute {
+        attribute,
+        value: value.to_owned(),
+    })
+}
+
+/// Decodes standard base64, ignoring whitespace
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for character in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match character {
+            b'A'..=b'Z' => character - b'A',
+            b'a'..=b'z' => character - b'a' + 26,
+            b'0'..=b'9' => character - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps `body` in a 2x2 map of 16x16 tiles
+    fn map(body: &str) -> Result<TmxMap, TmxError> {
+        let tmx = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16" infinite="0">
+{body}
+</map>"#
+        );
+        TmxMap::parse(tmx.as_bytes())
+    }
+
+    const TILESET: &str = r#"<tileset firstgid="1" name="terrain" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+  <image source="terrain.png" width="64" height="32"/>
+  <tile id="3">
+    <image source="tree.png" width="16" height="16"/>
+  </tile>
+</tileset>"#;
+
+    #[test]
+    fn csv_layer() {
+        let map = map(&format!(
+            r#"{TILESET}
+<layer id="1" name="ground" width="2" height="2">
+  <data encoding="csv">
+1,2,
+0,8
+</data>
+</layer>"#
+        ))
+        .unwrap();
+
+        assert_eq!((map.width, map.height), (2, 2));
+        assert_eq!((map.tile_width, map.tile_height), (16, 16));
+        assert_eq!(
+            map.tilesets,
+            [TmxTileset {
+                first_gid: 1,
+                name: "terrain".to_owned(),
+                tile_width: 16,
+                tile_height: 16,
+                columns: 4,
+                tile_count: 8,
+                image: Some("terrain.png".to_owned()),
+                source: None,
+            }]
+        );
+        assert_eq!(map.layer("ground").unwrap().tiles, [1, 2, 0, 8]);
+    }
+
+    #[test]
+    fn base64_layer_clears_flag_bits() {
+        // 1, 2, 3 flipped horizontally and 4 flipped vertically and diagonally
+        let map = map(&format!(
+            r#"{TILESET}
+<layer id="1" name="ground" width="2" height="2">
+  <data encoding="base64">
+    AQAAAAIAAAADAACABAAAYA==
+  </data>
+</layer>"#
+        ))
+        .unwrap();
+
+        assert_eq!(map.layers[0].tiles, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn tile_element_layer() {
+        let map = map(&format!(
+            r#"{TILESET}
+<layer id="1" name="ground" width="2" height="2">
+  <data>
+    <tile gid="5"/><tile/><tile gid="2147483654"/><tile gid="7"/>
+  </data>
+</layer>"#
+        ))
+        .unwrap();
+
+        assert_eq!(map.layers[0].tiles, [5, 0, 6, 7]);
+    }
+
+    #[test]
+    fn only_the_tileset_image_is_the_tileset_image() {
+        let map = map(&format!(
+            r#"{TILESET}
+<imagelayer id="2" name="sky">
+  <image source="sky.png" width="320" height="240"/>
+</imagelayer>"#
+        ))
+        .unwrap();
+
+        assert_eq!(map.tilesets[0].image.as_deref(), Some("terrain.png"));
+        assert!(map.layers.is_empty());
+    }
+
+    #[test]
+    fn external_tileset_and_lookup() {
+        let map = map(r#"<tileset firstgid="1" source="terrain.tsx"/>
+<tileset firstgid="9" name="props" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+  <image source="props.png" width="32" height="32"/>
+</tileset>"#)
+        .unwrap();
+
+        assert_eq!(map.tilesets[0].source.as_deref(), Some("terrain.tsx"));
+        assert_eq!(map.tilesets[0].image, None);
+        assert_eq!(map.tileset_for(8).unwrap().first_gid, 1);
+        assert_eq!(map.tileset_for(10 | 0x8000_0000).unwrap().first_gid, 9);
+        assert_eq!(map.tilesets[1].local_id(10), Some(1));
+        assert_eq!(map.tilesets[1].local_id(13), None);
+        assert_eq!(map.tilesets[1].local_id(0), None);
+    }
+
+    #[test]
+    fn wrong_tile_count() {
+        let error = map(r#"<layer id="1" name="ground" width="2" height="2">
+  <data encoding="csv">1,2,3</data>
+</layer>"#)
+        .unwrap_err();
+
+        assert!(matches!(
+            error,
+            TmxError::WrongTileCount {
+                expected: 4,
+                found: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn unsupported_data() {
+        let compressed = map(r#"<layer id="1" name="ground" width="2" height="2">
+  <data encoding="base64" compression="zlib">eJxjYGBgAAAABAAB</data>
+</layer>"#);
+        assert!(matches!(compressed, Err(TmxError::UnsupportedEncoding(_))));
+
+        let infinite = TmxMap::parse(
+            r#"<map width="2" height="2" tilewidth="16" tileheight="16" infinite="1"/>"#.as_bytes(),
+        );
+        assert!(matches!(infinite, Err(TmxError::InfiniteMap)));
+        assert!(matches!(
+            TmxMap::parse("<tileset/>".as_bytes()),
+            Err(TmxError::NoMap)
+        ));
+    }
+}
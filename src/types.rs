@@ -44,3 +44,447 @@ impl OpenGLType for i16 {
         gl::SHORT
     }
 }
+
+impl OpenGLType for i8 {
+    fn opengl_type() -> u32 {
+        gl::BYTE
+    }
+}
+
+#[cfg(feature = "half")]
+impl OpenGLType for half::f16 {
+    fn opengl_type() -> u32 {
+        gl::HALF_FLOAT
+    }
+}
+
+/// How a vertex attribute is stored in a vertex buffer, as passed to `glVertexAttribPointer`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AttributeFormat {
+    /// The OpenGL type of each component, or of the whole attribute for packed types such as
+    /// `GL_INT_2_10_10_10_REV`
+    pub ty: u32,
+    /// The number of components, from `1` to `4`
+    pub components: i32,
+    /// Whether integer components are mapped to `[0, 1]` (unsigned) or `[-1, 1]` (signed)
+    pub normalized: bool,
+}
+
+impl AttributeFormat {
+    /// Four signed components of 10, 10, 10 and 2 bits packed into a `u32`, normalized to
+    /// `[-1, 1]`, e.g. for normals and tangents
+    pub const SNORM_2_10_10_10_REV: Self = Self::new(gl::INT_2_10_10_10_REV, 4, true);
+    /// Four unsigned components of 10, 10, 10 and 2 bits packed into a `u32`, normalized to
+    /// `[0, 1]`, e.g. for HDR-ish colors
+    pub const UNORM_2_10_10_10_REV: Self = Self::new(gl::UNSIGNED_INT_2_10_10_10_REV, 4, true);
+
+    /// Constructs a format of `components` components of type `ty`
+    pub const fn new(ty: u32, components: i32, normalized: bool) -> Self {
+        Self {
+            ty,
+            components,
+            normalized,
+        }
+    }
+
+    /// Returns the same format with its integer components normalized
+    pub const fn to_normalized(self) -> Self {
+        Self {
+            normalized: true,
+            ..self
+        }
+    }
+
+    /// Returns whether all components are packed into a single 32-bit value
+    pub fn is_packed(self) -> bool {
+        matches!(
+            self.ty,
+            gl::INT_2_10_10_10_REV
+                | gl::UNSIGNED_INT_2_10_10_10_REV
+                | gl::UNSIGNED_INT_10F_11F_11F_REV
+        )
+    }
+
+    /// Returns the number of bytes the attribute occupies
+    pub fn size(self) -> usize {
+        if self.is_packed() {
+            return 4;
+        }
+
+        let component_size = match self.ty {
+            gl::BYTE | gl::UNSIGNED_BYTE => 1,
+            gl::SHORT | gl::UNSIGNED_SHORT | gl::HALF_FLOAT => 2,
+            gl::DOUBLE => 8,
+            _ => 4,
+        };
+        self.components.max(0) as usize * component_size
+    }
+}
+
+/// A Rust type that can be stored as a vertex attribute, used by
+/// [`crate::shader::VertexAttributeBuilder::attribute_of`] and `#[derive(Vertex)]`.
+///
+/// Integer types are converted to floats without normalization; wrap them in [`Normalized`]
+/// to map them to `[0, 1]` or `[-1, 1]` instead.
+pub trait AttributeType {
+    /// The format the type is stored in
+    const FORMAT: AttributeFormat;
+}
+
+/// An integer attribute that is normalized when read by the shader, e.g.
+/// `Normalized<[u8; 4]>` for an RGBA8 color
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Normalized<T>(pub T);
+
+impl<T: AttributeType> AttributeType for Normalized<T> {
+    const FORMAT: AttributeFormat = T::FORMAT.to_normalized();
+}
+
+macro_rules! scalar_attributes {
+    ($($ty:ty => $gl:expr),* $(,)?) => {
+        $(
+            impl AttributeType for $ty {
+                const FORMAT: AttributeFormat = AttributeFormat::new($gl, 1, false);
+            }
+
+            impl AttributeType for [$ty; 2] {
+                const FORMAT: AttributeFormat = AttributeFormat::new($gl, 2, false);
+            }
+
+            impl AttributeType for [$ty; 3] {
+                const FORMAT: AttributeFormat = AttributeFormat::new($gl, 3, false);
+            }
+
+            impl AttributeType for [$ty; 4] {
+                const FORMAT: AttributeFormat = AttributeFormat::new($gl, 4, false);
+            }
+        )*
+    };
+}
+
+scalar_attributes! {
+    f32 => gl::FLOAT,
+    f64 => gl::DOUBLE,
+    i32 => gl::INT,
+    u32 => gl::UNSIGNED_INT,
+    i16 => gl::SHORT,
+    u16 => gl::UNSIGNED_SHORT,
+    i8 => gl::BYTE,
+    u8 => gl::UNSIGNED_BYTE,
+}
+
+// Half-precision attributes take half the bandwidth of `f32`, which is plenty for normals
+// and texture coordinates
+#[cfg(feature = "half")]
+scalar_attributes! {
+    half::f16 => gl::HALF_FLOAT,
+}
+
+macro_rules! vector_attributes {
+    ($($ty:ty => $gl:expr),* $(,)?) => {
+        $(
+            impl AttributeType for glm::Vector2<$ty> {
+                const FORMAT: AttributeFormat = AttributeFormat::new($gl, 2, false);
+            }
+
+            impl AttributeType for glm::Vector3<$ty> {
+                const FORMAT: AttributeFormat = AttributeFormat::new($gl, 3, false);
+            }
+
+            impl AttributeType for glm::Vector4<$ty> {
+                const FORMAT: AttributeFormat = AttributeFormat::new($gl, 4, false);
+            }
+        )*
+    };
+}
+
+vector_attributes! {
+    f32 => gl::FLOAT,
+    f64 => gl::DOUBLE,
+    i32 => gl::INT,
+    u32 => gl::UNSIGNED_INT,
+}
+
+/// A type for which any bytes are a valid value, so that it can be built from the contents of
+/// a buffer as read back or mapped by [`crate::buffer::Buffer::read`] and
+/// [`crate::buffer::Buffer::map`].
+///
+/// Implemented for the integer and floating point types, arrays and glm vectors and matrices
+/// of them.
+///
+/// # Safety
+/// Every bit pattern of `size_of::<Self>()` bytes must be a valid value of the type. This
+/// rules out `bool`, `char`, references, most enums and structs with such fields. Structs
+/// need a defined layout such as `#[repr(C)]`, with only `Pod` fields.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! pod_types {
+    ($($ty:ty),* $(,)?) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+pod_types!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+#[cfg(feature = "half")]
+pod_types!(half::f16);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+// `Normalized` is `#[repr(transparent)]`
+unsafe impl<T: Pod> Pod for Normalized<T> {}
+
+// The glm vectors and matrices are `#[repr(C)]` structs of their components
+unsafe impl<T: Pod + glm::Primitive> Pod for glm::Vector2<T> {}
+unsafe impl<T: Pod + glm::Primitive> Pod for glm::Vector3<T> {}
+unsafe impl<T: Pod + glm::Primitive> Pod for glm::Vector4<T> {}
+
+macro_rules! pod_matrices {
+    ($($ty:ident),* $(,)?) => {
+        $(unsafe impl<T: Pod + glm::BaseFloat> Pod for glm::$ty<T> {})*
+    };
+}
+
+pod_matrices!(
+    Matrix2, Matrix3x2, Matrix4x2, Matrix2x3, Matrix3, Matrix4x3, Matrix2x4, Matrix3x4, Matrix4,
+);
+
+/// A type that can be used for the indices of an indexed draw call: `u8`, `u16` or `u32`
+pub trait IndexType: OpenGLType + Pod + std::fmt::Debug {}
+
+impl IndexType for u8 {}
+impl IndexType for u16 {}
+impl IndexType for u32 {}
+
+/// Returns whether `ty` is an opaque GLSL type (a sampler or image), which are set through
+/// integer texture unit indices
+pub fn is_opaque_type(ty: u32) -> bool {
+    matches!(
+        ty,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_1D_ARRAY
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::SAMPLER_BUFFER
+            | gl::SAMPLER_2D_RECT
+            | gl::SAMPLER_CUBE_MAP_ARRAY
+            | gl::SAMPLER_1D_SHADOW
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_1D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_ARRAY_SHADOW
+            | gl::SAMPLER_CUBE_SHADOW
+            | gl::SAMPLER_2D_RECT_SHADOW
+            | gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW
+            | gl::INT_SAMPLER_1D
+            | gl::INT_SAMPLER_2D
+            | gl::INT_SAMPLER_3D
+            | gl::INT_SAMPLER_CUBE
+            | gl::INT_SAMPLER_1D_ARRAY
+            | gl::INT_SAMPLER_2D_ARRAY
+            | gl::INT_SAMPLER_2D_MULTISAMPLE
+            | gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::INT_SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D_RECT
+            | gl::INT_SAMPLER_CUBE_MAP_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_1D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_3D
+            | gl::UNSIGNED_INT_SAMPLER_CUBE
+            | gl::UNSIGNED_INT_SAMPLER_1D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_BUFFER
+            | gl::UNSIGNED_INT_SAMPLER_2D_RECT
+            | gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY
+            | gl::IMAGE_1D
+            | gl::IMAGE_2D
+            | gl::IMAGE_3D
+            | gl::IMAGE_CUBE
+            | gl::IMAGE_1D_ARRAY
+            | gl::IMAGE_2D_ARRAY
+            | gl::IMAGE_2D_MULTISAMPLE
+            | gl::IMAGE_2D_MULTISAMPLE_ARRAY
+            | gl::IMAGE_BUFFER
+            | gl::IMAGE_2D_RECT
+            | gl::IMAGE_CUBE_MAP_ARRAY
+            | gl::INT_IMAGE_1D
+            | gl::INT_IMAGE_2D
+            | gl::INT_IMAGE_3D
+            | gl::INT_IMAGE_CUBE
+            | gl::INT_IMAGE_1D_ARRAY
+            | gl::INT_IMAGE_2D_ARRAY
+            | gl::INT_IMAGE_2D_MULTISAMPLE
+            | gl::INT_IMAGE_2D_MULTISAMPLE_ARRAY
+            | gl::INT_IMAGE_BUFFER
+            | gl::INT_IMAGE_2D_RECT
+            | gl::INT_IMAGE_CUBE_MAP_ARRAY
+            | gl::UNSIGNED_INT_IMAGE_1D
+            | gl::UNSIGNED_INT_IMAGE_2D
+            | gl::UNSIGNED_INT_IMAGE_3D
+            | gl::UNSIGNED_INT_IMAGE_CUBE
+            | gl::UNSIGNED_INT_IMAGE_1D_ARRAY
+            | gl::UNSIGNED_INT_IMAGE_2D_ARRAY
+            | gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE
+            | gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY
+            | gl::UNSIGNED_INT_IMAGE_BUFFER
+            | gl::UNSIGNED_INT_IMAGE_2D_RECT
+            | gl::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY
+    )
+}
+
+/// Returns the GLSL name of the OpenGL type `ty`, e.g. `vec3` for `GL_FLOAT_VEC3`
+pub fn glsl_type_name(ty: u32) -> &'static str {
+    match ty {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::DOUBLE => "double",
+        gl::DOUBLE_VEC2 => "dvec2",
+        gl::DOUBLE_VEC3 => "dvec3",
+        gl::DOUBLE_VEC4 => "dvec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        crate::ext::INT64 => "int64_t",
+        crate::ext::UNSIGNED_INT64 => "uint64_t",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::DOUBLE_MAT2 => "dmat2",
+        gl::DOUBLE_MAT3 => "dmat3",
+        gl::DOUBLE_MAT4 => "dmat4",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_CUBE_MAP_ARRAY => "samplerCubeArray",
+        gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW => "samplerCubeArrayShadow",
+        ty if is_opaque_type(ty) => "opaque type",
+        _ => "unknown type",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every sampler and image type of GLSL 4.60, with its GLSL name
+    const OPAQUE_TYPES: &[(u32, &str)] = &[
+        (gl::SAMPLER_1D, "sampler1D"),
+        (gl::SAMPLER_2D, "sampler2D"),
+        (gl::SAMPLER_3D, "sampler3D"),
+        (gl::SAMPLER_CUBE, "samplerCube"),
+        (gl::SAMPLER_1D_ARRAY, "sampler1DArray"),
+        (gl::SAMPLER_2D_ARRAY, "sampler2DArray"),
+        (gl::SAMPLER_2D_MULTISAMPLE, "sampler2DMS"),
+        (gl::SAMPLER_2D_MULTISAMPLE_ARRAY, "sampler2DMSArray"),
+        (gl::SAMPLER_BUFFER, "samplerBuffer"),
+        (gl::SAMPLER_2D_RECT, "sampler2DRect"),
+        (gl::SAMPLER_CUBE_MAP_ARRAY, "samplerCubeArray"),
+        (gl::SAMPLER_1D_SHADOW, "sampler1DShadow"),
+        (gl::SAMPLER_2D_SHADOW, "sampler2DShadow"),
+        (gl::SAMPLER_1D_ARRAY_SHADOW, "sampler1DArrayShadow"),
+        (gl::SAMPLER_2D_ARRAY_SHADOW, "sampler2DArrayShadow"),
+        (gl::SAMPLER_CUBE_SHADOW, "samplerCubeShadow"),
+        (gl::SAMPLER_2D_RECT_SHADOW, "sampler2DRectShadow"),
+        (gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW, "samplerCubeArrayShadow"),
+        (gl::INT_SAMPLER_1D, "isampler1D"),
+        (gl::INT_SAMPLER_2D, "isampler2D"),
+        (gl::INT_SAMPLER_3D, "isampler3D"),
+        (gl::INT_SAMPLER_CUBE, "isamplerCube"),
+        (gl::INT_SAMPLER_1D_ARRAY, "isampler1DArray"),
+        (gl::INT_SAMPLER_2D_ARRAY, "isampler2DArray"),
+        (gl::INT_SAMPLER_2D_MULTISAMPLE, "isampler2DMS"),
+        (gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY, "isampler2DMSArray"),
+        (gl::INT_SAMPLER_BUFFER, "isamplerBuffer"),
+        (gl::INT_SAMPLER_2D_RECT, "isampler2DRect"),
+        (gl::INT_SAMPLER_CUBE_MAP_ARRAY, "isamplerCubeArray"),
+        (gl::UNSIGNED_INT_SAMPLER_1D, "usampler1D"),
+        (gl::UNSIGNED_INT_SAMPLER_2D, "usampler2D"),
+        (gl::UNSIGNED_INT_SAMPLER_3D, "usampler3D"),
+        (gl::UNSIGNED_INT_SAMPLER_CUBE, "usamplerCube"),
+        (gl::UNSIGNED_INT_SAMPLER_1D_ARRAY, "usampler1DArray"),
+        (gl::UNSIGNED_INT_SAMPLER_2D_ARRAY, "usampler2DArray"),
+        (gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE, "usampler2DMS"),
+        (
+            gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+            "usampler2DMSArray",
+        ),
+        (gl::UNSIGNED_INT_SAMPLER_BUFFER, "usamplerBuffer"),
+        (gl::UNSIGNED_INT_SAMPLER_2D_RECT, "usampler2DRect"),
+        (gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY, "usamplerCubeArray"),
+        (gl::IMAGE_1D, "image1D"),
+        (gl::IMAGE_2D, "image2D"),
+        (gl::IMAGE_3D, "image3D"),
+        (gl::IMAGE_CUBE, "imageCube"),
+        (gl::IMAGE_1D_ARRAY, "image1DArray"),
+        (gl::IMAGE_2D_ARRAY, "image2DArray"),
+        (gl::IMAGE_2D_MULTISAMPLE, "image2DMS"),
+        (gl::IMAGE_2D_MULTISAMPLE_ARRAY, "image2DMSArray"),
+        (gl::IMAGE_BUFFER, "imageBuffer"),
+        (gl::IMAGE_2D_RECT, "image2DRect"),
+        (gl::IMAGE_CUBE_MAP_ARRAY, "imageCubeArray"),
+        (gl::INT_IMAGE_1D, "iimage1D"),
+        (gl::INT_IMAGE_2D, "iimage2D"),
+        (gl::INT_IMAGE_3D, "iimage3D"),
+        (gl::INT_IMAGE_CUBE, "iimageCube"),
+        (gl::INT_IMAGE_1D_ARRAY, "iimage1DArray"),
+        (gl::INT_IMAGE_2D_ARRAY, "iimage2DArray"),
+        (gl::INT_IMAGE_2D_MULTISAMPLE, "iimage2DMS"),
+        (gl::INT_IMAGE_2D_MULTISAMPLE_ARRAY, "iimage2DMSArray"),
+        (gl::INT_IMAGE_BUFFER, "iimageBuffer"),
+        (gl::INT_IMAGE_2D_RECT, "iimage2DRect"),
+        (gl::INT_IMAGE_CUBE_MAP_ARRAY, "iimageCubeArray"),
+        (gl::UNSIGNED_INT_IMAGE_1D, "uimage1D"),
+        (gl::UNSIGNED_INT_IMAGE_2D, "uimage2D"),
+        (gl::UNSIGNED_INT_IMAGE_3D, "uimage3D"),
+        (gl::UNSIGNED_INT_IMAGE_CUBE, "uimageCube"),
+        (gl::UNSIGNED_INT_IMAGE_1D_ARRAY, "uimage1DArray"),
+        (gl::UNSIGNED_INT_IMAGE_2D_ARRAY, "uimage2DArray"),
+        (gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE, "uimage2DMS"),
+        (
+            gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY,
+            "uimage2DMSArray",
+        ),
+        (gl::UNSIGNED_INT_IMAGE_BUFFER, "uimageBuffer"),
+        (gl::UNSIGNED_INT_IMAGE_2D_RECT, "uimage2DRect"),
+        (gl::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY, "uimageCubeArray"),
+    ];
+
+    #[test]
+    fn every_sampler_and_image_is_opaque() {
+        for &(ty, name) in OPAQUE_TYPES {
+            assert!(is_opaque_type(ty), "{name} ({ty:#x}) isn't opaque");
+            assert_ne!(glsl_type_name(ty), "unknown type", "{name}");
+        }
+    }
+
+    #[test]
+    fn values_are_not_opaque() {
+        for ty in [
+            gl::FLOAT,
+            gl::INT,
+            gl::UNSIGNED_INT,
+            gl::BOOL,
+            gl::FLOAT_MAT4,
+            gl::UNSIGNED_INT_ATOMIC_COUNTER,
+        ] {
+            assert!(!is_opaque_type(ty), "{} is opaque", glsl_type_name(ty));
+        }
+    }
+}
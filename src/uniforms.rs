@@ -1,18 +1,203 @@
+use crate::{
+    context,
+    diagnostics::diagnostic,
+    ext,
+    renderer::DrawError,
+    shader::LinkedProgram,
+    texture::{TextureBinding, TextureUnits},
+};
+use std::marker::PhantomData;
+
 /// A trait for types that can be used as OpenGL uniform values
 pub trait Uniform: std::fmt::Debug {
+    /// Uploads the value to `location` of the currently bound program
     fn upload(&self, location: i32);
+
+    /// Uploads the value to `location` of `program` without it having to be bound.
+    ///
+    /// The default implementation temporarily binds `program` and restores the previous
+    /// program afterwards. Implementations should override this with `glProgramUniform*`.
+    fn upload_to_program(&self, program: u32, location: i32) {
+        let mut previous = 0;
+        unsafe {
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, std::ptr::addr_of_mut!(previous));
+            gl::UseProgram(program);
+        };
+        self.upload(location);
+        unsafe { gl::UseProgram(previous as u32) };
+    }
+
+    /// The OpenGL type (e.g. `GL_FLOAT_VEC3`) this value is uploaded as.
+    ///
+    /// Used to validate uniforms against the program in debug builds. Returning `None` skips
+    /// validation for this value.
+    fn gl_type(&self) -> Option<u32> {
+        None
+    }
+
+    /// The number of array elements this value uploads
+    fn array_len(&self) -> usize {
+        1
+    }
+
+    /// The texture to bind for this value, if it is a sampler.
+    ///
+    /// Values returning a texture are assigned a texture unit when uploaded through
+    /// [`Uniforms`], and the unit's index is uploaded in place of the value.
+    fn texture(&self) -> Option<TextureBinding> {
+        None
+    }
+
+    /// Converts the value for storage in [`Uniforms`].
+    ///
+    /// The default implementation boxes the value. The most common built-in types are stored
+    /// inline, which doesn't allocate.
+    fn into_value(self) -> UniformValue
+    where
+        Self: Sized + 'static,
+    {
+        UniformValue::Boxed(Box::new(self))
+    }
+}
+
+impl<T> Uniform for &T
+where
+    T: Uniform + ?Sized,
+{
+    fn upload(&self, location: i32) {
+        (**self).upload(location)
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        (**self).upload_to_program(program, location)
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        (**self).gl_type()
+    }
+
+    fn array_len(&self) -> usize {
+        (**self).array_len()
+    }
+
+    fn texture(&self) -> Option<TextureBinding> {
+        (**self).texture()
+    }
+}
+
+/// Returns whether a value uploaded as `value_type` can be assigned to an active uniform of
+/// type `uniform_type`
+pub(crate) fn is_compatible(value_type: u32, uniform_type: u32) -> bool {
+    value_type == uniform_type
+        || (value_type == gl::INT
+            && (uniform_type == gl::BOOL || crate::types::is_opaque_type(uniform_type)))
+}
+
+/// A struct whose fields correspond to uniforms of a program.
+///
+/// Usually implemented with `#[derive(Uniforms)]`, which maps each field to the uniform of the
+/// same name. A field can be mapped to a differently named uniform with
+/// `#[uniform(rename = "...")]` or left out with `#[uniform(skip)]`.
+pub trait UniformStruct {
+    /// Uploads every field to the matching uniform of `program`
+    fn apply(&self, program: &LinkedProgram);
+}
+
+/// Returns whether `glProgramUniform*` (OpenGL 4.1 or `ARB_separate_shader_objects`) is
+/// available in the current context
+pub(crate) fn program_uniform_supported() -> bool {
+    gl::ProgramUniform1i::is_loaded()
+}
+
+/// Uploads `value` to `location` of `program`, using `glProgramUniform*` where available and
+/// binding the program for the duration of the upload otherwise.
+pub(crate) fn upload_to(program: u32, location: i32, value: &dyn Uniform) {
+    if program_uniform_supported() {
+        value.upload_to_program(program, location);
+    } else {
+        let mut previous = 0;
+        unsafe {
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, std::ptr::addr_of_mut!(previous));
+            gl::UseProgram(program);
+        };
+        value.upload(location);
+        unsafe { gl::UseProgram(previous as u32) };
+    }
+}
+
+/// A uniform location resolved once from a [`LinkedProgram`], accepting only values
+/// of type `T`.
+///
+/// Obtained through [`LinkedProgram::uniform`].
+#[derive(Debug)]
+pub struct UniformHandle<T> {
+    program: u32,
+    location: i32,
+    _phantom: PhantomData<fn(T)>,
+}
+
+impl<T> Clone for UniformHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for UniformHandle<T> {}
+
+impl<T> UniformHandle<T>
+where
+    T: Uniform,
+{
+    pub(crate) fn new(program: u32, location: i32) -> Self {
+        Self {
+            program,
+            location,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the resolved location, or `-1` if the uniform isn't active
+    pub fn location(&self) -> i32 {
+        self.location
+    }
+
+    /// Returns whether the uniform is active in the program it was resolved from
+    pub fn is_active(&self) -> bool {
+        self.location >= 0
+    }
+
+    /// Uploads `value` to the uniform
+    pub fn set(&self, value: T) {
+        if self.is_active() {
+            upload_to(self.program, self.location, &value);
+        }
+    }
 }
 
 impl Uniform for glm::Matrix4<f32> {
+    // The matrix is `#[repr(C)]` and column-major, as OpenGL expects
     fn upload(&self, location: i32) {
-        let data = self
-            .as_array()
-            .iter()
-            .flat_map(|v| v.as_array())
-            .copied()
-            .collect::<Vec<_>>();
+        unsafe { gl::UniformMatrix4fv(location, 1, gl::FALSE, std::ptr::addr_of!(*self).cast()) }
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        unsafe {
+            gl::ProgramUniformMatrix4fv(
+                program,
+                location,
+                1,
+                gl::FALSE,
+                std::ptr::addr_of!(*self).cast(),
+            )
+        }
+    }
 
-        unsafe { gl::UniformMatrix4fv(location, 1, gl::FALSE, data.as_ptr().cast()) }
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::FLOAT_MAT4)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Mat4(self)
     }
 }
 
@@ -21,6 +206,19 @@ impl Uniform for glm::Vector2<f32> {
         let data = self.as_array();
         unsafe { gl::Uniform2fv(location, 1, data.as_ptr().cast()) }
     }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        let data = self.as_array();
+        unsafe { gl::ProgramUniform2fv(program, location, 1, data.as_ptr().cast()) }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::FLOAT_VEC2)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Vec2(self)
+    }
 }
 
 impl Uniform for glm::Vector3<f32> {
@@ -28,6 +226,19 @@ impl Uniform for glm::Vector3<f32> {
         let data = self.as_array();
         unsafe { gl::Uniform3fv(location, 1, data.as_ptr().cast()) }
     }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        let data = self.as_array();
+        unsafe { gl::ProgramUniform3fv(program, location, 1, data.as_ptr().cast()) }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::FLOAT_VEC3)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Vec3(self)
+    }
 }
 
 impl Uniform for glm::Vector4<f32> {
@@ -35,34 +246,756 @@ impl Uniform for glm::Vector4<f32> {
         let data = self.as_array();
         unsafe { gl::Uniform4fv(location, 1, data.as_ptr().cast()) }
     }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        let data = self.as_array();
+        unsafe { gl::ProgramUniform4fv(program, location, 1, data.as_ptr().cast()) }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::FLOAT_VEC4)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Vec4(self)
+    }
 }
 
 impl Uniform for i32 {
     fn upload(&self, location: i32) {
         unsafe { gl::Uniform1i(location, *self) }
     }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        unsafe { gl::ProgramUniform1i(program, location, *self) }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::INT)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Int(self)
+    }
+}
+
+impl Uniform for u32 {
+    fn upload(&self, location: i32) {
+        unsafe { gl::Uniform1ui(location, *self) }
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        unsafe { gl::ProgramUniform1ui(program, location, *self) }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::UNSIGNED_INT)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::UInt(self)
+    }
+}
+
+impl Uniform for i64 {
+    /// Requires `GL_ARB_gpu_shader_int64` or `GL_NV_gpu_shader5`; logs an error and does
+    /// nothing without them
+    fn upload(&self, location: i32) {
+        if int64_uniforms_available() {
+            unsafe { ext::uniform_1i64(location, *self) }
+        }
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        if int64_uniforms_available() {
+            unsafe { ext::program_uniform_1i64(program, location, *self) }
+        }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(ext::INT64)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Int64(self)
+    }
+}
+
+impl Uniform for u64 {
+    /// Requires `GL_ARB_gpu_shader_int64` or `GL_NV_gpu_shader5`; logs an error and does
+    /// nothing without them
+    fn upload(&self, location: i32) {
+        if int64_uniforms_available() {
+            unsafe { ext::uniform_1ui64(location, *self) }
+        }
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        if int64_uniforms_available() {
+            unsafe { ext::program_uniform_1ui64(program, location, *self) }
+        }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(ext::UNSIGNED_INT64)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::UInt64(self)
+    }
+}
+
+/// Returns whether 64-bit integer uniforms can be uploaded, logging an error if they can't
+fn int64_uniforms_available() -> bool {
+    let supported = context::supports_int64_uniforms();
+    if !supported {
+        diagnostic!(
+            Error,
+            Shader,
+            "64-bit integer uniforms require GL_ARB_gpu_shader_int64 or GL_NV_gpu_shader5"
+        );
+    }
+    supported
 }
 
 impl Uniform for f64 {
     fn upload(&self, location: i32) {
         unsafe { gl::Uniform1d(location, *self) }
     }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        unsafe { gl::ProgramUniform1d(program, location, *self) }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::DOUBLE)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Double(self)
+    }
 }
 
 impl Uniform for f32 {
     fn upload(&self, location: i32) {
         unsafe { gl::Uniform1f(location, *self) }
     }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        unsafe { gl::ProgramUniform1f(program, location, *self) }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::FLOAT)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Float(self)
+    }
 }
 
+/// Element types of uniform arrays, such as `uniform vec3 positions[16]`.
+///
+/// Slices, arrays and `Vec`s of these are uploaded in a single call using the count parameter
+/// of `glUniform*v`, starting at the location of the first element.
+pub trait UniformArrayElement: Copy + std::fmt::Debug {
+    /// The OpenGL type of a single element, e.g. `GL_FLOAT_VEC3`
+    const GL_TYPE: u32;
+
+    /// Uploads `values` to consecutive elements starting at `location` of the currently
+    /// bound program
+    fn upload_array(values: &[Self], location: i32);
+
+    /// Uploads `values` to consecutive elements starting at `location` of `program`
+    fn upload_array_to_program(values: &[Self], program: u32, location: i32);
+}
+
+macro_rules! uniform_array_element {
+    ($ty: ty, $gl_type: expr, $upload: ident, $program_upload: ident, $element: ty) => {
+        impl UniformArrayElement for $ty {
+            const GL_TYPE: u32 = $gl_type;
+
+            fn upload_array(values: &[Self], location: i32) {
+                unsafe {
+                    gl::$upload(
+                        location,
+                        values.len() as i32,
+                        values.as_ptr().cast::<$element>(),
+                    )
+                }
+            }
+
+            fn upload_array_to_program(values: &[Self], program: u32, location: i32) {
+                unsafe {
+                    gl::$program_upload(
+                        program,
+                        location,
+                        values.len() as i32,
+                        values.as_ptr().cast::<$element>(),
+                    )
+                }
+            }
+        }
+    };
+}
+
+uniform_array_element!(f32, gl::FLOAT, Uniform1fv, ProgramUniform1fv, f32);
+uniform_array_element!(
+    glm::Vec2,
+    gl::FLOAT_VEC2,
+    Uniform2fv,
+    ProgramUniform2fv,
+    f32
+);
+uniform_array_element!(
+    glm::Vec3,
+    gl::FLOAT_VEC3,
+    Uniform3fv,
+    ProgramUniform3fv,
+    f32
+);
+uniform_array_element!(
+    glm::Vec4,
+    gl::FLOAT_VEC4,
+    Uniform4fv,
+    ProgramUniform4fv,
+    f32
+);
+uniform_array_element!(i32, gl::INT, Uniform1iv, ProgramUniform1iv, i32);
+uniform_array_element!(u32, gl::UNSIGNED_INT, Uniform1uiv, ProgramUniform1uiv, u32);
+
+uniform_array_element!(glm::IVec2, gl::INT_VEC2, Uniform2iv, ProgramUniform2iv, i32);
+uniform_array_element!(glm::IVec3, gl::INT_VEC3, Uniform3iv, ProgramUniform3iv, i32);
+uniform_array_element!(glm::IVec4, gl::INT_VEC4, Uniform4iv, ProgramUniform4iv, i32);
+uniform_array_element!(
+    glm::UVec2,
+    gl::UNSIGNED_INT_VEC2,
+    Uniform2uiv,
+    ProgramUniform2uiv,
+    u32
+);
+uniform_array_element!(
+    glm::UVec3,
+    gl::UNSIGNED_INT_VEC3,
+    Uniform3uiv,
+    ProgramUniform3uiv,
+    u32
+);
+uniform_array_element!(
+    glm::UVec4,
+    gl::UNSIGNED_INT_VEC4,
+    Uniform4uiv,
+    ProgramUniform4uiv,
+    u32
+);
+uniform_array_element!(f64, gl::DOUBLE, Uniform1dv, ProgramUniform1dv, f64);
+uniform_array_element!(
+    glm::DVec2,
+    gl::DOUBLE_VEC2,
+    Uniform2dv,
+    ProgramUniform2dv,
+    f64
+);
+uniform_array_element!(
+    glm::DVec3,
+    gl::DOUBLE_VEC3,
+    Uniform3dv,
+    ProgramUniform3dv,
+    f64
+);
+uniform_array_element!(
+    glm::DVec4,
+    gl::DOUBLE_VEC4,
+    Uniform4dv,
+    ProgramUniform4dv,
+    f64
+);
+
+// Matrices are `#[repr(C)]` and column-major, as OpenGL expects
+macro_rules! uniform_matrix_array_element {
+    ($ty: ty, $gl_type: expr, $upload: ident, $program_upload: ident) => {
+        impl UniformArrayElement for $ty {
+            const GL_TYPE: u32 = $gl_type;
+
+            fn upload_array(values: &[Self], location: i32) {
+                unsafe {
+                    gl::$upload(
+                        location,
+                        values.len() as i32,
+                        gl::FALSE,
+                        values.as_ptr().cast(),
+                    )
+                }
+            }
+
+            fn upload_array_to_program(values: &[Self], program: u32, location: i32) {
+                unsafe {
+                    gl::$program_upload(
+                        program,
+                        location,
+                        values.len() as i32,
+                        gl::FALSE,
+                        values.as_ptr().cast(),
+                    )
+                }
+            }
+        }
+    };
+}
+
+uniform_matrix_array_element!(
+    glm::Mat2,
+    gl::FLOAT_MAT2,
+    UniformMatrix2fv,
+    ProgramUniformMatrix2fv
+);
+uniform_matrix_array_element!(
+    glm::Mat3,
+    gl::FLOAT_MAT3,
+    UniformMatrix3fv,
+    ProgramUniformMatrix3fv
+);
+uniform_matrix_array_element!(
+    glm::Mat4,
+    gl::FLOAT_MAT4,
+    UniformMatrix4fv,
+    ProgramUniformMatrix4fv
+);
+uniform_matrix_array_element!(
+    glm::DMat2,
+    gl::DOUBLE_MAT2,
+    UniformMatrix2dv,
+    ProgramUniformMatrix2dv
+);
+uniform_matrix_array_element!(
+    glm::DMat3,
+    gl::DOUBLE_MAT3,
+    UniformMatrix3dv,
+    ProgramUniformMatrix3dv
+);
+uniform_matrix_array_element!(
+    glm::DMat4,
+    gl::DOUBLE_MAT4,
+    UniformMatrix4dv,
+    ProgramUniformMatrix4dv
+);
+
+/// Implements [`Uniform`] for single values of array element types not covered by a
+/// dedicated implementation, uploading them as arrays of one element
+macro_rules! single_element_uniform {
+    ($($ty: ty),+ $(,)?) => {
+        $(
+            impl Uniform for $ty {
+                fn upload(&self, location: i32) {
+                    <$ty>::upload_array(std::slice::from_ref(self), location)
+                }
+
+                fn upload_to_program(&self, program: u32, location: i32) {
+                    <$ty>::upload_array_to_program(std::slice::from_ref(self), program, location)
+                }
+
+                fn gl_type(&self) -> Option<u32> {
+                    Some(<$ty>::GL_TYPE)
+                }
+            }
+        )+
+    };
+}
+
+single_element_uniform!(
+    glm::IVec2,
+    glm::IVec3,
+    glm::IVec4,
+    glm::UVec2,
+    glm::UVec3,
+    glm::UVec4,
+    glm::DVec2,
+    glm::DVec3,
+    glm::DVec4,
+    glm::Mat2,
+    glm::Mat3,
+    glm::DMat2,
+    glm::DMat3,
+    glm::DMat4,
+);
+
+impl Uniform for bool {
+    fn upload(&self, location: i32) {
+        unsafe { gl::Uniform1i(location, *self as i32) }
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        unsafe { gl::ProgramUniform1i(program, location, *self as i32) }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(gl::BOOL)
+    }
+
+    fn into_value(self) -> UniformValue {
+        UniformValue::Int(self as i32)
+    }
+}
+
+impl<T: UniformArrayElement> Uniform for [T] {
+    /// Uploads every element; empty slices upload nothing
+    fn upload(&self, location: i32) {
+        if !self.is_empty() {
+            T::upload_array(self, location);
+        }
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        if !self.is_empty() {
+            T::upload_array_to_program(self, program, location);
+        }
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(T::GL_TYPE)
+    }
+
+    fn array_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: UniformArrayElement, const N: usize> Uniform for [T; N] {
+    fn upload(&self, location: i32) {
+        self.as_slice().upload(location)
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        self.as_slice().upload_to_program(program, location)
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(T::GL_TYPE)
+    }
+
+    fn array_len(&self) -> usize {
+        N
+    }
+}
+
+impl<T: UniformArrayElement> Uniform for Vec<T> {
+    fn upload(&self, location: i32) {
+        self.as_slice().upload(location)
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        self.as_slice().upload_to_program(program, location)
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        Some(T::GL_TYPE)
+    }
+
+    fn array_len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// A uniform value stored by [`Uniforms`]. The built-in types are stored inline, so typical
+/// uniform sets don't allocate; other types are boxed.
+///
+/// Created with [`Uniform::into_value`].
 #[derive(Debug)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2(glm::Vec2),
+    Vec3(glm::Vec3),
+    Vec4(glm::Vec4),
+    Mat4(glm::Mat4),
+    Int(i32),
+    UInt(u32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+    Texture(TextureBinding),
+    Boxed(Box<dyn Uniform>),
+}
+
+impl UniformValue {
+    fn as_uniform(&self) -> &dyn Uniform {
+        match self {
+            UniformValue::Float(value) => value,
+            UniformValue::Vec2(value) => value,
+            UniformValue::Vec3(value) => value,
+            UniformValue::Vec4(value) => value,
+            UniformValue::Mat4(value) => value,
+            UniformValue::Int(value) => value,
+            UniformValue::UInt(value) => value,
+            UniformValue::Int64(value) => value,
+            UniformValue::UInt64(value) => value,
+            UniformValue::Double(value) => value,
+            UniformValue::Texture(value) => value,
+            UniformValue::Boxed(value) => value.as_ref(),
+        }
+    }
+}
+
+impl Uniform for UniformValue {
+    fn upload(&self, location: i32) {
+        self.as_uniform().upload(location)
+    }
+
+    fn upload_to_program(&self, program: u32, location: i32) {
+        self.as_uniform().upload_to_program(program, location)
+    }
+
+    fn gl_type(&self) -> Option<u32> {
+        self.as_uniform().gl_type()
+    }
+
+    fn array_len(&self) -> usize {
+        self.as_uniform().array_len()
+    }
+
+    fn texture(&self) -> Option<TextureBinding> {
+        self.as_uniform().texture()
+    }
+
+    fn into_value(self) -> UniformValue {
+        self
+    }
+}
+
+/// The number of values [`UniformData`] stores without allocating
+const INLINE_UNIFORMS: usize = 8;
+
+/// The `(location, value)` pairs of [`Uniforms`]. The first few are stored inline, so
+/// typical uniform sets don't allocate.
+#[derive(Debug, Default)]
+pub struct UniformData {
+    inline: [Option<(i32, UniformValue)>; INLINE_UNIFORMS],
+    len: usize,
+    spilled: Vec<(i32, UniformValue)>,
+}
+
+impl UniformData {
+    /// Creates an empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the value for `location`
+    pub fn push(&mut self, (location, value): (i32, UniformValue)) {
+        match self.inline.get_mut(self.len) {
+            Some(slot) => *slot = Some((location, value)),
+            None => self.spilled.push((location, value)),
+        }
+        self.len += 1;
+    }
+
+    /// Removes and returns the last value
+    pub fn pop(&mut self) -> Option<(i32, UniformValue)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.len -= 1;
+        match self.inline.get_mut(self.len) {
+            Some(slot) => slot.take(),
+            None => self.spilled.pop(),
+        }
+    }
+
+    /// Returns the number of values
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether there are no values
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the `(location, value)` pairs in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = &(i32, UniformValue)> {
+        self.inline.iter().flatten().chain(&self.spilled)
+    }
+
+    /// Iterates mutably over the `(location, value)` pairs in the order they were added
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut (i32, UniformValue)> {
+        self.inline.iter_mut().flatten().chain(&mut self.spilled)
+    }
+}
+
+impl FromIterator<(i32, UniformValue)> for UniformData {
+    fn from_iter<I: IntoIterator<Item = (i32, UniformValue)>>(iter: I) -> Self {
+        let mut data = Self::new();
+        iter.into_iter().for_each(|pair| data.push(pair));
+        data
+    }
+}
+
+impl Extend<(i32, UniformValue)> for UniformData {
+    fn extend<I: IntoIterator<Item = (i32, UniformValue)>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|pair| self.push(pair));
+    }
+}
+
+impl IntoIterator for UniformData {
+    type Item = (i32, UniformValue);
+    type IntoIter = std::iter::Chain<
+        std::iter::Flatten<std::array::IntoIter<Option<(i32, UniformValue)>, INLINE_UNIFORMS>>,
+        std::vec::IntoIter<(i32, UniformValue)>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inline.into_iter().flatten().chain(self.spilled)
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Uniforms {
-    pub data: Vec<(i32, Box<dyn Uniform>)>,
+    pub data: UniformData,
 }
 
 impl Uniforms {
-    pub fn upload_all(&self) {
-        self.data.iter().for_each(|u| u.1.upload(u.0))
+    /// Starts building uniforms for `program`, looking up locations by name as values are
+    /// added. An alternative to the [`uniforms!`](crate::uniforms!) macro when the set of
+    /// uniforms is assembled in steps.
+    pub fn builder(program: &LinkedProgram) -> UniformsBuilder<'_> {
+        UniformsBuilder {
+            program,
+            uniforms: Uniforms::default(),
+        }
+    }
+
+    /// Adds a value for the uniform called `name` in `program`
+    pub fn add<T: Uniform + 'static>(&mut self, program: &LinkedProgram, name: &str, value: T) {
+        self.data
+            .push((program.get_uniform_location(name), value.into_value()));
+    }
+
+    /// Moves the values of `other` into these uniforms, e.g. to combine per-frame camera
+    /// uniforms with per-object material uniforms. Values of `other` replace values already
+    /// set for the same location.
+    ///
+    /// Both sets of uniforms must have been created for the same program, as uniform
+    /// locations differ between programs.
+    pub fn merge(&mut self, other: Uniforms) {
+        for (location, value) in other.data {
+            let existing = self
+                .data
+                .iter_mut()
+                .find(|(existing, _)| *existing == location && location != -1);
+            if let Some(existing) = existing {
+                existing.1 = value;
+            } else {
+                self.data.push((location, value));
+            }
+        }
+    }
+
+    /// Adds a value for the uniform behind `handle`
+    pub fn push<T>(&mut self, handle: &UniformHandle<T>, value: T)
+    where
+        T: Uniform + 'static,
+    {
+        self.data.push((handle.location(), value.into_value()));
+    }
+
+    /// Uploads every value to the currently bound program, assigning texture units to
+    /// textures starting from unit `0`. See [`Uniforms::upload_all_with`].
+    pub fn upload_all(&self) -> Result<(), DrawError> {
+        self.upload_all_with(&mut TextureUnits::new())
+    }
+
+    /// Uploads every value to the currently bound program, assigning texture units to
+    /// textures through `units`.
+    ///
+    /// # Returns
+    ///
+    /// [`DrawError::OutOfTextureUnits`] if the textures don't fit the remaining units, in
+    /// which case the values after the first texture that didn't fit aren't uploaded.
+    pub fn upload_all_with(&self, units: &mut TextureUnits) -> Result<(), DrawError> {
+        self.data.iter().try_for_each(|(location, value)| {
+            if let Some(texture) = value.texture() {
+                (assign_unit(units, texture)? as i32).upload(*location);
+            } else {
+                value.upload(*location);
+            }
+            Ok(())
+        })
+    }
+
+    /// Uploads every value to `program` without it having to be bound, assigning texture
+    /// units to textures starting from unit `0`.
+    ///
+    /// Uses `glProgramUniform*` where available (OpenGL 4.1), so uniforms can be prepared
+    /// ahead of the draw path; otherwise `program` is bound for the duration of the upload.
+    pub fn upload_to(&self, program: &LinkedProgram) -> Result<(), DrawError> {
+        self.upload_to_with(program, &mut TextureUnits::new())
+    }
+
+    /// Uploads every value to `program` without it having to be bound, assigning texture
+    /// units to textures through `units`. See [`Uniforms::upload_to`] and
+    /// [`Uniforms::upload_all_with`].
+    pub fn upload_to_with(
+        &self,
+        program: &LinkedProgram,
+        units: &mut TextureUnits,
+    ) -> Result<(), DrawError> {
+        if !program_uniform_supported() {
+            let _binding = program.bind_scoped();
+            return self.upload_all_with(units);
+        }
+
+        self.data.iter().try_for_each(|(location, value)| {
+            if let Some(texture) = value.texture() {
+                (assign_unit(units, texture)? as i32).upload_to_program(program.id(), *location);
+            } else {
+                value.upload_to_program(program.id(), *location);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Assigns `texture` a unit through `units`, failing if they're all taken
+fn assign_unit(units: &mut TextureUnits, texture: TextureBinding) -> Result<u32, DrawError> {
+    units.assign(texture).ok_or(DrawError::OutOfTextureUnits {
+        available: units.max_units(),
+    })
+}
+
+impl Extend<(i32, UniformValue)> for Uniforms {
+    fn extend<I: IntoIterator<Item = (i32, UniformValue)>>(&mut self, iter: I) {
+        self.data.extend(iter);
+    }
+}
+
+/// Builds [`Uniforms`] for a program by name. Created with [`Uniforms::builder`].
+pub struct UniformsBuilder<'a> {
+    program: &'a LinkedProgram,
+    uniforms: Uniforms,
+}
+
+impl UniformsBuilder<'_> {
+    /// Adds a value for the uniform called `name`
+    pub fn add<T: Uniform + 'static>(mut self, name: &str, value: T) -> Self {
+        self.uniforms.add(self.program, name, value);
+        self
+    }
+
+    /// Adds a value for the uniform behind `handle`
+    pub fn push<T: Uniform + 'static>(mut self, handle: &UniformHandle<T>, value: T) -> Self {
+        self.uniforms.push(handle, value);
+        self
+    }
+
+    /// Merges `other` into the uniforms being built. See [`Uniforms::merge`].
+    pub fn merge(mut self, other: Uniforms) -> Self {
+        self.uniforms.merge(other);
+        self
+    }
+
+    /// Returns the built uniforms
+    pub fn build(self) -> Uniforms {
+        self.uniforms
     }
 }
 
@@ -70,16 +1003,15 @@ impl Uniforms {
 macro_rules! uniforms {
     () => {{
         $crate::uniforms::Uniforms {
-            data: vec![]
+            data: $crate::uniforms::UniformData::new()
         }
     }};
 
     ( $program: ident => { $($name:literal : $uniform:expr),* } ) => {{
-        let mut data = Vec::new();
+        let mut data = $crate::uniforms::UniformData::new();
         $(
             let location = $program.get_uniform_location($name);
-            let b: std::boxed::Box<dyn $crate::uniforms::Uniform> = std::boxed::Box::new($uniform);
-            data.push((location, b));
+            data.push((location, $crate::uniforms::Uniform::into_value($uniform)));
         )*
 
         $crate::uniforms::Uniforms {
@@ -0,0 +1,461 @@
+#[cfg(feature = "backends")]
+use crate::{
+    glfw::{self, Context, OpenGlProfileHint, WindowHint, WindowMode},
+    renderer::Renderer,
+};
+use crate::{
+    include::{self, IncludeDirectory, IncludeError, IncludeResolver},
+    shader::{self, ShaderType},
+};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// An OpenGL 4.6 core context without a visible window, for compiling shaders outside of an
+/// application, e.g. from a build script or a test. The context stays current on the
+/// creating thread until it is dropped.
+#[cfg(feature = "backends")]
+pub struct HeadlessContext {
+    _window: glfw::PWindow,
+    _glfw: glfw::Glfw,
+}
+
+#[cfg(feature = "backends")]
+impl HeadlessContext {
+    /// Creates the context, makes it current and loads the OpenGL functions
+    pub fn new() -> Result<Self, HeadlessContextError> {
+        let mut glfw = glfw::init_no_callbacks().map_err(HeadlessContextError::Init)?;
+        glfw.window_hint(WindowHint::Visible(false));
+        glfw.window_hint(WindowHint::ContextVersion(4, 6));
+        glfw.window_hint(WindowHint::OpenGlProfile(OpenGlProfileHint::Core));
+
+        let (mut window, _events) = glfw
+            .create_window(1, 1, "", WindowMode::Windowed)
+            .ok_or(HeadlessContextError::Context)?;
+        window.make_current();
+        Renderer::load_opengl_functions(|name| glfw.get_proc_address_raw(name));
+
+        Ok(Self {
+            _window: window,
+            _glfw: glfw,
+        })
+    }
+}
+
+/// An error creating a [`HeadlessContext`]
+#[cfg(feature = "backends")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeadlessContextError {
+    /// GLFW failed to initialize, e.g. because there is no display
+    Init(glfw::InitError),
+    /// No OpenGL 4.6 core context could be created
+    Context,
+}
+
+#[cfg(feature = "backends")]
+impl fmt::Display for HeadlessContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Init(error) => write!(f, "Failed to initialize GLFW: {error}"),
+            Self::Context => write!(f, "Failed to create an OpenGL 4.6 core context"),
+        }
+    }
+}
+
+#[cfg(feature = "backends")]
+impl std::error::Error for HeadlessContextError {}
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A message from the shader compiler, parsed from its info log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The file the message refers to, if the log names a known source string
+    pub file: Option<String>,
+    /// The line the message refers to, if the log names one
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{file}:")?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{line}:")?;
+        }
+        if self.file.is_some() || self.line.is_some() {
+            write!(f, " ")?;
+        }
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity}: {}", self.message)
+    }
+}
+
+/// Why a shader failed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    /// The file couldn't be read
+    Io(String),
+    /// An `#include` couldn't be expanded
+    Include(IncludeError),
+    /// The compiler rejected the shader. `log` is the raw info log.
+    Compilation {
+        diagnostics: Vec<Diagnostic>,
+        log: String,
+    },
+}
+
+/// A shader that failed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub path: PathBuf,
+    pub kind: ValidationErrorKind,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.display();
+        match self.kind {
+            ValidationErrorKind::Io(ref error) => write!(f, "{path}: {error}"),
+            ValidationErrorKind::Include(ref error) => write!(f, "{path}: {error}"),
+            ValidationErrorKind::Compilation {
+                ref diagnostics,
+                ref log,
+            } => {
+                if diagnostics.is_empty() {
+                    return write!(f, "{path}: {}", log.trim());
+                }
+                for (index, diagnostic) in diagnostics.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    match diagnostic.file {
+                        Some(_) => write!(f, "{diagnostic}")?,
+                        None if diagnostic.line.is_some() => write!(f, "{path}:{diagnostic}")?,
+                        None => write!(f, "{path}: {diagnostic}")?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// The outcome of [`validate_directory`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The number of shaders compiled
+    pub validated: usize,
+    pub errors: Vec<ValidationError>,
+}
+
+impl ValidationReport {
+    /// Returns whether every shader compiled
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for error in &self.errors {
+            writeln!(f, "{error}")?;
+        }
+        write!(
+            f,
+            "{} of {} shaders failed to compile",
+            self.errors.len(),
+            self.validated
+        )
+    }
+}
+
+/// Compiles every shader below `directory` whose stage is known from its extension (see
+/// [`ShaderType::from_extension`]), resolving `#include` directives relative to the including
+/// file, without leaving `directory`. Other files, such as included `.glsl` files, are only
+/// compiled through the shaders including them.
+///
+/// Requires a current context, e.g. a [`HeadlessContext`].
+///
+/// # Returns
+///
+/// A report of every shader that failed, or an error if the directory couldn't be listed.
+pub fn validate_directory(directory: impl AsRef<Path>) -> io::Result<ValidationReport> {
+    let directory = directory.as_ref();
+    let resolver = IncludeDirectory::new(directory);
+    let mut files = Vec::new();
+    collect_files(directory, &mut files)?;
+    files.sort();
+
+    let mut report = ValidationReport::default();
+    for path in files {
+        let Some(shader_type) = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ShaderType::from_extension)
+        else {
+            continue;
+        };
+        let name = path.strip_prefix(directory).unwrap_or(&path);
+        report.validated += 1;
+        if let Err(kind) = validate_file(&path, &name.to_string_lossy(), shader_type, &resolver) {
+            report.errors.push(ValidationError { path, kind });
+        }
+    }
+    Ok(report)
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn validate_file<R>(
+    path: &Path,
+    name: &str,
+    shader_type: ShaderType,
+    resolver: &R,
+) -> Result<(), ValidationErrorKind>
+where
+    R: IncludeResolver + ?Sized,
+{
+    let source =
+        fs::read_to_string(path).map_err(|error| ValidationErrorKind::Io(error.to_string()))?;
+    validate_source(&source, name, shader_type, resolver)
+}
+
+/// Compiles `source`, the contents of the file `name`, as a shader of `shader_type` after
+/// expanding its `#include` directives with `resolver`.
+///
+/// Requires a current context, e.g. a [`HeadlessContext`].
+pub fn validate_source<R>(
+    source: &str,
+    name: &str,
+    shader_type: ShaderType,
+    resolver: &R,
+) -> Result<(), ValidationErrorKind>
+where
+    R: IncludeResolver + ?Sized,
+{
+    let expanded =
+        include::expand_includes(source, name, resolver).map_err(ValidationErrorKind::Include)?;
+    shader::check_compiles(&expanded.source, shader_type).map_err(|error| {
+        let log = error.to_string();
+        ValidationErrorKind::Compilation {
+            diagnostics: parse_info_log(&log, &expanded.files),
+            log,
+        }
+    })
+}
+
+/// Parses the messages of a compiler info log, naming the file of each source string number
+/// from `files`.
+///
+/// Understands the common `0(12) : error ...` and `0:12(3): error: ...` forms, optionally
+/// preceded by `ERROR: ` or `WARNING: `. Lines it doesn't understand become messages without a
+/// location.
+pub fn parse_info_log(log: &str, files: &[String]) -> Vec<Diagnostic> {
+    log.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (prefix_severity, unprefixed) = split_severity(line);
+            match parse_location(unprefixed) {
+                Some((source_string, line_number, message)) => {
+                    let (severity, message) = split_severity(message);
+                    Diagnostic {
+                        severity: prefix_severity.or(severity).unwrap_or(Severity::Error),
+                        file: files.get(source_string as usize).cloned(),
+                        line: Some(line_number),
+                        message: message.to_owned(),
+                    }
+                }
+                None => Diagnostic {
+                    severity: prefix_severity.unwrap_or(Severity::Error),
+                    file: None,
+                    line: None,
+                    message: line.to_owned(),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Splits a `<source string>(<line>)` or `<source string>:<line>` location off the start of
+/// `line`, returning both numbers and the rest of the message
+fn parse_location(line: &str) -> Option<(u32, u32, &str)> {
+    let digits = |text: &str| {
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len())
+    };
+
+    let source_end = digits(line);
+    let source_string = line[..source_end].parse().ok()?;
+    let rest = &line[source_end..];
+    let (line_number, rest) = match rest.chars().next()? {
+        '(' => {
+            let end = rest.find(')')?;
+            (rest[1..end].parse().ok()?, &rest[end + 1..])
+        }
+        ':' => {
+            let rest = &rest[1..];
+            let end = digits(rest);
+            (rest[..end].parse().ok()?, &rest[end..])
+        }
+        _ => return None,
+    };
+
+    // Skips a column in parentheses and the separators before the message
+    let rest = match rest.strip_prefix('(') {
+        Some(column) => column.find(')').map_or(rest, |end| &column[end + 1..]),
+        None => rest,
+    };
+    let message = rest.trim_start_matches(is_separator);
+    Some((source_string, line_number, message))
+}
+
+/// Splits an `error` or `warning` token, in any case, off the start of `text`, returning the
+/// severity it names and the rest of the text
+fn split_severity(text: &str) -> (Option<Severity>, &str) {
+    let end = text
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let severity = match text[..end].to_ascii_lowercase().as_str() {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        _ => return (None, text),
+    };
+    (Some(severity), text[end..].trim_start_matches(is_separator))
+}
+
+fn is_separator(c: char) -> bool {
+    c == ':' || c.is_whitespace()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<String> {
+        vec!["lit.frag".to_owned(), "lib/light.glsl".to_owned()]
+    }
+
+    #[test]
+    fn parses_nvidia_logs() {
+        assert_eq!(
+            parse_info_log(
+                "0(12) : error C1008: undefined variable \"albedo\"\n\
+                 1(3) : warning C7050: \"x\" might be used before being initialized\n",
+                &files()
+            ),
+            [
+                Diagnostic {
+                    severity: Severity::Error,
+                    file: Some("lit.frag".to_owned()),
+                    line: Some(12),
+                    message: "C1008: undefined variable \"albedo\"".to_owned(),
+                },
+                Diagnostic {
+                    severity: Severity::Warning,
+                    file: Some("lib/light.glsl".to_owned()),
+                    line: Some(3),
+                    message: "C7050: \"x\" might be used before being initialized".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_mesa_and_prefixed_logs() {
+        assert_eq!(
+            parse_info_log(
+                "0:7(15): error: `color' undeclared\nWARNING: 1:2: extension not supported",
+                &files()
+            ),
+            [
+                Diagnostic {
+                    severity: Severity::Error,
+                    file: Some("lit.frag".to_owned()),
+                    line: Some(7),
+                    message: "`color' undeclared".to_owned(),
+                },
+                Diagnostic {
+                    severity: Severity::Warning,
+                    file: Some("lib/light.glsl".to_owned()),
+                    line: Some(2),
+                    message: "extension not supported".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn severity_comes_from_its_token_only() {
+        let diagnostics = parse_info_log(
+            "0(4) : error C0000: treating warning as error\n\
+             ERROR: 0:5: 'warning' : undeclared identifier",
+            &files(),
+        );
+
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Error));
+        assert_eq!(diagnostics[1].message, "'warning' : undeclared identifier");
+    }
+
+    #[test]
+    fn unknown_lines_keep_their_text() {
+        assert_eq!(
+            parse_info_log(
+                "  \nCompilation failed, see warnings above\n9(1) : error x",
+                &files()
+            ),
+            [
+                Diagnostic {
+                    severity: Severity::Error,
+                    file: None,
+                    line: None,
+                    message: "Compilation failed, see warnings above".to_owned(),
+                },
+                Diagnostic {
+                    severity: Severity::Error,
+                    file: None,
+                    line: Some(1),
+                    message: "x".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_locations() {
+        assert_eq!(parse_location("0(12) : msg"), Some((0, 12, "msg")));
+        assert_eq!(parse_location("2:34(5): msg"), Some((2, 34, "msg")));
+        assert_eq!(parse_location("10:3: msg"), Some((10, 3, "msg")));
+        assert_eq!(parse_location("0(x) : msg"), None);
+        assert_eq!(parse_location("(12) : msg"), None);
+        assert_eq!(parse_location("0 : msg"), None);
+        assert_eq!(parse_location(""), None);
+    }
+}